@@ -11,6 +11,7 @@ local cmd_delete = require("UNL.cmd.delete")
 local cmd_stop = require("UNL.cmd.stop")
 local cmd_restart = require("UNL.cmd.restart")
 local cmd_rescan_assets = require("UNL.cmd.rescan_assets")
+local cmd_maintain = require("UNL.cmd.maintain")
 
 local M = {}
 
@@ -58,6 +59,7 @@ M.project = require("UNL.project")
 function M.setup(opts) cmd_setup.execute(opts) end
 function M.start(opts) cmd_start.execute(opts) end
 function M.refresh(opts) cmd_refresh.execute(opts) end
+function M.plan_refresh(opts) cmd_refresh.plan(opts) end
 function M.watch(opts) cmd_watch.execute(opts) end
 function M.cleanup(opts) cmd_cleanup.execute(opts) end
 function M.server_status(opts) cmd_status.execute(opts) end
@@ -66,6 +68,7 @@ function M.delete(opts) cmd_delete.execute(opts) end
 function M.stop(opts) cmd_stop.execute(opts) end
 function M.restart(opts) cmd_restart.execute(opts) end
 function M.rescan_assets(opts) cmd_rescan_assets.execute(opts) end
+function M.maintain(opts) cmd_maintain.execute(opts) end
 
 function M.register_client()
   require("UNL.scanner.server").register_self()
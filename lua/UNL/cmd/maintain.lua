@@ -0,0 +1,55 @@
+local path_util = require("UNL.path")
+local finder = require("UNL.finder")
+local log = require("UNL.logging").get("UNL")
+local rpc = require("UNL.rpc")
+local server_manager = require("UNL.scanner.server")
+
+local M = {}
+
+function M.execute(opts, on_complete)
+    opts = opts or {}
+
+    server_manager.ensure_running(function(ok)
+        if not ok then
+            if on_complete then on_complete(false) end
+            return
+        end
+
+        local cwd = vim.loop.cwd()
+        local project_info = finder.project.find_project(cwd)
+        if not (project_info and project_info.uproject) then
+            log.error("Could not find a .uproject file.")
+            if on_complete then on_complete(false) end
+            return
+        end
+
+        local project_root = vim.fn.fnamemodify(project_info.uproject, ":h")
+        local project_root_norm = path_util.normalize(project_root)
+
+        log.info("Starting index maintenance for: %s", vim.fn.fnamemodify(project_root, ":t"))
+
+        rpc.request("maintain_index", { project_root = project_root_norm }, nil, function(success, result_or_err)
+            if success and type(result_or_err) == "table" then
+                local r = result_or_err
+                local removed = {}
+                for table_name, count in pairs(r.orphans_removed or {}) do
+                    table.insert(removed, string.format("%s=%d", table_name, count))
+                end
+                table.sort(removed)
+                log.info("Index maintenance done. Removed: %s / stale links: %d / includes resolved: %d / %.1f MB -> %.1f MB",
+                    #removed > 0 and table.concat(removed, ", ") or "none",
+                    r.stale_links_cleared or 0, r.includes_resolved or 0,
+                    (r.size_before or 0) / 1048576, (r.size_after or 0) / 1048576)
+                if r.integrity_errors and #r.integrity_errors > 0 then
+                    log.warn("Integrity check reported problems: %s", table.concat(r.integrity_errors, "; "))
+                end
+                if on_complete then on_complete(true, r) end
+            else
+                log.error("Index maintenance failed: %s", tostring(result_or_err))
+                if on_complete then on_complete(false) end
+            end
+        end)
+    end)
+end
+
+return M
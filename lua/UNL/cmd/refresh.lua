@@ -13,6 +13,113 @@ local M = {}
 
 local active_refreshes = {}
 
+local function build_request(project_info, project_root_norm, opts)
+    local project_root = vim.fn.fnamemodify(project_info.uproject, ":h")
+    local engine_root = finder.engine.find_engine_root(project_info.uproject, {})
+
+    local config = {
+        include_extensions = {"uproject", "cpp", "h", "hpp", "inl", "ini", "cs", "usf", "ush", "verse"},
+        excludes_directory = {"Intermediate", "Binaries", "Saved", ".git", ".vs", "Templates"},
+        code_search = ((unl_config.get("UNL") or {}).code_search or {}).enable == true,
+    }
+
+    -- Full / Engine / Game 以外はコンポーネント名・モジュール名 (カンマ区切り) として部分リフレッシュする
+    local scope = opts.scope or "Full"
+    local targets = opts.targets
+    if not targets and not vim.tbl_contains({ "Full", "Engine", "Game", "Partial" }, scope) then
+        targets = vim.split(scope, ",", { trimempty = true })
+    end
+    if targets then scope = "Partial" end
+
+    return {
+        type = "refresh",
+        project_root = project_root_norm,
+        engine_root = engine_root and path_util.normalize(engine_root) or nil,
+        db_path = path_util.get_db_path(project_root),
+        scope = scope,
+        targets = targets,
+        config = config,
+        vcs_hash = vcs.get_current_hash(project_root),
+    }
+end
+
+local function join_names(list)
+    local names = {}
+    for _, item in ipairs(list or {}) do
+        table.insert(names, type(item) == "table" and (item.from .. " -> " .. item.to) or item)
+    end
+    return table.concat(names, ", ")
+end
+
+--- サーバーが返す RefreshReport を 1 行ずつの文字列にする
+function M.format_report(report)
+    local lines = {}
+    local files = report.files or {}
+    table.insert(lines, string.format("%s%s refresh: %d added, %d modified, %d deleted, %d touched file(s)",
+        report.dry_run and "[plan] " or "", report.mode or "full",
+        files.added or 0, files.modified or 0, files.deleted or 0, files.touched or 0))
+    if report.incremental_blocked_by then
+        table.insert(lines, "VCS incremental refresh not possible: " .. report.incremental_blocked_by)
+    end
+    if report.resumed then
+        table.insert(lines, "Resumed an interrupted refresh (discovery was skipped).")
+    end
+    if report.engine_seeded then
+        table.insert(lines, "Engine index is loaded from the shared engine cache.")
+    elseif report.engine_walk_skipped and report.mode == "full" then
+        table.insert(lines, "Engine scan is skipped (revision unchanged).")
+    end
+    if report.schema_change then table.insert(lines, "Database schema will be upgraded.") end
+    if report.macros_changed then table.insert(lines, "Custom macros changed: game files are re-parsed.") end
+    for _, kind in ipairs({ "modules", "plugins" }) do
+        local c = report[kind] or {}
+        for _, field in ipairs({ "added", "removed", "renamed" }) do
+            if c[field] and #c[field] > 0 then
+                table.insert(lines, string.format("%s %s: %s", kind, field, join_names(c[field])))
+            end
+        end
+    end
+    local per_module = {}
+    for name, counts in pairs(files.per_module or {}) do
+        table.insert(per_module, string.format("  %s: +%d ~%d -%d", name, counts.added or 0, counts.modified or 0, counts.deleted or 0))
+    end
+    table.sort(per_module)
+    vim.list_extend(lines, per_module)
+    return lines
+end
+
+--- 実際には更新せず、リフレッシュで何が変わるかを表示する
+function M.plan(opts, on_complete)
+    opts = opts or {}
+
+    server_manager.ensure_running(function(ok)
+        if not ok then
+            if on_complete then on_complete(false) end
+            return
+        end
+
+        local project_info = finder.project.find_project(vim.loop.cwd())
+        if not (project_info and project_info.uproject) then
+            log.error("Could not find a .uproject file.")
+            if on_complete then on_complete(false) end
+            return
+        end
+        local project_root_norm = path_util.normalize(vim.fn.fnamemodify(project_info.uproject, ":h"))
+        local req = build_request(project_info, project_root_norm, opts)
+
+        log.info("Planning refresh for: %s", vim.fn.fnamemodify(project_root_norm, ":t"))
+        rpc.request("plan_refresh", req, nil, function(success, result_or_err)
+            if success and type(result_or_err) == "table" then
+                log.info("%s", table.concat(M.format_report(result_or_err), "\n"))
+                if on_complete then on_complete(true, result_or_err) end
+            else
+                log.error("Refresh plan failed: %s", tostring(result_or_err))
+                if on_complete then on_complete(false) end
+            end
+        end, 600000)
+    end)
+end
+
 function M.execute(opts, on_complete)
     opts = opts or {}
     
@@ -40,24 +147,8 @@ function M.execute(opts, on_complete)
         end
 
         active_refreshes[project_root_norm] = true
-        
-        local engine_root = finder.engine.find_engine_root(project_info.uproject, {})
-        local current_vcs = vcs.get_current_hash(project_root)
-        
-        local config = {
-            include_extensions = {"uproject", "cpp", "h", "hpp", "inl", "ini", "cs", "usf", "ush"},
-            excludes_directory = {"Intermediate", "Binaries", "Saved", ".git", ".vs", "Templates"},
-        }
-
-        local req = {
-            type = "refresh",
-            project_root = project_root_norm,
-            engine_root = engine_root and path_util.normalize(engine_root) or nil,
-            db_path = path_util.get_db_path(project_root), 
-            scope = opts.scope or "Full",
-            config = config,
-            vcs_hash = current_vcs,
-        }
+
+        local req = build_request(project_info, project_root_norm, opts)
 
         local progress, _ = progress_backend.create_for_refresh(unl_config.get("UNL"), {
             title = "UNL Refresh: " .. vim.fn.fnamemodify(project_root, ":t"),
@@ -85,8 +176,13 @@ function M.execute(opts, on_complete)
             active_refreshes[project_root_norm] = nil
             progress:finish(success)
             if success then
-                log.debug("Refresh completed successfully.")
-                unl_events.publish(unl_event_types.ON_AFTER_REFRESH_COMPLETED, { project_root = project_root })
+                local report = type(result_or_err) == "table" and result_or_err or nil
+                if report then
+                    log.debug("Refresh completed.\n%s", table.concat(M.format_report(report), "\n"))
+                else
+                    log.debug("Refresh completed successfully.")
+                end
+                unl_events.publish(unl_event_types.ON_AFTER_REFRESH_COMPLETED, { project_root = project_root, report = report })
             else
                 log.error("Refresh failed: %s", tostring(result_or_err))
             end
@@ -30,7 +30,7 @@ function M.execute(opts, on_complete)
         local current_vcs = vcs.get_current_hash(project_root)
         
         local config = {
-            include_extensions = {"uproject", "cpp", "h", "hpp", "inl", "ini", "cs"},
+            include_extensions = {"uproject", "cpp", "h", "hpp", "inl", "ini", "cs", "usf", "ush", "verse"},
             excludes_directory = {"Intermediate", "Binaries", "Saved", ".git", ".vs", "Templates"},
         }
 
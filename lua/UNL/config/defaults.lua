@@ -78,6 +78,9 @@ local M = {
     },
   },
 
+  -- GrepCode 用のトライグラム索引 (DB サイズが大きくなるので既定では無効)
+  code_search = { enable = false },
+
   cache = { dirname = "UNL_cache" },
   project = {
     localrc_filename = ".unlrc.json",
@@ -68,14 +68,16 @@ function M.get_virtual_functions_in_inheritance_chain(class_name, callback)
     remote.get_virtual_functions_in_inheritance_chain(class_name, callback)
 end
 
---- プロジェクトのコンポーネント一覧を取得
-function M.get_components(callback)
-    remote.get_components(callback)
+--- プロジェクトのコンポーネント一覧を取得 (各要素の enabled は .uproject でのプラグインの有効状態)
+--- opts: { enabled_only = true } で無効なプラグインを除く
+function M.get_components(callback, opts)
+    remote.get_components(callback, opts)
 end
 
 --- プロジェクトのモジュール一覧を取得
-function M.get_modules(callback)
-    remote.get_modules(callback)
+--- opts: { enabled_only = true } で無効なプラグインのモジュールを除く
+function M.get_modules(callback, opts)
+    remote.get_modules(callback, opts)
 end
 
 --- モジュール詳細を取得 (ファイル一覧含む)
@@ -98,6 +100,13 @@ function M.search_symbols_in_modules(modules, symbol_type, filter, limit, callba
     remote.search_symbols_in_modules(modules, symbol_type, filter, limit, callback)
 end
 
+--- シンボルをあいまい検索する (UE プレフィックス / キャメルケース / 部分一致)
+--- opts: { limit, modules, kinds = {"class"|"struct"|"enum"|"function"|"property"|...}, reflected, enabled_only }
+--- 結果は一致度 → 種別 → Game/Plugin/Engine の順に並ぶ
+function M.search_symbols(pattern, opts, callback)
+    remote.search_symbols(pattern, opts, callback)
+end
+
 --- 全てのファイルパスを取得
 function M.get_all_file_paths(callback)
     remote.get_all_file_paths(callback)
@@ -150,6 +159,14 @@ function M.get_target_files(callback)
     remote.get_target_files(callback)
 end
 
+--- プロジェクト情報を取得
+--- callback(info): { name, root_path, uproject_path, engine_association,
+---                   engine = { version, major, minor, patch, branch, changelist, compatible_changelist } | nil,
+---                   targets = { { name, type, path }, ... } }
+function M.get_project_info(callback)
+    remote.get_project_info(callback)
+end
+
 --- 指定したファイルのシンボル情報を取得
 function M.get_file_symbols(file_path, callback)
     remote.get_file_symbols(file_path, callback)
@@ -165,6 +182,13 @@ function M.grep_assets(pattern, on_partial, on_complete)
     remote.grep_assets(pattern, on_partial, on_complete)
 end
 
+--- ソースコードを正規表現で検索する (非同期)
+--- opts: { modules, components = {"Game"|"Engine"|"Plugin"}, extensions, ignore_case, limit, enabled_only }
+--- 結果: { path, module_name, line, col, context }
+function M.grep_code(pattern, opts, on_partial, on_complete)
+    remote.grep_code(pattern, opts, on_partial, on_complete)
+end
+
 --- 指定したアセットを使用しているアセット一覧を取得
 function M.get_asset_usages(asset_path, callback)
     remote.get_asset_usages(asset_path, callback)
@@ -293,6 +317,24 @@ function M.check_includes(file_path, content, callback)
     remote.check_includes(file_path, content, callback)
 end
 
+--- C++ シェーダークラスと .usf/.ush エントリポイントの対応を取得する
+--- class_name / file_path はどちらも省略可 (.cpp ならその宣言、.usf なら参照元の宣言)
+function M.get_shader_bindings(class_name, file_path, callback)
+    remote.get_shader_bindings(class_name, file_path, callback)
+end
+
+--- Replicated プロパティ / RPC の整合性診断を取得する
+--- callback(diagnostics): diagnostics = [{severity, kind, class_name, member, message, file_path, line}]
+function M.get_replication_diagnostics(class_name, file_path, callback)
+    remote.get_replication_diagnostics(class_name, file_path, callback)
+end
+
+--- テンプレートのプライマリ定義と特殊化の一覧を取得する (プライマリが先頭)
+--- callback(list): list = [{class_name, file_path, line_number, template_params, specialization_args, is_specialization}]
+function M.get_template_specializations(class_name, callback)
+    remote.get_template_specializations(class_name, callback)
+end
+
 --- 汎用的な RPC クエリ
 function M.query(kind, args, callback)
     remote.request(kind, args, callback)
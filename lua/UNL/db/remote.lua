@@ -95,6 +95,20 @@ function M.grep_assets(pattern, on_partial, on_complete)
     M.request_streaming("GrepAssets", { pattern = pattern }, on_partial, on_complete)
 end
 
+--- opts: { modules, components, extensions, ignore_case, limit }
+function M.grep_code(pattern, opts, on_partial, on_complete)
+    opts = opts or {}
+    M.request_streaming("GrepCode", {
+        pattern = pattern,
+        modules = opts.modules,
+        components = opts.components,
+        extensions = opts.extensions,
+        ignore_case = opts.ignore_case,
+        limit = opts.limit,
+        enabled_only = opts.enabled_only,
+    }, on_partial, on_complete)
+end
+
 function M.get_asset_usages(asset_path, callback)
     M.request("GetAssetUsages", { asset_path = asset_path }, callback)
 end
@@ -107,12 +121,29 @@ function M.get_assets(callback)
     M.request("GetAssets", {}, callback)
 end
 
+--- 変更済み (未保存) の C++ バッファを path -> content で返す。無ければ nil
+local function collect_modified_buffers()
+    local buffers = {}
+    local found = false
+    for _, b in ipairs(vim.api.nvim_list_bufs()) do
+        if vim.api.nvim_buf_is_loaded(b) and vim.bo[b].modified then
+            local name = vim.api.nvim_buf_get_name(b)
+            local ext = name:match("%.(%w+)$")
+            if ext and ({ h = true, hpp = true, inl = true, cpp = true })[ext:lower()] then
+                buffers[name] = table.concat(vim.api.nvim_buf_get_lines(b, 0, -1, false), "\n")
+                found = true
+            end
+        end
+    end
+    return found and buffers or nil
+end
+
 function M.find_symbol_usages(symbol_name, file_path, method_name, cb)
     if type(method_name) == "function" then
         cb = method_name
         method_name = nil
     end
-    M.request("FindSymbolUsages", { symbol_name = symbol_name, file_path = file_path, method_name = method_name }, cb)
+    M.request("FindSymbolUsages", { symbol_name = symbol_name, file_path = file_path, method_name = method_name, buffers = collect_modified_buffers() }, cb)
 end
 
 function M.find_symbol_usages_streaming(symbol_name, file_path, method_name, on_partial, on_complete)
@@ -121,7 +152,7 @@ function M.find_symbol_usages_streaming(symbol_name, file_path, method_name, on_
         on_partial = method_name
         method_name = nil
     end
-    M.request_streaming("FindSymbolUsagesAsync", { symbol_name = symbol_name, file_path = file_path, method_name = method_name }, on_partial, on_complete)
+    M.request_streaming("FindSymbolUsagesAsync", { symbol_name = symbol_name, file_path = file_path, method_name = method_name, buffers = collect_modified_buffers() }, on_partial, on_complete)
 end
 
 --- 指定ファイルをインクルードしているファイル一覧をストリーミングで取得する（include 逆引き）
@@ -265,12 +296,12 @@ function M.get_enum_values(enum_name, cb)
     M.request("GetEnumValues", { enum_name = enum_name }, cb)
 end
 
-function M.get_components(cb)
-    M.request("GetComponents", {}, cb)
+function M.get_components(cb, opts)
+    M.request("GetComponents", { enabled_only = (opts or {}).enabled_only }, cb)
 end
 
-function M.get_modules(cb)
-    M.request("GetModules", {}, cb)
+function M.get_modules(cb, opts)
+    M.request("GetModules", { enabled_only = (opts or {}).enabled_only }, cb)
 end
 
 function M.get_module_id_by_name(name, cb)
@@ -305,6 +336,19 @@ function M.search_symbols_in_modules(modules, symbol_type, filter, limit, cb)
     M.request("SearchSymbolsInModules", { modules = modules, symbol_type = symbol_type, filter = filter, limit = limit }, cb)
 end
 
+--- opts: { limit, modules, kinds, reflected, enabled_only }
+function M.search_symbols(pattern, opts, cb)
+    opts = opts or {}
+    M.request("SearchSymbols", {
+        pattern = pattern,
+        limit = opts.limit or 100,
+        modules = opts.modules,
+        kinds = opts.kinds,
+        reflected = opts.reflected,
+        enabled_only = opts.enabled_only,
+    }, cb)
+end
+
 function M.get_directories_in_module(module_id, cb)
     M.request("GetDirectoriesInModule", { module_id = module_id }, cb)
 end
@@ -373,6 +417,10 @@ function M.get_target_files(cb)
     M.request("GetTargetFiles", {}, cb)
 end
 
+function M.get_project_info(cb)
+    M.request("GetProjectInfo", {}, cb)
+end
+
 function M.get_all_file_paths(cb)
     M.request("GetAllFilePaths", {}, cb)
 end
@@ -405,4 +453,22 @@ function M.check_includes(file_path, content, cb)
     }, cb)
 end
 
+function M.get_shader_bindings(class_name, file_path, cb)
+    M.request("GetShaderBindings", {
+        class_name = class_name,
+        file_path = file_path,
+    }, cb)
+end
+
+function M.get_replication_diagnostics(class_name, file_path, cb)
+    M.request("GetReplicationDiagnostics", {
+        class_name = class_name,
+        file_path = file_path,
+    }, cb)
+end
+
+function M.get_template_specializations(class_name, cb)
+    M.request("GetTemplateSpecializations", { class_name = class_name }, cb)
+end
+
 return M
\ No newline at end of file
@@ -91,7 +91,7 @@ function M.get_hash(root)
 end
 
 --- 2つのコミット間の変更ファイルリストを非同期で取得する
---- @param root string Git リポジトリのルートパス
+--- @param root string Git リポジトリのルート (またはその配下のディレクトリ。差分はその配下に限る)
 --- @param old_hash string 前回のコミットハッシュ（"git:" プレフィックス付きも可）
 --- @param new_hash string 現在のコミットハッシュ（"git:" プレフィックス付きも可）
 --- @param callback function(files: string[]|nil) 変更ファイルの絶対パスリスト
@@ -103,7 +103,7 @@ function M.get_changed_files(root, old_hash, new_hash, callback)
     local old_ref = old_hash:gsub("^git:", "")
     local new_ref = new_hash:gsub("^git:", "")
 
-    spawn_git({ "diff", "--name-only", "--diff-filter=ACMR", old_ref, new_ref }, root, function(output)
+    spawn_git({ "diff", "--name-only", "--relative", "--diff-filter=ACMR", old_ref, new_ref }, root, function(output)
         if not output then return callback(nil) end
         local root_norm = unl_path.normalize(root)
         local files = {}
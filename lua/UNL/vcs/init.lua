@@ -6,6 +6,7 @@ local providers = {
     { name = "p4",  module = require("UNL.vcs.p4") },
     { name = "git", module = require("UNL.vcs.git") },
     { name = "svn", module = require("UNL.vcs.svn") },
+    { name = "plastic", module = require("UNL.vcs.plastic") },
 }
 
 ---現在のプロジェクトのVCSハッシュ/リビジョンを取得する
@@ -20,11 +21,16 @@ function M.get_current_hash(root)
     local has_git = vim.fn.finddir(".git",  root .. ";") ~= ""
                  or vim.fn.findfile(".git", root .. ";") ~= ""
     local has_svn = vim.fn.finddir(".svn", root .. ";") ~= ""
+    local has_plastic = vim.fn.finddir(".plastic", root .. ";") ~= ""
 
     if has_git then
         local h = providers[2].module.get_hash(root)
         if h then return h end
     end
+    if has_plastic then
+        local h = providers[4].module.get_hash(root)
+        if h then return h end
+    end
     if has_svn then
         local h = providers[3].module.get_hash(root)
         if h then return h end
@@ -110,7 +116,7 @@ end
 
 --- 2つのVCSリビジョン間の変更ファイルリストを非同期で取得する
 --- @param root string プロジェクトルートパス
---- @param old_hash string 前回のハッシュ（"git:xxx" / "p4:xxx" / "svn:xxx"）
+--- @param old_hash string 前回のハッシュ（"git:xxx" / "p4:xxx" / "svn:xxx" / "plastic:xxx"）
 --- @param new_hash string 現在のハッシュ
 --- @param callback function(files: string[]|nil) 変更ファイルの絶対パスリスト。未対応VCSの場合nil
 function M.get_changed_files(root, old_hash, new_hash, callback)
@@ -126,6 +132,8 @@ function M.get_changed_files(root, old_hash, new_hash, callback)
         providers[1].module.get_changed_files(root, old_hash, new_hash, callback)
     elseif prefix == "svn" and providers[3].module.get_changed_files then
         providers[3].module.get_changed_files(root, old_hash, new_hash, callback)
+    elseif prefix == "plastic" and providers[4].module.get_changed_files then
+        providers[4].module.get_changed_files(root, old_hash, new_hash, callback)
     else
         callback(nil)
     end
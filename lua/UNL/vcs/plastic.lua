@@ -0,0 +1,89 @@
+-- lua/UNL/vcs/plastic.lua
+-- Plastic SCM (Unity Version Control) 用。`cm` CLI を使う。
+local unl_path = require("UNL.path")
+local M = {}
+
+-- 非同期 cm コマンド実行
+local function spawn_cm(args, cwd, on_success)
+    local stdout = vim.loop.new_pipe(false)
+    local stderr = vim.loop.new_pipe(false)
+    local output_data = ""
+
+    local handle, pid
+    handle, pid = vim.loop.spawn("cm", {
+        args = args,
+        cwd = cwd,
+        stdio = { nil, stdout, stderr }
+    }, function(code, signal)
+        stdout:read_stop()
+        stderr:read_stop()
+        stdout:close()
+        stderr:close()
+        handle:close()
+
+        vim.schedule(function()
+            if code == 0 then
+                on_success(output_data)
+            else
+                on_success(nil)
+            end
+        end)
+    end)
+
+    if handle then
+        vim.loop.read_start(stdout, function(err, data)
+            if data then output_data = output_data .. data end
+        end)
+        vim.loop.read_start(stderr, function(err, data) end)
+    else
+        vim.schedule(function() on_success(nil) end)
+    end
+end
+
+--- .plastic を親方向に探してワークスペースのルートを返す
+function M.find_workspace_root(root)
+    local dir = vim.fn.finddir(".plastic", root .. ";")
+    if dir == "" then return nil end
+    return unl_path.normalize(vim.fn.fnamemodify(dir, ":p:h:h"))
+end
+
+function M.get_hash(root)
+    if not M.find_workspace_root(root) then return nil end
+    local output = vim.fn.systemlist("cm status --header --machinereadable " .. vim.fn.shellescape(root))
+    if vim.v.shell_error == 0 and #output > 0 then
+        -- "STATUS 1234 MyRepo localhost:8087"
+        local cs = output[1]:match("^STATUS%s+(%d+)")
+        if cs then return "plastic:" .. cs end
+    end
+    return nil
+end
+
+--- 2つのチェンジセット間の変更ファイルリストを非同期で取得する
+--- @param root string プロジェクトルートパス
+--- @param old_hash string 前回のハッシュ（"plastic:CS" 形式）
+--- @param new_hash string 現在のハッシュ（"plastic:CS" 形式）
+--- @param callback function(files: string[]|nil) 変更ファイルの絶対パスリスト
+function M.get_changed_files(root, old_hash, new_hash, callback)
+    local ws_root = root and M.find_workspace_root(root)
+    if not ws_root or not old_hash or not new_hash then
+        return callback(nil)
+    end
+    local old_cs = old_hash:gsub("^plastic:", "")
+    local new_cs = new_hash:gsub("^plastic:", "")
+
+    -- 出力: "C|/Source/Foo.cpp" (ステータス | ワークスペースからのパス)
+    spawn_cm({ "diff", "cs:" .. old_cs, "cs:" .. new_cs, "--format={status}|{path}" }, root, function(output)
+        if not output then return callback(nil) end
+        local files = {}
+        for line in output:gmatch("[^\r\n]+") do
+            local status, path = line:match("^(%a)|(.+)$")
+            -- 削除されたファイルはスキップ（DBに残す意味がない）
+            if status and status ~= "D" then
+                table.insert(files, ws_root .. "/" .. vim.fn.trim(path):gsub("^[/\\]+", ""))
+            end
+        end
+        callback(files)
+    end)
+end
+
+return M
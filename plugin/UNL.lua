@@ -42,7 +42,12 @@ builder.create({
     ["refresh"] = {
       handler = api.refresh,
       bang = true,
-      desc = "Refresh UNL project database.",
+      desc = "Refresh UNL project database (scope: Full / Engine / Game, or comma-separated plugin/module names).",
+      args = {{ name = "scope", required = false }},
+    },
+    ["plan"] = {
+      handler = api.plan_refresh,
+      desc = "Show what a refresh would change (modules, plugins, files) without updating the database.",
       args = {{ name = "scope", required = false }},
     },
     ["watch"] = {
@@ -85,6 +90,11 @@ builder.create({
       desc = "Force a full re-scan of all uasset/umap files for the current project.",
       args = {},
     },
+    ["maintain"] = {
+      handler = api.maintain,
+      desc = "Check, garbage-collect and compact the current project's index.",
+      args = {},
+    },
     -- (将来、ここに :UNL clear_cache のようなコマンドが追加されるかもしれませんね)
   },
 })
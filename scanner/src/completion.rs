@@ -106,15 +106,42 @@ impl<'a> RequestContext<'a> {
         if class_name.is_empty() { return Ok(Vec::new()); }
 
         // 1. まずは完全一致（UE::Math::TVector などが一個の文字列で登録されている可能性）
+        //    テンプレートの特殊化はプライマリテンプレートがあればそちらを優先する
         if let Some(name_id) = self.get_string_id(class_name)? {
-            let ids: Vec<i64> = {
-                let mut stmt = self.conn.prepare_cached("SELECT id FROM classes WHERE name_id = ?")?;
-                let x: Vec<i64> = stmt.query_map([name_id], |row| row.get(0))?.filter_map(|r| r.ok()).collect(); x
+            let rows: Vec<(i64, bool)> = {
+                let mut stmt = self.conn.prepare_cached("SELECT id, specialization_args IS NOT NULL FROM classes WHERE name_id = ?")?;
+                let x: Vec<(i64, bool)> = stmt.query_map([name_id], |row| Ok((row.get(0)?, row.get(1)?)))?.filter_map(|r| r.ok()).collect(); x
             };
-            if !ids.is_empty() { return Ok(ids); }
+            let primary: Vec<i64> = rows.iter().filter(|(_, is_spec)| !is_spec).map(|(id, _)| *id).collect();
+            if !primary.is_empty() { return Ok(primary); }
+            if !rows.is_empty() { return Ok(rows.into_iter().map(|(id, _)| id).collect()); }
         }
 
-        // 2. 名前空間で分割して検索（UE::Math と TVector に分かれている可能性）
+        // TMyTraits<int32> → 一致する特殊化、なければプライマリテンプレート
+        if let (base, Some(args)) = crate::parser::template::split_type_args(class_name) {
+            if let Some(name_id) = self.get_string_id(base)? {
+                let args = crate::parser::template::normalize_args(args);
+                let ids: Vec<i64> = {
+                    let mut stmt = self.conn.prepare_cached("SELECT id FROM classes WHERE name_id = ? AND specialization_args = ?")?;
+                    let x: Vec<i64> = stmt.query_map(params![name_id, args], |row| row.get(0))?.filter_map(|r| r.ok()).collect(); x
+                };
+                if !ids.is_empty() { return Ok(ids); }
+            }
+            if !base.is_empty() && base != class_name { return self.get_class_ids_by_name(base); }
+        }
+
+        // 2. 完全修飾名で検索（FMyOuter::FInner のようなネスト型）
+        if class_name.contains("::") {
+            if let Some(qn_id) = self.get_string_id(class_name)? {
+                let ids: Vec<i64> = {
+                    let mut stmt = self.conn.prepare_cached("SELECT id FROM classes WHERE qualified_name_id = ?")?;
+                    let x: Vec<i64> = stmt.query_map([qn_id], |row| row.get(0))?.filter_map(|r| r.ok()).collect(); x
+                };
+                if !ids.is_empty() { return Ok(ids); }
+            }
+        }
+
+        // 3. 名前空間で分割して検索（UE::Math と TVector に分かれている可能性）
         if class_name.contains("::") {
             let parts: Vec<&str> = class_name.split("::").collect();
             if parts.len() >= 2 {
@@ -665,7 +692,14 @@ fn resolve_expression_type(
         "subscript_expression" => {
             if let Some(obj_node) = node.child_by_field_name("argument") {
                 if let Some(obj_type) = resolve_expression_type(ctx, obj_node, root, content, cursor_row, absolute_line)? {
-                    return Ok(Some(unwrap_container_type(&obj_type)));
+                    let element = unwrap_container_type(&obj_type);
+                    // 既知のコンテナ以外のユーザーテンプレートは operator[] の戻り値型から解決する
+                    if element == obj_type.trim() && obj_type.contains('<') {
+                        if let Some(rt) = find_member_return_type(ctx, &obj_type, "operator[]")? {
+                            return Ok(Some(rt));
+                        }
+                    }
+                    return Ok(Some(element));
                 }
             }
             Ok(None)
@@ -783,8 +817,11 @@ fn find_member_return_type(ctx: &mut RequestContext, class_name: &str, member_na
     
     let start_class_ids = ctx.get_class_ids_by_name(&resolved_class)?;
     if start_class_ids.is_empty() { return Ok(None); }
+    // TMyBox<FFoo> の場合、開始クラスのメンバー型に含まれる T を FFoo に置き換える
+    let template_args = crate::parser::template::split_type_args(&resolved_class).1
+        .map(crate::parser::template::split_template_args);
 
-    let mut queue = start_class_ids;
+    let mut queue = start_class_ids.clone();
     let mut visited = HashMap::new();
     while let Some(cls_id) = queue.pop() {
         if visited.contains_key(&cls_id) { continue; }
@@ -800,8 +837,12 @@ fn find_member_return_type(ctx: &mut RequestContext, class_name: &str, member_na
         ")?;
         let mut rows = stmt.query(params![cls_id, member_name])?;
         if let Some(row) = rows.next()? {
-            if let Some(rt) = row.get::<_, Option<String>>(0)? {
+            if let Some(mut rt) = row.get::<_, Option<String>>(0)? {
                 tracing::info!("find_member_return_type: FOUND in class_id={}, return_type='{}'", cls_id, rt);
+                if let Some(args) = template_args.as_deref().filter(|_| start_class_ids.contains(&cls_id)) {
+                    let params = get_class_template_params(ctx.conn, cls_id)?;
+                    rt = crate::parser::template::substitute_template_args(&rt, &params, args);
+                }
                 return Ok(Some(extract_clean_type(&rt)));
             }
         }
@@ -826,6 +867,14 @@ fn find_member_return_type(ctx: &mut RequestContext, class_name: &str, member_na
     Ok(None)
 }
 
+/// classes.template_params (JSON) を読み出す
+fn get_class_template_params(conn: &Connection, class_id: i64) -> anyhow::Result<Vec<crate::types::TemplateParam>> {
+    let json: Option<String> = conn
+        .query_row("SELECT template_params FROM classes WHERE id = ?", [class_id], |r| r.get(0))
+        .optional()?
+        .flatten();
+    Ok(json.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default())
+}
 
 /// Tree-sitter でウィンドウ外にあるクラス宣言を DB の行番号情報から特定するフォールバック。
 /// `absolute_line` はファイル先頭から0始まりの行番号。
@@ -989,7 +1038,8 @@ fn resolve_typedef(ctx: &mut RequestContext, type_name: &str) -> anyhow::Result<
     Ok(current)
 }
 
-/// `ClassName::` 補完: staticメンバのみ返す。
+/// `ClassName::` 補完: staticメンバとネストした型を返す。
+/// `Outer::Inner::` のようなネストしたスコープは完全修飾名で解決する。
 fn resolve_qualified_members(
     ctx: &mut RequestContext,
     scope_name: &str,
@@ -998,10 +1048,34 @@ fn resolve_qualified_members(
 ) -> anyhow::Result<Value> {
     let clean_scope = extract_clean_type(scope_name);
     let t_name = resolve_typedef(ctx, &clean_scope)?;
-    let members = fetch_members_recursive(ctx, &t_name, prefix, cache, None, true)?;
+    let mut members = fetch_members_recursive(ctx, &t_name, prefix.clone(), cache, None, true)?;
+    let scope_ids = ctx.get_class_ids_by_name(&t_name)?;
+    members.extend(fetch_nested_types(ctx.conn, &scope_ids, prefix.as_deref())?);
     Ok(json!(members))
 }
 
+/// `parent_id` が指すスコープ直下のネスト型 (class / struct / enum) を補完候補として返す。
+fn fetch_nested_types(conn: &Connection, scope_ids: &[i64], prefix: Option<&str>) -> anyhow::Result<Vec<Value>> {
+    if scope_ids.is_empty() { return Ok(Vec::new()); }
+    let ids_sql = scope_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+    let sql = format!(
+        "SELECT DISTINCT s.text, c.symbol_type FROM classes c JOIN strings s ON c.name_id = s.id
+         WHERE c.parent_id IN ({}) AND s.text LIKE ? ORDER BY s.text LIMIT 200",
+        ids_sql
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([format!("{}%", prefix.unwrap_or(""))], |row| {
+        let name: String = row.get(0)?;
+        let sym_type: String = row.get(1)?;
+        let kind = match sym_type.as_str() {
+            "enum" | "UENUM" => 13,
+            _                => 7,
+        };
+        Ok(json!({ "label": name, "kind": kind, "detail": sym_type, "insertText": name }))
+    })?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
 fn is_subclass_of(ctx: &mut RequestContext, child: &str, parent: &str) -> anyhow::Result<bool> {
     if child == parent { return Ok(true); }
     let cache_key = (child.to_string(), parent.to_string());
@@ -1132,18 +1206,18 @@ fn fetch_members_recursive(
         let impl_filter = if exclude_impl { "AND (m.access IS NULL OR m.access != 'impl')" } else { "" };
         let static_filter = if static_only { "AND m.is_static = 1" } else { "" };
         let sql = format!(
-            "{} SELECT smn.text, smt.text, srt.text, access, detail, m.line_number, dp.full_path || '/' || sn.text
+            "SELECT smn.text, smt.text, srt.text, access, detail, m.line_number,
+                    (SELECT dp.full_path || '/' || sn.text FROM files f
+                     JOIN dir_paths dp ON f.directory_id = dp.id
+                     JOIN strings sn ON f.filename_id = sn.id
+                     WHERE f.id = m.file_id)
              FROM members m
              JOIN strings smn ON m.name_id = smn.id
              JOIN strings smt ON m.type_id = smt.id
              LEFT JOIN strings srt ON m.return_type_id = srt.id
-             LEFT JOIN files f ON m.file_id = f.id
-             LEFT JOIN dir_paths dp ON f.directory_id = dp.id
-             LEFT JOIN strings sn ON f.filename_id = sn.id
              WHERE m.class_id IN ({}) {} {}
              {}
              ORDER BY smn.text ASC LIMIT 2000",
-            crate::db::path::PATH_CTE,
             ids_sql,
             static_filter,
             if prefix_search.is_some() { "AND smn.text LIKE ?" } else { "" },
@@ -1184,6 +1258,7 @@ fn fetch_members_recursive(
     let mut seen_members: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
 
     for (m_name, m_type, r_type, access, detail, line, f_path) in member_data {
+        if crate::parser::cpp::is_operator_name(&m_name) { continue; }
         let access_str = access.as_deref().unwrap_or("");
 
         let is_accessible = if accessor_val.is_empty() {
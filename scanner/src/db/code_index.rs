@@ -0,0 +1,206 @@
+//! Optional trigram index over source lines (`GrepCode`).
+//!
+//! Every source file contributes the set of trigrams (3 ASCII-lowercased bytes,
+//! never spanning a line break or containing whitespace) that occur in it.  A
+//! regex query extracts the literal runs it requires, intersects their
+//! trigrams' posting lists to get candidate files, and only those files are
+//! read and matched line by line.
+//!
+//! The index is off unless the refresh request sets `config.code_search`.
+//! `sync` runs at the end of every refresh and re-indexes files whose `mtime`
+//! differs from the one recorded in `code_index_files`; the watcher calls
+//! `index_file` for single-file updates.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use rayon::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::types::ProgressReporter;
+
+/// インデックス対象の拡張子
+pub const CODE_EXTENSIONS: &[&str] = &["h", "hpp", "cpp", "c", "cc", "inl", "cs", "usf", "ush", "verse", "ini"];
+
+/// これより大きいファイルは生成物とみなしてインデックスしない
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+const ENABLED_KEY: &str = "code_search_enabled";
+
+pub fn is_enabled(conn: &Connection) -> bool {
+    conn.query_row("SELECT value FROM project_meta WHERE key = ?", [ENABLED_KEY], |r| r.get::<_, String>(0))
+        .optional()
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "1")
+}
+
+fn pack(w: &[u8]) -> i64 {
+    ((w[0].to_ascii_lowercase() as i64) << 16) | ((w[1].to_ascii_lowercase() as i64) << 8) | w[2].to_ascii_lowercase() as i64
+}
+
+fn indexable(w: &[u8]) -> bool {
+    !w.iter().any(|b| b.is_ascii_whitespace())
+}
+
+/// ファイル内容に含まれるトライグラム (重複なし)
+pub fn extract_trigrams(content: &[u8]) -> Vec<i64> {
+    let mut set: HashSet<i64> = HashSet::new();
+    for w in content.windows(3) {
+        if indexable(w) { set.insert(pack(w)); }
+    }
+    set.into_iter().collect()
+}
+
+/// 正規表現がマッチするために必ず含まれるリテラル片のトライグラム。
+/// トップレベルに `|` がある場合など、必須リテラルを決められなければ None (全候補を走査する)。
+pub fn required_trigrams(pattern: &str) -> Option<Vec<i64>> {
+    let mut runs: Vec<Vec<u8>> = Vec::new();
+    let mut run: Vec<u8> = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = pattern.chars().peekable();
+    let flush = |run: &mut Vec<u8>, runs: &mut Vec<Vec<u8>>| {
+        if run.len() >= 3 { runs.push(std::mem::take(run)); } else { run.clear(); }
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '|' if depth == 0 => return None,
+            '(' => { flush(&mut run, &mut runs); depth += 1; }
+            ')' => { depth = depth.saturating_sub(1); }
+            '[' => {
+                flush(&mut run, &mut runs);
+                // クラスの中身は読み飛ばす (`[]...]` / `[^]...]` の先頭 `]` はリテラル)
+                if chars.peek() == Some(&'^') { chars.next(); }
+                if chars.peek() == Some(&']') { chars.next(); }
+                while let Some(x) = chars.next() {
+                    if x == '\\' { chars.next(); } else if x == ']' { break; }
+                }
+            }
+            // 直前の 1 文字は省略可能
+            '?' | '*' | '{' => {
+                run.pop();
+                flush(&mut run, &mut runs);
+                if c == '{' { for x in chars.by_ref() { if x == '}' { break; } } }
+            }
+            '+' | '.' | '^' | '$' => flush(&mut run, &mut runs),
+            '\\' => match chars.next() {
+                Some(e) if e.is_ascii_punctuation() => if depth == 0 { run.push(e as u8) },
+                // `\x41` / `\u{..}` / `\p{..}` は後続を読み解けないので諦める
+                Some('x' | 'u' | 'U' | 'p' | 'P') => return None,
+                _ => flush(&mut run, &mut runs),
+            },
+            _ if depth > 0 => {}
+            _ if c.is_ascii() => run.push(c as u8),
+            _ => flush(&mut run, &mut runs),
+        }
+    }
+    flush(&mut run, &mut runs);
+
+    let mut set: HashSet<i64> = HashSet::new();
+    for r in &runs {
+        for w in r.windows(3) {
+            if indexable(w) { set.insert(pack(w)); }
+        }
+    }
+    if set.is_empty() { None } else { Some(set.into_iter().collect()) }
+}
+
+fn read_indexable(path: &str) -> Option<Vec<i64>> {
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.len() > MAX_FILE_SIZE { return Some(Vec::new()); }
+    let content = std::fs::read(path).ok()?;
+    if content.contains(&0) { return Some(Vec::new()); }
+    Some(extract_trigrams(&content))
+}
+
+fn store(conn: &Connection, file_id: i64, mtime: i64, trigrams: &[i64]) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM code_trigrams WHERE file_id = ?", [file_id])?;
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO code_trigrams (trigram, file_id) VALUES (?, ?)")?;
+    for t in trigrams {
+        stmt.execute(params![t, file_id])?;
+    }
+    conn.execute("INSERT OR REPLACE INTO code_index_files (file_id, mtime) VALUES (?, ?)", params![file_id, mtime])?;
+    Ok(())
+}
+
+/// 1 ファイル分を (再) インデックスする。インデックスが無効なら何もしない
+pub fn index_file(conn: &mut Connection, path: &str) -> anyhow::Result<()> {
+    if !is_enabled(conn) { return Ok(()); }
+    let Some(file_id) = super::path::find_file_id(conn, std::path::Path::new(path))? else { return Ok(()) };
+    let mtime: i64 = conn.query_row("SELECT COALESCE(mtime, 0) FROM files WHERE id = ?", [file_id], |r| r.get(0))?;
+    let Some(trigrams) = read_indexable(path) else { return Ok(()) };
+    let tx = conn.transaction()?;
+    store(&tx, file_id, mtime, &trigrams)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// refresh の最後に呼ばれる。`enabled` が false ならインデックスを破棄する
+pub fn sync(conn: &mut Connection, enabled: bool, reporter: &Arc<dyn ProgressReporter>) -> anyhow::Result<()> {
+    if !enabled {
+        if is_enabled(conn) {
+            conn.execute_batch(
+                "DELETE FROM code_trigrams; DELETE FROM code_index_files;
+                 DELETE FROM project_meta WHERE key = 'code_search_enabled';"
+            )?;
+        }
+        return Ok(());
+    }
+    conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, '1')", [ENABLED_KEY])?;
+    // 削除済みファイルの分 (save_to_db は FK OFF で行を置き換える)
+    conn.execute_batch(
+        "DELETE FROM code_trigrams WHERE file_id NOT IN (SELECT id FROM files);
+         DELETE FROM code_index_files WHERE file_id NOT IN (SELECT id FROM files);"
+    )?;
+
+    let exts = CODE_EXTENSIONS.iter().map(|e| format!("'{}'", e)).collect::<Vec<_>>().join(",");
+    let sql = format!(
+        "SELECT f.id, dp.full_path || '/' || sn.text, COALESCE(f.mtime, 0)
+         FROM files f
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sn ON f.filename_id = sn.id
+         LEFT JOIN code_index_files ci ON ci.file_id = f.id
+         WHERE LOWER(f.extension) IN ({}) AND (ci.file_id IS NULL OR ci.mtime != COALESCE(f.mtime, 0))", exts
+    );
+    let stale: Vec<(i64, String, i64)> = conn.prepare(&sql)?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+    if stale.is_empty() { return Ok(()); }
+
+    let total = stale.len();
+    tracing::info!("Indexing code of {} file(s) for GrepCode", total);
+    let mut done = 0usize;
+    for chunk in stale.chunks(500) {
+        let extracted: Vec<(i64, i64, Option<Vec<i64>>)> = chunk.par_iter()
+            .map(|(id, path, mtime)| (*id, *mtime, read_indexable(path)))
+            .collect();
+        let tx = conn.transaction()?;
+        for (id, mtime, trigrams) in &extracted {
+            if let Some(t) = trigrams { store(&tx, *id, *mtime, t)?; }
+        }
+        tx.commit()?;
+        done += chunk.len();
+        reporter.report("finalizing", done, total, &format!("Indexing code: {}/{}", done, total));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_trigrams() {
+        let t = required_trigrams("GetWorld\\(\\)").unwrap();
+        let mut expected = extract_trigrams(b"GetWorld()");
+        expected.sort();
+        let mut t_sorted = t.clone();
+        t_sorted.sort();
+        assert_eq!(t_sorted, expected);
+
+        // `d?` は省略可能なので "Worl" までしか必須にならない
+        let t = required_trigrams("Worl(d)?").unwrap();
+        assert_eq!(t.len(), 2);
+        assert!(required_trigrams("Foo|Bar").is_none());
+        assert!(required_trigrams("a.b").is_none());
+        assert!(required_trigrams("[A-Z]+Component").is_some());
+    }
+}
@@ -0,0 +1,468 @@
+//! Shared engine index.
+//!
+//! Engine rows live in one DB per engine root + revision (`engine-<hash>.db` next
+//! to the project DBs).  A project seeded from it copies only the tables every row
+//! refers to (strings, directories, components, modules); the rest stays in the
+//! shared DB, which query connections `ATTACH` and read through
+//! `main UNION ALL engine_cache` views.  Game rows get ids above the engine's.
+
+use std::path::{Path, PathBuf};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Sha256, Digest};
+use super::DB_VERSION;
+
+const SCHEMA: &str = "engine_cache";
+
+/// project_meta: 読み込んでいる共有 DB のパス
+const ATTACHED_KEY: &str = "engine_cache_path";
+
+/// 共有 DB の id をそのまま使うため、プロジェクト DB にもコピーするテーブル (コピー順)
+const KEY_TABLES: &[&str] = &["strings", "directories", "components", "modules"];
+
+/// 共有 DB にだけ置き、読み取り時に `main` との和集合を見せるテーブル
+const SHARED_TABLES: &[&str] = &[
+    "files", "classes", "members", "enum_values", "inheritance", "symbols_fts", "symbol_calls",
+    "file_includes", "shader_bindings", "shader_mappings", "symbol_refs", "code_trigrams", "code_index_files",
+];
+
+/// `<db_dir>/engine-<hash>.db`
+pub fn cache_path(db_path: &str, engine_root: &str, revision: &str) -> PathBuf {
+    let key = format!("{}\n{}", engine_root.replace('\\', "/").trim_end_matches('/').to_lowercase(), revision);
+    let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+    let dir = Path::new(db_path).parent().unwrap_or(Path::new("."));
+    dir.join(format!("engine-{}.db", &hash[..16]))
+}
+
+/// 現在の DB_VERSION で作られた共有 DB が存在するか
+pub fn is_valid(cache: &Path) -> bool {
+    cache.exists() && read_meta(cache, "db_version").and_then(|v| v.parse::<i32>().ok()) == Some(DB_VERSION)
+}
+
+fn read_meta(cache: &Path, key: &str) -> Option<String> {
+    let c = Connection::open(cache).ok()?;
+    c.query_row("SELECT value FROM project_meta WHERE key = ?", [key], |r| r.get(0)).optional().ok().flatten()
+}
+
+/// プロジェクト DB が読み込んでいる共有 DB
+pub fn attached_path(conn: &Connection) -> Option<PathBuf> {
+    conn.query_row("SELECT value FROM project_meta WHERE key = ?", [ATTACHED_KEY], |r| r.get::<_, String>(0))
+        .ok()
+        .map(PathBuf::from)
+}
+
+fn is_empty(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT NOT EXISTS (SELECT 1 FROM files) AND NOT EXISTS (SELECT 1 FROM strings)", [], |r| r.get(0))
+}
+
+fn is_attached(conn: &Connection) -> bool {
+    conn.query_row("SELECT 1 FROM pragma_database_list WHERE name = ?", [SCHEMA], |_| Ok(())).is_ok()
+}
+
+/// `seed` が共有 DB を取り込むか (dry run 用)。DB がまだなければ `None`
+pub fn would_seed(conn: Option<&Connection>, cache: &Path) -> bool {
+    is_valid(cache) && conn.is_none_or(|c| match attached_path(c) {
+        Some(current) => current != cache,
+        None => is_empty(c).unwrap_or(false),
+    })
+}
+
+/// リフレッシュの最初に呼ぶ。別のリビジョンの共有 DB を読んでいた (または共有 DB が
+/// 使えなくなった) プロジェクト DB は空にし、`cache` があれば取り込む。取り込んだ場合は true。
+pub fn prepare(conn: &Connection, cache: Option<&Path>, allow_seed: bool) -> anyhow::Result<bool> {
+    if let Some(current) = attached_path(conn) {
+        if cache != Some(current.as_path()) || !is_valid(&current) {
+            // ゲーム側の行は古い共有 DB の id を参照しているので作り直す
+            tracing::info!("Shared engine index {} no longer applies; rebuilding the project index", current.display());
+            reset(conn)?;
+        }
+    }
+    match cache {
+        Some(cache) if allow_seed => seed(conn, cache),
+        _ => Ok(false),
+    }
+}
+
+/// db_version 以外を全て消す
+fn reset(conn: &Connection) -> rusqlite::Result<()> {
+    let tables: Vec<String> = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+         AND name NOT LIKE 'symbols\\_fts\\_%' ESCAPE '\\' AND name != 'project_meta'",
+    )?.query_map([], |r| r.get(0))?.collect::<Result<_, _>>()?;
+    let tx = conn.unchecked_transaction()?;
+    // 全テーブルを消し終えるまで FK の検査を遅らせる
+    tx.execute("PRAGMA defer_foreign_keys = ON", [])?;
+    for table in &tables {
+        tx.execute(&format!("DELETE FROM {}", table), [])?;
+    }
+    tx.execute_batch(
+        "DELETE FROM sqlite_sequence;
+         DELETE FROM project_meta WHERE key != 'db_version';",
+    )?;
+    tx.commit()
+}
+
+/// 空のプロジェクト DB を共有エンジンインデックスから始める。
+/// 取り込んだ場合は true (`vcs_engine_revision` も書き込まれる)。
+pub fn seed(conn: &Connection, cache: &Path) -> anyhow::Result<bool> {
+    if !is_valid(cache) || !is_empty(conn)? { return Ok(false); }
+
+    conn.execute("ATTACH DATABASE ? AS engine_cache", [cache.to_string_lossy()])?;
+    let result = (|| -> anyhow::Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM components", [])?;
+        for table in KEY_TABLES {
+            copy_rows(&tx, SCHEMA, "main", table, "1", [])?;
+        }
+        // 親より先に子がコピーされた行はトリガーでパスが作れないので作り直す
+        super::path::rebuild_dir_paths(&tx)?;
+        // ゲーム側の行は共有 DB の最大 id の後ろから採番する (ビューで和集合を取っても id が重ならない)
+        for table in SHARED_TABLES {
+            tx.execute("DELETE FROM main.sqlite_sequence WHERE name = ?", [table])?;
+            tx.execute(
+                "INSERT INTO main.sqlite_sequence (name, seq) SELECT name, seq FROM engine_cache.sqlite_sequence WHERE name = ?",
+                [table],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO main.project_meta (key, value)
+             SELECT CASE key WHEN 'engine_revision' THEN 'vcs_engine_revision' ELSE key END, value
+             FROM engine_cache.project_meta WHERE key = 'engine_revision' OR key LIKE 'ue_version_%'",
+            [],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO main.project_meta (key, value) VALUES (?, ?)",
+            params![ATTACHED_KEY, cache.to_string_lossy()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })();
+    conn.execute("DETACH DATABASE engine_cache", [])?;
+    result?;
+    tracing::info!("Using shared engine index: {}", cache.display());
+    Ok(true)
+}
+
+/// プロジェクト DB が共有 DB を使っていれば `engine_cache` として ATTACH する。
+/// 使える状態なら true (既に ATTACH 済みの場合も含む)。トランザクションの外で呼ぶこと
+pub fn attach(conn: &Connection) -> rusqlite::Result<bool> {
+    if is_attached(conn) { return Ok(true); }
+    let Some(cache) = attached_path(conn) else { return Ok(false) };
+    if !is_valid(&cache) {
+        tracing::warn!("Shared engine index is missing or outdated, engine symbols are unavailable until the next refresh: {}", cache.display());
+        return Ok(false);
+    }
+    conn.execute("ATTACH DATABASE ? AS engine_cache", [cache.to_string_lossy()])?;
+    Ok(true)
+}
+
+pub fn detach(conn: &Connection) -> rusqlite::Result<()> {
+    if is_attached(conn) {
+        conn.execute("DETACH DATABASE engine_cache", [])?;
+    }
+    Ok(())
+}
+
+/// 読み取り用の接続で共有 DB を ATTACH し、`SHARED_TABLES` を同名の一時ビュー
+/// (`main` と `engine_cache` の UNION ALL) で覆う。以降のクエリはそのままエンジン側も読む。
+/// 一時ビューがあると書き込みが通らないので、書き込む接続では呼ばないこと
+pub fn attach_views(conn: &Connection) -> rusqlite::Result<bool> {
+    if !attach(conn)? { return Ok(false); }
+    for table in SHARED_TABLES {
+        let main_cols = columns(conn, "main", table)?;
+        let shared_cols = columns(conn, SCHEMA, table)?;
+        // FTS5 の MATCH はテーブル名の隠し列に対して書くので、ビューにも出しておく
+        let hidden = if *table == "symbols_fts" { "symbols_fts, " } else { "" };
+        let shared_list = main_cols.iter()
+            .map(|c| if shared_cols.contains(c) { c.clone() } else { format!("NULL AS {}", c) })
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute_batch(&format!(
+            "CREATE TEMP VIEW IF NOT EXISTS {t} AS
+             SELECT {h}{main} FROM main.{t} UNION ALL SELECT {h}{shared} FROM engine_cache.{t};",
+            t = table, h = hidden, main = main_cols.join(", "), shared = shared_list,
+        ))?;
+    }
+    Ok(true)
+}
+
+/// ゲーム側の行から共有 DB の行への参照 (親クラス・include 先) を解決する。
+/// `main` の FK は共有 DB の行を知らないので、この更新の間だけ外す。トランザクションの外で呼ぶこと
+pub(crate) fn link(conn: &Connection) -> anyhow::Result<()> {
+    let was_attached = is_attached(conn);
+    if !attach(conn)? { return Ok(()); }
+    let fk: bool = conn.query_row("PRAGMA foreign_keys", [], |r| r.get(0))?;
+    if fk { conn.execute_batch("PRAGMA foreign_keys = OFF")?; }
+    let result = link_attached(conn);
+    if fk { conn.execute_batch("PRAGMA foreign_keys = ON")?; }
+    if !was_attached { detach(conn)?; }
+    result
+}
+
+fn link_attached(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE main.inheritance SET parent_class_id = (
+             SELECT c.id FROM engine_cache.classes c WHERE c.name_id = inheritance.parent_name_id LIMIT 1
+         ) WHERE parent_class_id IS NULL",
+        [],
+    )?;
+    // ゲーム側に同名のファイルがなく、エンジン側で一意な場合だけ (resolve_file_includes_by_path と同じ規則)
+    conn.execute(
+        "UPDATE main.file_includes SET resolved_file_id = (
+             SELECT f.id FROM engine_cache.files f WHERE f.filename_id = file_includes.base_filename_id
+         )
+         WHERE resolved_file_id IS NULL
+           AND NOT EXISTS (SELECT 1 FROM main.files f WHERE f.filename_id = file_includes.base_filename_id)
+           AND (SELECT COUNT(*) FROM engine_cache.files f WHERE f.filename_id = file_includes.base_filename_id) = 1",
+        [],
+    )?;
+
+    // `#include "/Engine/..."` (resolve_shader_includes のエンジン側)
+    let mappings: Vec<(String, String)> = conn.prepare(
+        "SELECT virtual_path, real_path FROM main.shader_mappings UNION ALL SELECT virtual_path, real_path FROM engine_cache.shader_mappings",
+    )?.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<Result<_, _>>()?;
+    let pending: Vec<(i64, String)> = conn.prepare(
+        "SELECT fi.rowid, s.text FROM main.file_includes fi JOIN main.strings s ON fi.include_path_id = s.id
+         WHERE fi.resolved_file_id IS NULL AND s.text LIKE '/%'",
+    )?.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<Result<_, _>>()?;
+    for (rowid, virtual_path) in pending {
+        let Some(real) = crate::parser::shader::resolve_virtual_path(&virtual_path, &mappings) else { continue };
+        let real = Path::new(&real);
+        let (Some(parent), Some(name)) = (real.parent(), real.file_name().and_then(|n| n.to_str())) else { continue };
+        let Some(dir_id) = super::path::find_directory_id(conn, parent)? else { continue };
+        let file_id: Option<i64> = conn.query_row(
+            "SELECT f.id FROM engine_cache.files f JOIN main.strings s ON f.filename_id = s.id WHERE f.directory_id = ? AND s.text = ?",
+            params![dir_id, name], |r| r.get(0),
+        ).optional()?;
+        if let Some(file_id) = file_id {
+            conn.execute("UPDATE main.file_includes SET resolved_file_id = ? WHERE rowid = ?", params![file_id, rowid])?;
+        }
+    }
+    Ok(())
+}
+
+/// ゲーム側の行から共有 DB の行への参照の数 (ATTACH 済みの接続で呼ぶ)
+pub(crate) fn link_count(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM main.inheritance WHERE parent_class_id IN (SELECT id FROM engine_cache.classes))
+              + (SELECT COUNT(*) FROM main.file_includes WHERE resolved_file_id IN (SELECT id FROM engine_cache.files))",
+        [], |r| r.get::<_, i64>(0),
+    ).map(|n| n as usize)
+}
+
+/// 共有 DB から読んでいる行をプロジェクト DB (のコピー) に書き込み、単独で使える DB にする。
+/// id は共有 DB のものをそのまま使うので、ゲーム側からの参照も保たれる。スナップショットの書き出し用
+pub fn materialize(conn: &Connection) -> anyhow::Result<()> {
+    let Some(cache) = attached_path(conn) else { return Ok(()) };
+    if !attach(conn)? {
+        anyhow::bail!("Shared engine index is missing or outdated ({}); run a refresh first", cache.display());
+    }
+    let result = (|| -> anyhow::Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        for table in SHARED_TABLES {
+            copy_rows(&tx, SCHEMA, "main", table, "1", [])?;
+        }
+        tx.execute("DELETE FROM main.project_meta WHERE key = ?", [ATTACHED_KEY])?;
+        tx.commit()?;
+        Ok(())
+    })();
+    detach(conn)?;
+    result
+}
+
+/// エンジンルート配下の行を共有 DB に書き出す。一時ファイルに作ってから置き換える。
+pub fn publish(conn: &Connection, cache: &Path, engine_root: &Path, engine_name: &str, revision: &str) -> anyhow::Result<()> {
+    let Some(root_dir_id) = super::path::find_directory_id(conn, engine_root)? else { return Ok(()) };
+    let tmp = cache.with_extension("db.tmp");
+    let _ = std::fs::remove_file(&tmp);
+    super::init_db(&Connection::open(&tmp)?)?;
+
+    conn.execute("ATTACH DATABASE ? AS engine_cache", [tmp.to_string_lossy()])?;
+    let result = (|| -> anyhow::Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        // strings は最後にコピーするので、それまで FK の検査を遅らせる
+        tx.execute("PRAGMA defer_foreign_keys = ON", [])?;
+        tx.execute_batch(
+            "DROP TABLE IF EXISTS temp.ec_dirs;
+             DROP TABLE IF EXISTS temp.ec_path;
+             DROP TABLE IF EXISTS temp.ec_files;
+             DROP TABLE IF EXISTS temp.ec_classes;
+             DROP TABLE IF EXISTS temp.ec_members;",
+        )?;
+        tx.execute(
+            "CREATE TEMP TABLE ec_dirs AS
+             WITH RECURSIVE sub(id) AS (SELECT ? UNION ALL SELECT d.id FROM main.directories d JOIN sub ON d.parent_id = sub.id)
+             SELECT id FROM sub",
+            [root_dir_id],
+        )?;
+        // エンジンルートまでの祖先 (パスの組み立てに必要)。兄弟ディレクトリは含めない
+        tx.execute(
+            "CREATE TEMP TABLE ec_path AS
+             WITH RECURSIVE up(id, parent_id) AS (
+                 SELECT id, parent_id FROM main.directories WHERE id = ?
+                 UNION ALL SELECT d.id, d.parent_id FROM main.directories d JOIN up ON d.id = up.parent_id)
+             SELECT id FROM up",
+            [root_dir_id],
+        )?;
+        tx.execute_batch(
+            "CREATE TEMP TABLE ec_files AS SELECT id FROM main.files WHERE directory_id IN temp.ec_dirs;
+             CREATE TEMP TABLE ec_classes AS SELECT id FROM main.classes WHERE file_id IN temp.ec_files;
+             CREATE TEMP TABLE ec_members AS SELECT id FROM main.members WHERE class_id IN temp.ec_classes;",
+        )?;
+
+        // id はそのまま保つ。ゲーム側のディレクトリ・名前は持ち出さない
+        copy_rows(&tx, "main", SCHEMA, "directories", "id IN temp.ec_dirs OR id IN temp.ec_path", [])?;
+        copy_rows(&tx, "main", SCHEMA, "components", "owner_name = ?1", [engine_name])?;
+        copy_rows(&tx, "main", SCHEMA, "modules", "owner_name = ?1", [engine_name])?;
+        copy_rows(&tx, "main", SCHEMA, "files", "id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "classes", "id IN temp.ec_classes", [])?;
+        copy_rows(&tx, "main", SCHEMA, "members", "id IN temp.ec_members", [])?;
+        copy_rows(&tx, "main", SCHEMA, "enum_values", "enum_id IN temp.ec_classes", [])?;
+        copy_rows(&tx, "main", SCHEMA, "inheritance", "child_id IN temp.ec_classes", [])?;
+        // symbols_fts.rowid_ref は classes / members のどちらの id か区別がないので type も照合する
+        copy_rows(&tx, "main", SCHEMA, "symbols_fts",
+            "EXISTS (SELECT 1 FROM main.classes c WHERE c.id = symbols_fts.rowid_ref AND c.id IN temp.ec_classes AND c.symbol_type = symbols_fts.type)
+             OR EXISTS (SELECT 1 FROM main.members m JOIN main.strings s ON m.type_id = s.id
+                        WHERE m.id = symbols_fts.rowid_ref AND m.id IN temp.ec_members AND s.text = symbols_fts.type)", [])?;
+        copy_rows(&tx, "main", SCHEMA, "symbol_calls", "file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "file_includes", "file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "shader_bindings", "file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "symbol_refs", "file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "shader_mappings", "file_id IS NULL OR file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "code_trigrams", "file_id IN temp.ec_files", [])?;
+        copy_rows(&tx, "main", SCHEMA, "code_index_files", "file_id IN temp.ec_files", [])?;
+
+        // ゲーム側を指している参照を外す
+        tx.execute_batch(
+            "UPDATE engine_cache.files SET module_id = NULL WHERE module_id NOT IN (SELECT id FROM engine_cache.modules);
+             UPDATE engine_cache.inheritance SET parent_class_id = NULL WHERE parent_class_id NOT IN (SELECT id FROM engine_cache.classes);
+             UPDATE engine_cache.file_includes SET resolved_file_id = NULL WHERE resolved_file_id NOT IN (SELECT id FROM engine_cache.files);
+             DELETE FROM engine_cache.project_meta;",
+        )?;
+        let referenced = super::maintain::STRING_REFS.iter()
+            .map(|(t, c)| format!("SELECT {c} FROM engine_cache.{t} WHERE {c} IS NOT NULL", t = t, c = c))
+            .collect::<Vec<_>>()
+            .join(" UNION ");
+        copy_rows(&tx, "main", SCHEMA, "strings", &format!("id IN ({})", referenced), [])?;
+
+        let root_str = engine_root.to_string_lossy().replace('\\', "/");
+        for (k, v) in [
+            ("db_version", DB_VERSION.to_string()),
+            ("engine_revision", revision.to_string()),
+            ("engine_root", root_str),
+            ("engine_name", engine_name.to_string()),
+        ] {
+            tx.execute("INSERT INTO engine_cache.project_meta (key, value) VALUES (?, ?)", params![k, v])?;
+        }
+        tx.execute(
+            "INSERT INTO engine_cache.project_meta (key, value) SELECT key, value FROM main.project_meta WHERE key LIKE 'ue_version_%'",
+            [],
+        )?;
+        tx.execute_batch(
+            "DROP TABLE temp.ec_dirs; DROP TABLE temp.ec_path; DROP TABLE temp.ec_files; DROP TABLE temp.ec_classes; DROP TABLE temp.ec_members;",
+        )?;
+        tx.commit()?;
+        Ok(())
+    })();
+    conn.execute("DETACH DATABASE engine_cache", [])?;
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, cache)?;
+    tracing::info!("Published shared engine index ({}): {}", revision, cache.display());
+    Ok(())
+}
+
+fn columns(conn: &Connection, schema: &str, table: &str) -> rusqlite::Result<Vec<String>> {
+    conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect()
+}
+
+/// 両スキーマに存在する列だけを id ごとコピーする (移行済み DB では列順が異なるため)
+fn copy_rows<P: rusqlite::Params>(conn: &Connection, from: &str, to: &str, table: &str, filter: &str, params: P) -> rusqlite::Result<usize> {
+    let src = columns(conn, from, table)?;
+    let cols: Vec<String> = columns(conn, to, table)?.into_iter().filter(|c| src.contains(c)).collect();
+    if cols.is_empty() { return Ok(0); }
+    let list = cols.join(", ");
+    conn.execute(
+        &format!("INSERT INTO {to}.{table} ({list}) SELECT {list} FROM {from}.{table} WHERE {filter}"),
+        params,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_and_seed_engine_rows() {
+        let dir = std::env::temp_dir().join(format!("unl_engine_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let project_db = dir.join("project.db");
+        let cache = cache_path(&project_db.to_string_lossy(), "/UE/Engine5", "ue:5.4.0+release");
+
+        let conn = Connection::open(&project_db).unwrap();
+        super::super::init_db(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO strings (id, text) VALUES (1, '/'), (2, 'UE'), (3, 'Game'), (4, 'Actor.h'), (5, 'MyActor.h'), (6, 'AActor'), (7, 'AMyActor');
+             INSERT INTO directories (id, parent_id, name_id) VALUES (1, NULL, 1), (2, 1, 2), (3, 1, 3);
+             INSERT INTO files (id, directory_id, filename_id, extension, mtime) VALUES (1, 2, 4, 'h', 1), (2, 3, 5, 'h', 1);
+             INSERT INTO classes (id, name_id, file_id, symbol_type) VALUES (1, 6, 1, 'class'), (2, 7, 2, 'class');
+             INSERT INTO inheritance (child_id, parent_name_id, parent_class_id) VALUES (2, 6, 1);",
+        ).unwrap();
+        publish(&conn, &cache, Path::new("/UE"), "UE", "ue:5.4.0+release").unwrap();
+        assert!(is_valid(&cache));
+        // ゲーム側の名前・ディレクトリは共有 DB に入らない
+        let published = Connection::open(&cache).unwrap();
+        let strings: Vec<String> = published.prepare("SELECT text FROM strings ORDER BY id").unwrap()
+            .query_map([], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(strings, vec!["/", "UE", "Actor.h", "AActor"]);
+        let dirs: i64 = published.query_row("SELECT COUNT(*) FROM directories", [], |r| r.get(0)).unwrap();
+        assert_eq!(dirs, 2);
+        drop(published);
+
+        let fresh = Connection::open(dir.join("other.db")).unwrap();
+        super::super::init_db(&fresh).unwrap();
+        assert!(seed(&fresh, &cache).unwrap());
+        // 名前・ディレクトリだけコピーし、シンボルは共有 DB から読む
+        let main_classes: i64 = fresh.query_row("SELECT COUNT(*) FROM classes", [], |r| r.get(0)).unwrap();
+        assert_eq!(main_classes, 0);
+        let dir_path: String = fresh.query_row("SELECT full_path FROM dir_paths WHERE id = 2", [], |r| r.get(0)).unwrap();
+        assert_eq!(dir_path, "/UE");
+        assert_eq!(attached_path(&fresh).as_deref(), Some(cache.as_path()));
+        let rev: String = fresh.query_row("SELECT value FROM project_meta WHERE key = 'vcs_engine_revision'", [], |r| r.get(0)).unwrap();
+        assert_eq!(rev, "ue:5.4.0+release");
+        // 既にデータがある DB には取り込まない
+        assert!(!seed(&fresh, &cache).unwrap());
+
+        // ゲーム側の行は共有 DB の id の後ろから採番され、親クラスは link で共有 DB の行を指す
+        fresh.execute("INSERT INTO strings (text) VALUES ('AMyPawn')", []).unwrap();
+        let name_id = fresh.last_insert_rowid();
+        fresh.execute("INSERT INTO files (directory_id, filename_id, extension, mtime) VALUES (2, 4, 'h', 1)", []).unwrap();
+        let file_id = fresh.last_insert_rowid();
+        assert!(file_id > 1);
+        fresh.execute("INSERT INTO classes (name_id, file_id, symbol_type) VALUES (?, ?, 'class')", params![name_id, file_id]).unwrap();
+        let class_id = fresh.last_insert_rowid();
+        assert!(class_id > 1);
+        fresh.execute("INSERT INTO inheritance (child_id, parent_name_id) VALUES (?, 6)", [class_id]).unwrap();
+        link(&fresh).unwrap();
+        let parent: Option<i64> = fresh.query_row("SELECT parent_class_id FROM inheritance", [], |r| r.get(0)).unwrap();
+        assert_eq!(parent, Some(1));
+
+        let reader = Connection::open_with_flags(dir.join("other.db"), rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+        assert!(attach_views(&reader).unwrap());
+        let classes: Vec<String> = reader.prepare("SELECT s.text FROM classes c JOIN strings s ON c.name_id = s.id ORDER BY c.id").unwrap()
+            .query_map([], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(classes, vec!["AActor", "AMyPawn"]);
+        drop(reader);
+
+        // 別のリビジョンに切り替わったら作り直す
+        assert!(!prepare(&fresh, None, true).unwrap());
+        assert_eq!(attached_path(&fresh), None);
+        let left: i64 = fresh.query_row("SELECT COUNT(*) FROM classes", [], |r| r.get(0)).unwrap();
+        assert_eq!(left, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,202 @@
+//! Index maintenance (`maintain_index` RPC / `maintain-index` CLI).
+//!
+//! Watcher updates and incremental refreshes replace rows one file at a time
+//! and never shrink `strings` / `directories`, and deleted files leave
+//! `file_includes.resolved_file_id` pointing nowhere.  This pass checks the DB,
+//! removes everything that is no longer reachable, re-resolves includes,
+//! rebuilds `symbols_fts` from `classes` / `members` and compacts the file.
+
+use std::collections::BTreeMap;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// strings を参照している列 (table, column)
+pub(crate) const STRING_REFS: &[(&str, &str)] = &[
+    ("directories", "name_id"),
+    ("modules", "name_id"),
+    ("files", "filename_id"),
+    ("classes", "name_id"),
+    ("classes", "namespace_id"),
+    ("classes", "qualified_name_id"),
+    ("classes", "base_class_id"),
+    ("members", "name_id"),
+    ("members", "type_id"),
+    ("members", "return_type_id"),
+    ("enum_values", "name_id"),
+    ("inheritance", "parent_name_id"),
+    ("symbol_calls", "name_id"),
+    ("file_includes", "include_path_id"),
+    ("file_includes", "base_filename_id"),
+    ("shader_bindings", "class_name_id"),
+    ("symbol_refs", "name_id"),
+    ("symbol_refs", "scope_id"),
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct MaintenanceReport {
+    /// `PRAGMA integrity_check` の指摘 (問題なければ空)
+    pub integrity_errors: Vec<String>,
+    /// 掃除後に残った `PRAGMA foreign_key_check` の件数
+    pub foreign_key_violations: usize,
+    /// table → 削除した行数 (0 件のテーブルは含まない)
+    pub orphans_removed: BTreeMap<String, usize>,
+    /// 消えたファイルを指していた resolved_file_id / parent_class_id / parent_id
+    pub stale_links_cleared: usize,
+    pub includes_resolved: usize,
+    pub fts_rows_before: usize,
+    pub fts_rows_after: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+fn db_size(conn: &Connection) -> rusqlite::Result<u64> {
+    let pages: i64 = conn.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |r| r.get(0))?;
+    Ok((pages * page_size) as u64)
+}
+
+fn count(conn: &Connection, sql: &str) -> rusqlite::Result<usize> {
+    conn.query_row(sql, [], |r| r.get::<_, i64>(0)).map(|n| n as usize)
+}
+
+/// 整合性チェック・孤立行の削除・include の再解決・FTS の再構築・VACUUM / ANALYZE を行う
+pub fn maintain_index(conn: &mut Connection) -> anyhow::Result<MaintenanceReport> {
+    let mut report = MaintenanceReport { size_before: db_size(conn)?, ..Default::default() };
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    report.integrity_errors = stmt.query_map([], |r| r.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .filter(|m| m != "ok")
+        .collect();
+    drop(stmt);
+    if !report.integrity_errors.is_empty() {
+        tracing::warn!("Index integrity check reported {} problem(s)", report.integrity_errors.len());
+    }
+    report.fts_rows_before = count(conn, "SELECT COUNT(*) FROM symbols_fts")?;
+
+    // 共有エンジンインデックスを使う DB では、エンジン側の行が参照する strings / directories と
+    // エンジン側の行への参照は、プロジェクト DB からは参照されていないように見えても残す
+    let shared = super::engine_cache::attach(conn)?;
+    let unless_shared = |column: &str, table: &str| if shared {
+        format!(" AND {} NOT IN (SELECT id FROM engine_cache.{})", column, table)
+    } else {
+        String::new()
+    };
+
+    let tx = conn.transaction()?;
+    let mut removed: BTreeMap<String, usize> = BTreeMap::new();
+    let mut add = |table: &str, n: usize| if n > 0 { *removed.entry(table.to_string()).or_default() += n; };
+
+    // 親ディレクトリが消えたもの、何からも参照されない末端ディレクトリを消えなくなるまで繰り返す
+    loop {
+        let n = tx.execute(&format!(
+            "DELETE FROM directories WHERE
+                ((parent_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM directories p WHERE p.id = directories.parent_id))
+             OR (NOT EXISTS (SELECT 1 FROM directories c WHERE c.parent_id = directories.id)
+                 AND NOT EXISTS (SELECT 1 FROM files f WHERE f.directory_id = directories.id)
+                 AND NOT EXISTS (SELECT 1 FROM modules m WHERE m.root_directory_id = directories.id))){}",
+            unless_shared("id", "directories"),
+        ), [])?;
+        add("directories", n);
+        if n == 0 { break; }
+    }
+    for (table, n) in super::purge_orphaned_rows(&tx)? {
+        add(table, n);
+    }
+
+    report.stale_links_cleared = tx.execute(&format!(
+        "UPDATE file_includes SET resolved_file_id = NULL
+         WHERE resolved_file_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM files f WHERE f.id = file_includes.resolved_file_id){}",
+        unless_shared("resolved_file_id", "files"),
+    ), [])? + tx.execute(&format!(
+        "UPDATE inheritance SET parent_class_id = NULL
+         WHERE parent_class_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = inheritance.parent_class_id){}",
+        unless_shared("parent_class_id", "classes"),
+    ), [])? + tx.execute(
+        "UPDATE classes SET parent_id = NULL
+         WHERE parent_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = classes.parent_id)",
+        [],
+    )?;
+    tx.execute("UPDATE inheritance SET parent_class_id = (SELECT c.id FROM classes c WHERE c.name_id = inheritance.parent_name_id LIMIT 1) WHERE parent_class_id IS NULL", [])?;
+    report.includes_resolved = super::resolve_file_includes_by_path(&tx)?;
+
+    let referenced = STRING_REFS.iter()
+        .map(|(t, c)| format!("SELECT {c} FROM {t} WHERE {c} IS NOT NULL", t = t, c = c))
+        .collect::<Vec<_>>()
+        .join(" UNION ");
+    add("strings", tx.execute(&format!("DELETE FROM strings WHERE id NOT IN ({}){}", referenced, unless_shared("id", "strings")), [])?);
+
+    // symbols_fts は FK を持たないので作り直す (save_to_db と同じ列)
+    tx.execute_batch(&format!(
+        "DELETE FROM symbols_fts;
+         INSERT INTO symbols_fts (name, type, class_name, rowid_ref)
+             SELECT sc.text, c.symbol_type, sc.text, c.id FROM classes c JOIN strings sc ON c.name_id = sc.id;
+         INSERT INTO symbols_fts (name, type, class_name, rowid_ref)
+             SELECT sm.text, st.text, sc.text, m.id FROM members m
+             JOIN strings sm ON m.name_id = sm.id
+             JOIN strings st ON m.type_id = st.id
+             JOIN classes c ON m.class_id = c.id
+             JOIN strings sc ON c.name_id = sc.id
+             WHERE {};", super::not_operator_sql("sm.text")
+    ))?;
+    tx.commit()?;
+    report.orphans_removed = removed;
+    report.fts_rows_after = count(conn, "SELECT COUNT(*) FROM symbols_fts")?;
+
+    if shared { super::engine_cache::link(conn)?; }
+
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    report.foreign_key_violations = stmt.query_map([], |_| Ok(()))?.count();
+    drop(stmt);
+    if shared {
+        // エンジン側の行への参照は main の FK からは違反に見える
+        report.foreign_key_violations = report.foreign_key_violations.saturating_sub(super::engine_cache::link_count(conn)?);
+        super::engine_cache::detach(conn)?;
+    }
+
+    conn.execute_batch("ANALYZE; VACUUM;")?;
+    report.size_after = db_size(conn)?;
+    tracing::info!(
+        "Index maintenance: removed {:?}, cleared {} stale link(s), resolved {} include(s), {} -> {} bytes",
+        report.orphans_removed, report.stale_links_cleared, report.includes_resolved, report.size_before, report.size_after
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[test]
+    fn test_maintain_removes_orphans() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        super::super::init_db(&conn).unwrap();
+        // 孤立行を作るため FK を切って投入する
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO strings (id, text) VALUES (1, 'Src'), (2, 'A.h'), (3, 'Gone'), (4, 'AActor'), (5, 'Unused');
+             INSERT INTO directories (id, parent_id, name_id) VALUES (1, NULL, 1), (2, NULL, 3);
+             INSERT INTO files (id, directory_id, filename_id, extension) VALUES (1, 1, 2, 'h');
+             INSERT INTO classes (id, name_id, file_id, line_number) VALUES (1, 4, 1, 1), (2, 4, 99, 1);
+             INSERT INTO file_includes (file_id, include_path_id, base_filename_id, resolved_file_id) VALUES (1, 2, 2, 42);
+             INSERT INTO symbols_fts (name, type, class_name, rowid_ref) VALUES ('Stale', 'class', 'Stale', 77);
+             PRAGMA foreign_keys = ON;"
+        ).unwrap();
+
+        let report = maintain_index(&mut conn).unwrap();
+        assert!(report.integrity_errors.is_empty());
+        assert_eq!(report.orphans_removed.get("classes"), Some(&1));
+        assert_eq!(report.orphans_removed.get("directories"), Some(&1));
+        // 'Gone' (消えたディレクトリ名) と 'Unused'
+        assert_eq!(report.orphans_removed.get("strings"), Some(&2));
+        assert_eq!(report.stale_links_cleared, 1);
+        // A.h は一意なので自分自身に解決される
+        assert_eq!(report.includes_resolved, 1);
+        assert_eq!((report.fts_rows_before, report.fts_rows_after), (1, 1));
+        let fts_ref: i64 = conn.query_row("SELECT rowid_ref FROM symbols_fts", [], |r| r.get(0)).unwrap();
+        assert_eq!(fts_ref, 1);
+        let strings: i64 = conn.query_row("SELECT COUNT(*) FROM strings WHERE id = ?", params![4], |r| r.get(0)).unwrap();
+        assert_eq!(strings, 1);
+    }
+}
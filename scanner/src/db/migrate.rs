@@ -0,0 +1,297 @@
+//! Incremental schema migrations.
+//!
+//! Each step upgrades the schema by one `DB_VERSION`.  Steps only ALTER existing
+//! tables and backfill what can be derived from rows already in the DB; new
+//! tables and indices are created afterwards by `init_db` (everything there is
+//! `IF NOT EXISTS`).  When a column can only be filled by parsing the sources
+//! again, the step lists the affected extensions: those files get `mtime = 0`
+//! so the next refresh re-parses them in place instead of rebuilding the DB.
+//!
+//! Applied steps are recorded in `project_meta` as `migration_v<N>`.
+
+use std::collections::HashSet;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+/// これより古い DB は移行手順がないため作り直す
+pub const MIN_MIGRATABLE_VERSION: i32 = 25;
+
+/// 再パースが必要な拡張子 (カンマ区切り) を保持する project_meta のキー
+pub const REINDEX_PENDING_KEY: &str = "reindex_pending";
+
+struct Migration {
+    version: i32,
+    description: &'static str,
+    apply: fn(&Connection) -> rusqlite::Result<()>,
+    /// 既存行を埋めるのに再パースが必要なファイルの拡張子
+    reparse: &'static [&'static str],
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 26,
+        description: "shader_bindings / shader_mappings tables",
+        apply: |_| Ok(()),
+        reparse: &["cpp"],
+    },
+    Migration {
+        version: 27,
+        description: "members.specifiers",
+        apply: |conn| add_column(conn, "members", "specifiers", "TEXT"),
+        reparse: &["h", "hpp", "inl"],
+    },
+    Migration {
+        version: 28,
+        description: "classes.language",
+        apply: migrate_v28,
+        reparse: &[],
+    },
+    Migration {
+        version: 29,
+        description: "classes.qualified_name_id / classes.parent_id",
+        apply: migrate_v29,
+        reparse: &[],
+    },
+    Migration {
+        version: 30,
+        description: "template parameters and specializations",
+        apply: |conn| {
+            add_column(conn, "classes", "template_params", "TEXT")?;
+            add_column(conn, "classes", "specialization_args", "TEXT")?;
+            add_column(conn, "members", "template_params", "TEXT")
+        },
+        reparse: &["h", "hpp", "inl"],
+    },
+    Migration {
+        version: 31,
+        description: "symbol_refs table",
+        apply: |_| Ok(()),
+        reparse: &["h", "hpp", "inl", "cpp"],
+    },
+    Migration {
+        version: 32,
+        description: "code_trigrams / code_index_files tables",
+        apply: |_| Ok(()),
+        reparse: &[],
+    },
+    Migration {
+        version: 33,
+        description: "dir_paths table",
+        apply: |_| Ok(()),
+        reparse: &[],
+    },
+    Migration {
+        version: 34,
+        description: "components.enabled",
+        apply: |conn| add_column(conn, "components", "enabled", "INTEGER NOT NULL DEFAULT 1"),
+        reparse: &[],
+    },
+];
+
+/// 移行の結果
+pub enum MigrationOutcome {
+    /// `from` から `DB_VERSION` まで移行した。再パースが必要なら true
+    Migrated { reparse: bool },
+    /// 移行できない (古すぎる / 新しすぎる / 途中で失敗した)。作り直しが必要
+    Unsupported,
+}
+
+/// `from` → `target` のスキーマ移行を 1 トランザクションで適用する。
+pub fn migrate(conn: &mut Connection, from: i32, target: i32) -> anyhow::Result<MigrationOutcome> {
+    let steps: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > from && m.version <= target).collect();
+    let covers_all = from >= MIN_MIGRATABLE_VERSION
+        && from < target
+        && steps.len() == (target - from) as usize;
+    if !covers_all {
+        return Ok(MigrationOutcome::Unsupported);
+    }
+
+    let tx = conn.transaction()?;
+    let mut reparse: Vec<&str> = Vec::new();
+    let mut prev = from;
+    for step in &steps {
+        tracing::info!("DB migration v{} -> v{}: {}", prev, step.version, step.description);
+        (step.apply)(&tx)?;
+        for ext in step.reparse {
+            if !reparse.contains(ext) { reparse.push(ext); }
+        }
+        let record = json!({
+            "from": prev,
+            "description": step.description,
+            "applied_at": now_secs(),
+            "reparse": step.reparse,
+        });
+        tx.execute(
+            "INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)",
+            params![format!("migration_v{}", step.version), record.to_string()],
+        )?;
+        prev = step.version;
+    }
+
+    if !reparse.is_empty() {
+        mark_for_reparse(&tx, &reparse)?;
+    }
+    tx.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (\"db_version\", ?)", [target.to_string()])?;
+    tx.commit()?;
+    Ok(MigrationOutcome::Migrated { reparse: !reparse.is_empty() })
+}
+
+/// 前回の移行で再パース待ちのファイルがあるか
+pub fn reindex_pending(conn: &Connection) -> bool {
+    conn.query_row("SELECT 1 FROM project_meta WHERE key = ?", [REINDEX_PENDING_KEY], |_| Ok(()))
+        .optional()
+        .ok()
+        .flatten()
+        .is_some()
+}
+
+/// 再パース待ちの拡張子。`mtime = 0` でもこれ以外の拡張子のファイル
+/// (スナップショットの取り込み直後など) は内容のハッシュで判断してよい
+pub fn pending_extensions(conn: &Connection) -> HashSet<String> {
+    conn.query_row("SELECT value FROM project_meta WHERE key = ?", [REINDEX_PENDING_KEY], |r| r.get::<_, String>(0))
+        .optional()
+        .ok()
+        .flatten()
+        .map(|v| v.split(',').filter(|s| !s.is_empty()).map(|s| s.to_lowercase()).collect())
+        .unwrap_or_default()
+}
+
+/// 再パースが完了したら呼ぶ
+pub fn clear_reindex_pending(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM project_meta WHERE key = ?", [REINDEX_PENDING_KEY])?;
+    Ok(())
+}
+
+fn mark_for_reparse(conn: &Connection, extensions: &[&str]) -> rusqlite::Result<()> {
+    let existing: Option<String> = conn
+        .query_row("SELECT value FROM project_meta WHERE key = ?", [REINDEX_PENDING_KEY], |r| r.get(0))
+        .optional()?;
+    let mut all: Vec<String> = existing
+        .map(|v| v.split(',').filter(|s| !s.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    for ext in extensions {
+        if !all.iter().any(|e| e == ext) { all.push(ext.to_string()); }
+    }
+    let placeholders = vec!["?"; extensions.len()].join(",");
+    let updated = conn.execute(
+        &format!("UPDATE files SET mtime = 0 WHERE extension IN ({})", placeholders),
+        rusqlite::params_from_iter(extensions.iter()),
+    )?;
+    tracing::info!("Marked {} file(s) ({}) for re-parse", updated, extensions.join(", "));
+    conn.execute(
+        "INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)",
+        params![REINDEX_PENDING_KEY, all.join(",")],
+    )?;
+    Ok(())
+}
+
+/// 既に列がある場合 (途中まで適用された DB など) は何もしない
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let exists = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
+fn migrate_v28(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "classes", "language", "TEXT DEFAULT \"cpp\"")?;
+    conn.execute(
+        "UPDATE classes SET language = 'hlsl'
+         WHERE file_id IN (SELECT id FROM files WHERE extension IN ('usf', 'ush'))",
+        [],
+    )?;
+    Ok(())
+}
+
+/// 完全修飾名は namespace + name から、外側の型は同一ファイル内の完全修飾名一致から復元する
+fn migrate_v29(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "classes", "qualified_name_id", "INTEGER")?;
+    add_column(conn, "classes", "parent_id", "INTEGER")?;
+
+    conn.execute("UPDATE classes SET qualified_name_id = name_id WHERE namespace_id IS NULL", [])?;
+    let rows: Vec<(i64, String, String, Option<String>)> = conn
+        .prepare(
+            "SELECT c.id, sn.text, sc.text, c.language FROM classes c
+             JOIN strings sn ON c.namespace_id = sn.id
+             JOIN strings sc ON c.name_id = sc.id",
+        )?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    {
+        let mut insert_string = conn.prepare_cached("INSERT OR IGNORE INTO strings (text) VALUES (?)")?;
+        let mut update = conn.prepare_cached(
+            "UPDATE classes SET qualified_name_id = (SELECT id FROM strings WHERE text = ?) WHERE id = ?",
+        )?;
+        for (id, ns, name, language) in rows {
+            let sep = if language.as_deref() == Some("verse") { "." } else { "::" };
+            let qualified = if name.starts_with(&format!("{}{}", ns, sep)) { name } else { format!("{}{}{}", ns, sep, name) };
+            insert_string.execute([&qualified])?;
+            update.execute(params![qualified, id])?;
+        }
+    }
+
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_classes_qualified_name ON classes(qualified_name_id)", [])?;
+    conn.execute(
+        "UPDATE classes SET parent_id = (
+             SELECT p.id FROM classes p
+             WHERE p.qualified_name_id = classes.namespace_id
+               AND p.file_id = classes.file_id
+               AND p.id != classes.id
+               AND p.symbol_type != 'namespace'
+             LIMIT 1)
+         WHERE namespace_id IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i32> = (MIN_MIGRATABLE_VERSION + 1..=crate::db::DB_VERSION).collect();
+        assert_eq!(versions, expected, "every DB_VERSION bump needs a migration step");
+    }
+
+    #[test]
+    fn test_v29_backfills_qualified_names() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE project_meta (key TEXT PRIMARY KEY, value TEXT);
+             CREATE TABLE strings (id INTEGER PRIMARY KEY AUTOINCREMENT, text TEXT NOT NULL UNIQUE);
+             CREATE TABLE files (id INTEGER PRIMARY KEY, extension TEXT, mtime INTEGER);
+             CREATE TABLE classes (id INTEGER PRIMARY KEY AUTOINCREMENT, name_id INTEGER, namespace_id INTEGER,
+                                   file_id INTEGER, symbol_type TEXT, language TEXT DEFAULT 'cpp');
+             INSERT INTO strings (text) VALUES ('FOuter'), ('FInner');
+             INSERT INTO files VALUES (1, 'h', 123);
+             INSERT INTO classes (name_id, namespace_id, file_id, symbol_type) VALUES (1, NULL, 1, 'struct'), (2, 1, 1, 'struct');",
+        ).unwrap();
+
+        let outcome = migrate(&mut conn, 28, 29).unwrap();
+        assert!(matches!(outcome, MigrationOutcome::Migrated { reparse: false }));
+
+        let (qualified, parent): (String, Option<i64>) = conn.query_row(
+            "SELECT s.text, c.parent_id FROM classes c JOIN strings s ON c.qualified_name_id = s.id WHERE c.id = 2",
+            [], |r| Ok((r.get(0)?, r.get(1)?)),
+        ).unwrap();
+        assert_eq!((qualified.as_str(), parent), ("FOuter::FInner", Some(1)));
+        assert!(conn.query_row("SELECT 1 FROM project_meta WHERE key = 'migration_v29'", [], |_| Ok(())).is_ok());
+        assert!(matches!(migrate(&mut conn, 20, 29).unwrap(), MigrationOutcome::Unsupported));
+    }
+}
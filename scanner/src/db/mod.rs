@@ -1,16 +1,25 @@
+pub mod code_index;
+pub mod engine_cache;
+pub mod maintain;
+pub mod migrate;
 pub mod path;
+pub mod snapshot;
 
 use std::path::Path;
 use std::sync::Arc;
+use std::borrow::Borrow;
 use std::collections::HashMap;
 use rusqlite::{params, Connection, OptionalExtension};
 use crate::types::{ParseResult, ProgressReporter};
+use crate::parser::cpp::is_operator_name;
 
-pub const DB_VERSION: i32 = 25;
+pub const DB_VERSION: i32 = 34;
 
+/// DB のスキーマを `DB_VERSION` に合わせる。
+/// 移行可能なら `migrate` でその場で更新し、できなければ作り直す。
+/// 戻り値が true の場合は (全体または一部の) 再インデックスが必要。
 pub fn ensure_correct_version(db_path: &str) -> anyhow::Result<bool> {
-    let mut version_match = false;
-    let mut re_initialized = false;
+    let mut stored_version: Option<i32> = None;
     {
         if let Ok(conn) = rusqlite::Connection::open(db_path) {
             if let Ok(version_str) = conn.query_row(
@@ -18,25 +27,36 @@ pub fn ensure_correct_version(db_path: &str) -> anyhow::Result<bool> {
                 [],
                 |row| row.get::<_, String>(0),
             ) {
-                if let Ok(version) = version_str.parse::<i32>() {
-                    if version == DB_VERSION { version_match = true; }
-                }
+                stored_version = version_str.parse::<i32>().ok();
+            }
+        }
+    }
+
+    if stored_version == Some(DB_VERSION) {
+        return Ok(false);
+    }
+
+    if let Some(from) = stored_version {
+        let mut conn = rusqlite::Connection::open(db_path)?;
+        conn.busy_timeout(std::time::Duration::from_millis(5000))?;
+        match migrate::migrate(&mut conn, from, DB_VERSION) {
+            Ok(migrate::MigrationOutcome::Migrated { reparse }) => {
+                init_db(&conn)?;
+                tracing::info!("DB migrated v{} -> v{} (re-parse needed: {}): {}", from, DB_VERSION, reparse, db_path);
+                return Ok(reparse);
             }
+            Ok(migrate::MigrationOutcome::Unsupported) => {}
+            Err(e) => tracing::warn!("DB migration v{} -> v{} failed, rebuilding: {}", from, DB_VERSION, e),
         }
     }
 
-    if !version_match && Path::new(db_path).exists() {
+    if Path::new(db_path).exists() {
         tracing::info!("DB version mismatch or missing (Current: {}). Re-initializing: {}", DB_VERSION, db_path);
         let _ = std::fs::remove_file(db_path);
-        let conn = rusqlite::Connection::open(db_path)?;
-        init_db(&conn)?;
-        re_initialized = true;
-    } else if !Path::new(db_path).exists() {
-        let conn = rusqlite::Connection::open(db_path)?;
-        init_db(&conn)?;
-        re_initialized = true;
     }
-    Ok(re_initialized)
+    let conn = rusqlite::Connection::open(db_path)?;
+    init_db(&conn)?;
+    Ok(true)
 }
 
 pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
@@ -56,6 +76,13 @@ pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
         )",
         [],
     )?;
+    conn.execute_batch(path::DIR_PATHS_SCHEMA)?;
+    let needs_backfill: bool = conn.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM dir_paths) AND EXISTS (SELECT 1 FROM directories)", [], |r| r.get(0),
+    )?;
+    if needs_backfill {
+        path::rebuild_dir_paths(conn)?;
+    }
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS modules (
@@ -103,8 +130,14 @@ pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
             line_number INTEGER,
             end_line_number INTEGER,
             symbol_type TEXT DEFAULT \"class\",
+            language TEXT DEFAULT \"cpp\",
+            qualified_name_id INTEGER,
+            parent_id INTEGER,
+            template_params TEXT,
+            specialization_args TEXT,
             FOREIGN KEY(name_id) REFERENCES strings(id),
             FOREIGN KEY(namespace_id) REFERENCES strings(id),
+            FOREIGN KEY(qualified_name_id) REFERENCES strings(id),
             FOREIGN KEY(base_class_id) REFERENCES strings(id),
             FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
         )",
@@ -124,6 +157,8 @@ pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
             is_static INTEGER,
             line_number INTEGER,
             file_id INTEGER,
+            specifiers TEXT,
+            template_params TEXT,
             FOREIGN KEY(class_id) REFERENCES classes(id) ON DELETE CASCADE,
             FOREIGN KEY(name_id) REFERENCES strings(id),
             FOREIGN KEY(type_id) REFERENCES strings(id),
@@ -173,7 +208,8 @@ pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
             root_path TEXT,
             uplugin_path TEXT,
             uproject_path TEXT,
-            engine_association TEXT
+            engine_association TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1
         )",
         [],
     )?;
@@ -204,6 +240,68 @@ pub fn init_db(conn: &Connection) -> rusqlite::Result<()> {
         [],
     )?;
 
+    // シェーダー: C++ クラス → .usf エントリポイント
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS shader_bindings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_id INTEGER NOT NULL,
+            class_name_id INTEGER NOT NULL,
+            virtual_path TEXT NOT NULL,
+            entry_point TEXT NOT NULL,
+            frequency TEXT,
+            line_number INTEGER NOT NULL,
+            FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE,
+            FOREIGN KEY(class_name_id) REFERENCES strings(id)
+        )",
+        [],
+    )?;
+
+    // シェーダー仮想パス → 実ディレクトリ (file_id が NULL のものはエンジン組み込み)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS shader_mappings (
+            file_id INTEGER,
+            virtual_path TEXT NOT NULL,
+            real_path TEXT NOT NULL,
+            line_number INTEGER,
+            FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    // 型 / メンバーの参照箇所 (FindSymbolUsages 用)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS symbol_refs (
+            file_id INTEGER NOT NULL,
+            name_id INTEGER NOT NULL,
+            scope_id INTEGER,
+            kind TEXT NOT NULL,
+            line_number INTEGER NOT NULL,
+            col INTEGER NOT NULL,
+            FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE,
+            FOREIGN KEY(name_id) REFERENCES strings(id),
+            FOREIGN KEY(scope_id) REFERENCES strings(id)
+        )",
+        [],
+    )?;
+
+    // GrepCode 用のトライグラム索引 (config.code_search が有効な場合のみ埋まる)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS code_trigrams (
+            trigram INTEGER NOT NULL,
+            file_id INTEGER NOT NULL,
+            PRIMARY KEY(trigram, file_id),
+            FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
+        ) WITHOUT ROWID",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS code_index_files (
+            file_id INTEGER PRIMARY KEY,
+            mtime INTEGER NOT NULL,
+            FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     // 2. Indices (Now create indices after all tables exist)
     create_indices(conn)?;
 
@@ -218,6 +316,8 @@ fn create_indices(conn: &Connection) -> rusqlite::Result<()> {
     conn.execute("CREATE INDEX IF NOT EXISTS idx_files_dir_id ON files(directory_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_classes_covering ON classes(name_id, file_id, line_number, symbol_type)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_classes_file_id ON classes(file_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_classes_qualified_name ON classes(qualified_name_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_classes_parent_id ON classes(parent_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_members_name_id ON members(name_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_members_file_id ON members(file_id)", [])?;
     // 補完のclass_id検索に必須（なければ全件スキャン）
@@ -227,6 +327,12 @@ fn create_indices(conn: &Connection) -> rusqlite::Result<()> {
     conn.execute("CREATE INDEX IF NOT EXISTS idx_file_includes_file_id ON file_includes(file_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_file_includes_resolved_id ON file_includes(resolved_file_id)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_file_includes_base_name ON file_includes(base_filename_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_shader_bindings_class ON shader_bindings(class_name_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_shader_bindings_file_id ON shader_bindings(file_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_shader_mappings_file_id ON shader_mappings(file_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_symbol_refs_name ON symbol_refs(name_id, kind)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_symbol_refs_file_id ON symbol_refs(file_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_code_trigrams_file_id ON code_trigrams(file_id)", [])?;
     Ok(())
 }
 
@@ -234,10 +340,12 @@ fn drop_indices(conn: &Connection) -> rusqlite::Result<()> {
     let indices = [
         "idx_strings_text", "idx_directories_parent",
         "idx_files_filename_id", "idx_files_dir_id", 
-        "idx_classes_covering", "idx_classes_file_id",
+        "idx_classes_covering", "idx_classes_file_id", "idx_classes_qualified_name", "idx_classes_parent_id",
         "idx_members_name_id", "idx_members_file_id",
         "idx_members_class_id", "idx_inheritance_child_id", "idx_enum_values_enum_id",
-        "idx_file_includes_file_id", "idx_file_includes_resolved_id", "idx_file_includes_base_name"
+        "idx_file_includes_file_id", "idx_file_includes_resolved_id", "idx_file_includes_base_name",
+        "idx_shader_bindings_class", "idx_shader_bindings_file_id", "idx_shader_mappings_file_id",
+        "idx_symbol_refs_name", "idx_symbol_refs_file_id", "idx_code_trigrams_file_id"
     ];
     for idx in indices {
         let _ = conn.execute(&format!("DROP INDEX IF EXISTS {}", idx), []);
@@ -260,7 +368,29 @@ pub fn get_or_create_string(tx: &rusqlite::Transaction, cache: &mut HashMap<Stri
     Ok(id)
 }
 
+/// 1 トランザクションで書き込む結果の件数。エンジン全体を 1 つのトランザクションに
+/// 積むと WAL とページキャッシュが膨らむので区切ってコミットする。
+const SAVE_BATCH_SIZE: usize = 2000;
+
+/// `operator[]` などの演算子メンバーを除く WHERE 条件 (`is_operator_name` と同じ判定)。
+/// 演算子は補完のテンプレート解決用に members には残すが、一覧・検索・エクスポートには出さない。
+pub fn not_operator_sql(name_col: &str) -> String {
+    format!("({c} <> 'operator' AND {c} NOT GLOB 'operator[^A-Za-z0-9_]*')", c = name_col)
+}
+
 pub fn save_to_db(conn: &mut Connection, results: &[ParseResult], reporter: Arc<dyn ProgressReporter>) -> anyhow::Result<()> {
+    save_results(conn, results.iter(), results.len(), reporter, |_, _| Ok(()))
+}
+
+/// `results` を届いた順に書き込む。イテレータなので、パース中の結果をチャネルから
+/// 流し込めば全件をメモリに溜めずに済む。`total` は進捗表示にだけ使う。
+/// `on_batch(tx, saved)` は各バッチのコミット直前に呼ばれる (進捗をバッチと同時に記録する用)。
+pub fn save_results<I, F>(conn: &mut Connection, results: I, total: usize, reporter: Arc<dyn ProgressReporter>, mut on_batch: F) -> anyhow::Result<()>
+where
+    I: IntoIterator,
+    I::Item: Borrow<ParseResult>,
+    F: FnMut(&rusqlite::Transaction, usize) -> rusqlite::Result<()>,
+{
     // 最初にテーブルが存在することを保証する
     init_db(conn)?;
 
@@ -275,90 +405,143 @@ pub fn save_to_db(conn: &mut Connection, results: &[ParseResult], reporter: Arc<
     reporter.report("db_sync", 0, 100, "Dropping indices for faster insertion...");
     let _ = drop_indices(conn);
 
-    let total = results.len();
     reporter.report("db_sync", 0, total, &format!("Saving results (0/{})", total));
 
     let mut string_cache: HashMap<String, i64> = HashMap::new();
     let mut dir_cache: HashMap<(Option<i64>, i64), i64> = HashMap::new();
-
-    // 2. 巨大な単一トランザクションを開始
-    let tx = conn.transaction()?;
-    {
-        let mut stmt_del_file = tx.prepare("DELETE FROM files WHERE directory_id = ? AND filename_id = ?")?;
-        let mut stmt_file = tx.prepare("INSERT INTO files (directory_id, filename_id, extension, mtime, file_hash, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?, ?)")?;
-        let mut stmt_class = tx.prepare("INSERT INTO classes (name_id, namespace_id, base_class_id, file_id, line_number, symbol_type, end_line_number) VALUES (?, ?, ?, ?, ?, ?, ?)")?;
-        let mut stmt_inheritance = tx.prepare("INSERT INTO inheritance (child_id, parent_name_id) VALUES (?, ?)")?;
-        let mut stmt_enum = tx.prepare("INSERT INTO enum_values (enum_id, name_id, line_number, file_id) VALUES (?, ?, ?, ?)")?;
-        let mut stmt_member = tx.prepare("INSERT INTO members (class_id, name_id, type_id, flags, access, detail, return_type_id, is_static, line_number, file_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
-        let mut stmt_fts = tx.prepare("INSERT INTO symbols_fts (name, type, class_name, rowid_ref) VALUES (?, ?, ?, ?)")?;
-        let mut stmt_include = tx.prepare("INSERT INTO file_includes (file_id, include_path_id, base_filename_id) VALUES (?, ?, ?)")?;
-
-        for (i, result) in results.iter().enumerate() {
-            if i % 500 == 0 {
-                reporter.report("db_sync", i, total, &format!("Saving results ({}/{})", i, total));
-            }
-            if result.status != "parsed" { continue; }
-            let data = match &result.data { Some(d) => d, None => continue };
-
-            let path_obj = Path::new(&result.path);
-            let parent_dir = path_obj.parent().unwrap_or(Path::new(""));
-            let filename = path_obj.file_name().and_then(|s| s.to_str()).unwrap_or("unknown");
-            let extension = path_obj.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
-            
-            let dir_id = path::get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, parent_dir)?;
-            let filename_id = get_or_create_string(&tx, &mut string_cache, filename)?;
-            
-            let _ = stmt_del_file.execute(params![dir_id, filename_id]);
-
-            if stmt_file.execute(params![
-                dir_id, filename_id, extension, result.mtime as i64, data.new_hash, result.module_id,
-                if extension == "h" || extension == "hpp" { 1 } else { 0 }
-            ]).is_ok() {
-                let file_id: i64 = tx.last_insert_rowid();
-                for cls in &data.classes {
-                    let cls_id = get_or_create_string(&tx, &mut string_cache, &cls.class_name)?;
-                    let ns_id = match &cls.namespace { Some(ns) => Some(get_or_create_string(&tx, &mut string_cache, ns)?), None => None };
-                    let base_id = match cls.base_classes.first() { Some(b) => Some(get_or_create_string(&tx, &mut string_cache, b)?), None => None };
-                    let _ = stmt_class.execute(params![cls_id, ns_id, base_id, file_id, cls.line as i64, cls.symbol_type, cls.end_line as i64]);
-                    let class_id: i64 = tx.last_insert_rowid();
-                    let _ = stmt_fts.execute(params![cls.class_name, cls.symbol_type, cls.class_name, class_id]);
-                    for parent in &cls.base_classes {
-                        let p_name_id = get_or_create_string(&tx, &mut string_cache, parent)?;
-                        let _ = stmt_inheritance.execute(params![class_id, p_name_id]);
-                    }
-                    for mem in &cls.members {
-                        let mem_name_id = get_or_create_string(&tx, &mut string_cache, &mem.name)?;
-                        if mem.mem_type == "enum_item" {
-                            let _ = stmt_enum.execute(params![class_id, mem_name_id, mem.line as i64, file_id]);
-                        } else {
-                            let rt_id = match &mem.return_type { Some(rt) => Some(get_or_create_string(&tx, &mut string_cache, rt)?), None => None };
-                            let type_id = get_or_create_string(&tx, &mut string_cache, &mem.mem_type)?;
-                            let _ = stmt_member.execute(params![class_id, mem_name_id, type_id, mem.flags, mem.access, mem.detail, rt_id, if mem.flags.contains("static") {1} else {0}, mem.line as i64, file_id]);
-                            let _ = stmt_fts.execute(params![mem.name, mem.mem_type, cls.class_name, tx.last_insert_rowid()]);
+    let mut replaced_files = 0usize;
+    let mut results = results.into_iter();
+    let mut saved = 0usize;
+
+    // 2. SAVE_BATCH_SIZE 件ごとのトランザクションで書き込む
+    loop {
+        let tx = conn.transaction()?;
+        let mut batch_len = 0usize;
+        {
+            let mut stmt_del_file = tx.prepare("DELETE FROM files WHERE directory_id = ? AND filename_id = ?")?;
+            let mut stmt_file = tx.prepare("INSERT INTO files (directory_id, filename_id, extension, mtime, file_hash, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?, ?)")?;
+            let mut stmt_class = tx.prepare("INSERT INTO classes (name_id, namespace_id, base_class_id, file_id, line_number, symbol_type, end_line_number, language, qualified_name_id, template_params, specialization_args) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            let mut stmt_inheritance = tx.prepare("INSERT INTO inheritance (child_id, parent_name_id) VALUES (?, ?)")?;
+            let mut stmt_enum = tx.prepare("INSERT INTO enum_values (enum_id, name_id, line_number, file_id) VALUES (?, ?, ?, ?)")?;
+            let mut stmt_member = tx.prepare("INSERT INTO members (class_id, name_id, type_id, flags, access, detail, return_type_id, is_static, line_number, file_id, specifiers, template_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            let mut stmt_fts = tx.prepare("INSERT INTO symbols_fts (name, type, class_name, rowid_ref) VALUES (?, ?, ?, ?)")?;
+            let mut stmt_include = tx.prepare("INSERT INTO file_includes (file_id, include_path_id, base_filename_id) VALUES (?, ?, ?)")?;
+            let mut stmt_shader_binding = tx.prepare("INSERT INTO shader_bindings (file_id, class_name_id, virtual_path, entry_point, frequency, line_number) VALUES (?, ?, ?, ?, ?, ?)")?;
+            let mut stmt_shader_mapping = tx.prepare("INSERT INTO shader_mappings (file_id, virtual_path, real_path, line_number) VALUES (?, ?, ?, ?)")?;
+            let mut stmt_ref = tx.prepare(INSERT_SYMBOL_REF)?;
+            let mut stmt_touch_file = tx.prepare("UPDATE files SET mtime = ?, module_id = ? WHERE directory_id = ? AND filename_id = ?")?;
+
+            for result in results.by_ref().take(SAVE_BATCH_SIZE) {
+                let result: &ParseResult = result.borrow();
+                if saved.is_multiple_of(500) {
+                    reporter.report("db_sync", saved, total, &format!("Saving results ({}/{})", saved, total));
+                }
+                saved += 1;
+                batch_len += 1;
+                // 内容が同じ (ハッシュ一致) なら既存の行を残し、mtime と module_id だけ更新する
+                if result.status == "cache_hit" {
+                    let path_obj = Path::new(&result.path);
+                    let dir_id = path::get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, path_obj.parent().unwrap_or(Path::new("")))?;
+                    let filename_id = get_or_create_string(&tx, &mut string_cache, path_obj.file_name().and_then(|s| s.to_str()).unwrap_or("unknown"))?;
+                    let _ = stmt_touch_file.execute(params![result.mtime as i64, result.module_id, dir_id, filename_id]);
+                    continue;
+                }
+                if result.status != "parsed" { continue; }
+                let data = match &result.data { Some(d) => d, None => continue };
+
+                let path_obj = Path::new(&result.path);
+                let parent_dir = path_obj.parent().unwrap_or(Path::new(""));
+                let filename = path_obj.file_name().and_then(|s| s.to_str()).unwrap_or("unknown");
+                let extension = path_obj.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+                
+                let dir_id = path::get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, parent_dir)?;
+                let filename_id = get_or_create_string(&tx, &mut string_cache, filename)?;
+                
+                if stmt_del_file.execute(params![dir_id, filename_id]).unwrap_or(0) > 0 { replaced_files += 1; }
+
+                if stmt_file.execute(params![
+                    dir_id, filename_id, extension, result.mtime as i64, data.new_hash, result.module_id,
+                    if extension == "h" || extension == "hpp" { 1 } else { 0 }
+                ]).is_ok() {
+                    let file_id: i64 = tx.last_insert_rowid();
+                    let language = crate::parser::language_for_extension(&extension);
+                    let separator = scope_separator(language);
+                    let mut qualified_ids: HashMap<String, i64> = HashMap::new();
+                    let mut nested: Vec<(i64, &str)> = Vec::new();
+                    for cls in &data.classes {
+                        let cls_id = get_or_create_string(&tx, &mut string_cache, &cls.class_name)?;
+                        let ns_id = match &cls.namespace { Some(ns) => Some(get_or_create_string(&tx, &mut string_cache, ns)?), None => None };
+                        let base_id = match cls.base_classes.first() { Some(b) => Some(get_or_create_string(&tx, &mut string_cache, b)?), None => None };
+                        let qualified = cls.qualified_name(separator);
+                        let qn_id = get_or_create_string(&tx, &mut string_cache, &qualified)?;
+                        let _ = stmt_class.execute(params![cls_id, ns_id, base_id, file_id, cls.line as i64, cls.symbol_type, cls.end_line as i64, language, qn_id, template_params_json(&cls.template_params), cls.specialization_args]);
+                        let class_id: i64 = tx.last_insert_rowid();
+                        qualified_ids.entry(qualified).or_insert(class_id);
+                        if let Some(p) = &cls.parent_type { nested.push((class_id, p)); }
+                        let _ = stmt_fts.execute(params![cls.class_name, cls.symbol_type, cls.class_name, class_id]);
+                        for parent in &cls.base_classes {
+                            let p_name_id = get_or_create_string(&tx, &mut string_cache, parent)?;
+                            let _ = stmt_inheritance.execute(params![class_id, p_name_id]);
+                        }
+                        for mem in &cls.members {
+                            let mem_name_id = get_or_create_string(&tx, &mut string_cache, &mem.name)?;
+                            if mem.mem_type == "enum_item" {
+                                let _ = stmt_enum.execute(params![class_id, mem_name_id, mem.line as i64, file_id]);
+                            } else {
+                                let rt_id = match &mem.return_type { Some(rt) => Some(get_or_create_string(&tx, &mut string_cache, rt)?), None => None };
+                                let type_id = get_or_create_string(&tx, &mut string_cache, &mem.mem_type)?;
+                                let _ = stmt_member.execute(params![class_id, mem_name_id, type_id, mem.flags, mem.access, mem.detail, rt_id, if mem.flags.contains("static") {1} else {0}, mem.line as i64, file_id, mem.specifiers, template_params_json(&mem.template_params)]);
+                                if !is_operator_name(&mem.name) {
+                                    let _ = stmt_fts.execute(params![mem.name, mem.mem_type, cls.class_name, tx.last_insert_rowid()]);
+                                }
+                            }
                         }
                     }
-                }
-                for inc in &data.includes {
-                    let inc_path_id = get_or_create_string(&tx, &mut string_cache, inc)?;
-                    let inc_fn = Path::new(inc).file_name().and_then(|s| s.to_str()).unwrap_or(inc);
-                    let inc_fn_id = get_or_create_string(&tx, &mut string_cache, inc_fn)?;
-                    let _ = stmt_include.execute(params![file_id, inc_path_id, inc_fn_id]);
+                    link_enclosing_types(&tx, &qualified_ids, &nested)?;
+                    for inc in &data.includes {
+                        let inc_path_id = get_or_create_string(&tx, &mut string_cache, inc)?;
+                        let inc_fn = Path::new(inc).file_name().and_then(|s| s.to_str()).unwrap_or(inc);
+                        let inc_fn_id = get_or_create_string(&tx, &mut string_cache, inc_fn)?;
+                        let _ = stmt_include.execute(params![file_id, inc_path_id, inc_fn_id]);
+                    }
+                    for b in &data.shader_bindings {
+                        let b_cls_id = get_or_create_string(&tx, &mut string_cache, &b.class_name)?;
+                        let _ = stmt_shader_binding.execute(params![file_id, b_cls_id, b.virtual_path, b.entry_point, b.frequency, b.line as i64]);
+                    }
+                    for m in &data.shader_mappings {
+                        let _ = stmt_shader_mapping.execute(params![file_id, m.virtual_path, m.real_path, m.line as i64]);
+                    }
+                    insert_symbol_refs(&tx, &mut string_cache, &mut stmt_ref, file_id, &data.refs)?;
                 }
             }
         }
+        on_batch(&tx, saved)?;
+        tx.commit()?;
+        if batch_len < SAVE_BATCH_SIZE { break; }
     }
-    tx.commit()?; // トランザクションをコミット
 
     // 3. インデックスを再構築
     reporter.report("finalizing", 70, 100, "Re-creating indices (this may take a while)...");
     create_indices(conn)?;
 
+    // FK OFF のまま files を置き換えたのでカスケード削除が効いていない。
+    // maintain_index と同じ孤立行の GC を、置き換えがあったときだけ行う
+    if replaced_files > 0 {
+        reporter.report("finalizing", 75, 100, &format!("Removing stale symbols of {} re-parsed file(s)...", replaced_files));
+        let removed: usize = purge_orphaned_rows(conn)?.iter().map(|(_, n)| n).sum();
+        tracing::info!("Removed {} orphaned row(s) after replacing {} file(s)", removed, replaced_files);
+    }
+
     conn.execute("PRAGMA foreign_keys = ON", [])?; 
 
     reporter.report("finalizing", 80, 100, "Optimizing inheritance graph...");
     let _ = conn.execute("UPDATE inheritance SET parent_class_id = (SELECT c.id FROM classes c JOIN strings s ON c.name_id = s.id WHERE s.id = inheritance.parent_name_id LIMIT 1) WHERE parent_class_id IS NULL", []);
     reporter.report("finalizing", 85, 100, "Resolving file includes...");
+    let _ = resolve_shader_includes(conn);
     let _ = resolve_file_includes_by_path(conn);
+    if let Err(e) = engine_cache::link(conn) {
+        tracing::warn!("Failed to link game symbols to the shared engine index: {}", e);
+    }
     reporter.report("finalizing", 95, 100, "Vacuuming and optimizing...");
     let _ = conn.execute("PRAGMA optimize", []);
     Ok(())
@@ -366,9 +549,9 @@ pub fn save_to_db(conn: &mut Connection, results: &[ParseResult], reporter: Arc<
 
 /// file_includes.resolved_file_id をファイル名マッチングで解決する（ファイル名が一意のケースのみ）。
 /// 同名ファイルが複数存在する場合は NULL のままとし、クエリ時に base_filename_id で検索する。
-fn resolve_file_includes_by_path(conn: &mut Connection) -> anyhow::Result<()> {
+pub(crate) fn resolve_file_includes_by_path(conn: &Connection) -> anyhow::Result<usize> {
     // ファイル名が一意の場合のみ高速に解決する（SQLレベルで完結）
-    conn.execute(
+    let resolved = conn.execute(
         "UPDATE file_includes
          SET resolved_file_id = (
              SELECT f.id FROM files f WHERE f.filename_id = file_includes.base_filename_id LIMIT 1
@@ -377,6 +560,107 @@ fn resolve_file_includes_by_path(conn: &mut Connection) -> anyhow::Result<()> {
            AND (SELECT COUNT(*) FROM files f WHERE f.filename_id = file_includes.base_filename_id) = 1",
         [],
     )?;
+    Ok(resolved)
+}
+
+const INSERT_SYMBOL_REF: &str = "INSERT INTO symbol_refs (file_id, name_id, scope_id, kind, line_number, col) VALUES (?, ?, ?, ?, ?, ?)";
+
+fn insert_symbol_refs(
+    tx: &rusqlite::Transaction,
+    string_cache: &mut HashMap<String, i64>,
+    stmt: &mut rusqlite::Statement,
+    file_id: i64,
+    refs: &[crate::types::SymbolRef],
+) -> rusqlite::Result<()> {
+    for r in refs {
+        let name_id = get_or_create_string(tx, string_cache, &r.name)?;
+        let scope_id = match &r.scope { Some(s) => Some(get_or_create_string(tx, string_cache, s)?), None => None };
+        let _ = stmt.execute(params![file_id, name_id, scope_id, r.kind, r.line as i64, r.col as i64]);
+    }
+    Ok(())
+}
+
+/// (table, 親が消えた行を消す DELETE)。親テーブルから順に並べる
+pub(crate) const ORPHAN_PURGES: &[(&str, &str)] = &[
+    ("dir_paths", "DELETE FROM dir_paths WHERE NOT EXISTS (SELECT 1 FROM directories d WHERE d.id = dir_paths.id)"),
+    ("files", "DELETE FROM files WHERE NOT EXISTS (SELECT 1 FROM directories d WHERE d.id = files.directory_id)"),
+    ("classes", "DELETE FROM classes WHERE file_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM files f WHERE f.id = classes.file_id)"),
+    ("members", "DELETE FROM members WHERE NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = members.class_id)"),
+    ("enum_values", "DELETE FROM enum_values WHERE NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = enum_values.enum_id)"),
+    ("inheritance", "DELETE FROM inheritance WHERE NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = inheritance.child_id)"),
+    ("file_includes", "DELETE FROM file_includes WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = file_includes.file_id)"),
+    ("symbol_calls", "DELETE FROM symbol_calls WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = symbol_calls.file_id)"),
+    ("shader_bindings", "DELETE FROM shader_bindings WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = shader_bindings.file_id)"),
+    ("shader_mappings", "DELETE FROM shader_mappings WHERE file_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM files f WHERE f.id = shader_mappings.file_id)"),
+    ("symbol_refs", "DELETE FROM symbol_refs WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = symbol_refs.file_id)"),
+    ("code_trigrams", "DELETE FROM code_trigrams WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = code_trigrams.file_id)"),
+    ("code_index_files", "DELETE FROM code_index_files WHERE NOT EXISTS (SELECT 1 FROM files f WHERE f.id = code_index_files.file_id)"),
+];
+
+/// 削除済みの files / classes を参照したまま残っている行を消す。戻り値: (table, 削除件数)
+pub(crate) fn purge_orphaned_rows(conn: &Connection) -> rusqlite::Result<Vec<(&'static str, usize)>> {
+    ORPHAN_PURGES.iter().map(|(table, sql)| Ok((*table, conn.execute(sql, [])?))).collect()
+}
+
+/// テンプレート仮引数を JSON 文字列で保存する (非テンプレートは NULL)
+fn template_params_json(params: &[crate::types::TemplateParam]) -> Option<String> {
+    if params.is_empty() { None } else { serde_json::to_string(params).ok() }
+}
+
+/// 言語ごとのスコープ区切り (C++ / HLSL は `::`、Verse は `.`)
+fn scope_separator(language: &str) -> &'static str {
+    if language == "verse" { "." } else { "::" }
+}
+
+/// 同一ファイル内で外側の型を完全修飾名から探し、`classes.parent_id` を設定する。
+fn link_enclosing_types(conn: &Connection, qualified_ids: &HashMap<String, i64>, nested: &[(i64, &str)]) -> rusqlite::Result<()> {
+    if nested.is_empty() { return Ok(()); }
+    let mut stmt = conn.prepare_cached("UPDATE classes SET parent_id = ? WHERE id = ?")?;
+    for (class_id, parent) in nested {
+        if let Some(parent_id) = qualified_ids.get(*parent) {
+            stmt.execute(params![parent_id, class_id])?;
+        }
+    }
+    Ok(())
+}
+
+/// `#include "/Engine/Private/Common.ush"` のようなシェーダー仮想パスを
+/// shader_mappings を使って実ファイルに解決する。ファイル名マッチングより先に実行すること。
+fn resolve_shader_includes(conn: &mut Connection) -> anyhow::Result<()> {
+    let mappings: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT virtual_path, real_path FROM shader_mappings")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+    if mappings.is_empty() { return Ok(()); }
+
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT fi.rowid, s.text FROM file_includes fi JOIN strings s ON fi.include_path_id = s.id
+             WHERE fi.resolved_file_id IS NULL AND s.text LIKE '/%'"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    let tx = conn.transaction()?;
+    for (rowid, virtual_path) in pending {
+        let Some(real) = crate::parser::shader::resolve_virtual_path(&virtual_path, &mappings) else { continue };
+        if let Some(file_id) = path::find_file_id(&tx, Path::new(&real))? {
+            tx.execute("UPDATE file_includes SET resolved_file_id = ? WHERE rowid = ?", params![file_id, rowid])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// エンジン組み込みの `/Engine` シェーダーマッピングを登録し直す。
+pub fn set_engine_shader_mapping(conn: &Connection, engine_shaders_dir: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM shader_mappings WHERE file_id IS NULL", [])?;
+    conn.execute(
+        "INSERT INTO shader_mappings (file_id, virtual_path, real_path, line_number) VALUES (NULL, '/Engine', ?, NULL)",
+        [engine_shaders_dir.replace('\\', "/")],
+    )?;
     Ok(())
 }
 
@@ -451,15 +735,19 @@ pub fn update_single_file(conn: &mut Connection, result: &ParseResult) -> anyhow
                 "INSERT INTO files (directory_id, filename_id, extension, mtime, file_hash, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?, ?)"
             )?;
             let mut stmt_class = tx.prepare(
-                "INSERT INTO classes (name_id, namespace_id, base_class_id, file_id, line_number, symbol_type, end_line_number) VALUES (?, ?, ?, ?, ?, ?, ?)"
+                "INSERT INTO classes (name_id, namespace_id, base_class_id, file_id, line_number, symbol_type, end_line_number, language, qualified_name_id, template_params, specialization_args) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
             )?;
             let mut stmt_inh = tx.prepare("INSERT INTO inheritance (child_id, parent_name_id) VALUES (?, ?)")?;
             let mut stmt_enum = tx.prepare("INSERT INTO enum_values (enum_id, name_id, line_number, file_id) VALUES (?, ?, ?, ?)")?;
             let mut stmt_mem = tx.prepare(
-                "INSERT INTO members (class_id, name_id, type_id, flags, access, detail, return_type_id, is_static, line_number, file_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                "INSERT INTO members (class_id, name_id, type_id, flags, access, detail, return_type_id, is_static, line_number, file_id, specifiers, template_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
             )?;
             let mut stmt_fts = tx.prepare("INSERT INTO symbols_fts (name, type, class_name, rowid_ref) VALUES (?, ?, ?, ?)")?;
             let mut stmt_inc = tx.prepare("INSERT INTO file_includes (file_id, include_path_id, base_filename_id) VALUES (?, ?, ?)")?;
+            let mut stmt_shader_binding = tx.prepare(
+                "INSERT INTO shader_bindings (file_id, class_name_id, virtual_path, entry_point, frequency, line_number) VALUES (?, ?, ?, ?, ?, ?)"
+            )?;
+            let mut stmt_shader_mapping = tx.prepare("INSERT INTO shader_mappings (file_id, virtual_path, real_path, line_number) VALUES (?, ?, ?, ?)")?;
 
             stmt_file.execute(params![
                 dir_id, fname_id, extension,
@@ -467,6 +755,10 @@ pub fn update_single_file(conn: &mut Connection, result: &ParseResult) -> anyhow
                 if extension == "h" || extension == "hpp" { 1i64 } else { 0i64 },
             ])?;
             let file_id: i64 = tx.last_insert_rowid();
+            let language = crate::parser::language_for_extension(&extension);
+            let separator = scope_separator(language);
+            let mut qualified_ids: HashMap<String, i64> = HashMap::new();
+            let mut nested: Vec<(i64, &str)> = Vec::new();
 
             for cls in &data.classes {
                 class_names.push(cls.class_name.clone());
@@ -479,11 +771,16 @@ pub fn update_single_file(conn: &mut Connection, result: &ParseResult) -> anyhow
                     Some(b) => Some(get_or_create_string(&tx, &mut string_cache, b)?),
                     None => None,
                 };
+                let qualified = cls.qualified_name(separator);
+                let qn_id = get_or_create_string(&tx, &mut string_cache, &qualified)?;
                 let _ = stmt_class.execute(params![
                     cls_name_id, ns_id, base_id, file_id,
-                    cls.line as i64, cls.symbol_type, cls.end_line as i64,
+                    cls.line as i64, cls.symbol_type, cls.end_line as i64, language, qn_id,
+                    template_params_json(&cls.template_params), cls.specialization_args,
                 ]);
                 let class_id: i64 = tx.last_insert_rowid();
+                qualified_ids.entry(qualified).or_insert(class_id);
+                if let Some(p) = &cls.parent_type { nested.push((class_id, p)); }
                 let _ = stmt_fts.execute(params![cls.class_name, cls.symbol_type, cls.class_name, class_id]);
 
                 for parent in &cls.base_classes {
@@ -503,18 +800,30 @@ pub fn update_single_file(conn: &mut Connection, result: &ParseResult) -> anyhow
                         let _ = stmt_mem.execute(params![
                             class_id, mem_name_id, type_id, mem.flags, mem.access, mem.detail,
                             rt_id, if mem.flags.contains("static") { 1i64 } else { 0i64 },
-                            mem.line as i64, file_id,
+                            mem.line as i64, file_id, mem.specifiers, template_params_json(&mem.template_params),
                         ]);
-                        let _ = stmt_fts.execute(params![mem.name, mem.mem_type, cls.class_name, tx.last_insert_rowid()]);
+                        if !is_operator_name(&mem.name) {
+                            let _ = stmt_fts.execute(params![mem.name, mem.mem_type, cls.class_name, tx.last_insert_rowid()]);
+                        }
                     }
                 }
             }
+            link_enclosing_types(&tx, &qualified_ids, &nested)?;
             for inc in &data.includes {
                 let inc_path_id = get_or_create_string(&tx, &mut string_cache, inc)?;
                 let inc_fn = Path::new(inc).file_name().and_then(|s| s.to_str()).unwrap_or(inc);
                 let inc_fn_id = get_or_create_string(&tx, &mut string_cache, inc_fn)?;
                 let _ = stmt_inc.execute(params![file_id, inc_path_id, inc_fn_id]);
             }
+            for b in &data.shader_bindings {
+                let b_cls_id = get_or_create_string(&tx, &mut string_cache, &b.class_name)?;
+                let _ = stmt_shader_binding.execute(params![file_id, b_cls_id, b.virtual_path, b.entry_point, b.frequency, b.line as i64]);
+            }
+            for m in &data.shader_mappings {
+                let _ = stmt_shader_mapping.execute(params![file_id, m.virtual_path, m.real_path, m.line as i64]);
+            }
+            let mut stmt_ref = tx.prepare(INSERT_SYMBOL_REF)?;
+            insert_symbol_refs(&tx, &mut string_cache, &mut stmt_ref, file_id, &data.refs)?;
         }
     }
 
@@ -536,6 +845,8 @@ pub fn update_single_file(conn: &mut Connection, result: &ParseResult) -> anyhow
            AND (SELECT COUNT(*) FROM files f WHERE f.filename_id = file_includes.base_filename_id) = 1",
         [],
     );
+    let _ = resolve_shader_includes(conn);
+    let _ = engine_cache::link(conn);
 
     Ok(class_names)
 }
@@ -572,9 +883,12 @@ pub fn get_module_id_for_path(conn: &Connection, file_path: &str) -> anyhow::Res
     Ok(best_id)
 }
 
-pub fn get_components(conn: &Connection) -> anyhow::Result<serde_json::Value> {
-    let mut stmt = conn.prepare("SELECT name, display_name, type, owner_name, root_path, uplugin_path, uproject_path, engine_association FROM components")?;
-    let rows = stmt.query_map([], |row| {
+pub fn get_components(conn: &Connection, enabled_only: bool) -> anyhow::Result<serde_json::Value> {
+    let mut stmt = conn.prepare(
+        "SELECT name, display_name, type, owner_name, root_path, uplugin_path, uproject_path, engine_association, enabled
+         FROM components WHERE ?1 = 0 OR enabled = 1"
+    )?;
+    let rows = stmt.query_map([enabled_only], |row| {
         Ok(serde_json::json!({
             "name": row.get::<_, String>(0)?,
             "display_name": row.get::<_, String>(1)?,
@@ -584,6 +898,7 @@ pub fn get_components(conn: &Connection) -> anyhow::Result<serde_json::Value> {
             "uplugin_path": row.get::<_, Option<String>>(5)?,
             "uproject_path": row.get::<_, Option<String>>(6)?,
             "engine_association": row.get::<_, Option<String>>(7)?,
+            "enabled": row.get::<_, bool>(8)?,
         }))
     })?;
     let results: Vec<_> = rows.filter_map(|r| r.ok()).collect();
@@ -591,3 +906,17 @@ pub fn get_components(conn: &Connection) -> anyhow::Result<serde_json::Value> {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_operator_sql_matches_is_operator_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        for name in ["operator[]", "operator==", "operator ()", "operator", "operatorFoo", "Operator", "GetOperator", "Num"] {
+            let kept: bool = conn.query_row(&format!("SELECT {}", not_operator_sql("?1")), [name], |r| r.get(0)).unwrap();
+            assert_eq!(kept, !is_operator_name(name), "{}", name);
+        }
+    }
+}
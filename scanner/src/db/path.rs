@@ -1,6 +1,6 @@
 use std::path::{Path, Component};
 use std::collections::HashMap;
-use rusqlite::{params, Connection, Transaction};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
 
 /// ディレクトリツリーを辿って directory_id を取得または作成する
 pub fn get_or_create_directory(
@@ -48,44 +48,95 @@ pub fn get_or_create_directory(
     Ok(current_parent_id.unwrap_or(0))
 }
 
-/// directory_id からフルパスを復元する (Rust側)
+/// directory_id と filename_id からフルパスを取得する
 pub fn get_full_path(conn: &Connection, directory_id: i64, filename_id: i64) -> anyhow::Result<String> {
-    let mut stmt = conn.prepare(
-        "WITH RECURSIVE path_builder(id, parent_id, name_id) AS (
-            SELECT id, parent_id, name_id FROM directories WHERE id = ?
-            UNION ALL
-            SELECT d.id, d.parent_id, d.name_id FROM directories d JOIN path_builder pb ON d.id = pb.parent_id
-        )
-        SELECT s.text FROM path_builder pb JOIN strings s ON pb.name_id = s.id"
+    let full: String = conn.query_row(
+        "SELECT dp.full_path || '/' || s.text FROM dir_paths dp, strings s WHERE dp.id = ? AND s.id = ?",
+        params![directory_id, filename_id],
+        |r| r.get(0),
     )?;
-    
-    let mut rows = stmt.query([directory_id])?;
-    let mut parts = Vec::new();
-    while let Some(row) = rows.next()? {
-        parts.push(row.get::<_, String>(0)?);
-    }
-    parts.reverse();
-    
-    let filename: String = conn.query_row("SELECT text FROM strings WHERE id = ?", [filename_id], |r| r.get(0))?;
-    
-    let mut full = parts.join("/");
-    if !full.ends_with('/') && !full.is_empty() { full.push('/'); }
-    full.push_str(&filename);
     Ok(full.replace("//", "/").replace("\\", "/"))
 }
 
-/// SQL クエリ内でフルパスを生成するための共通 CTE 文字列
-pub const PATH_CTE: &str = "
-    WITH RECURSIVE dir_paths(id, full_path) AS (
-        SELECT d.id, s.text FROM directories d JOIN strings s ON d.name_id = s.id WHERE d.parent_id IS NULL
-        UNION ALL
-        SELECT d.id, CASE WHEN dp.full_path = '/' THEN '/' || s.text ELSE dp.full_path || '/' || s.text END
-        FROM directories d
-        JOIN dir_paths dp ON d.parent_id = dp.id
-        JOIN strings s ON d.name_id = s.id
-    )
+/// `dir_paths` (directories.id → フルパス) を directories のトリガーで同期させる。
+/// ルートは名前そのまま、`/` 直下は `/name`、それ以外は `parent/name` (Windows は `C:` → `C://` → `C:///foo`)。
+pub const DIR_PATHS_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS dir_paths (
+        id INTEGER PRIMARY KEY,
+        full_path TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_dir_paths_full_path ON dir_paths(full_path);
+
+    CREATE TRIGGER IF NOT EXISTS trg_dir_paths_insert AFTER INSERT ON directories BEGIN
+        INSERT OR REPLACE INTO dir_paths (id, full_path)
+        SELECT NEW.id, CASE WHEN NEW.parent_id IS NULL THEN s.text
+                            WHEN p.full_path = '/' THEN '/' || s.text
+                            ELSE p.full_path || '/' || s.text END
+        FROM strings s LEFT JOIN dir_paths p ON p.id = NEW.parent_id
+        WHERE s.id = NEW.name_id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS trg_dir_paths_update AFTER UPDATE OF parent_id, name_id ON directories BEGIN
+        -- 配下のパスを先に付け替える (自分の行はまだ古いパスを持っている)
+        UPDATE dir_paths SET full_path =
+            (SELECT CASE WHEN NEW.parent_id IS NULL THEN s.text
+                         WHEN p.full_path = '/' THEN '/' || s.text
+                         ELSE p.full_path || '/' || s.text END
+             FROM strings s LEFT JOIN dir_paths p ON p.id = NEW.parent_id WHERE s.id = NEW.name_id)
+            || substr(full_path, length((SELECT full_path FROM dir_paths WHERE id = OLD.id)) + 1)
+        WHERE substr(full_path, 1, length((SELECT full_path FROM dir_paths WHERE id = OLD.id)) + 1)
+              = (SELECT full_path FROM dir_paths WHERE id = OLD.id) || '/';
+        INSERT OR REPLACE INTO dir_paths (id, full_path)
+        SELECT NEW.id, CASE WHEN NEW.parent_id IS NULL THEN s.text
+                            WHEN p.full_path = '/' THEN '/' || s.text
+                            ELSE p.full_path || '/' || s.text END
+        FROM strings s LEFT JOIN dir_paths p ON p.id = NEW.parent_id
+        WHERE s.id = NEW.name_id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS trg_dir_paths_delete AFTER DELETE ON directories BEGIN
+        DELETE FROM dir_paths WHERE id = OLD.id;
+    END;
 ";
 
+/// dir_paths を directories から作り直す (移行直後・トリガーを通らずに行を入れた後)
+pub fn rebuild_dir_paths(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "DELETE FROM dir_paths;
+         INSERT INTO dir_paths (id, full_path)
+         WITH RECURSIVE walk(id, full_path) AS (
+             SELECT d.id, s.text FROM directories d JOIN strings s ON d.name_id = s.id WHERE d.parent_id IS NULL
+             UNION ALL
+             SELECT d.id, CASE WHEN w.full_path = '/' THEN '/' || s.text ELSE w.full_path || '/' || s.text END
+             FROM directories d
+             JOIN walk w ON d.parent_id = w.id
+             JOIN strings s ON d.name_id = s.id
+         )
+         SELECT id, full_path FROM walk;"
+    )
+}
+
+/// `path` を (dir_paths.full_path, ファイル名) に分ける。`dp.full_path = ? AND sn.text = ?` で索引が効く
+pub fn split_db_path(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("", path),
+    }
+}
+
+/// DB 形式のフルパス (`C:///foo/Bar.h`) から files.id を検索する
+pub fn find_file_id_by_db_path(conn: &Connection, db_path: &str) -> rusqlite::Result<Option<i64>> {
+    let (dir, filename) = split_db_path(db_path);
+    conn.query_row(
+        "SELECT f.id FROM dir_paths dp
+         JOIN files f ON f.directory_id = dp.id
+         JOIN strings sn ON f.filename_id = sn.id
+         WHERE dp.full_path = ? AND sn.text = ? LIMIT 1",
+        params![dir, filename],
+        |r| r.get(0),
+    ).optional()
+}
+
 /// files テーブルと dir_paths を結合してパスを取得する共通 SELECT 部分
 pub const FILE_PATH_SELECT: &str = "
     SELECT f.*, dp.full_path || '/' || sn.text as path
@@ -95,7 +146,7 @@ pub const FILE_PATH_SELECT: &str = "
 ";
 
 /// Lua の unl_path.normalize が返す `C:/foo` 形式を
-/// PATH_CTE が生成する `C:///foo` 形式（Prefix("C:") + RootDir("/") + Normal）に変換する。
+/// dir_paths に入っている `C:///foo` 形式（Prefix("C:") + RootDir("/") + Normal）に変換する。
 /// Linux パスや既に変換済みのパスはそのまま返す。
 pub fn to_db_path_format(path: &str) -> String {
     let b = path.as_bytes();
@@ -105,3 +156,74 @@ pub fn to_db_path_format(path: &str) -> String {
         }
     path.to_string()
 }
+
+/// パスから directories.id を検索する（作成しない読み取り専用版）
+pub fn find_directory_id(conn: &Connection, path: &Path) -> rusqlite::Result<Option<i64>> {
+    let mut current_parent_id: Option<i64> = None;
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(s) => s.to_string_lossy().to_string(),
+            Component::RootDir => "/".to_string(),
+            Component::Prefix(p) => p.as_os_str().to_string_lossy().to_string(),
+            _ => continue,
+        };
+        let id: Option<i64> = conn.query_row(
+            "SELECT d.id FROM directories d JOIN strings s ON d.name_id = s.id WHERE (d.parent_id IS ? OR d.parent_id = ?) AND s.text = ?",
+            params![current_parent_id, current_parent_id, name],
+            |row| row.get(0)
+        ).optional()?;
+        match id {
+            Some(id) => current_parent_id = Some(id),
+            None => return Ok(None),
+        }
+    }
+    Ok(current_parent_id)
+}
+
+/// パスから files.id を検索する（ディレクトリ・文字列は作成しない読み取り専用版）
+pub fn find_file_id(conn: &Connection, path: &Path) -> anyhow::Result<Option<i64>> {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let filename = match path.file_name().and_then(|s| s.to_str()) { Some(f) => f, None => return Ok(None) };
+    let dir_id = if parent.as_os_str().is_empty() { None } else {
+        match find_directory_id(conn, parent)? { Some(id) => Some(id), None => return Ok(None) }
+    };
+
+    let file_id = conn.query_row(
+        "SELECT f.id FROM files f JOIN strings s ON f.filename_id = s.id WHERE f.directory_id = ? AND s.text = ?",
+        params![dir_id.unwrap_or(0), filename],
+        |row| row.get(0)
+    ).optional()?;
+    Ok(file_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_paths_follow_directory_changes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let tx = conn.transaction().unwrap();
+        let (mut sc, mut dc) = (HashMap::new(), HashMap::new());
+        let dir = get_or_create_directory(&tx, &mut sc, &mut dc, Path::new("/home/a/Proj/Source")).unwrap();
+        let work = get_or_create_directory(&tx, &mut sc, &mut dc, Path::new("/work")).unwrap();
+        let name = crate::db::get_or_create_string(&tx, &mut sc, "A.h").unwrap();
+        tx.execute("INSERT INTO files (directory_id, filename_id, extension) VALUES (?, ?, 'h')", params![dir, name]).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(get_full_path(&conn, dir, name).unwrap(), "/home/a/Proj/Source/A.h");
+        assert!(find_file_id_by_db_path(&conn, "/home/a/Proj/Source/A.h").unwrap().is_some());
+
+        // Proj を /work の下に付け替えると配下のパスも変わる
+        let proj = find_directory_id(&conn, Path::new("/home/a/Proj")).unwrap().unwrap();
+        conn.execute("UPDATE directories SET parent_id = ? WHERE id = ?", params![work, proj]).unwrap();
+        assert_eq!(get_full_path(&conn, dir, name).unwrap(), "/work/Proj/Source/A.h");
+        assert!(find_file_id_by_db_path(&conn, "/home/a/Proj/Source/A.h").unwrap().is_none());
+
+        // 移行直後 (トリガーを通らない) と同じ状態から作り直せる
+        conn.execute("DELETE FROM dir_paths", []).unwrap();
+        rebuild_dir_paths(&conn).unwrap();
+        assert!(find_file_id_by_db_path(&conn, "/work/Proj/Source/A.h").unwrap().is_some());
+        assert_eq!(split_db_path("C:///foo/A.h"), ("C:///foo", "A.h"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Portable index snapshots (`export-index` / `import-index`).
+//!
+//! A snapshot is a copy of the project DB in which the engine and project root
+//! directories are detached from the directory tree and renamed to
+//! `{ENGINE}` / `{PROJECT}`; absolute path columns (components, modules,
+//! shader mappings) get the same placeholders.  Importing re-attaches those
+//! directories under the local roots and zeroes every `mtime`, so the next
+//! refresh hashes each file and only re-parses the ones whose `file_hash`
+//! differs (`process_file` returns `cache_hit` for the rest).
+
+use std::collections::HashMap;
+use std::path::Path;
+use rusqlite::{params, Connection, OptionalExtension};
+use super::path::{find_directory_id, get_or_create_directory};
+use super::{get_or_create_string, DB_VERSION};
+
+pub const ENGINE_PLACEHOLDER: &str = "{ENGINE}";
+pub const PROJECT_PLACEHOLDER: &str = "{PROJECT}";
+
+/// (table, column) — 絶対パスを文字列で持つ列
+const PATH_COLUMNS: &[(&str, &str)] = &[
+    ("components", "root_path"),
+    ("components", "uplugin_path"),
+    ("components", "uproject_path"),
+    ("modules", "build_cs_path"),
+    ("shader_mappings", "real_path"),
+];
+
+/// スナップショット固有ではない、マシン依存の project_meta
+const LOCAL_META_KEYS: &[&str] = &["vcs_game_revision", "custom_macros_hash", "reindex_pending"];
+
+fn normalize_root(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").trim_end_matches('/').to_string()
+}
+
+/// `db_path` の内容を `out_path` に書き出し、ルートを置き換え可能な形にする。
+/// ルートを省略した場合は components の Engine / Game エントリから求める。
+pub fn export_index(db_path: &Path, out_path: &Path, engine_root: Option<&Path>, project_root: Option<&Path>) -> anyhow::Result<()> {
+    let src = Connection::open(db_path)?;
+    let version: Option<String> = src
+        .query_row("SELECT value FROM project_meta WHERE key = 'db_version'", [], |r| r.get(0))
+        .optional()?;
+    if version.as_deref() != Some(DB_VERSION.to_string().as_str()) {
+        anyhow::bail!("DB version mismatch ({:?}, expected {}). Run a refresh first: {}", version, DB_VERSION, db_path.display());
+    }
+    let root_of = |comp_type: &str| -> Option<String> {
+        src.query_row("SELECT root_path FROM components WHERE type = ? LIMIT 1", [comp_type], |r| r.get(0)).ok()
+    };
+    let engine = engine_root.map(normalize_root).or_else(|| root_of("Engine"));
+    let project = project_root.map(normalize_root).or_else(|| root_of("Game"));
+    if engine.is_none() && project.is_none() {
+        anyhow::bail!("Could not determine engine/project roots; pass --engine / --project");
+    }
+
+    let _ = std::fs::remove_file(out_path);
+    src.execute("VACUUM INTO ?", [out_path.to_string_lossy()])?;
+    drop(src);
+
+    let mut conn = Connection::open(out_path)?;
+    // 共有エンジンインデックスから読んでいる行も書き出す (スナップショットだけで完結させる)
+    super::engine_cache::materialize(&conn)?;
+    let tx = conn.transaction()?;
+    let mut string_cache = HashMap::new();
+    // プロジェクトがエンジン配下にある場合に備えて、深いルートから置き換える
+    let mut roots: Vec<(&str, String)> = [(ENGINE_PLACEHOLDER, engine), (PROJECT_PLACEHOLDER, project)]
+        .into_iter()
+        .filter_map(|(p, r)| r.map(|r| (p, r)))
+        .collect();
+    roots.sort_by_key(|(_, r)| std::cmp::Reverse(r.len()));
+    for (placeholder, root) in &roots {
+        let Some(dir_id) = find_directory_id(&tx, Path::new(root))? else {
+            tracing::warn!("Root not found in index, left as is: {}", root);
+            continue;
+        };
+        let name_id = get_or_create_string(&tx, &mut string_cache, placeholder)?;
+        tx.execute("UPDATE directories SET parent_id = NULL, name_id = ? WHERE id = ?", params![name_id, dir_id])?;
+        rewrite_path_columns(&tx, root, placeholder)?;
+    }
+    // 切り離したルートの祖先 (ローカルの絶対パス) は空になるので、名前ごと消す
+    loop {
+        let n = tx.execute(
+            "DELETE FROM directories WHERE
+                 NOT EXISTS (SELECT 1 FROM directories c WHERE c.parent_id = directories.id)
+                 AND NOT EXISTS (SELECT 1 FROM files f WHERE f.directory_id = directories.id)
+                 AND NOT EXISTS (SELECT 1 FROM modules m WHERE m.root_directory_id = directories.id)",
+            [],
+        )?;
+        if n == 0 { break; }
+    }
+    let referenced = super::maintain::STRING_REFS.iter()
+        .map(|(t, c)| format!("SELECT {c} FROM {t} WHERE {c} IS NOT NULL", t = t, c = c))
+        .collect::<Vec<_>>()
+        .join(" UNION ");
+    tx.execute(&format!("DELETE FROM strings WHERE id NOT IN ({})", referenced), [])?;
+    for key in LOCAL_META_KEYS {
+        tx.execute("DELETE FROM project_meta WHERE key = ?", [key])?;
+    }
+    tx.commit()?;
+    conn.execute("VACUUM", [])?;
+    tracing::info!("Exported index snapshot: {}", out_path.display());
+    Ok(())
+}
+
+/// スナップショットを `db_path` に展開し、`{ENGINE}` / `{PROJECT}` をローカルのルートに付け替える。
+pub fn import_index(snapshot_path: &Path, db_path: &Path, engine_root: Option<&Path>, project_root: &Path) -> anyhow::Result<()> {
+    let tmp = db_path.with_extension("db.import");
+    let _ = std::fs::remove_file(&tmp);
+    Connection::open(snapshot_path)?.execute("VACUUM INTO ?", [tmp.to_string_lossy()])?;
+
+    let result = (|| -> anyhow::Result<()> {
+        let mut conn = Connection::open(&tmp)?;
+        let tx = conn.transaction()?;
+        let mut string_cache = HashMap::new();
+        let mut dir_cache = HashMap::new();
+        // エンジン側を先に付け替える (プロジェクトの親ディレクトリがエンジン配下の場合がある)
+        let roots = [(ENGINE_PLACEHOLDER, engine_root), (PROJECT_PLACEHOLDER, Some(project_root))];
+        for (placeholder, local) in roots {
+            let dir_id: Option<i64> = tx.query_row(
+                "SELECT d.id FROM directories d JOIN strings s ON d.name_id = s.id WHERE d.parent_id IS NULL AND s.text = ?",
+                [placeholder], |r| r.get(0),
+            ).optional()?;
+            let Some(dir_id) = dir_id else { continue };
+            let Some(local) = local else {
+                anyhow::bail!("Snapshot contains {} paths; pass --engine <root>", placeholder);
+            };
+            let name = local.file_name().and_then(|n| n.to_str())
+                .ok_or_else(|| anyhow::anyhow!("Invalid root: {}", local.display()))?;
+            let parent_id = match local.parent() {
+                Some(p) if !p.as_os_str().is_empty() => Some(get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p)?),
+                _ => None,
+            };
+            let name_id = get_or_create_string(&tx, &mut string_cache, name)?;
+            tx.execute("UPDATE directories SET parent_id = ?, name_id = ? WHERE id = ?", params![parent_id, name_id, dir_id])
+                .map_err(|e| anyhow::anyhow!("Failed to rebase {} onto {}: {}", placeholder, local.display(), e))?;
+            rewrite_path_columns(&tx, placeholder, &normalize_root(local))?;
+        }
+        // ローカルの mtime とは一致しないので、次回の refresh で全ファイルをハッシュ比較させる
+        tx.execute("UPDATE files SET mtime = 0", [])?;
+        tx.commit()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, db_path)?;
+    // 古いバージョンのスナップショットはここで移行される
+    super::ensure_correct_version(&db_path.to_string_lossy())?;
+    tracing::info!("Imported index snapshot into {}", db_path.display());
+    Ok(())
+}
+
+/// 各パス列の `from` で始まる値を `to` に置き換える
+fn rewrite_path_columns(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<()> {
+    for (table, column) in PATH_COLUMNS {
+        conn.execute(
+            &format!(
+                "UPDATE {t} SET {c} = ?2 || substr({c}, length(?1) + 1)
+                 WHERE substr({c}, 1, length(?1)) = ?1 AND (length({c}) = length(?1) OR substr({c}, length(?1) + 1, 1) = '/')",
+                t = table, c = column
+            ),
+            params![from, to],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_import_rebases_roots() {
+        let dir = std::env::temp_dir().join(format!("unl_snapshot_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = dir.join("src.db");
+        {
+            let conn = Connection::open(&db).unwrap();
+            super::super::init_db(&conn).unwrap();
+            let mut conn = conn;
+            let tx = conn.transaction().unwrap();
+            let (mut sc, mut dc) = (HashMap::new(), HashMap::new());
+            for (path, name) in [("/home/a/UE/Engine/Source", "Actor.h"), ("/home/a/Proj/Source", "MyActor.h")] {
+                let d = get_or_create_directory(&tx, &mut sc, &mut dc, Path::new(path)).unwrap();
+                let f = get_or_create_string(&tx, &mut sc, name).unwrap();
+                tx.execute("INSERT INTO files (directory_id, filename_id, extension, mtime, file_hash) VALUES (?, ?, 'h', 42, 'abc')", params![d, f]).unwrap();
+            }
+            tx.execute("INSERT INTO components (name, type, root_path) VALUES ('UE', 'Engine', '/home/a/UE'), ('Proj', 'Game', '/home/a/Proj')", []).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let snap = dir.join("snap.db");
+        export_index(&db, &snap, None, None).unwrap();
+        // ルートより上のローカルパス (/home/a) は残らない
+        let leaked: i64 = Connection::open(&snap).unwrap()
+            .query_row("SELECT COUNT(*) FROM strings WHERE text IN ('/', 'home', 'a', 'UE', 'Proj')", [], |r| r.get(0)).unwrap();
+        assert_eq!(leaked, 0);
+        let imported = dir.join("dst.db");
+        import_index(&snap, &imported, Some(Path::new("/opt/UE5")), Path::new("/work/Proj")).unwrap();
+
+        let conn = Connection::open(&imported).unwrap();
+        let engine_file = super::super::path::find_file_id(&conn, Path::new("/opt/UE5/Engine/Source/Actor.h")).unwrap();
+        let game_file = super::super::path::find_file_id(&conn, Path::new("/work/Proj/Source/MyActor.h")).unwrap();
+        assert!(engine_file.is_some() && game_file.is_some());
+        let roots: Vec<String> = conn.prepare("SELECT root_path FROM components ORDER BY name").unwrap()
+            .query_map([], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(roots, vec!["/work/Proj", "/opt/UE5"]);
+        let mtime: i64 = conn.query_row("SELECT MAX(mtime) FROM files", [], |r| r.get(0)).unwrap();
+        assert_eq!(mtime, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
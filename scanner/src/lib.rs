@@ -2,6 +2,8 @@ pub mod types;
 pub mod parser;
 pub mod db;
 pub mod refresh;
+pub mod refresh_report;
+pub mod refresh_checkpoint;
 pub mod query;
 pub mod completion;
 pub mod uasset;
@@ -9,6 +11,8 @@ pub mod uba;
 pub mod server;
 pub mod modify;
 pub mod vcs;
+pub mod lsif;
+pub mod plugin;
 
 // Backward compatibility: existing code using `scanner::` continues to work.
 // Future language parsers will live alongside cpp: parser::verse, parser::blueprint, etc.
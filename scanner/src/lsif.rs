@@ -0,0 +1,490 @@
+//! LSIF (Language Server Index Format 0.6) exporter.
+//!
+//! Emits one JSON object per line so that code-intelligence tools
+//! (Sourcegraph, review bots, ...) can consume the unl index:
+//! - definitions   : classes / members (+ hover with the declaration)
+//! - references    : symbol_calls, type usages in member signatures, base classes
+//! - implementation: base class → derived class definitions (inheritance)
+//! - monikers      : scheme "unl", identifier "<Module>:<Qualified::Name>";
+//!   symbols defined outside the exported root become "import" monikers
+//!
+//! The DB only stores line numbers, so columns are recovered by searching the
+//! symbol name on that line of the source file (column 0 if the file is missing).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use rusqlite::Connection;
+use crate::db::not_operator_sql;
+use serde_json::{json, Value};
+
+const MONIKER_SCHEME: &str = "unl";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Sym {
+    Class(i64),
+    Member(i64),
+}
+
+struct FileRow {
+    path: String,
+    module: Option<String>,
+}
+
+struct ClassRow {
+    name: String,
+    qualified: String,
+    symbol_type: String,
+    file_id: Option<i64>,
+    line: i64,
+    is_specialization: bool,
+    /// classes.language (cpp / verse / hlsl)。ホバーのコードブロックの言語に使う
+    language: String,
+}
+
+struct MemberRow {
+    class_id: i64,
+    name: String,
+    mem_type: String,
+    return_type: Option<String>,
+    file_id: Option<i64>,
+    line: i64,
+}
+
+/// Export summary returned to the CLI.
+pub struct LsifStats {
+    pub documents: usize,
+    pub definitions: usize,
+    pub references: usize,
+}
+
+struct Emitter<W: Write> {
+    out: W,
+    next_id: u64,
+}
+
+impl<W: Write> Emitter<W> {
+    fn vertex(&mut self, label: &str, extra: Value) -> anyhow::Result<u64> {
+        self.next_id += 1;
+        let mut obj = json!({ "id": self.next_id, "type": "vertex", "label": label });
+        if let (Some(o), Value::Object(e)) = (obj.as_object_mut(), extra) { o.extend(e); }
+        writeln!(self.out, "{}", obj)?;
+        Ok(self.next_id)
+    }
+
+    fn edge(&mut self, label: &str, out_v: u64, in_vs: &[u64], extra: Value) -> anyhow::Result<()> {
+        self.next_id += 1;
+        let mut obj = if in_vs.len() == 1 && label != "contains" && label != "item" {
+            json!({ "id": self.next_id, "type": "edge", "label": label, "outV": out_v, "inV": in_vs[0] })
+        } else {
+            json!({ "id": self.next_id, "type": "edge", "label": label, "outV": out_v, "inVs": in_vs })
+        };
+        if let (Some(o), Value::Object(e)) = (obj.as_object_mut(), extra) { o.extend(e); }
+        writeln!(self.out, "{}", obj)?;
+        Ok(())
+    }
+}
+
+fn identifier_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b[A-Za-z_]\w*\b").unwrap())
+}
+
+/// dir_paths の `C:///foo` 形式を `file:///C:/foo` に変換する
+fn to_uri(path: &str) -> String {
+    let p = path.replacen(":///", ":/", 1);
+    if p.starts_with('/') { format!("file://{}", p) } else { format!("file:///{}", p) }
+}
+
+fn language_id(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase().as_str() {
+        "usf" | "ush" => "hlsl",
+        "verse" => "verse",
+        "cs" => "csharp",
+        _ => "cpp",
+    }
+}
+
+/// `db_path` の内容を LSIF として `out_path` に書き出す。
+/// `root` 配下のファイルだけをドキュメントとして出力する (省略時は Game コンポーネントのルート)。
+pub fn export_lsif(db_path: &Path, out_path: &Path, root: Option<&str>) -> anyhow::Result<LsifStats> {
+    let conn = Connection::open(db_path)?;
+    crate::db::engine_cache::attach_views(&conn)?;
+    let root = match root {
+        Some(r) => r.replace('\\', "/").trim_end_matches('/').to_string(),
+        None => conn
+            .query_row("SELECT root_path FROM components WHERE type = 'Game' LIMIT 1", [], |r| r.get::<_, String>(0))
+            .map_err(|_| anyhow::anyhow!("No Game component in index; pass --root <dir>"))?,
+    };
+    let out = BufWriter::new(fs::File::create(out_path)?);
+    let stats = write_lsif(&conn, &root, out)?;
+    tracing::info!("Exported LSIF ({} documents) to {}", stats.documents, out_path.display());
+    Ok(stats)
+}
+
+fn write_lsif<W: Write>(conn: &Connection, root: &str, out: W) -> anyhow::Result<LsifStats> {
+    let files = load_files(conn)?;
+    let classes = load_classes(conn)?;
+    let members = load_members(conn)?;
+
+    let root_db = crate::db::path::to_db_path_format(root);
+    let in_root = |file_id: Option<i64>| -> bool {
+        file_id.and_then(|id| files.get(&id)).is_some_and(|f| {
+            f.path.starts_with(&root_db) && f.path[root_db.len()..].starts_with('/')
+        })
+    };
+
+    // 名前 → クラス (主テンプレートを優先)
+    let mut class_by_name: HashMap<&str, i64> = HashMap::new();
+    for (id, c) in &classes {
+        for key in [c.name.as_str(), c.qualified.as_str()] {
+            let entry = class_by_name.entry(key).or_insert(*id);
+            if classes[entry].is_specialization && !c.is_specialization { *entry = *id; }
+        }
+    }
+    // 名前 → メンバー (一意なものだけ参照の解決に使う)
+    let mut member_by_name: HashMap<&str, Option<i64>> = HashMap::new();
+    for (id, m) in &members {
+        member_by_name.entry(m.name.as_str()).and_modify(|e| *e = None).or_insert(Some(*id));
+    }
+
+    let mut em = Emitter { out, next_id: 0 };
+    let project_uri = to_uri(&root_db);
+    em.vertex("metaData", json!({
+        "version": "0.6.0",
+        "projectRoot": project_uri,
+        "positionEncoding": "utf-16",
+        "toolInfo": { "name": "unl-scanner", "version": env!("CARGO_PKG_VERSION") },
+    }))?;
+    let project_id = em.vertex("project", json!({ "kind": "cpp" }))?;
+
+    let mut doc_ids: BTreeMap<i64, u64> = BTreeMap::new();
+    let mut doc_ranges: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    let mut used_ranges: HashSet<(u64, i64, usize, Sym)> = HashSet::new();
+    let mut source_lines: HashMap<i64, Option<Vec<String>>> = HashMap::new();
+    let mut result_sets: HashMap<Sym, u64> = HashMap::new();
+    let mut defs: BTreeMap<Sym, Vec<(u64, u64)>> = BTreeMap::new();
+    let mut refs: BTreeMap<Sym, Vec<(u64, u64)>> = BTreeMap::new();
+    let mut impls: BTreeMap<Sym, Vec<(u64, u64)>> = BTreeMap::new();
+
+    macro_rules! result_set {
+        ($sym:expr) => {{
+            let sym: Sym = $sym;
+            match result_sets.get(&sym) {
+                Some(&rs) => rs,
+                None => {
+                    let rs = em.vertex("resultSet", json!({}))?;
+                    let (identifier, hover, defined_here) = describe(sym, &classes, &members, &files);
+                    let defined_here = in_root(defined_here);
+                    let moniker = em.vertex("moniker", json!({
+                        "scheme": MONIKER_SCHEME,
+                        "identifier": identifier,
+                        "kind": if defined_here { "export" } else { "import" },
+                        "unique": "workspace",
+                    }))?;
+                    em.edge("moniker", rs, &[moniker], json!({}))?;
+                    let hover_id = em.vertex("hoverResult", json!({
+                        "result": { "contents": { "kind": "markdown", "value": hover } }
+                    }))?;
+                    em.edge("textDocument/hover", rs, &[hover_id], json!({}))?;
+                    result_sets.insert(sym, rs);
+                    rs
+                }
+            }
+        }};
+    }
+
+    // 1 箇所 (file, line, name) に range を作り、対応する resultSet に next で繋ぐ
+    macro_rules! place {
+        ($file_id:expr, $line:expr, $name:expr, $sym:expr) => {{
+            let (file_id, line, name, sym): (i64, i64, &str, Sym) = ($file_id, $line, $name, $sym);
+            let doc = match doc_ids.get(&file_id) {
+                Some(&d) => d,
+                None => {
+                    let f = &files[&file_id];
+                    let d = em.vertex("document", json!({ "uri": to_uri(&f.path), "languageId": language_id(&f.path) }))?;
+                    doc_ids.insert(file_id, d);
+                    d
+                }
+            };
+            let lines = source_lines.entry(file_id).or_insert_with(|| {
+                fs::read_to_string(&files[&file_id].path.replacen(":///", ":/", 1)).ok()
+                    .map(|s| s.lines().map(str::to_string).collect())
+            });
+            let row = (line - 1).max(0);
+            let start = lines.as_ref()
+                .and_then(|ls| ls.get(row as usize))
+                .and_then(|text| find_word(text, name))
+                .unwrap_or(0);
+            if used_ranges.insert((doc, row, start, sym)) {
+                let end = start + name.encode_utf16().count();
+                let range = em.vertex("range", json!({
+                    "start": { "line": row, "character": start },
+                    "end": { "line": row, "character": end },
+                }))?;
+                doc_ranges.entry(doc).or_default().push(range);
+                let rs = result_set!(sym);
+                em.edge("next", range, &[rs], json!({}))?;
+                Some((doc, range))
+            } else {
+                None
+            }
+        }};
+    }
+    // Definitions
+    for (id, c) in &classes {
+        if !in_root(c.file_id) { continue; }
+        if let Some(r) = place!(c.file_id.unwrap(), c.line, &c.name, Sym::Class(*id)) {
+            defs.entry(Sym::Class(*id)).or_default().push(r);
+        }
+    }
+    for (id, m) in &members {
+        let file_id = m.file_id.or_else(|| classes.get(&m.class_id).and_then(|c| c.file_id));
+        if !in_root(file_id) { continue; }
+        if let Some(r) = place!(file_id.unwrap(), m.line, &m.name, Sym::Member(*id)) {
+            defs.entry(Sym::Member(*id)).or_default().push(r);
+        }
+    }
+
+    // Inheritance: 派生クラスの定義行にある基底クラス名を参照とし、implementation に派生クラスを登録する
+    {
+        let mut stmt = conn.prepare("SELECT child_id, parent_class_id FROM inheritance WHERE parent_class_id IS NOT NULL")?;
+        let rows: Vec<(i64, i64)> = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?.filter_map(|r| r.ok()).collect();
+        for (child, parent) in rows {
+            let (Some(c), Some(p)) = (classes.get(&child), classes.get(&parent)) else { continue };
+            if !in_root(c.file_id) { continue; }
+            if let Some(r) = place!(c.file_id.unwrap(), c.line, &p.name, Sym::Class(parent)) {
+                refs.entry(Sym::Class(parent)).or_default().push(r);
+            }
+            if let Some(def) = defs.get(&Sym::Class(child)).and_then(|d| d.first()).copied() {
+                impls.entry(Sym::Class(parent)).or_default().push(def);
+            }
+        }
+    }
+
+    // Type usages in member signatures
+    for m in members.values() {
+        let file_id = m.file_id.or_else(|| classes.get(&m.class_id).and_then(|c| c.file_id));
+        if !in_root(file_id) { continue; }
+        let sig = format!("{} {}", m.mem_type, m.return_type.as_deref().unwrap_or(""));
+        let mut seen = HashSet::new();
+        for word in identifier_regex().find_iter(&sig).map(|w| w.as_str()) {
+            if !seen.insert(word) { continue; }
+            let Some(&cid) = class_by_name.get(word) else { continue };
+            if let Some(r) = place!(file_id.unwrap(), m.line, word, Sym::Class(cid)) {
+                refs.entry(Sym::Class(cid)).or_default().push(r);
+            }
+        }
+    }
+
+    // symbol_calls
+    {
+        let mut stmt = conn.prepare(
+            "SELECT sc.file_id, sc.line, s.text FROM symbol_calls sc JOIN strings s ON sc.name_id = s.id",
+        )?;
+        let rows: Vec<(i64, i64, String)> = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?.filter_map(|r| r.ok()).collect();
+        for (file_id, line, name) in rows {
+            if !in_root(Some(file_id)) { continue; }
+            let sym = match (class_by_name.get(name.as_str()), member_by_name.get(name.as_str())) {
+                (Some(&cid), _) => Sym::Class(cid),
+                (None, Some(Some(mid))) => Sym::Member(*mid),
+                _ => continue,
+            };
+            if let Some(r) = place!(file_id, line, &name, sym) {
+                refs.entry(sym).or_default().push(r);
+            }
+        }
+    }
+
+    // Result vertices
+    let (mut n_defs, mut n_refs) = (0, 0);
+    let all_syms: Vec<Sym> = result_sets.keys().copied().collect::<std::collections::BTreeSet<_>>().into_iter().collect();
+    for sym in all_syms {
+        let rs = result_sets[&sym];
+        let sym_defs = defs.get(&sym).cloned().unwrap_or_default();
+        if !sym_defs.is_empty() {
+            let def_result = em.vertex("definitionResult", json!({}))?;
+            em.edge("textDocument/definition", rs, &[def_result], json!({}))?;
+            for (doc, ranges) in group_by_doc(&sym_defs) {
+                em.edge("item", def_result, &ranges, json!({ "document": doc }))?;
+            }
+            n_defs += sym_defs.len();
+        }
+        let sym_refs = refs.get(&sym).cloned().unwrap_or_default();
+        let ref_result = em.vertex("referenceResult", json!({}))?;
+        em.edge("textDocument/references", rs, &[ref_result], json!({}))?;
+        for (doc, ranges) in group_by_doc(&sym_defs) {
+            em.edge("item", ref_result, &ranges, json!({ "document": doc, "property": "definitions" }))?;
+        }
+        for (doc, ranges) in group_by_doc(&sym_refs) {
+            em.edge("item", ref_result, &ranges, json!({ "document": doc, "property": "references" }))?;
+        }
+        n_refs += sym_refs.len();
+        if let Some(sym_impls) = impls.get(&sym) {
+            let impl_result = em.vertex("implementationResult", json!({}))?;
+            em.edge("textDocument/implementation", rs, &[impl_result], json!({}))?;
+            for (doc, ranges) in group_by_doc(sym_impls) {
+                em.edge("item", impl_result, &ranges, json!({ "document": doc }))?;
+            }
+        }
+    }
+
+    for (doc, ranges) in &doc_ranges {
+        em.edge("contains", *doc, ranges, json!({}))?;
+    }
+    let docs: Vec<u64> = doc_ids.values().copied().collect();
+    if !docs.is_empty() {
+        em.edge("contains", project_id, &docs, json!({}))?;
+    }
+    em.out.flush()?;
+    Ok(LsifStats { documents: docs.len(), definitions: n_defs, references: n_refs })
+}
+
+fn group_by_doc(items: &[(u64, u64)]) -> BTreeMap<u64, Vec<u64>> {
+    let mut map: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for (doc, range) in items { map.entry(*doc).or_default().push(*range); }
+    map
+}
+
+/// (moniker identifier, hover text, 定義ファイル)
+fn describe(sym: Sym, classes: &HashMap<i64, ClassRow>, members: &HashMap<i64, MemberRow>, files: &HashMap<i64, FileRow>) -> (String, String, Option<i64>) {
+    let module_of = |file_id: Option<i64>| -> String {
+        file_id.and_then(|id| files.get(&id)).and_then(|f| f.module.clone()).unwrap_or_default()
+    };
+    match sym {
+        Sym::Class(id) => {
+            let c = &classes[&id];
+            (format!("{}:{}", module_of(c.file_id), c.qualified), fenced(&c.language, &format!("{} {}", c.symbol_type, c.qualified)), c.file_id)
+        }
+        Sym::Member(id) => {
+            let m = &members[&id];
+            let owner = classes.get(&m.class_id);
+            let file_id = m.file_id.or_else(|| owner.and_then(|c| c.file_id));
+            let scope = owner.map(|c| c.qualified.as_str()).unwrap_or("");
+            let ty = m.return_type.as_deref().unwrap_or(&m.mem_type);
+            let language = owner.map(|c| c.language.as_str()).unwrap_or("cpp");
+            (format!("{}:{}::{}", module_of(file_id), scope, m.name), fenced(language, &format!("{} {}::{}", ty, scope, m.name)), file_id)
+        }
+    }
+}
+
+/// ホバー用の Markdown コードブロック (言語はシンボルの classes.language)
+fn fenced(language: &str, code: &str) -> String {
+    format!("```{}\n{}\n```", language, code)
+}
+
+/// 単語境界で `name` を探し、UTF-16 の列位置を返す
+fn find_word(text: &str, name: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(name) {
+        let start = from + pos;
+        let end = start + name.len();
+        let before_ok = text[..start].chars().last().is_none_or(|c| !is_ident(c));
+        let after_ok = text[end..].chars().next().is_none_or(|c| !is_ident(c));
+        if before_ok && after_ok { return Some(text[..start].encode_utf16().count()); }
+        from = end;
+    }
+    None
+}
+
+fn load_files(conn: &Connection) -> anyhow::Result<HashMap<i64, FileRow>> {
+    let sql = "SELECT f.id, dp.full_path || '/' || sf.text, sm.text
+         FROM files f
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sf ON f.filename_id = sf.id
+         LEFT JOIN modules m ON f.module_id = m.id
+         LEFT JOIN strings sm ON m.name_id = sm.id";
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, FileRow { path: r.get(1)?, module: r.get(2)? })))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+fn load_classes(conn: &Connection) -> anyhow::Result<HashMap<i64, ClassRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, sc.text, COALESCE(sq.text, sc.text), c.symbol_type, c.file_id, c.line_number, c.specialization_args IS NOT NULL,
+                COALESCE(c.language, 'cpp')
+         FROM classes c
+         JOIN strings sc ON c.name_id = sc.id
+         LEFT JOIN strings sq ON c.qualified_name_id = sq.id
+         WHERE c.symbol_type != 'namespace'",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, ClassRow {
+        name: r.get(1)?,
+        qualified: r.get(2)?,
+        symbol_type: r.get::<_, Option<String>>(3)?.unwrap_or_else(|| "class".to_string()),
+        file_id: r.get(4)?,
+        line: r.get::<_, Option<i64>>(5)?.unwrap_or(1),
+        is_specialization: r.get(6)?,
+        language: r.get(7)?,
+    })))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+fn load_members(conn: &Connection) -> anyhow::Result<HashMap<i64, MemberRow>> {
+    let mut stmt = conn.prepare(
+        &format!("SELECT m.id, m.class_id, sn.text, st.text, sr.text, m.file_id, m.line_number
+         FROM members m
+         JOIN strings sn ON m.name_id = sn.id
+         JOIN strings st ON m.type_id = st.id
+         LEFT JOIN strings sr ON m.return_type_id = sr.id
+         WHERE {}", not_operator_sql("sn.text"),
+    ))?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, MemberRow {
+        class_id: r.get(1)?,
+        name: r.get(2)?,
+        mem_type: r.get(3)?,
+        return_type: r.get(4)?,
+        file_id: r.get(5)?,
+        line: r.get::<_, Option<i64>>(6)?.unwrap_or(1),
+    })))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_definitions_monikers_and_implementations() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO strings (id, text) VALUES (1, '/'), (2, 'proj'), (3, 'Actor.h'), (4, 'AMyActor'), (5, 'AActor'),
+                                                  (6, 'Tick'), (7, 'void'), (8, 'MyGame'), (9, 'FVector'), (10, 'Location'), (11, 'ue');
+             INSERT INTO directories (id, parent_id, name_id) VALUES (1, NULL, 1), (2, 1, 2), (3, 1, 11);
+             INSERT INTO modules (id, name_id, root_directory_id) VALUES (1, 8, 2);
+             INSERT INTO files (id, directory_id, filename_id, module_id) VALUES (1, 2, 3, 1), (2, 3, 3, NULL);
+             INSERT INTO classes (id, name_id, qualified_name_id, file_id, line_number, symbol_type) VALUES
+                 (1, 4, 4, 1, 3, 'class'), (2, 5, 5, 2, 1, 'class'), (3, 9, 9, 2, 2, 'struct');
+             UPDATE classes SET language = 'verse' WHERE id = 2;
+             INSERT INTO members (id, class_id, name_id, type_id, file_id, line_number) VALUES
+                 (1, 1, 6, 7, 1, 5), (2, 1, 10, 9, 1, 6);
+             INSERT INTO inheritance (child_id, parent_name_id, parent_class_id) VALUES (1, 5, 2);",
+        ).unwrap();
+
+        let mut buf = Vec::new();
+        let stats = write_lsif(&conn, "/proj", &mut buf).unwrap();
+        assert_eq!((stats.documents, stats.definitions, stats.references), (1, 3, 2));
+
+        let lines: Vec<Value> = String::from_utf8(buf).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let monikers: HashSet<(String, String)> = lines.iter()
+            .filter(|v| v["type"] == "vertex" && v["label"] == "moniker")
+            .map(|v| (v["identifier"].as_str().unwrap().to_string(), v["kind"].as_str().unwrap().to_string()))
+            .collect();
+        assert!(monikers.contains(&("MyGame:AMyActor".to_string(), "export".to_string())));
+        assert!(monikers.contains(&("MyGame:AMyActor::Tick".to_string(), "export".to_string())));
+        assert!(monikers.contains(&(":AActor".to_string(), "import".to_string())));
+        assert!(lines.iter().any(|v| v["label"] == "textDocument/implementation"));
+        assert!(lines.iter().all(|v| v["label"] != "document" || v["uri"] == "file:///proj/Actor.h"));
+        let hovers: HashSet<&str> = lines.iter()
+            .filter(|v| v["label"] == "hoverResult")
+            .filter_map(|v| v["result"]["contents"]["value"].as_str())
+            .collect();
+        assert!(hovers.contains("```cpp\nclass AMyActor\n```"));
+        assert!(hovers.contains("```verse\nclass AActor\n```"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use rayon::prelude::*;
 use tree_sitter::Query;
@@ -23,6 +24,38 @@ fn main() -> anyhow::Result<()> {
                 }
                 return Ok(());
             },
+            "export-index" => {
+                let usage = "Usage: export-index <db_path> <out_path> [--engine <root>] [--project <root>]";
+                let db_path = args.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let out_path = args.get(3).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let engine = flag_value(&args, "--engine").map(PathBuf::from);
+                let project = flag_value(&args, "--project").map(PathBuf::from);
+                db::snapshot::export_index(Path::new(db_path), Path::new(out_path), engine.as_deref(), project.as_deref())?;
+                println!("Exported: {}", out_path);
+                return Ok(());
+            },
+            "import-index" => {
+                let usage = "Usage: import-index <snapshot> <db_path> --project <root> [--engine <root>]";
+                let snapshot = args.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let db_path = args.get(3).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let project = flag_value(&args, "--project").map(PathBuf::from).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let engine = flag_value(&args, "--engine").map(PathBuf::from);
+                if is_server_running {
+                    eprintln!("Warning: the server may hold {} open; restart it after importing.", db_path);
+                }
+                db::snapshot::import_index(Path::new(snapshot), Path::new(db_path), engine.as_deref(), &project)?;
+                println!("Imported: {} (run a refresh to re-parse changed files)", db_path);
+                return Ok(());
+            },
+            "export-lsif" => {
+                let usage = "Usage: export-lsif <db_path> <out_path> [--root <dir>]";
+                let db_path = args.get(2).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let out_path = args.get(3).ok_or_else(|| anyhow::anyhow!(usage))?;
+                let root = flag_value(&args, "--root");
+                let stats = unl_core::lsif::export_lsif(Path::new(db_path), Path::new(out_path), root.as_deref())?;
+                println!("Exported {} documents, {} definitions, {} references: {}", stats.documents, stats.definitions, stats.references, out_path);
+                return Ok(());
+            },
             "refresh" | "watch" | "query" | "setup" => {
                 let arg = args.get(2).ok_or_else(|| anyhow::anyhow!("Missing config"))?;
                 let input_str = if arg.starts_with('{') { arg.clone() } else if std::path::Path::new(arg).exists() { std::fs::read_to_string(arg)? } else { arg.clone() };
@@ -30,7 +63,7 @@ fn main() -> anyhow::Result<()> {
                     return proxy_to_server(server_port, cmd, &input_str);
                 } else if cmd == "refresh" {
                     let req: unl_core::types::RefreshRequest = serde_json::from_str(&input_str)?;
-                    return refresh::run_refresh(req, Arc::new(unl_core::types::StdoutReporter));
+                    return refresh::run_refresh(req, Arc::new(unl_core::types::StdoutReporter)).map(|_| ());
                 } else {
                     return Err(anyhow::anyhow!("Server not running"));
                 }
@@ -49,6 +82,10 @@ fn main() -> anyhow::Result<()> {
     run_scan_command(server_port, is_server_running)
 }
 
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
+
 fn proxy_to_server(port: u16, method: &str, json_payload: &str) -> anyhow::Result<()> {
     let params: Value = serde_json::from_str(json_payload).map_err(|e| anyhow::anyhow!("JSON Parse Error: {}", e))?;
     let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port))?;
@@ -1,2 +1,26 @@
 pub mod uproject;
 pub mod target;
+
+use std::path::Path;
+use anyhow::Result;
+use crate::vcs::{self, VcsAction};
+
+/// Writes the modified contents back, keeping the original as `<file>.old`.
+/// The file is checked out through the VCS that controls it first (`p4 edit`
+/// for read-only Perforce files), so the backup and the write don't fail on
+/// read-only workspaces.  Writable files without a changelist need no checkout,
+/// so the VCS (whose detection may run `p4`) is only looked up otherwise.  A
+/// `changelist` for a file that is not under Perforce is an error, not ignored.
+fn write_file(file_path: &str, contents: &str, changelist: Option<&str>) -> Result<Option<VcsAction>> {
+    let path = Path::new(file_path);
+    let readonly = path.metadata().map(|m| m.permissions().readonly()).unwrap_or(false);
+    let vcs_action = if readonly || changelist.is_some() {
+        vcs::detect(path.parent().unwrap_or(Path::new("."))).checkout(path, changelist)?
+    } else {
+        None
+    };
+    let backup = format!("{}.old", file_path);
+    std::fs::copy(file_path, &backup)?;
+    std::fs::write(file_path, contents)?;
+    Ok(vcs_action)
+}
@@ -6,6 +6,7 @@
 //!     contains the named module in its `ExtraModuleNames.AddRange` call.
 
 use anyhow::{anyhow, Result};
+use crate::vcs::VcsAction;
 use tree_sitter::{Node, Parser, Query, QueryCursor};
 use streaming_iterator::StreamingIterator;
 
@@ -13,7 +14,8 @@ use streaming_iterator::StreamingIterator;
 // Public entry point
 // ---------------------------------------------------------------------------
 
-pub fn add_module(file_path: &str, module_name: &str) -> Result<()> {
+/// Returns the VCS action run to make the file writable, if any.
+pub fn add_module(file_path: &str, module_name: &str, changelist: Option<&str>) -> Result<Option<VcsAction>> {
     let raw = std::fs::read_to_string(file_path)?;
     let mut lines: Vec<String> = raw.lines().map(|l| l.to_string()).collect();
 
@@ -108,11 +110,8 @@ pub fn add_module(file_path: &str, module_name: &str) -> Result<()> {
         offset += 6;
     }
 
-    let backup = format!("{}.old", file_path);
-    std::fs::copy(file_path, &backup)?;
-    std::fs::write(file_path, lines.join("\n"))?;
     let _ = offset;
-    Ok(())
+    super::write_file(file_path, &lines.join("\n"), changelist)
 }
 
 // ---------------------------------------------------------------------------
@@ -4,6 +4,7 @@
 //! then inserts a new Modules entry if the named module is not already listed.
 
 use anyhow::{anyhow, Result};
+use crate::vcs::VcsAction;
 use tree_sitter::{Node, Parser, Query, QueryCursor};
 use streaming_iterator::StreamingIterator;
 
@@ -13,12 +14,14 @@ use streaming_iterator::StreamingIterator;
 
 /// Add a module entry to `file_path`.
 /// Mirrors `M.add_module(file_path, module_opts)` in uproject.lua.
+/// Returns the VCS action run to make the file writable, if any.
 pub fn add_module(
     file_path: &str,
     module_name: &str,
     module_type: &str,
     loading_phase: &str,
-) -> Result<()> {
+    changelist: Option<&str>,
+) -> Result<Option<VcsAction>> {
     let raw = std::fs::read_to_string(file_path)?;
     let mut lines: Vec<String> = raw.lines().map(|l| l.to_string()).collect();
 
@@ -179,7 +182,7 @@ pub fn add_module(
     }
 
     if module_exists {
-        return Ok(());
+        return Ok(None);
     }
 
     if modules_node.is_none() {
@@ -230,10 +233,7 @@ pub fn add_module(
         lines.insert(end_row + 1, format!("{}{{", t2));
     }
 
-    let backup = format!("{}.old", file_path);
-    std::fs::copy(file_path, &backup)?;
-    std::fs::write(file_path, lines.join("\n"))?;
-    Ok(())
+    super::write_file(file_path, &lines.join("\n"), changelist)
 }
 
 // ---------------------------------------------------------------------------
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::cell::RefCell;
 use std::path::Path;
@@ -7,7 +8,11 @@ use streaming_iterator::StreamingIterator;
 use sha2::{Sha256, Digest};
 use memmap2::Mmap;
 use regex::Regex;
-use crate::types::{InputFile, ParseResult, ParseData, ClassInfo, MemberInfo};
+use crate::types::{InputFile, ParseResult, ParseData, ClassInfo, MemberInfo, TemplateParam};
+use crate::parser::template;
+
+/// (classes, calls, includes, refs)
+pub type ParsedContent = (Vec<ClassInfo>, Vec<crate::types::CallInfo>, Vec<String>, Vec<crate::types::SymbolRef>);
 
 struct CleanRegexes {
     keywords: Vec<Regex>,
@@ -37,6 +42,8 @@ thread_local! {
 pub const QUERY_STR: &str = r#"
   (class_specifier name: (type_identifier) @class_name) @class_def
   (struct_specifier name: (type_identifier) @struct_name) @struct_def
+  (class_specifier name: (template_type name: (type_identifier) @class_name)) @class_spec_def
+  (struct_specifier name: (template_type name: (type_identifier) @struct_name)) @struct_spec_def
   (enum_specifier name: (type_identifier) @enum_name) @enum_def
   (unreal_class_declaration name: (type_identifier) @class_name) @uclass_def
   (unreal_struct_declaration name: (_) @struct_name) @ustruct_def
@@ -59,14 +66,17 @@ pub const QUERY_STR: &str = r#"
 
 pub const INCLUDE_QUERY_STR: &str = "(preproc_include path: [(string_literal) @path (system_lib_string) @path]) @include";
 
+/// files.file_hash に保存する内容ハッシュ (SHA-256)
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
 pub fn process_file(input: &InputFile, language: &tree_sitter::Language, query: &Query, include_query: &Query) -> anyhow::Result<ParseResult> {
     let file = File::open(&input.path)?;
     let mmap = unsafe { Mmap::map(&file)? };
     let content_bytes = &mmap[..];
     
-    let mut hasher = Sha256::new();
-    hasher.update(content_bytes);
-    let new_hash = format!("{:x}", hasher.finalize());
+    let new_hash = content_hash(content_bytes);
 
     if let Some(old) = &input.old_hash {
         if old == &new_hash {
@@ -81,33 +91,59 @@ pub fn process_file(input: &InputFile, language: &tree_sitter::Language, query:
     let ext = Path::new(&input.path).extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
     let is_header = matches!(ext.as_str(), "h" | "hpp" | "inl");
 
+    // シェーダー / Verse ソースは Tree-sitter を通さず専用スキャナで解析
+    if matches!(ext.as_str(), "usf" | "ush" | "verse") {
+        let content = String::from_utf8_lossy(content_bytes);
+        let (classes, includes, parser) = if ext == "verse" {
+            let (c, i) = crate::parser::verse::parse_verse_content(&content);
+            (c, i, "verse")
+        } else {
+            let (c, i) = crate::parser::shader::parse_shader_content(&content);
+            (c, i, "shader")
+        };
+        return Ok(ParseResult {
+            path: input.path.clone(), status: "parsed".to_string(), mtime: input.mtime,
+            data: Some(ParseData { classes, calls: vec![], includes, parser: parser.to_string(), new_hash, shader_bindings: vec![], shader_mappings: vec![], refs: vec![] }),
+            module_id: input.module_id,
+        });
+    }
+
     if is_header {
         // ヘッダーファイルのみ、高速フィルタリングを適用
         let has_important_keywords = 
             content_bytes.windows(7).any(|w| w == b"UCLASS(" || w == b"USTRUCT" || w == b"UENUM(" || w == b"DECLARE" || w == b"include" || w == b"#define")
             || content_bytes.windows(10).any(|w| w == b"UFUNCTION" || w == b"UPROPERTY")
-            || content_bytes.windows(12).any(|w| w == b"GAMEPLAY_TAG");
+            || content_bytes.windows(12).any(|w| w == b"GAMEPLAY_TAG")
+            || crate::parser::macros::for_path(&input.path).is_some_and(|m| crate::parser::macros::mentions_any(content_bytes, &m));
 
         if !has_important_keywords {
             return Ok(ParseResult {
                 path: input.path.clone(), status: "parsed".to_string(), mtime: input.mtime,
-                data: Some(ParseData { classes: vec![], calls: vec![], includes: vec![], parser: "fast-skip".to_string(), new_hash }),
+                data: Some(ParseData { classes: vec![], calls: vec![], includes: vec![], parser: "fast-skip".to_string(), new_hash, shader_bindings: vec![], shader_mappings: vec![], refs: vec![] }),
                 module_id: input.module_id,
             });
         }
     }
 
     // .cpp や重要なヘッダーはパース実行
-    let (classes, calls, includes) = parse_content_mmap(content_bytes, &input.path, language, query, include_query)?;
+    let (classes, calls, includes, refs) = parse_content_mmap(content_bytes, &input.path, language, query, include_query)?;
+
+    // IMPLEMENT_GLOBAL_SHADER 等と AddShaderSourceDirectoryMapping の抽出
+    let (shader_bindings, shader_mappings) =
+        if content_bytes.windows(10).any(|w| w == b"IMPLEMENT_") || content_bytes.windows(31).any(|w| w == b"AddShaderSourceDirectoryMapping") {
+            crate::parser::shader::scan_cpp_shader_declarations(&String::from_utf8_lossy(content_bytes), &input.path)
+        } else {
+            (vec![], vec![])
+        };
 
     Ok(ParseResult {
         path: input.path.clone(), status: "parsed".to_string(), mtime: input.mtime,
-        data: Some(ParseData { classes, calls, includes, parser: "treesitter".to_string(), new_hash }),
+        data: Some(ParseData { classes, calls, includes, parser: "treesitter".to_string(), new_hash, shader_bindings, shader_mappings, refs }),
         module_id: input.module_id,
     })
 }
 
-pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sitter::Language, query: &Query, include_query: &Query) -> anyhow::Result<(Vec<ClassInfo>, Vec<crate::types::CallInfo>, Vec<String>)> {
+pub fn parse_content_mmap(content_bytes: &[u8], path: &str, language: &tree_sitter::Language, query: &Query, include_query: &Query) -> anyhow::Result<ParsedContent> {
     PARSER.with(|p_cell| {
         let mut parser = p_cell.borrow_mut();
         parser.set_language(language).unwrap();
@@ -122,6 +158,7 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
             let mut calls: Vec<crate::types::CallInfo> = Vec::new();
             let mut includes: Vec<String> = Vec::new();
             let mut members: Vec<(MemberInfo, usize, usize, bool)> = Vec::new();
+            let mut class_index = ClassIndex::default();
 
             // インクルード解析 (渡された include_query を使用)
             let mut include_matches = cursor.matches(include_query, root, content_bytes);
@@ -148,10 +185,17 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                 }
 
                 if *capture_name == "class_name" || *capture_name == "struct_name" || *capture_name == "enum_name" {
-                    if let Some(parent) = node.parent() {
+                    if let Some(mut parent) = node.parent() {
+                        // template<> struct TFoo<int32> { ... } → 名前は template_type の内側にある
+                        let mut specialization_args = None;
+                        if parent.kind() == "template_type" {
+                            specialization_args = parent.child_by_field_name("arguments").map(|a| template::normalize_args(get_node_text(&a, content_bytes)));
+                            match parent.parent() { Some(p) => parent = p, None => continue }
+                        }
                         if parent.child_by_field_name("body").is_some() {
                             let mut name = get_node_text(&node, content_bytes).to_string();
                             let namespace = get_namespace(&parent, content_bytes);
+                            let parent_type = if has_enclosing_type(&parent) { namespace.clone() } else { None };
                             if *capture_name == "enum_name" && name == "Type" { if let Some(ns) = &namespace { name = format!("{}::{}", ns, name); } }
 
                             let mut symbol_type = match *capture_name { "struct_name" => "struct", "enum_name" => "enum", _ => "class" };
@@ -164,7 +208,8 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                                 class_name: name, namespace, base_classes: Vec::new(), symbol_type: symbol_type.to_string(),
                                 line: node.start_position().row + 1, end_line: parent.end_position().row + 1,
                                 range_start: parent.start_byte(), range_end: parent.end_byte(),
-                                members: Vec::new(), is_final: false, is_interface: false,
+                                members: Vec::new(), is_final: false, is_interface: false, parent_type,
+                                template_params: get_template_params(&parent, content_bytes), specialization_args,
                             });
                         }
                     }
@@ -189,6 +234,8 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                         loop {
                             match current.kind() {
                                 "identifier" | "field_identifier" => { member_name = get_node_text(&current, content_bytes).to_string(); break; },
+                                // operator[] などは補完には出さないが、テンプレート引数の解決に使う
+                                "operator_name" => { member_name = get_node_text(&current, content_bytes).split_whitespace().collect::<Vec<_>>().join(" "); break; },
                                 "qualified_identifier" => {
                                     if let Some(s) = current.child_by_field_name("scope") { scope_name = Some(get_node_text(&s, content_bytes).to_string()); }
                                     if let Some(n) = current.child_by_field_name("name") { member_name = get_node_text(&n, content_bytes).to_string(); }
@@ -206,6 +253,7 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                         let mut access = if scope_name.is_some() && is_function { "impl".to_string() } else { "public".to_string() };
                         if has_child_type(node, "ufunction_macro") || node.kind() == "unreal_function_declaration" { flags.push("UFUNCTION"); is_function = true; }
                         if has_child_type(node, "uproperty_macro") { flags.push("UPROPERTY"); is_function = false; }
+                        let specifiers = get_macro_specifiers(node, content_bytes);
                         
                         let mut curr = node;
                         while let Some(parent) = curr.parent() {
@@ -247,10 +295,15 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                         if is_function { if let Some(params) = find_child_by_type(node, "parameter_list") { detail = Some(get_node_text(&params, content_bytes).to_string()); } }
 
                         if !["virtual", "static", "void", "const"].contains(&member_name.as_str()) {
-                            let mut member = MemberInfo { name: member_name.clone(), mem_type: (if is_function { "function" } else { "property" }).to_string(), flags: flags.join(" "), access, line: node.start_position().row + 1, end_line: node.end_position().row + 1, detail, return_type };
+                            let mut member = MemberInfo { name: member_name.clone(), mem_type: (if is_function { "function" } else { "property" }).to_string(), flags: flags.join(" "), access, line: node.start_position().row + 1, end_line: node.end_position().row + 1, detail, return_type, specifiers, template_params: get_template_params(&node, content_bytes) };
                             if let Some(sn) = scope_name {
-                                let idx = classes.iter().position(|c| c.class_name == sn).unwrap_or_else(|| {
-                                    classes.push(ClassInfo { class_name: sn.clone(), namespace: None, base_classes: vec![], symbol_type: "class".to_string(), line: 1, end_line: 999999, range_start: 0, range_end: 0, members: vec![], is_final: false, is_interface: false });
+                                // `void FOuter::FInner::Func()` は完全修飾名でも照合する
+                                let idx = class_index.find(&classes, &sn).unwrap_or_else(|| {
+                                    let (namespace, class_name) = match sn.rsplit_once("::") {
+                                        Some((ns, name)) => (Some(ns.to_string()), name.to_string()),
+                                        None => (None, sn.clone()),
+                                    };
+                                    classes.push(ClassInfo { class_name, namespace, base_classes: vec![], symbol_type: "class".to_string(), line: 1, end_line: 999999, range_start: 0, range_end: 0, members: vec![], is_final: false, is_interface: false, parent_type: None, template_params: vec![], specialization_args: None });
                                     classes.len() - 1
                                 });
                                 member.access = "impl".to_string();
@@ -294,12 +347,14 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                                 end_line: node.end_position().row + 1,
                                 detail: if alias_type.is_empty() { None } else { Some(alias_type.clone()) },
                                 return_type: if alias_type.is_empty() { None } else { Some(alias_type) },
+                                specifiers: None,
+                                template_params: vec![],
                             };
                             members.push((member, node.start_byte(), node.end_byte(), is_inside_compound_statement(node)));
                         }
                     }
                 } else if *capture_name == "enum_val_name" {
-                    members.push((MemberInfo { name: get_node_text(&node, content_bytes).to_string(), mem_type: "enum_item".to_string(), flags: "".to_string(), access: "public".to_string(), line: node.start_position().row + 1, end_line: node.end_position().row + 1, detail: None, return_type: None }, node.start_byte(), node.end_byte(), false));
+                    members.push((MemberInfo { name: get_node_text(&node, content_bytes).to_string(), mem_type: "enum_item".to_string(), flags: "".to_string(), access: "public".to_string(), line: node.start_position().row + 1, end_line: node.end_position().row + 1, detail: None, return_type: None, specifiers: None, template_params: vec![] }, node.start_byte(), node.end_byte(), false));
                 }
             }
             for (member, m_start, m_end, in_compound) in members {
@@ -312,7 +367,7 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                         "type_alias" => "type_alias",
                         _            => "global_var",
                     };
-                    if !member.name.is_empty() {
+                    if !member.name.is_empty() && !is_operator_name(&member.name) {
                         classes.push(ClassInfo {
                             class_name: member.name.clone(),
                             namespace: None,
@@ -325,6 +380,9 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
                             members: vec![],
                             is_final: false,
                             is_interface: false,
+                            parent_type: None,
+                            template_params: member.template_params.clone(),
+                            specialization_args: None,
                         });
                     }
                 }
@@ -336,24 +394,49 @@ pub fn parse_content_mmap(content_bytes: &[u8], _path: &str, language: &tree_sit
             scan_gameplay_tag_namespaces(root, content_bytes, &mut classes);
             scan_preproc_defines(root, content_bytes, &mut classes);
             scan_delegate_and_log_macros(root, content_bytes, &mut classes);
+            // .unl/macros.json で定義されたプロジェクト固有マクロ
+            if let Some(custom) = crate::parser::macros::for_path(path) {
+                crate::parser::macros::scan_custom_macros(&String::from_utf8_lossy(content_bytes), &custom, &mut classes);
+            }
 
-            Ok((classes, calls, includes))
+            let refs = crate::parser::refs::collect_refs(root, content_bytes);
+            Ok((classes, calls, includes, refs))
         })
     })
 }
 
-pub fn parse_content(content: &str, path: &str, language: &tree_sitter::Language, query: &Query) -> anyhow::Result<(Vec<ClassInfo>, Vec<crate::types::CallInfo>, Vec<String>)> {
+pub fn parse_content(content: &str, path: &str, language: &tree_sitter::Language, query: &Query) -> anyhow::Result<ParsedContent> {
     let include_query = Query::new(language, INCLUDE_QUERY_STR).unwrap();
     parse_content_mmap(content.as_bytes(), path, language, query, &include_query)
 }
 
+/// クラス名 (短い名前と `::` 区切りの完全修飾名) → `classes` の添字。
+/// 解析中に `classes` が伸びるので、前回から追加された分だけ索引する
+#[derive(Default)]
+struct ClassIndex {
+    by_name: HashMap<String, usize>,
+    indexed: usize,
+}
+
+impl ClassIndex {
+    /// 名前が一致する最初のクラス
+    fn find(&mut self, classes: &[ClassInfo], name: &str) -> Option<usize> {
+        for (i, c) in classes.iter().enumerate().skip(self.indexed) {
+            self.by_name.entry(c.class_name.clone()).or_insert(i);
+            self.by_name.entry(c.qualified_name("::")).or_insert(i);
+        }
+        self.indexed = classes.len();
+        self.by_name.get(name).copied()
+    }
+}
+
 fn get_node_text<'a>(node: &Node, source: &'a [u8]) -> &'a str { node.utf8_text(source).unwrap_or("") }
 
 fn get_namespace<'a>(node: &Node<'a>, source: &'a [u8]) -> Option<String> {
     let mut parts = Vec::new();
     let mut curr = node.parent();
     while let Some(n) = curr {
-        if matches!(n.kind(), "namespace_definition" | "class_specifier" | "struct_specifier") {
+        if n.kind() == "namespace_definition" || is_type_scope(n.kind()) {
             if let Some(name) = n.child_by_field_name("name") { parts.push(get_node_text(&name, source).to_string()); }
         }
         curr = n.parent();
@@ -361,6 +444,35 @@ fn get_namespace<'a>(node: &Node<'a>, source: &'a [u8]) -> Option<String> {
     if parts.is_empty() { None } else { parts.reverse(); Some(parts.join("::")) }
 }
 
+/// `operator[]` / `operator==` のような演算子オーバーロード名か
+pub fn is_operator_name(name: &str) -> bool {
+    name.strip_prefix("operator").is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// `template<...>` 直下の宣言ならテンプレート仮引数を返す
+fn get_template_params(node: &Node, source: &[u8]) -> Vec<TemplateParam> {
+    node.parent()
+        .filter(|p| p.kind() == "template_declaration")
+        .and_then(|p| p.child_by_field_name("parameters"))
+        .map(|list| template::parse_template_params(get_node_text(&list, source)))
+        .unwrap_or_default()
+}
+
+fn is_type_scope(kind: &str) -> bool {
+    matches!(kind, "class_specifier" | "struct_specifier" | "unreal_class_declaration" | "unreal_struct_declaration")
+}
+
+/// 最も内側のスコープが名前空間ではなくクラス / 構造体かどうか
+fn has_enclosing_type(node: &Node) -> bool {
+    let mut curr = node.parent();
+    while let Some(n) = curr {
+        if n.kind() == "namespace_definition" { return false; }
+        if is_type_scope(n.kind()) && n.child_by_field_name("name").is_some() { return true; }
+        curr = n.parent();
+    }
+    false
+}
+
 fn find_child_by_type<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
@@ -378,6 +490,16 @@ fn has_child_type(node: Node, type_name: &str) -> bool {
     false
 }
 
+/// `UFUNCTION(Server, Reliable)` / `UPROPERTY(Replicated)` マクロの括弧内を返す。
+fn get_macro_specifiers(node: Node, source: &[u8]) -> Option<String> {
+    let mut cursor = node.walk();
+    let macro_node = node.children(&mut cursor).find(|c| matches!(c.kind(), "ufunction_macro" | "uproperty_macro"))?;
+    let text = get_node_text(&macro_node, source);
+    let inner = &text[text.find('(')? + 1..text.rfind(')')?];
+    let inner = inner.split_whitespace().collect::<Vec<_>>().join(" ");
+    if inner.is_empty() { None } else { Some(inner) }
+}
+
 /// ノードの直接の子に `storage_class_specifier` として "static" があるか確認する。
 fn has_static_specifier(node: Node, source: &[u8]) -> bool {
     let mut cursor = node.walk();
@@ -463,6 +585,9 @@ fn scan_preproc_defines(root: Node, content_bytes: &[u8], classes: &mut Vec<Clas
                 members: vec![],
                 is_final: false,
                 is_interface: false,
+                parent_type: None,
+                template_params: vec![],
+                specialization_args: None,
             });
         }
     }
@@ -517,6 +642,9 @@ fn scan_gameplay_tag_namespaces(root: Node, content_bytes: &[u8], classes: &mut
                             members,
                             is_final: false,
                             is_interface: false,
+                            parent_type: None,
+                            template_params: vec![],
+                            specialization_args: None,
                         });
                     }
                 }
@@ -560,6 +688,8 @@ fn collect_direct_tag_members(body: Node, content_bytes: &[u8]) -> Vec<MemberInf
                     end_line: call_node.end_position().row + 1,
                     detail: if detail.is_empty() { None } else { Some(detail) },
                     return_type: Some("FGameplayTag".to_string()),
+                    specifiers: None,
+                    template_params: vec![],
                 });
                 continue;
             }
@@ -575,6 +705,8 @@ fn collect_direct_tag_members(body: Node, content_bytes: &[u8]) -> Vec<MemberInf
                 end_line: child.end_position().row + 1,
                 detail: None,
                 return_type: Some(type_name),
+                specifiers: None,
+                template_params: vec![],
             });
         }
     }
@@ -712,6 +844,9 @@ fn scan_delegate_and_log_macros(root: Node, content_bytes: &[u8], classes: &mut
                         members:      vec![],
                         is_final:     false,
                         is_interface: false,
+                        parent_type: None,
+                        template_params: vec![],
+                        specialization_args: None,
                     });
                 }
             }
@@ -729,6 +864,9 @@ fn scan_delegate_and_log_macros(root: Node, content_bytes: &[u8], classes: &mut
                         members:      vec![],
                         is_final:     false,
                         is_interface: false,
+                        parent_type: None,
+                        template_params: vec![],
+                        specialization_args: None,
                     });
                 }
             }
@@ -0,0 +1,216 @@
+//! User-configurable macro recognition.
+//!
+//! Studios often wrap `UCLASS` / `DECLARE_*` in their own macros
+//! (`MYGAME_DECLARE_SUBSYSTEM(...)`, custom stat / tag macros, ...).  A project
+//! can declare them in `<project>/.unl/macros.json`:
+//!
+//! ```json
+//! {
+//!   "macros": [
+//!     { "name": "MYGAME_DECLARE_SUBSYSTEM", "arg_index": 0, "symbol_type": "class" },
+//!     { "name": "MYGAME_STAT_*",            "arg_index": 1, "symbol_type": "stat" }
+//!   ]
+//! }
+//! ```
+//!
+//! `name` may end with `*` to match a prefix.  Matching calls are registered as
+//! `ClassInfo` entries exactly like the built-in delegate / log category macros.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::Deserialize;
+use sha2::{Sha256, Digest};
+use crate::types::ClassInfo;
+
+pub const MACROS_FILE: &str = ".unl/macros.json";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CustomMacro {
+    pub name: String,
+    /// シンボル名として使う引数のインデックス (0 始まり)
+    #[serde(default)]
+    pub arg_index: usize,
+    #[serde(default = "default_symbol_type")]
+    pub symbol_type: String,
+}
+
+fn default_symbol_type() -> String { "macro_symbol".to_string() }
+
+impl CustomMacro {
+    fn prefix(&self) -> &str { self.name.trim_end_matches('*') }
+    fn is_prefix(&self) -> bool { self.name.ends_with('*') }
+    fn matches(&self, macro_name: &str) -> bool {
+        if self.is_prefix() { macro_name.starts_with(self.prefix()) } else { macro_name == self.name }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct MacrosFile {
+    #[serde(default)]
+    macros: Vec<CustomMacro>,
+}
+
+/// project_root (正規化済み) -> マクロ定義
+static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<Vec<CustomMacro>>>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<Vec<CustomMacro>>>> {
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn normalize_root(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").trim_end_matches('/').to_lowercase()
+}
+
+/// `<project_root>/.unl/macros.json` を読み込んでレジストリに登録する。
+/// ファイルの SHA-256 を返す（存在しない場合は None で、登録も解除される）。
+pub fn register_project(project_root: &Path) -> anyhow::Result<Option<String>> {
+    let key = normalize_root(project_root);
+    let path = project_root.join(MACROS_FILE);
+    let Ok(bytes) = std::fs::read(&path) else {
+        registry().write().remove(&key);
+        return Ok(None);
+    };
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    let parsed: MacrosFile = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+    tracing::info!("Loaded {} custom macro(s) from {}", parsed.macros.len(), path.display());
+    registry().write().insert(key, Arc::new(parsed.macros));
+    Ok(Some(hash))
+}
+
+/// `.unl/macros.json` の SHA-256 だけを返す（レジストリには触れない）。
+pub fn file_hash(project_root: &Path) -> Option<String> {
+    std::fs::read(project_root.join(MACROS_FILE)).ok().map(|bytes| format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// ファイルが属するプロジェクトのカスタムマクロ定義を返す。
+pub fn for_path(file_path: &str) -> Option<Arc<Vec<CustomMacro>>> {
+    let reg = registry().read();
+    if reg.is_empty() { return None; }
+    let norm = file_path.replace('\\', "/").to_lowercase();
+    reg.iter()
+        .filter(|(root, _)| norm.starts_with(root.as_str()) && norm[root.len()..].starts_with('/'))
+        .max_by_key(|(root, _)| root.len())
+        .map(|(_, m)| Arc::clone(m))
+}
+
+/// ヘッダーの高速スキップ判定用: 内容がいずれかのカスタムマクロ名を含むか
+pub fn mentions_any(content_bytes: &[u8], macros: &[CustomMacro]) -> bool {
+    macros.iter().any(|m| {
+        let p = m.prefix().as_bytes();
+        !p.is_empty() && content_bytes.windows(p.len()).any(|w| w == p)
+    })
+}
+
+fn call_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b([A-Za-z_]\w*)\s*\(").unwrap())
+}
+
+/// カスタムマクロ呼び出しを探し、ClassInfo として登録する。
+pub fn scan_custom_macros(content: &str, macros: &[CustomMacro], classes: &mut Vec<ClassInfo>) {
+    for cap in call_regex().captures_iter(content) {
+        let name_match = cap.get(1).unwrap();
+        let Some(def) = macros.iter().find(|m| m.matches(name_match.as_str())) else { continue };
+
+        // `#define MYGAME_DECLARE_SUBSYSTEM(Name)` 自体やコメント内の出現は除外
+        let line_start = content[..name_match.start()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_prefix = &content[line_start..name_match.start()];
+        if line_prefix.trim_start().starts_with('#') || line_prefix.contains("//") { continue; }
+
+        let args_start = cap.get(0).unwrap().end();
+        let Some(args) = balanced_args(&content[args_start..]) else { continue };
+        let parts = split_top_level_args(args);
+        let Some(raw) = parts.get(def.arg_index) else { continue };
+        let symbol = unquote(raw).unwrap_or_else(|| raw.trim().to_string());
+        if symbol.is_empty() || symbol.contains(char::is_whitespace) { continue; }
+
+        let end = args_start + args.len() + 1;
+        classes.push(ClassInfo {
+            class_name:   symbol,
+            namespace:    None,
+            base_classes: vec![],
+            symbol_type:  def.symbol_type.clone(),
+            line:         line_of(content, name_match.start()),
+            end_line:     line_of(content, end.min(content.len())),
+            range_start:  name_match.start(),
+            range_end:    end,
+            members:      vec![],
+            is_final:     false,
+            is_interface: false,
+            parent_type: None,
+            template_params: vec![],
+            specialization_args: None,
+        });
+    }
+}
+
+/// `(` の直後から対応する `)` までの文字列を返す。
+pub(crate) fn balanced_args(s: &str) -> Option<&str> {
+    let mut depth = 1;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => { depth -= 1; if depth == 0 { return Some(&s[..i]); } }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `template<>, FFoo, TEXT("/a"), TEXT("Main"), SF_Pixel` をトップレベルの `,` で分割する。
+pub(crate) fn split_top_level_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut in_str = false;
+    let mut current = String::new();
+    for ch in args.chars() {
+        match ch {
+            '"' => { in_str = !in_str; current.push(ch); }
+            '(' | '<' if !in_str => { depth += 1; current.push(ch); }
+            ')' | '>' if !in_str => { depth -= 1; current.push(ch); }
+            ',' if !in_str && depth == 0 => { parts.push(std::mem::take(&mut current)); }
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+/// `"x"` / `TEXT("x")` の中身を返す。文字列リテラルでなければ None。
+pub(crate) fn unquote(arg: &str) -> Option<String> {
+    let mut s = arg.trim();
+    if let Some(inner) = s.strip_prefix("TEXT") {
+        s = inner.trim().strip_prefix('(')?.strip_suffix(')')?.trim();
+    }
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.to_string())
+}
+
+pub(crate) fn line_of(content: &str, byte_pos: usize) -> usize {
+    content[..byte_pos].bytes().filter(|&b| b == b'\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_custom_macros() {
+        let macros = vec![
+            CustomMacro { name: "MYGAME_DECLARE_SUBSYSTEM".into(), arg_index: 0, symbol_type: "class".into() },
+            CustomMacro { name: "MYGAME_STAT_*".into(), arg_index: 1, symbol_type: "stat".into() },
+        ];
+        let src = "#define MYGAME_DECLARE_SUBSYSTEM(Name) class Name\n\
+                   // MYGAME_DECLARE_SUBSYSTEM(UCommented)\n\
+                   MYGAME_DECLARE_SUBSYSTEM(UInventorySubsystem, UGameInstanceSubsystem)\n\
+                   MYGAME_STAT_COUNTER(STATGROUP_Game, STAT_Spawns, TEXT(\"Spawns\"));\n";
+        let mut classes = Vec::new();
+        scan_custom_macros(src, &macros, &mut classes);
+        let found: Vec<(&str, &str, usize)> = classes.iter().map(|c| (c.class_name.as_str(), c.symbol_type.as_str(), c.line)).collect();
+        assert_eq!(found, vec![("UInventorySubsystem", "class", 3), ("STAT_Spawns", "stat", 4)]);
+    }
+}
@@ -1 +1,15 @@
 pub mod cpp;
+pub mod macros;
+pub mod refs;
+pub mod shader;
+pub mod template;
+pub mod verse;
+
+/// 拡張子からソース言語を判定する (classes.language に保存される値)
+pub fn language_for_extension(ext: &str) -> &'static str {
+    match ext {
+        "verse" => "verse",
+        "usf" | "ush" => "hlsl",
+        _ => "cpp",
+    }
+}
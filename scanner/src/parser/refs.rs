@@ -0,0 +1,150 @@
+//! Type / member references recorded at index time (`symbol_refs`).
+//!
+//! `FindSymbolUsages` used to re-parse every candidate file at query time; the
+//! same walk now runs once per file during indexing and the hits are stored by
+//! name.  Only unsaved buffers are still parsed on the fly (`find_refs`).
+//!
+//! - `type`   : `type_identifier` (except the name of a class / struct being
+//!   defined) and the scope of a `qualified_identifier` (`AFoo::StaticClass()`)
+//! - `member` : `obj.Foo()` / `obj->Foo` (`scope` = None) and `AFoo::Foo()` (`scope` = `AFoo`)
+//!
+//! Primitive typedefs (`int32`, `TCHAR`, ...), all-caps macro-like names
+//! (`ENGINE_API`, `FORCEINLINE`) and single-letter template parameters are not
+//! recorded, and hits are deduplicated per (name, kind, line) so the table stays
+//! proportional to the number of distinct usages rather than tokens.
+
+use std::collections::HashMap;
+use tree_sitter::{Node, Parser};
+use crate::types::SymbolRef;
+
+pub const KIND_TYPE: &str = "type";
+pub const KIND_MEMBER: &str = "member";
+
+/// UE / C の基本型 typedef。参照を検索する意味がないので記録しない
+const PRIMITIVE_TYPES: &[&str] = &[
+    "int8", "int16", "int32", "int64", "uint8", "uint16", "uint32", "uint64",
+    "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t",
+    "size_t", "ssize_t", "ptrdiff_t", "intptr_t", "uintptr_t", "nullptr_t", "wchar_t", "char8_t", "char16_t", "char32_t",
+    "TCHAR", "ANSICHAR", "WIDECHAR", "UTF8CHAR", "UCS2CHAR", "UTF16CHAR", "UTF32CHAR",
+    "SIZE_T", "SSIZE_T", "PTRINT", "UPTRINT", "BYTE", "DWORD", "WORD", "UINT", "INT", "BOOL",
+];
+
+/// 基本型・マクロ風 (大文字と数字と `_` のみ)・1 文字の名前は参照として扱わない
+fn is_trivial_name(name: &str) -> bool {
+    name.len() <= 1
+        || PRIMITIVE_TYPES.contains(&name)
+        || !name.bytes().any(|b| b.is_ascii_lowercase())
+}
+
+fn text<'a>(node: &Node, src: &'a [u8]) -> &'a str { node.utf8_text(src).unwrap_or("") }
+
+fn is_definition_name(node: &Node) -> bool {
+    node.parent().is_some_and(|p| {
+        matches!(p.kind(), "class_specifier" | "struct_specifier" | "unreal_class_declaration" | "unreal_struct_declaration")
+            && p.child_by_field_name("name").is_some_and(|n| n.id() == node.id())
+    })
+}
+
+fn classify(node: &Node, src: &[u8]) -> Option<SymbolRef> {
+    let make = |kind: &str, scope: Option<String>| {
+        let pos = node.start_position();
+        SymbolRef { name: text(node, src).to_string(), kind: kind.to_string(), scope, line: pos.row + 1, col: pos.column }
+    };
+    let parent = node.parent()?;
+    match node.kind() {
+        "type_identifier" if !is_definition_name(node) => Some(make(KIND_TYPE, None)),
+        "namespace_identifier" if parent.kind() == "qualified_identifier"
+            && parent.child_by_field_name("scope").is_some_and(|s| s.id() == node.id()) => Some(make(KIND_TYPE, None)),
+        "field_identifier" if parent.kind() == "field_expression" => Some(make(KIND_MEMBER, None)),
+        "identifier" if parent.kind() == "qualified_identifier"
+            && parent.child_by_field_name("name").is_some_and(|n| n.id() == node.id()) => {
+            let scope = parent.child_by_field_name("scope").map(|s| text(&s, src).to_string());
+            Some(make(KIND_MEMBER, scope))
+        }
+        _ => None,
+    }
+}
+
+/// 構文木全体から参照を収集する (コメント等の extra ノードは除外)。
+/// 同じ行の同名・同種の参照は 1 件にまとめ、スコープが食い違う場合は None (常に候補) にする
+pub fn collect_refs(root: Node, src: &[u8]) -> Vec<SymbolRef> {
+    let mut refs: Vec<SymbolRef> = Vec::new();
+    let mut seen: HashMap<(String, &'static str, usize), usize> = HashMap::new();
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        let descend = !node.is_extra();
+        if descend {
+            if let Some(r) = classify(&node, src) {
+                if !is_trivial_name(&r.name) {
+                    let kind = if r.kind == KIND_TYPE { KIND_TYPE } else { KIND_MEMBER };
+                    match seen.get(&(r.name.clone(), kind, r.line)) {
+                        Some(&i) => {
+                            if refs[i].scope != r.scope { refs[i].scope = None; }
+                        }
+                        None => {
+                            seen.insert((r.name.clone(), kind, r.line), refs.len());
+                            refs.push(r);
+                        }
+                    }
+                }
+            }
+        }
+        if descend && cursor.goto_first_child() { continue; }
+        loop {
+            if cursor.goto_next_sibling() { break; }
+            if !cursor.goto_parent() { return refs; }
+        }
+    }
+}
+
+/// 未保存バッファ用: 内容をその場でパースして `name` への参照だけを返す
+pub fn find_refs(content: &str, name: &str, kind: &str, scope: Option<&str>) -> Vec<SymbolRef> {
+    let language: tree_sitter::Language = tree_sitter_unreal_cpp::LANGUAGE.into();
+    let mut parser = Parser::new();
+    if parser.set_language(&language).is_err() { return vec![]; }
+    let Some(tree) = parser.parse(content, None) else { return vec![] };
+    collect_refs(tree.root_node(), content.as_bytes())
+        .into_iter()
+        .filter(|r| r.name == name && r.kind == kind && matches_scope(r.scope.as_deref(), scope))
+        .collect()
+}
+
+/// メンバー参照のスコープ判定: `obj->Foo` (scope なし) は常に候補、`AFoo::Foo` は一致時のみ
+pub fn matches_scope(ref_scope: Option<&str>, wanted: Option<&str>) -> bool {
+    match (ref_scope, wanted) {
+        (None, _) | (_, None) => true,
+        (Some(s), Some(w)) => s == w,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_refs_types_and_members() {
+        let src = "class AFoo : public ABase {};\nvoid F(ABase* B) { B->Tick(); ABase::Tick(); AOther::Tick(); }\n";
+        let types = find_refs(src, "ABase", KIND_TYPE, None);
+        let lines: Vec<usize> = types.iter().map(|r| r.line).collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert!(find_refs(src, "AFoo", KIND_TYPE, None).is_empty());
+
+        let members = find_refs(src, "Tick", KIND_MEMBER, Some("ABase"));
+        assert_eq!(members.len(), 1);
+        assert_eq!((members[0].line, members[0].col, members[0].scope.as_deref()), (2, 22, None));
+    }
+
+    #[test]
+    fn test_trivial_names_skipped() {
+        let src = "template<typename T> ENGINE_API void F(int32 A, TCHAR* B, T C, FString D) { D.Len(); D.Len(); }\n";
+        let tree = {
+            let language: tree_sitter::Language = tree_sitter_unreal_cpp::LANGUAGE.into();
+            let mut parser = Parser::new();
+            parser.set_language(&language).unwrap();
+            parser.parse(src, None).unwrap()
+        };
+        let names: Vec<String> = collect_refs(tree.root_node(), src.as_bytes()).into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["FString", "Len"]);
+    }
+}
@@ -0,0 +1,318 @@
+//! HLSL shader source (.usf / .ush) indexer.
+//!
+//! There is no tree-sitter grammar for Unreal's HLSL dialect in the dependency
+//! set, so this is a line-based scanner (the same approach `refresh.rs` uses
+//! for `.Build.cs`).  It extracts:
+//!
+//! - `#include "/Engine/..."` virtual paths
+//! - top-level `struct` declarations with their fields (+ semantics)
+//! - top-level functions (entry points and helpers)
+//! - `#define` macros
+//!
+//! It also hosts the C++ side of the shader binding: `IMPLEMENT_GLOBAL_SHADER`
+//! style macros and `AddShaderSourceDirectoryMapping` calls.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::types::{ClassInfo, MemberInfo, ShaderBindingInfo, ShaderMappingInfo};
+use super::macros::{balanced_args, split_top_level_args, unquote, line_of};
+
+struct ShaderRegexes {
+    include: Regex,
+    define: Regex,
+    struct_decl: Regex,
+    function: Regex,
+    field: Regex,
+    implement: Regex,
+    mapping: Regex,
+    shader_subdir: Regex,
+}
+
+static SHADER_REGEXES: OnceLock<ShaderRegexes> = OnceLock::new();
+
+fn get_regexes() -> &'static ShaderRegexes {
+    SHADER_REGEXES.get_or_init(|| ShaderRegexes {
+        include: Regex::new(r#"^\s*#\s*include\s+"([^"]+)""#).unwrap(),
+        define: Regex::new(r"^\s*#\s*define\s+([A-Za-z_]\w*)").unwrap(),
+        struct_decl: Regex::new(r"^\s*struct\s+([A-Za-z_]\w*)").unwrap(),
+        function: Regex::new(
+            r"^\s*(?:\[[^\]]*\]\s*)*(?:(?:static|inline|precise|export)\s+)*([A-Za-z_]\w*(?:<[^>]*>)?)\s+([A-Za-z_]\w*)\s*\(([^)]*)\)?"
+        ).unwrap(),
+        field: Regex::new(
+            r"^\s*(?:(?:nointerpolation|linear|centroid|noperspective|sample|precise|uniform|const)\s+)*([A-Za-z_]\w*(?:<[^>]*>)?)\s+([A-Za-z_]\w*)\s*(?:\[[^\]]*\])?\s*(?::\s*([A-Za-z_]\w*))?\s*;"
+        ).unwrap(),
+        implement: Regex::new(r"\b(IMPLEMENT_(?:GLOBAL_SHADER|SHADER_TYPE|MATERIAL_SHADER_TYPE)\w*)\s*\(").unwrap(),
+        mapping: Regex::new(r#"AddShaderSourceDirectoryMapping\s*\(\s*(?:TEXT\s*\(\s*)?"([^"]+)""#).unwrap(),
+        shader_subdir: Regex::new(r#"TEXT\s*\(\s*"(Shaders[^"]*)"\s*\)"#).unwrap(),
+    })
+}
+
+/// Keywords that the function regex would otherwise mistake for a return type.
+const NON_TYPE_KEYWORDS: &[&str] = &[
+    "return", "else", "if", "for", "while", "do", "switch", "case", "struct",
+    "cbuffer", "tbuffer", "typedef", "namespace",
+];
+
+/// .usf / .ush の内容を解析し、(シンボル, インクルードパス) を返す。
+pub fn parse_shader_content(content: &str) -> (Vec<ClassInfo>, Vec<String>) {
+    let re = get_regexes();
+    let lines = strip_comments(content);
+
+    let mut classes: Vec<ClassInfo> = Vec::new();
+    let mut includes: Vec<String> = Vec::new();
+
+    let mut depth: i32 = 0;
+    // `struct Foo` / `float4 Main(...)` seen at depth 0, waiting for its '{'
+    let mut pending: Option<ClassInfo> = None;
+    // struct/function whose body is currently open
+    let mut open: Option<ClassInfo> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let line_no = idx + 1;
+
+        if let Some(cap) = re.include.captures(line) {
+            includes.push(cap[1].to_string());
+            continue;
+        }
+        if let Some(cap) = re.define.captures(line) {
+            classes.push(new_symbol(&cap[1], "define", line_no));
+            continue;
+        }
+
+        if depth == 0 && open.is_none() {
+            if let Some(cap) = re.struct_decl.captures(line) {
+                // `struct Foo;` は前方宣言なので無視する
+                if !line.contains(';') || line.contains('{') {
+                    pending = Some(new_symbol(&cap[1], "shader_struct", line_no));
+                }
+            } else if let Some(cap) = re.function.captures(line) {
+                let ret = cap[1].to_string();
+                let name = cap[2].to_string();
+                let is_prototype = line.trim_end().ends_with(';');
+                if !NON_TYPE_KEYWORDS.contains(&ret.as_str()) && !NON_TYPE_KEYWORDS.contains(&name.as_str()) && !is_prototype {
+                    pending = Some(new_symbol(&name, "shader_function", line_no));
+                }
+            }
+        } else if depth == 1 {
+            if let Some(cls) = open.as_mut().filter(|c| c.symbol_type == "shader_struct") {
+                if let Some(cap) = re.field.captures(line) {
+                    cls.members.push(MemberInfo {
+                        name: cap[2].to_string(),
+                        mem_type: "property".to_string(),
+                        flags: "".to_string(),
+                        access: "public".to_string(),
+                        line: line_no,
+                        end_line: line_no,
+                        detail: cap.get(3).map(|s| format!(": {}", s.as_str())),
+                        return_type: Some(cap[1].to_string()),
+                        specifiers: None,
+                        template_params: vec![],
+                    });
+                }
+            }
+        }
+
+        for ch in line.chars() {
+            match ch {
+                '{' => {
+                    if depth == 0 {
+                        if let Some(p) = pending.take() { open = Some(p); }
+                    }
+                    depth += 1;
+                }
+                '}' => {
+                    depth = (depth - 1).max(0);
+                    if depth == 0 {
+                        if let Some(mut cls) = open.take() {
+                            cls.end_line = line_no;
+                            classes.push(cls);
+                        }
+                    }
+                }
+                ';' if depth == 0 => {
+                    // `struct Foo` の次の行が '{' でなかった場合など
+                    pending = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    (classes, includes)
+}
+
+fn new_symbol(name: &str, symbol_type: &str, line: usize) -> ClassInfo {
+    ClassInfo {
+        class_name: name.to_string(),
+        namespace: None,
+        base_classes: vec![],
+        symbol_type: symbol_type.to_string(),
+        line,
+        end_line: line,
+        range_start: 0,
+        range_end: 0,
+        members: vec![],
+        is_final: false,
+        is_interface: false,
+        parent_type: None,
+        template_params: vec![],
+        specialization_args: None,
+    }
+}
+
+/// `//` と `/* */` コメントを空白に置き換え、行数を保ったまま行ごとに返す。
+fn strip_comments(content: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut in_block = false;
+    for raw in content.lines() {
+        let mut line = String::with_capacity(raw.len());
+        let bytes = raw.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if in_block {
+                if bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/') { in_block = false; i += 2; } else { i += 1; }
+                continue;
+            }
+            if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'/') { break; }
+            if bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'*') { in_block = true; i += 2; continue; }
+            line.push(bytes[i] as char);
+            i += 1;
+        }
+        out.push(line);
+    }
+    out
+}
+
+// ─── C++ side ────────────────────────────────────────────────────────────────
+
+/// C++ ソースから `IMPLEMENT_GLOBAL_SHADER` / `IMPLEMENT_SHADER_TYPE` /
+/// `IMPLEMENT_MATERIAL_SHADER_TYPE` と `AddShaderSourceDirectoryMapping` を抽出する。
+pub fn scan_cpp_shader_declarations(content: &str, file_path: &str) -> (Vec<ShaderBindingInfo>, Vec<ShaderMappingInfo>) {
+    let re = get_regexes();
+    let mut bindings = Vec::new();
+    let mut mappings = Vec::new();
+
+    for m in re.implement.find_iter(content) {
+        let args_start = m.end();
+        let Some(args) = balanced_args(&content[args_start..]) else { continue };
+        let parts = split_top_level_args(args);
+        // 最初の文字列リテラルが仮想パス、その直前がクラス名、直後がエントリポイント
+        let Some(path_idx) = parts.iter().position(|a| unquote(a).is_some()) else { continue };
+        if path_idx == 0 { continue; }
+        let (Some(virtual_path), Some(entry_point)) = (unquote(&parts[path_idx]), parts.get(path_idx + 1).and_then(|a| unquote(a))) else { continue };
+        bindings.push(ShaderBindingInfo {
+            class_name: parts[path_idx - 1].trim().to_string(),
+            virtual_path,
+            entry_point,
+            frequency: parts.get(path_idx + 2).map(|s| s.trim().to_string()).unwrap_or_default(),
+            line: line_of(content, m.start()),
+        });
+    }
+
+    if content.contains("AddShaderSourceDirectoryMapping") {
+        // 実ディレクトリは通常 `FPaths::Combine(Plugin->GetBaseDir(), TEXT("Shaders"))` のように
+        // 実行時に組み立てられるため、所属プラグイン/プロジェクトのルート + Shaders サブディレクトリと推定する。
+        let subdir = re.shader_subdir.captures(content).map(|c| c[1].to_string()).unwrap_or_else(|| "Shaders".to_string());
+        if let Some(root) = find_owner_root(Path::new(file_path)) {
+            let real_path = root.join(&subdir).to_string_lossy().replace('\\', "/");
+            for cap in re.mapping.captures_iter(content) {
+                let whole = cap.get(0).unwrap();
+                mappings.push(ShaderMappingInfo {
+                    virtual_path: cap[1].trim_end_matches('/').to_string(),
+                    real_path: real_path.clone(),
+                    line: line_of(content, whole.start()),
+                });
+            }
+        }
+    }
+
+    (bindings, mappings)
+}
+
+/// ファイルを含む .uplugin / .uproject のディレクトリを探す。
+fn find_owner_root(file_path: &Path) -> Option<PathBuf> {
+    let mut dir = file_path.parent();
+    while let Some(d) = dir {
+        if let Ok(entries) = std::fs::read_dir(d) {
+            let found = entries.filter_map(|e| e.ok()).any(|e| {
+                e.path().extension().is_some_and(|ext| ext == "uplugin" || ext == "uproject")
+            });
+            if found { return Some(d.to_path_buf()); }
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+/// 仮想シェーダーパスをマッピング (仮想ディレクトリ, 実ディレクトリ) の最長一致で実パスに変換する。
+pub fn resolve_virtual_path(virtual_path: &str, mappings: &[(String, String)]) -> Option<String> {
+    let mut best: Option<(&String, &String)> = None;
+    for (vdir, real) in mappings {
+        let matches = virtual_path.strip_prefix(vdir.as_str()).is_some_and(|rest| rest.starts_with('/'));
+        if !matches { continue; }
+        match best {
+            Some((b, _)) if b.len() >= vdir.len() => {}
+            _ => best = Some((vdir, real)),
+        }
+    }
+    best.map(|(vdir, real)| format!("{}{}", real.trim_end_matches('/'), &virtual_path[vdir.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_struct_and_entry_point() {
+        let src = r#"
+#include "/Engine/Private/Common.ush"
+
+struct FVSOut
+{
+    float4 Position : SV_POSITION; // clip space
+    float2 UV : TEXCOORD0;
+};
+
+[numthreads(8, 8, 1)]
+void MainCS(uint3 DTid : SV_DispatchThreadID)
+{
+    if (DTid.x > 0) { return; }
+}
+"#;
+        let (classes, includes) = parse_shader_content(src);
+        assert_eq!(includes, vec!["/Engine/Private/Common.ush".to_string()]);
+        let s = classes.iter().find(|c| c.class_name == "FVSOut").unwrap();
+        assert_eq!(s.symbol_type, "shader_struct");
+        assert_eq!(s.members.len(), 2);
+        assert_eq!(s.members[0].detail.as_deref(), Some(": SV_POSITION"));
+        let f = classes.iter().find(|c| c.class_name == "MainCS").unwrap();
+        assert_eq!(f.symbol_type, "shader_function");
+        assert_eq!((f.line, f.end_line), (11, 14));
+    }
+
+    #[test]
+    fn test_scan_implement_macros() {
+        let src = "IMPLEMENT_GLOBAL_SHADER(FMyCS, \"/Plugin/My/Private/My.usf\", \"MainCS\", SF_Compute);\n\
+                   IMPLEMENT_SHADER_TYPE(template<>, FMyPS, TEXT(\"/Engine/Private/X.usf\"), TEXT(\"MainPS\"), SF_Pixel);";
+        let (bindings, _) = scan_cpp_shader_declarations(src, "/nonexistent/My.cpp");
+        assert_eq!(bindings.len(), 2);
+        assert_eq!(bindings[0].class_name, "FMyCS");
+        assert_eq!(bindings[0].entry_point, "MainCS");
+        assert_eq!(bindings[1].class_name, "FMyPS");
+        assert_eq!(bindings[1].virtual_path, "/Engine/Private/X.usf");
+        assert_eq!(bindings[1].frequency, "SF_Pixel");
+        assert_eq!(bindings[1].line, 2);
+    }
+
+    #[test]
+    fn test_resolve_virtual_path_longest_prefix() {
+        let maps = vec![
+            ("/Engine".to_string(), "/ue/Engine/Shaders".to_string()),
+            ("/Plugin/My".to_string(), "/proj/Plugins/My/Shaders".to_string()),
+        ];
+        assert_eq!(resolve_virtual_path("/Plugin/My/Private/A.usf", &maps).as_deref(), Some("/proj/Plugins/My/Shaders/Private/A.usf"));
+        assert_eq!(resolve_virtual_path("/Engine/Public/B.ush", &maps).as_deref(), Some("/ue/Engine/Shaders/Public/B.ush"));
+        assert_eq!(resolve_virtual_path("/EngineX/C.ush", &maps), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Template parameter lists and specialization arguments.
+//!
+//! The tree-sitter side (`cpp.rs`) only hands over the text of the
+//! `template_parameter_list` / `template_argument_list`; everything here is
+//! plain text processing so that completion can reuse it on type strings
+//! coming back from the DB (`TMyBox<FFoo>`).
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::types::TemplateParam;
+use super::macros::split_top_level_args;
+
+fn identifier_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b[A-Za-z_]\w*\b").unwrap())
+}
+
+/// `<typename T, int32 N = 4, template<typename> class TT>` を解析する。
+pub fn parse_template_params(list_text: &str) -> Vec<TemplateParam> {
+    split_template_args(list_text).into_iter().filter_map(|part| {
+        let (decl, default) = match split_default(&part) {
+            Some((d, v)) => (d, Some(v)),
+            None => (part.as_str(), None),
+        };
+        let decl = decl.trim();
+        let kind = if decl.starts_with("template") {
+            "template"
+        } else if decl.starts_with("typename") || decl.starts_with("class") {
+            "type"
+        } else {
+            "value"
+        };
+        // template<typename> class TT の場合、名前は最後の `>` より後ろ
+        let tail = if kind == "template" { decl.rsplit('>').next().unwrap_or(decl) } else { decl };
+        let name = identifier_regex().find_iter(tail).last()?.as_str();
+        if matches!(name, "typename" | "class") { return None; }
+        Some(TemplateParam {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            default,
+            variadic: decl.contains("..."),
+        })
+    }).collect()
+}
+
+/// トップレベルの `=` で宣言部とデフォルト値に分ける (`<`/`(` の内側は無視)
+fn split_default(part: &str) -> Option<(&str, String)> {
+    let mut depth = 0i32;
+    for (i, ch) in part.char_indices() {
+        match ch {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            '=' if depth == 0 => return Some((&part[..i], part[i + 1..].trim().to_string())),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `<FFoo, TArray<int32>>` / `FFoo, TArray<int32>` を引数ごとに分割する。
+pub fn split_template_args(text: &str) -> Vec<String> {
+    let t = text.trim();
+    let inner = t.strip_prefix('<').and_then(|s| s.strip_suffix('>')).unwrap_or(t);
+    split_top_level_args(inner).into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// 特殊化引数を比較用に正規化する (`< int32 , FFoo* >` → `<int32,FFoo*>`)
+pub fn normalize_args(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut out = String::with_capacity(collapsed.len());
+    let chars: Vec<char> = collapsed.chars().collect();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch == ' ' {
+            let prev = out.chars().last();
+            let next = chars.get(i + 1).copied();
+            let is_punct = |c: Option<char>| matches!(c, Some('<' | '>' | ',' | '*' | '&' | ':' | '(' | ')'));
+            if is_punct(prev) || is_punct(next) { continue; }
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// `TMyBox<FFoo>` → (`TMyBox`, Some(`<FFoo>`))
+pub fn split_type_args(type_name: &str) -> (&str, Option<&str>) {
+    let t = type_name.trim();
+    match (t.find('<'), t.rfind('>')) {
+        (Some(lt), Some(gt)) if gt > lt => (t[..lt].trim(), Some(&t[lt..=gt])),
+        _ => (t, None),
+    }
+}
+
+/// 型文字列中のテンプレート仮引数を実引数 (なければデフォルト値) で置き換える。
+pub fn substitute_template_args(type_text: &str, params: &[TemplateParam], args: &[String]) -> String {
+    let mut map: HashMap<&str, &str> = HashMap::new();
+    for (i, p) in params.iter().enumerate() {
+        if p.variadic { break; }
+        if let Some(v) = args.get(i).map(String::as_str).or(p.default.as_deref()) {
+            map.insert(p.name.as_str(), v);
+        }
+    }
+    if map.is_empty() { return type_text.to_string(); }
+    identifier_regex()
+        .replace_all(type_text, |caps: &regex::Captures| {
+            let word = &caps[0];
+            map.get(word).map(|v| v.to_string()).unwrap_or_else(|| word.to_string())
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_substitute() {
+        let params = parse_template_params("<typename T, int32 N = 4, typename Allocator = FDefaultAllocator, template<typename> class TT, typename... Ts>");
+        let summary: Vec<(&str, &str, Option<&str>, bool)> = params.iter()
+            .map(|p| (p.name.as_str(), p.kind.as_str(), p.default.as_deref(), p.variadic))
+            .collect();
+        assert_eq!(summary, vec![
+            ("T", "type", None, false),
+            ("N", "value", Some("4"), false),
+            ("Allocator", "type", Some("FDefaultAllocator"), false),
+            ("TT", "template", None, false),
+            ("Ts", "type", None, true),
+        ]);
+
+        let args = split_template_args("<FFoo*, 8>");
+        assert_eq!(substitute_template_args("const T*", &params, &args), "const FFoo**");
+        assert_eq!(substitute_template_args("TArray<T, Allocator>", &params, &args), "TArray<FFoo*, FDefaultAllocator>");
+        assert_eq!(normalize_args("< int32 , TArray< FFoo > >"), "<int32,TArray<FFoo>>");
+    }
+}
@@ -0,0 +1,337 @@
+//! Verse (.verse) source indexer.
+//!
+//! Verse is indentation based (`Name := class(Base):` followed by an indented
+//! body), so instead of a tree-sitter grammar this is a line scanner with an
+//! indentation stack.  It produces the same `ClassInfo` / `MemberInfo` shapes
+//! as the C++ parser so that the rest of the pipeline (DB, search, goto) does
+//! not need to care about the source language:
+//!
+//! - `class` / `struct` / `interface` / `enum` / `module` definitions → `ClassInfo`
+//! - functions and `var` / constant data members → `MemberInfo`
+//! - top-level functions / constants → `global_function` / `global_var`
+//! - `using { /Fortnite.com/Devices }` → include paths
+
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::types::{ClassInfo, MemberInfo};
+
+struct VerseRegexes {
+    using: Regex,
+    type_def: Regex,
+    function: Regex,
+    data: Regex,
+    enum_item: Regex,
+    specifier: Regex,
+}
+
+static VERSE_REGEXES: OnceLock<VerseRegexes> = OnceLock::new();
+
+fn get_regexes() -> &'static VerseRegexes {
+    VERSE_REGEXES.get_or_init(|| VerseRegexes {
+        using: Regex::new(r"^using\s*\{\s*([^}]+?)\s*\}").unwrap(),
+        // my_device<public> := class<concrete>(creative_device):
+        type_def: Regex::new(
+            r"^([A-Za-z_]\w*)((?:<[\w\s,]*>)*)\s*:=\s*(class|struct|interface|enum|module)((?:<[\w\s,]*>)*)\s*(?:\(([^)]*)\))?"
+        ).unwrap(),
+        // OnBegin<override>()<suspends> : void =
+        function: Regex::new(
+            r"^([A-Za-z_]\w*)((?:<[\w\s,]*>)*)\s*\(([^)]*)\)((?:\s*<[\w\s,]*>)*)\s*:\s*([^=]+?)\s*(?:(=|:=)\s*(.*))?$"
+        ).unwrap(),
+        // var Health<private> : int = 100   /   MaxHealth : float = 100.0   /   Name := "x"
+        data: Regex::new(
+            r"^(var\s+)?([A-Za-z_]\w*)((?:<[\w\s,]*>)*)\s*(?::\s*([^=]+?))?\s*(?::=|=)"
+        ).unwrap(),
+        enum_item: Regex::new(r"^([A-Za-z_]\w*)\s*,?\s*$").unwrap(),
+        specifier: Regex::new(r"<\s*([\w\s,]*?)\s*>").unwrap(),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FrameKind {
+    /// `classes` のインデックス
+    Type(usize),
+    /// 関数本体 (中身はインデックスしない)
+    Body,
+}
+
+struct Frame {
+    indent: usize,
+    kind: FrameKind,
+}
+
+/// .verse の内容を解析し、(シンボル, using パス) を返す。
+pub fn parse_verse_content(content: &str) -> (Vec<ClassInfo>, Vec<String>) {
+    let re = get_regexes();
+    let lines = strip_comments(content);
+
+    let mut classes: Vec<ClassInfo> = Vec::new();
+    let mut includes: Vec<String> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pending_attrs: Vec<String> = Vec::new();
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let line_no = idx + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() { continue; }
+        let indent = indent_width(raw);
+
+        while stack.last().is_some_and(|f| f.indent >= indent) {
+            stack.pop();
+        }
+        for f in &stack {
+            if let FrameKind::Type(i) = f.kind { classes[i].end_line = line_no; }
+        }
+        if stack.last().is_some_and(|f| f.kind == FrameKind::Body) { continue; }
+
+        // @editable などの属性は次の定義に付与する
+        if let Some(attr) = trimmed.strip_prefix('@') {
+            pending_attrs.push(attr.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("").to_string());
+            continue;
+        }
+        let attrs = std::mem::take(&mut pending_attrs);
+
+        if stack.is_empty() {
+            if let Some(cap) = re.using.captures(trimmed) {
+                includes.push(cap[1].to_string());
+                continue;
+            }
+        }
+
+        let owner = stack.iter().rev().find_map(|f| match f.kind { FrameKind::Type(i) => Some(i), FrameKind::Body => None });
+
+        // 1. class / struct / interface / enum / module
+        if let Some(cap) = re.type_def.captures(trimmed) {
+            let name_specs = specifiers(&cap[2]);
+            let kind = cap[3].to_string();
+            let type_specs = specifiers(&cap[4]);
+            let namespace = {
+                let path: Vec<&str> = stack.iter()
+                    .filter_map(|f| match f.kind { FrameKind::Type(i) => Some(classes[i].class_name.as_str()), FrameKind::Body => None })
+                    .collect();
+                if path.is_empty() { None } else { Some(path.join(".")) }
+            };
+            let mut cls = ClassInfo {
+                class_name: cap[1].to_string(),
+                // Verse ではスコープは常に型 / module なので名前空間 = 外側の型
+                parent_type: namespace.clone(),
+                namespace,
+                template_params: vec![],
+                specialization_args: None,
+                base_classes: cap.get(5)
+                    .map(|b| b.as_str().split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                    .unwrap_or_default(),
+                symbol_type: kind.clone(),
+                line: line_no,
+                end_line: line_no,
+                range_start: 0,
+                range_end: 0,
+                members: vec![],
+                is_final: type_specs.iter().any(|s| s == "final") || name_specs.iter().any(|s| s == "final"),
+                is_interface: kind == "interface",
+            };
+            // `enum{A, B}` / `struct{X:int = 0}` の 1 行形式
+            if kind == "enum" {
+                if let Some(body) = inline_body(trimmed) {
+                    for item in body.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        cls.members.push(member(item, "enum_item", line_no, "public", None, None, None));
+                    }
+                }
+            }
+            classes.push(cls);
+            stack.push(Frame { indent, kind: FrameKind::Type(classes.len() - 1) });
+            continue;
+        }
+
+        // 2. enum の列挙子
+        if let Some(i) = owner.filter(|&i| classes[i].symbol_type == "enum") {
+            if let Some(cap) = re.enum_item.captures(trimmed) {
+                classes[i].members.push(member(&cap[1], "enum_item", line_no, "public", None, None, None));
+            }
+            continue;
+        }
+
+        // 3. 関数
+        if let Some(cap) = re.function.captures(trimmed) {
+            let name = cap[1].to_string();
+            let name_specs = specifiers(&cap[2]);
+            let mut all_specs = name_specs.clone();
+            all_specs.extend(specifiers(&cap[4]));
+            all_specs.extend(attrs);
+            let access = access_of(&name_specs);
+            let detail = format!("({})", cap[3].trim());
+            let ret = cap[5].trim().to_string();
+            let has_body = cap.get(6).is_some();
+
+            match owner {
+                Some(i) => {
+                    let spec = if all_specs.is_empty() { None } else { Some(all_specs.join(", ")) };
+                    classes[i].members.push(member(&name, "function", line_no, &access, Some(detail), Some(ret), spec));
+                }
+                None => classes.push(global(&name, "global_function", line_no)),
+            }
+            if has_body {
+                stack.push(Frame { indent, kind: FrameKind::Body });
+            }
+            continue;
+        }
+
+        // 4. データメンバー / 定数
+        if let Some(cap) = re.data.captures(trimmed) {
+            let name = cap[2].to_string();
+            let name_specs = specifiers(&cap[3]);
+            let mut flags = Vec::new();
+            if cap.get(1).is_some() { flags.push("var"); }
+            let mut all_specs = name_specs.clone();
+            all_specs.extend(attrs);
+            match owner {
+                Some(i) => {
+                    let mut m = member(&name, "property", line_no, &access_of(&name_specs), None,
+                        cap.get(4).map(|t| t.as_str().trim().to_string()),
+                        if all_specs.is_empty() { None } else { Some(all_specs.join(", ")) });
+                    m.flags = flags.join(" ");
+                    classes[i].members.push(m);
+                }
+                None => classes.push(global(&name, "global_var", line_no)),
+            }
+            // 複数行にまたがる初期化式の中身は無視する
+            stack.push(Frame { indent, kind: FrameKind::Body });
+        }
+    }
+
+    (classes, includes)
+}
+
+fn member(name: &str, mem_type: &str, line: usize, access: &str, detail: Option<String>, return_type: Option<String>, specifiers: Option<String>) -> MemberInfo {
+    MemberInfo {
+        name: name.to_string(),
+        mem_type: mem_type.to_string(),
+        flags: "".to_string(),
+        access: access.to_string(),
+        line,
+        end_line: line,
+        detail,
+        return_type,
+        specifiers,
+        template_params: vec![],
+    }
+}
+
+fn global(name: &str, symbol_type: &str, line: usize) -> ClassInfo {
+    ClassInfo {
+        class_name: name.to_string(),
+        namespace: None,
+        base_classes: vec![],
+        symbol_type: symbol_type.to_string(),
+        line,
+        end_line: line,
+        range_start: 0,
+        range_end: 0,
+        members: vec![],
+        is_final: false,
+        is_interface: false,
+        parent_type: None,
+        template_params: vec![],
+        specialization_args: None,
+    }
+}
+
+/// `<public><override>` → ["public", "override"]
+fn specifiers(text: &str) -> Vec<String> {
+    get_regexes().specifier.captures_iter(text)
+        .flat_map(|c| c[1].split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Verse の既定アクセスは `internal` だが、補完では public と同様に扱う。
+fn access_of(specs: &[String]) -> String {
+    specs.iter()
+        .find(|s| matches!(s.as_str(), "public" | "protected" | "private"))
+        .cloned()
+        .unwrap_or_else(|| "public".to_string())
+}
+
+/// `enum{A, B}` の `{}` 内
+fn inline_body(line: &str) -> Option<&str> {
+    let start = line.find('{')?;
+    let end = line.rfind('}')?;
+    if end > start { Some(&line[start + 1..end]) } else { None }
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// `#` 行コメントと `<# ... #>` ブロックコメントを除去する（行数は保持）。
+fn strip_comments(content: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut block_depth = 0usize;
+    for raw in content.lines() {
+        let mut line = String::with_capacity(raw.len());
+        let mut chars = raw.chars().peekable();
+        let mut in_str = false;
+        while let Some(ch) = chars.next() {
+            if block_depth > 0 {
+                if ch == '<' && chars.peek() == Some(&'#') { chars.next(); block_depth += 1; }
+                else if ch == '#' && chars.peek() == Some(&'>') { chars.next(); block_depth -= 1; }
+                continue;
+            }
+            if ch == '"' { in_str = !in_str; }
+            if !in_str {
+                if ch == '<' && chars.peek() == Some(&'#') { chars.next(); block_depth += 1; continue; }
+                if ch == '#' { break; }
+            }
+            line.push(ch);
+        }
+        out.push(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_class_members_and_globals() {
+        let src = r#"
+using { /Fortnite.com/Devices }
+using { /Verse.org/Simulation }
+
+# A device
+my_device<public> := class<concrete>(creative_device):
+    @editable
+    var Health<private> : int = 100
+
+    OnBegin<override>()<suspends> : void =
+        Local := 1
+        Print("started")
+
+    Heal(Amount : int) : void = set Health += Amount
+
+team_color := enum:
+    Red
+    Blue
+
+Clamp(X : float) : float = X
+"#;
+        let (classes, includes) = parse_verse_content(src);
+        assert_eq!(includes, vec!["/Fortnite.com/Devices".to_string(), "/Verse.org/Simulation".to_string()]);
+
+        let dev = classes.iter().find(|c| c.class_name == "my_device").unwrap();
+        assert_eq!(dev.symbol_type, "class");
+        assert_eq!(dev.base_classes, vec!["creative_device".to_string()]);
+        let names: Vec<&str> = dev.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Health", "OnBegin", "Heal"]);
+        assert_eq!(dev.members[0].access, "private");
+        assert_eq!(dev.members[0].flags, "var");
+        assert_eq!(dev.members[0].specifiers.as_deref(), Some("private, editable"));
+        assert_eq!(dev.members[1].specifiers.as_deref(), Some("override, suspends"));
+        assert_eq!((dev.line, dev.end_line), (6, 14));
+
+        let color = classes.iter().find(|c| c.class_name == "team_color").unwrap();
+        assert_eq!(color.members.len(), 2);
+        assert!(classes.iter().any(|c| c.class_name == "Clamp" && c.symbol_type == "global_function"));
+        assert!(!classes.iter().any(|c| c.class_name == "Local"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Effective plugin enablement (`components.enabled`).
+//!
+//! Follows the editor's rules: an entry in the `.uproject` `Plugins[]` list
+//! wins; otherwise the plugin's own `EnabledByDefault` decides (unspecified →
+//! project plugins on, engine plugins off, and `DisableEnginePluginsByDefault`
+//! turns engine defaults off).  Every enabled plugin then enables the
+//! non-optional plugins listed in its own `Plugins[]`.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct PluginReference {
+    name: String,
+    enabled: bool,
+    optional: bool,
+}
+
+/// .uproject / .uplugin の共通部分
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct Descriptor {
+    plugins: Vec<PluginReference>,
+    enabled_by_default: Option<bool>,
+    disable_engine_plugins_by_default: bool,
+}
+
+fn read_descriptor(path: &Path) -> Descriptor {
+    std::fs::read_to_string(path).ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// 有効なプラグイン名 (小文字) の集合。`plugins`: 小文字の名前 → (プロジェクト側か, .uplugin)
+fn resolve(project: &Descriptor, plugins: &HashMap<String, (bool, Descriptor)>) -> HashSet<String> {
+    let explicit: HashMap<String, bool> = project.plugins.iter()
+        .map(|p| (p.name.to_lowercase(), p.enabled))
+        .collect();
+    let mut stack: Vec<String> = plugins.iter()
+        .filter(|(name, (is_project, d))| match explicit.get(name.as_str()) {
+            Some(&on) => on,
+            None => match d.enabled_by_default {
+                Some(on) => on && (*is_project || !project.disable_engine_plugins_by_default),
+                None => *is_project,
+            },
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let mut enabled = HashSet::new();
+    while let Some(name) = stack.pop() {
+        let Some((_, d)) = plugins.get(&name) else { continue };
+        for r in d.plugins.iter().filter(|r| r.enabled && !r.optional) {
+            let dep = r.name.to_lowercase();
+            if !enabled.contains(&dep) { stack.push(dep); }
+        }
+        enabled.insert(name);
+    }
+    enabled
+}
+
+/// components.enabled を .uproject / .uplugin から計算し直す。戻り値: 無効なプラグインの数。
+/// .uproject が見つからなければ判断できないので全て有効にする。
+pub fn update_enabled(conn: &Connection) -> anyhow::Result<usize> {
+    let game: Option<(String, Option<String>)> = conn.query_row(
+        "SELECT owner_name, uproject_path FROM components WHERE type = 'Game' LIMIT 1", [],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).optional()?;
+    let Some((game_owner, Some(uproject))) = game else {
+        conn.execute("UPDATE components SET enabled = 1", [])?;
+        return Ok(0);
+    };
+    let project = read_descriptor(Path::new(&uproject));
+
+    let mut stmt = conn.prepare(
+        "SELECT name, display_name, owner_name, uplugin_path FROM components WHERE type = 'Plugin' AND uplugin_path IS NOT NULL"
+    )?;
+    let rows: Vec<(String, String, Option<String>, String)> = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+        .collect::<Result<_, _>>()?;
+    let plugins: HashMap<String, (bool, Descriptor)> = rows.iter()
+        .map(|(_, display, owner, path)| {
+            (display.to_lowercase(), (owner.as_deref() == Some(game_owner.as_str()), read_descriptor(Path::new(path))))
+        })
+        .collect();
+    let enabled = resolve(&project, &plugins);
+
+    let mut update = conn.prepare("UPDATE components SET enabled = ? WHERE name = ?")?;
+    let mut disabled = 0;
+    for (name, display, _, _) in &rows {
+        let on = enabled.contains(&display.to_lowercase());
+        if !on { disabled += 1; }
+        update.execute(params![on, name])?;
+    }
+    Ok(disabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(json: &str) -> Descriptor {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_plugin_enablement() {
+        let project = desc(r#"{ "Plugins": [ { "Name": "Niagara", "Enabled": true }, { "Name": "Paper2D", "Enabled": false } ] }"#);
+        let plugins: HashMap<String, (bool, Descriptor)> = [
+            ("niagara", false, r#"{ "Plugins": [ { "Name": "NiagaraShared", "Enabled": true } ] }"#),
+            ("niagarashared", false, "{}"),
+            ("paper2d", false, r#"{ "EnabledByDefault": true }"#),
+            ("chaoscloth", false, r#"{ "EnabledByDefault": true }"#),
+            ("oculusvr", false, "{}"),
+            ("mygameplugin", true, r#"{ "Plugins": [ { "Name": "OculusVR", "Enabled": true, "Optional": true } ] }"#),
+        ].into_iter().map(|(n, p, j)| (n.to_string(), (p, desc(j)))).collect();
+
+        let enabled = resolve(&project, &plugins);
+        let mut names: Vec<&str> = enabled.iter().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, vec!["chaoscloth", "mygameplugin", "niagara", "niagarashared"]);
+
+        let strict = desc(r#"{ "DisableEnginePluginsByDefault": true }"#);
+        let enabled = resolve(&strict, &plugins);
+        assert!(!enabled.contains("chaoscloth"));
+        assert!(enabled.contains("mygameplugin"));
+    }
+}
@@ -3,23 +3,21 @@ use serde_json::{json, Value};
 use rayon::prelude::*;
 use memmap2::Mmap;
 use std::fs::File;
-use crate::db::path::{PATH_CTE};
 
 pub fn grep_assets<F>(conn: &Connection, pattern: String, mut on_items: F) -> anyhow::Result<Value> 
 where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
     tracing::info!("Grepping assets for pattern: '{}'", pattern);
     
-    let sql = format!("
-        {}
+    let sql = "
         SELECT dp.full_path || '/' || sn.text
         FROM files f 
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         WHERE (LOWER(f.extension) = 'uasset' OR LOWER(f.extension) = 'umap') 
         AND dp.full_path LIKE '%/Content/%'
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let file_paths: Vec<String> = stmt.query_map([], |row| row.get(0))?
         .filter_map(|r| r.ok())
         .collect();
@@ -54,17 +52,16 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
 }
 
 pub fn get_assets(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT dp.full_path || '/' || sn.text as path, sn.text as filename
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         WHERE LOWER(f.extension) IN ('uasset', 'umap')
         LIMIT 1000
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let rows = stmt.query_map([], |row| {
         Ok(json!({
             "path": row.get::<_, String>(0)?,
@@ -75,15 +72,14 @@ pub fn get_assets(conn: &Connection) -> anyhow::Result<Value> {
 }
 
 pub fn search_files(conn: &Connection, part: String) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT dp.full_path || '/' || sn.text as path, sn.text as filename 
         FROM files f 
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         WHERE sn.text LIKE ? LIMIT 100
-    ", PATH_CTE);
-    let mut stmt = conn.prepare(&sql)?;
+    ";
+    let mut stmt = conn.prepare(sql)?;
     let param = format!("%{}%", part);
     let rows = stmt.query_map([param], |row| {
         Ok(json!({
@@ -103,7 +99,7 @@ pub fn search_files_in_modules(conn: &Connection, modules: Vec<String>, filter:
          let remaining = limit_val - all_files.len();
          let placeholders: Vec<String> = chunk.iter().map(|_| "?".to_string()).collect();
          let sql = format!(
-            "{}
+            "
              SELECT dp.full_path || '/' || sn.text, f.extension, sm.text, rd.full_path
              FROM files f 
              JOIN dir_paths dp ON f.directory_id = dp.id
@@ -111,8 +107,7 @@ pub fn search_files_in_modules(conn: &Connection, modules: Vec<String>, filter:
              JOIN modules m ON f.module_id = m.id 
              JOIN strings sm ON m.name_id = sm.id
              JOIN dir_paths rd ON m.root_directory_id = rd.id
-             WHERE sm.text IN ({}) AND (dp.full_path || '/' || sn.text) LIKE ? LIMIT ?", 
-            PATH_CTE, placeholders.join(",")
+             WHERE sm.text IN ({}) AND (dp.full_path || '/' || sn.text) LIKE ? LIMIT ?", placeholders.join(",")
          );
          let filter_param = format!("%{}%", filter);
          let mut params: Vec<&dyn ToSql> = chunk.iter().map(|s| s as &dyn ToSql).collect();
@@ -139,7 +134,7 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
          let remaining = limit_val - total_sent;
          let placeholders: Vec<String> = chunk.iter().map(|_| "?".to_string()).collect();
          let sql = format!(
-            "{}
+            "
              SELECT dp.full_path || '/' || sn.text, f.extension, sm.text, rd.full_path
              FROM files f 
              JOIN dir_paths dp ON f.directory_id = dp.id
@@ -147,8 +142,7 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
              JOIN modules m ON f.module_id = m.id 
              JOIN strings sm ON m.name_id = sm.id
              JOIN dir_paths rd ON m.root_directory_id = rd.id
-             WHERE sm.text IN ({}) AND (dp.full_path || '/' || sn.text) LIKE ? LIMIT ?", 
-            PATH_CTE, placeholders.join(",")
+             WHERE sm.text IN ({}) AND (dp.full_path || '/' || sn.text) LIKE ? LIMIT ?", placeholders.join(",")
          );
          
          let filter_param = format!("%{}%", filter);
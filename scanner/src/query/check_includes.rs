@@ -1,9 +1,10 @@
 use std::collections::{HashMap, HashSet};
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
 use serde_json::{json, Value};
 use tree_sitter::{Parser, Query, QueryCursor, StreamingIterator};
-use crate::db::path::{PATH_CTE, to_db_path_format};
+use crate::db::path::{split_db_path, to_db_path_format};
 use super::include::compute_include_path;
+use super::util::walk_ids;
 
 /// バッファ内の #include ステートメントを解析して返す
 struct ExistingInclude {
@@ -97,20 +98,18 @@ pub fn check_includes(
 
     // 3. 現在ファイルの file_id / module_id / module_root を取得
     let db_path = to_db_path_format(file_path);
+    let (dir, filename) = split_db_path(&db_path);
     let (current_file_id, module_id, module_root): (i64, i64, String) = {
-        let sql = format!(
-            "{}
+        let sql = "
             SELECT f.id, f.module_id, mroot.full_path
             FROM files f
             JOIN modules m ON f.module_id = m.id
             JOIN dir_paths mroot ON m.root_directory_id = mroot.id
             JOIN dir_paths dp ON f.directory_id = dp.id
             JOIN strings sn ON f.filename_id = sn.id
-            WHERE dp.full_path || '/' || sn.text = ?
-            LIMIT 1",
-            PATH_CTE
-        );
-        match conn.query_row(&sql, [&db_path], |row| {
+            WHERE dp.full_path = ? AND sn.text = ?
+            LIMIT 1";
+        match conn.query_row(sql, params![dir, filename], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
         }) {
             Ok(v) => v,
@@ -134,13 +133,12 @@ pub fn check_includes(
             if !dep_names.is_empty() {
                 let placeholders = dep_names.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
                 let sql = format!(
-                    "{}
+                    "
                     SELECT m.id, dp.full_path
                     FROM modules m
                     JOIN strings sm ON m.name_id = sm.id
                     JOIN dir_paths dp ON m.root_directory_id = dp.id
-                    WHERE sm.text IN ({})",
-                    PATH_CTE, placeholders
+                    WHERE sm.text IN ({})", placeholders
                 );
                 let mut stmt = conn.prepare(&sql)?;
                 let params: Vec<&dyn rusqlite::ToSql> = dep_names.iter()
@@ -175,7 +173,7 @@ pub fn check_includes(
     let type_names: Vec<String> = type_usages.keys().cloned().collect();
     let placeholders = type_names.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
     let sql = format!(
-        "{}
+        "
         SELECT sc.text, dp.full_path || '/' || sn.text, f.module_id, f.id
         FROM classes c
         JOIN strings sc ON c.name_id = sc.id
@@ -185,8 +183,7 @@ pub fn check_includes(
         WHERE sc.text IN ({})
           AND f.is_header = 1
           AND f.module_id IN ({})
-          AND f.id != ?",
-        PATH_CTE, placeholders, module_ids_str
+          AND f.id != ?", placeholders, module_ids_str
     );
 
     let mut stmt = conn.prepare(&sql)?;
@@ -316,35 +313,21 @@ fn get_transitive_reachable_file_ids(
         return Ok(HashSet::new());
     }
 
-    // ステップ2: WITH RECURSIVE で推移的インクルードを展開
+    // ステップ2: 推移的インクルードを展開
     // - resolved_file_id が設定済みの場合はそれを使用（正確）
     // - NULL の場合は base_filename_id で同名ファイルを全て含める（保守的だが false positive 抑制に有効）
-    let seed_str = seed_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
-    let recursive_sql = format!(
-        "WITH RECURSIVE reachable(fid) AS (
-            SELECT id FROM files WHERE id IN ({seed})
-            UNION
-            SELECT fi.resolved_file_id
-            FROM file_includes fi
-            JOIN reachable r ON fi.file_id = r.fid
-            WHERE fi.resolved_file_id IS NOT NULL
-            UNION
-            SELECT f2.id
-            FROM file_includes fi2
-            JOIN reachable r2 ON fi2.file_id = r2.fid
-            JOIN files f2 ON f2.filename_id = fi2.base_filename_id
-            WHERE fi2.resolved_file_id IS NULL AND f2.is_header = 1
-        )
-        SELECT DISTINCT fid FROM reachable",
-        seed = seed_str
-    );
-
-    let mut stmt2 = conn.prepare(&recursive_sql)?;
-    let mut rows2 = stmt2.query([])?;
-    let mut result = HashSet::new();
-    while let Some(row) = rows2.next()? {
-        result.insert(row.get::<_, i64>(0)?);
-    }
+    let reached = walk_ids(
+        conn, &seed_ids,
+        "SELECT resolved_file_id FROM file_includes
+         WHERE file_id IN ({ids}) AND resolved_file_id IS NOT NULL
+         UNION
+         SELECT f2.id
+         FROM file_includes fi2
+         JOIN files f2 ON f2.filename_id = fi2.base_filename_id
+         WHERE fi2.file_id IN ({ids}) AND fi2.resolved_file_id IS NULL AND f2.is_header = 1",
+        usize::MAX,
+    )?;
+    let result: HashSet<i64> = reached.into_iter().map(|(id, _)| id).collect();
 
     Ok(result)
 }
@@ -352,5 +335,4 @@ fn get_transitive_reachable_file_ids(
 /// インクルードパスの正規化（大文字小文字無視 + スラッシュ統一）
 fn normalize_include_path(path: &str) -> String {
     path.replace('\\', "/").to_lowercase()
-}
-
\ No newline at end of file
+}
\ No newline at end of file
@@ -1,7 +1,9 @@
 use rusqlite::{Connection, params, ToSql};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use crate::db::path::{PATH_CTE};
+use crate::db::not_operator_sql;
+use crate::db::path::find_file_id_by_db_path;
+use super::util::walk_ids;
 
 /// DB 内の全クラスを返す。`extra_where` は `WHERE 1=1` の後に追加するオプション句。
 /// `params` は `extra_where` 内のプレースホルダーに対応するバインド値。
@@ -12,7 +14,7 @@ pub fn get_classes(
 ) -> anyhow::Result<Value> {
     let where_clause = extra_where.unwrap_or("");
     let sql = format!(
-        "{} SELECT sc.text, sb.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type
+        "SELECT sc.text, sb.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type
          FROM classes c
          JOIN strings sc ON c.name_id = sc.id
          LEFT JOIN strings sb ON c.base_class_id = sb.id
@@ -20,8 +22,7 @@ pub fn get_classes(
          JOIN dir_paths dp ON f.directory_id = dp.id
          JOIN strings sf ON f.filename_id = sf.id
          WHERE 1=1 {}
-         ORDER BY sc.text",
-        PATH_CTE, where_clause
+         ORDER BY sc.text", where_clause
     );
     let dyn_params: Vec<&dyn ToSql> = params.iter().map(|s| s as &dyn ToSql).collect();
     let mut stmt = conn.prepare(&sql)?;
@@ -41,16 +42,9 @@ pub fn get_classes(
 
 pub fn get_file_symbols(conn: &Connection, file_path: &str) -> anyhow::Result<Value> {
     // フルパスで file_id を特定する
-    let file_id_sql = format!(
-        "{} SELECT f.id FROM files f
-         JOIN dir_paths dp ON f.directory_id = dp.id
-         JOIN strings sf ON f.filename_id = sf.id
-         WHERE dp.full_path || '/' || sf.text = ?
-         LIMIT 1",
-        PATH_CTE
-    );
-    let file_id: i64 = conn
-        .query_row(&file_id_sql, [file_path], |r| r.get(0))
+    let file_id: i64 = find_file_id_by_db_path(conn, file_path)
+        .ok()
+        .flatten()
         .unwrap_or(0);
 
     // フルパスでマッチしなければファイル名のみでフォールバック
@@ -74,28 +68,28 @@ pub fn get_file_symbols(conn: &Connection, file_path: &str) -> anyhow::Result<Va
     }
 
     let mut stmt = conn.prepare("
-        SELECT c.id, sc.text as name, c.line_number, c.symbol_type, c.end_line_number
+        SELECT c.id, sc.text as name, c.line_number, c.symbol_type, c.end_line_number, c.language,
+               sq.text as qualified_name,
+               (SELECT spq.text FROM classes pc JOIN strings spq ON pc.qualified_name_id = spq.id WHERE pc.id = c.parent_id) as parent_name
         FROM classes c
         JOIN strings sc ON c.name_id = sc.id
+        LEFT JOIN strings sq ON c.qualified_name_id = sq.id
         WHERE c.file_id = ?
     ")?;
 
     // メンバーのファイルパスも含めて返す
-    let member_sql = format!(
-        "{} SELECT sn.text, st.text, m.access, m.flags, m.line_number, m.detail,
+    let member_sql = format!("SELECT sn.text, st.text, m.access, m.flags, m.line_number, m.detail,
                 srt.text, m.is_static,
-                COALESCE(dp.full_path || '/' || sf.text, '') as file_path
+                COALESCE((SELECT dp.full_path || '/' || sf.text FROM files mf
+                          JOIN dir_paths dp ON mf.directory_id = dp.id
+                          JOIN strings sf  ON mf.filename_id = sf.id
+                          WHERE mf.id = m.file_id), '') as file_path
          FROM members m
          JOIN strings sn  ON m.name_id = sn.id
          JOIN strings st  ON m.type_id = st.id
          LEFT JOIN strings srt ON m.return_type_id = srt.id
-         LEFT JOIN files mf    ON m.file_id = mf.id
-         LEFT JOIN dir_paths dp ON mf.directory_id = dp.id
-         LEFT JOIN strings sf  ON mf.filename_id = sf.id
-         WHERE m.class_id = ?
-         ORDER BY m.line_number",
-        PATH_CTE
-    );
+         WHERE m.class_id = ? AND {}
+         ORDER BY m.line_number", not_operator_sql("sn.text"));
 
     let mut rows = stmt.query([file_id])?;
     let mut results = Vec::new();
@@ -126,6 +120,9 @@ pub fn get_file_symbols(conn: &Connection, file_path: &str) -> anyhow::Result<Va
             "line":      row.get::<_, i64>(2)?,
             "kind":      row.get::<_, String>(3)?,
             "end_line":  row.get::<_, i64>(4)?,
+            "language":  row.get::<_, Option<String>>(5)?,
+            "qualified_name": row.get::<_, Option<String>>(6)?,
+            "parent":    row.get::<_, Option<String>>(7)?,
             "file_path": file_path,
             "members":   members,
         }));
@@ -134,16 +131,16 @@ pub fn get_file_symbols(conn: &Connection, file_path: &str) -> anyhow::Result<Va
 }
 
 pub fn get_class_members(conn: &Connection, class_name: &str) -> anyhow::Result<Value> {
-    let mut stmt = conn.prepare("
-        SELECT m.name_id, sn.text as name, st.text as type, m.access, m.flags, m.line_number, m.detail, srt.text as return_type, m.is_static
+    let mut stmt = conn.prepare(&format!("
+        SELECT m.name_id, sn.text as name, st.text as type, m.access, m.flags, m.line_number, m.detail, srt.text as return_type, m.is_static, m.specifiers
         FROM members m
         JOIN strings sn ON m.name_id = sn.id
         JOIN strings st ON m.type_id = st.id
         LEFT JOIN strings srt ON m.return_type_id = srt.id
         JOIN classes c ON m.class_id = c.id
         JOIN strings sc ON c.name_id = sc.id
-        WHERE sc.text = ?
-    ")?;
+        WHERE sc.text = ? AND {}
+    ", not_operator_sql("sn.text")))?;
     let mut rows = stmt.query([class_name])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
@@ -156,6 +153,7 @@ pub fn get_class_members(conn: &Connection, class_name: &str) -> anyhow::Result<
             "detail": row.get::<_, Option<String>>(6)?,
             "return_type": row.get::<_, Option<String>>(7)?,
             "is_static": row.get::<_, i64>(8)? == 1,
+            "specifiers": row.get::<_, Option<String>>(9)?,
         }));
     }
     Ok(json!(results))
@@ -179,7 +177,7 @@ pub fn get_classes_in_modules(
         let placeholders = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let type_clause = if symbol_type.is_some() { " AND c.symbol_type = ?" } else { "" };
         let sql = format!(
-            "{}
+            "
              SELECT sc.text, sb.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type
              FROM classes c
              JOIN strings sc ON c.name_id = sc.id
@@ -190,8 +188,7 @@ pub fn get_classes_in_modules(
              JOIN modules m ON f.module_id = m.id
              JOIN strings sm ON m.name_id = sm.id
              WHERE sm.text IN ({}){}
-             ORDER BY dp.full_path || '/' || sf.text, c.line_number",
-            PATH_CTE, placeholders, type_clause
+             ORDER BY dp.full_path || '/' || sf.text, c.line_number", placeholders, type_clause
         );
 
         let mut dyn_params: Vec<&dyn ToSql> = chunk.iter().map(|s| s as &dyn ToSql).collect();
@@ -236,7 +233,7 @@ where
         let placeholders = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let type_clause = if symbol_type.is_some() { " AND c.symbol_type = ?" } else { "" };
         let sql = format!(
-            "{}
+            "
              SELECT sc.text, sb.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type
              FROM classes c
              JOIN strings sc ON c.name_id = sc.id
@@ -246,8 +243,7 @@ where
              JOIN strings sf ON f.filename_id = sf.id
              JOIN modules m ON f.module_id = m.id
              JOIN strings sm ON m.name_id = sm.id
-             WHERE sm.text IN ({}){}",
-            PATH_CTE, placeholders, type_clause
+             WHERE sm.text IN ({}){}", placeholders, type_clause
         );
 
         let mut dyn_params: Vec<&dyn ToSql> = chunk.iter().map(|s| s as &dyn ToSql).collect();
@@ -332,8 +328,7 @@ pub fn get_enum_values(conn: &Connection, enum_name: &str) -> anyhow::Result<Val
 
 /// 指定クラスの直接の派生クラス（子クラス）を返す
 pub fn find_derived_classes(conn: &Connection, base_class: &str) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sc.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type,
                COALESCE(sm.text, '')
         FROM classes c
@@ -347,8 +342,8 @@ pub fn find_derived_classes(conn: &Connection, base_class: &str) -> anyhow::Resu
         LEFT JOIN strings sm ON m.name_id = sm.id
         WHERE sp.text = ?
         ORDER BY sc.text
-    ", PATH_CTE);
-    let mut stmt = conn.prepare(&sql)?;
+    ";
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([base_class])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
@@ -371,42 +366,75 @@ pub fn find_derived_classes(conn: &Connection, base_class: &str) -> anyhow::Resu
     Ok(json!(results))
 }
 
+/// テンプレートのプライマリ定義と部分 / 明示的特殊化の一覧を返す (プライマリが先頭)
+pub fn get_template_specializations(conn: &Connection, class_name: &str) -> anyhow::Result<Value> {
+    let base = crate::parser::template::split_type_args(class_name).0;
+    let sql = "
+        SELECT sc.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type,
+               c.template_params, c.specialization_args
+        FROM classes c
+        JOIN strings sc ON c.name_id = sc.id
+        JOIN files f ON c.file_id = f.id
+        JOIN dir_paths dp ON f.directory_id = dp.id
+        JOIN strings sf ON f.filename_id = sf.id
+        WHERE sc.text = ? AND (c.template_params IS NOT NULL OR c.specialization_args IS NOT NULL)
+        ORDER BY c.specialization_args IS NOT NULL, c.specialization_args
+    ";
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([base])?;
+    let mut results = Vec::new();
+    while let Some(row) = rows.next()? {
+        let params: Option<String> = row.get(4)?;
+        let spec_args: Option<String> = row.get(5)?;
+        results.push(json!({
+            "class_name":          row.get::<_, String>(0)?,
+            "file_path":           row.get::<_, String>(1)?,
+            "line_number":         row.get::<_, i64>(2)?,
+            "symbol_type":         row.get::<_, String>(3)?,
+            "template_params":     params.and_then(|p| serde_json::from_str::<Value>(&p).ok()).unwrap_or_else(|| json!([])),
+            "specialization_args": spec_args,
+            "is_specialization":   spec_args.is_some(),
+        }));
+    }
+    Ok(json!(results))
+}
+
 /// 指定クラスの全派生クラスを再帰的に返す
 pub fn get_recursive_derived_classes(conn: &Connection, base_class: &str) -> anyhow::Result<Value> {
-    let sql = "
+    let seeds: Vec<i64> = conn.prepare(
+        "SELECT i.child_id FROM inheritance i JOIN strings sp ON i.parent_name_id = sp.id WHERE sp.text = ?1",
+    )?.query_map([base_class], |r| r.get(0))?.collect::<Result<_, _>>()?;
+    let derived = walk_ids(
+        conn, &seeds,
+        "SELECT child_id FROM inheritance WHERE parent_class_id IN ({ids})",
+        usize::MAX,
+    )?;
+    if derived.is_empty() {
+        return Ok(json!([]));
+    }
+    let ids = derived.iter().map(|(id, _)| id.to_string()).collect::<Vec<_>>().join(", ");
+    let sql = format!("
         WITH RECURSIVE
         dir_paths(id, full_path) AS (
             SELECT d.id, s.text FROM directories d JOIN strings s ON d.name_id = s.id WHERE d.parent_id IS NULL
             UNION ALL
             SELECT d.id, CASE WHEN dp.full_path = '/' THEN '/' || s.text ELSE dp.full_path || '/' || s.text END
             FROM directories d JOIN dir_paths dp ON d.parent_id = dp.id JOIN strings s ON d.name_id = s.id
-        ),
-        derived(class_id) AS (
-            SELECT c.id
-            FROM classes c
-            JOIN inheritance i ON i.child_id = c.id
-            JOIN strings sp ON i.parent_name_id = sp.id
-            WHERE sp.text = ?1
-            UNION
-            SELECT c.id
-            FROM classes c
-            JOIN inheritance i ON i.child_id = c.id
-            JOIN derived d ON i.parent_class_id = d.class_id
         )
         SELECT sc.text, dp.full_path || '/' || sf.text, c.line_number, c.symbol_type,
                COALESCE(sm.text, '')
-        FROM derived d
-        JOIN classes c ON d.class_id = c.id
+        FROM classes c
         JOIN strings sc ON c.name_id = sc.id
         JOIN files f ON c.file_id = f.id
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sf ON f.filename_id = sf.id
         LEFT JOIN modules m ON f.module_id = m.id
         LEFT JOIN strings sm ON m.name_id = sm.id
+        WHERE c.id IN ({})
         ORDER BY sc.text
-    ";
-    let mut stmt = conn.prepare(sql)?;
-    let mut rows = stmt.query([base_class])?;
+    ", ids);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
         let name: String = row.get(0)?;
@@ -430,76 +458,78 @@ pub fn get_recursive_derived_classes(conn: &Connection, base_class: &str) -> any
 
 /// 指定クラスの全親クラスを再帰的に返す（継承チェーン）
 pub fn get_recursive_parent_classes(conn: &Connection, child_class: &str) -> anyhow::Result<Value> {
-    let sql = "
+    let seeds: Vec<i64> = conn.prepare("SELECT id FROM strings WHERE text = ?1")?
+        .query_map([child_class], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    // 深さ 0 は子クラス自身
+    let mut chain: Vec<(i64, usize)> = walk_ids(
+        conn, &seeds,
+        "SELECT i.parent_name_id FROM classes c JOIN inheritance i ON i.child_id = c.id WHERE c.name_id IN ({ids})",
+        50,
+    )?.into_iter().filter(|(_, depth)| *depth > 0).collect();
+    if chain.is_empty() {
+        return Ok(json!([]));
+    }
+    let ids = chain.iter().map(|(id, _)| id.to_string()).collect::<Vec<_>>().join(", ");
+    let names: HashMap<i64, String> = conn.prepare(&format!("SELECT id, text FROM strings WHERE id IN ({})", ids))?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    // 同名のクラスが複数あればヘッダー側・id の小さい方
+    let sql = format!("
         WITH RECURSIVE
         dir_paths(id, full_path) AS (
             SELECT d.id, s.text FROM directories d JOIN strings s ON d.name_id = s.id WHERE d.parent_id IS NULL
             UNION ALL
             SELECT d.id, CASE WHEN dp.full_path = '/' THEN '/' || s.text ELSE dp.full_path || '/' || s.text END
             FROM directories d JOIN dir_paths dp ON d.parent_id = dp.id JOIN strings s ON d.name_id = s.id
-        ),
-        parent_chain(name_id, depth) AS (
-            SELECT i.parent_name_id, 1
-            FROM classes c
-            JOIN strings sc ON c.name_id = sc.id
-            JOIN inheritance i ON i.child_id = c.id
-            WHERE sc.text = ?1
-            UNION ALL
-            SELECT i.parent_name_id, p.depth + 1
-            FROM parent_chain p
-            JOIN classes c ON c.name_id = p.name_id
-            JOIN inheritance i ON i.child_id = c.id
-            WHERE p.depth < 50
         )
-        SELECT sp.text,
+        SELECT c.name_id,
                COALESCE(dp.full_path || '/' || sf.text, ''),
                COALESCE(c.line_number, 0),
                COALESCE(c.symbol_type, ''),
                COALESCE(sm.text, ''),
-               MIN(p.depth) as min_depth
-        FROM parent_chain p
-        JOIN strings sp ON sp.id = p.name_id
-        LEFT JOIN classes c ON c.id = (
-            SELECT c2.id FROM classes c2
-            JOIN files f2 ON c2.file_id = f2.id
-            WHERE c2.name_id = sp.id
-            ORDER BY f2.is_header DESC, c2.id ASC
-            LIMIT 1
-        )
-        LEFT JOIN files f ON c.file_id = f.id
+               f.is_header,
+               c.id
+        FROM classes c
+        JOIN files f ON c.file_id = f.id
         LEFT JOIN dir_paths dp ON f.directory_id = dp.id
         LEFT JOIN strings sf ON f.filename_id = sf.id
         LEFT JOIN modules m ON f.module_id = m.id
         LEFT JOIN strings sm ON m.name_id = sm.id
-        GROUP BY p.name_id
-        ORDER BY min_depth DESC
-    ";
-    let mut stmt = conn.prepare(sql)?;
-    let mut rows = stmt.query([child_class])?;
-    let mut results = Vec::new();
+        WHERE c.name_id IN ({})
+    ", ids);
+    let entry = |name: &str, path: String, line: i64, sym_type: String, module_name: String| json!({
+        "name": name,
+        "class_name": name,
+        "path": if path.is_empty() { serde_json::Value::Null } else { json!(path.clone()) },
+        "file_path": if path.is_empty() { serde_json::Value::Null } else { json!(path) },
+        "line": line,
+        "line_number": line,
+        "symbol_type": sym_type,
+        "module_name": if module_name.is_empty() { serde_json::Value::Null } else { json!(module_name) },
+    });
+    let mut picked: HashMap<i64, ((bool, i64), Value)> = HashMap::new();
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
     while let Some(row) = rows.next()? {
-        let name: String = row.get(0)?;
-        let path: String = row.get(1)?;
-        let line: i64 = row.get(2)?;
-        let sym_type: String = row.get(3)?;
-        let module_name: String = row.get(4)?;
-        results.push(json!({
-            "name": name,
-            "class_name": name,
-            "path": if path.is_empty() { serde_json::Value::Null } else { json!(path.clone()) },
-            "file_path": if path.is_empty() { serde_json::Value::Null } else { json!(path) },
-            "line": line,
-            "line_number": line,
-            "symbol_type": sym_type,
-            "module_name": if module_name.is_empty() { serde_json::Value::Null } else { json!(module_name) },
-        }));
+        let name_id: i64 = row.get(0)?;
+        let rank = (!row.get::<_, bool>(5)?, row.get::<_, i64>(6)?);
+        if picked.get(&name_id).is_some_and(|(best, _)| *best <= rank) { continue; }
+        let name = names.get(&name_id).map(String::as_str).unwrap_or_default();
+        picked.insert(name_id, (rank, entry(name, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)));
     }
+
+    chain.sort_by_key(|(_, depth)| std::cmp::Reverse(*depth));
+    let results: Vec<Value> = chain.into_iter().map(|(name_id, _)| match picked.remove(&name_id) {
+        Some((_, v)) => v,
+        None => entry(names.get(&name_id).map(String::as_str).unwrap_or_default(), String::new(), 0, String::new(), String::new()),
+    }).collect();
     Ok(json!(results))
 }
 
 pub fn find_symbol_usages(conn: &Connection, symbol_name: &str, limit: usize) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sc.line, dp.full_path || '/' || sn.text as path
         FROM symbol_calls sc
         JOIN strings s ON sc.name_id = s.id
@@ -508,9 +538,9 @@ pub fn find_symbol_usages(conn: &Connection, symbol_name: &str, limit: usize) ->
         JOIN strings sn ON f.filename_id = sn.id
         WHERE s.text = ?
         LIMIT ?
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query(params![symbol_name, limit])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
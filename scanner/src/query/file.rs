@@ -1,6 +1,7 @@
 use rusqlite::{Connection};
 use serde_json::{json, Value};
-use crate::db::path::{PATH_CTE, to_db_path_format};
+use crate::db::path::{split_db_path, to_db_path_format};
+use super::util::walk_ids;
 
 /// ファイルの依存関係を取得する
 pub fn get_depend_files(conn: &Connection, file_path: &str, recursive: bool, game_only: bool) -> anyhow::Result<Value> {
@@ -16,32 +17,31 @@ pub fn get_depend_files(conn: &Connection, file_path: &str, recursive: bool, gam
     };
 
     let sql = if recursive {
+        let reached = walk_ids(
+            conn, &[file_id],
+            "SELECT resolved_file_id FROM file_includes WHERE file_id IN ({ids}) AND resolved_file_id IS NOT NULL",
+            usize::MAX,
+        )?;
+        let ids = reached.iter().filter(|(_, depth)| *depth > 0).map(|(id, _)| id.to_string()).collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(json!(results));
+        }
         format!("
-            {}
-            WITH RECURSIVE dependency_graph(file_id, resolved_id) AS (
-                SELECT file_id, resolved_file_id FROM file_includes WHERE file_id = ?
-                UNION
-                SELECT fi.file_id, fi.resolved_file_id 
-                FROM file_includes fi 
-                JOIN dependency_graph dg ON fi.file_id = dg.resolved_id 
-                WHERE fi.resolved_file_id IS NOT NULL
-            )
             SELECT DISTINCT 
                 dp.full_path || '/' || sn.text as path, 
                 sm.text as module_name, 
                 rd.full_path as module_root, 
                 f.extension
-            FROM dependency_graph dg 
-            JOIN files f ON dg.resolved_id = f.id
+            FROM files f
             JOIN dir_paths dp ON f.directory_id = dp.id
             JOIN strings sn ON f.filename_id = sn.id
             LEFT JOIN modules m ON f.module_id = m.id
             LEFT JOIN strings sm ON m.name_id = sm.id
             LEFT JOIN dir_paths rd ON m.root_directory_id = rd.id
-        ", PATH_CTE)
+            WHERE f.id IN ({})
+        ", ids.join(", "))
     } else {
-        format!("
-            {}
+        "
             SELECT DISTINCT 
                 dp.full_path || '/' || sn.text as path, 
                 sm.text as module_name, 
@@ -55,11 +55,11 @@ pub fn get_depend_files(conn: &Connection, file_path: &str, recursive: bool, gam
             LEFT JOIN strings sm ON m.name_id = sm.id
             LEFT JOIN dir_paths rd ON m.root_directory_id = rd.id
             WHERE fi.file_id = ? AND fi.resolved_file_id IS NOT NULL
-        ", PATH_CTE)
+        ".to_string()
     };
 
     let mut stmt = conn.prepare(&sql)?;
-    let mut rows = stmt.query([file_id])?;
+    let mut rows = if recursive { stmt.query([])? } else { stmt.query([file_id])? };
     while let Some(row) = rows.next()? {
         let path: String = row.get(0)?;
         if !game_only || (!path.contains("/Engine/") && !path.contains("\\Engine\\")) {
@@ -77,7 +77,6 @@ pub fn get_depend_files(conn: &Connection, file_path: &str, recursive: bool, gam
 /// モジュール内の全ファイルを取得する
 pub fn get_files_in_modules(conn: &Connection, modules: Vec<String>, extensions: Option<Vec<String>>, filter: Option<String>) -> anyhow::Result<Value> {
     let sql = format!("
-        {}
         SELECT dp.full_path || '/' || sn.text as path, sm.text as module_name, rd.full_path as module_root, f.extension
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
@@ -86,7 +85,7 @@ pub fn get_files_in_modules(conn: &Connection, modules: Vec<String>, extensions:
         JOIN strings sm ON m.name_id = sm.id
         JOIN dir_paths rd ON m.root_directory_id = rd.id
         WHERE sm.text IN ({})
-    ", PATH_CTE, modules.iter().map(|_| "?").collect::<Vec<_>>().join(","));
+    ", modules.iter().map(|_| "?").collect::<Vec<_>>().join(","));
 
     let mut stmt = conn.prepare(&sql)?;
     let mut rows = stmt.query(rusqlite::params_from_iter(modules))?;
@@ -139,7 +138,6 @@ pub fn search_files_by_path_part(conn: &Connection, part: &str) -> anyhow::Resul
 
     // ファイル名 (sn.text) 検索
     let sql = format!("
-        {}
         SELECT sn.text as filename, dp.full_path || '/' || sn.text as path,
                sm.text as module_name, rd.full_path as module_root
         FROM files f
@@ -150,7 +148,7 @@ pub fn search_files_by_path_part(conn: &Connection, part: &str) -> anyhow::Resul
         LEFT JOIN dir_paths rd ON m.root_directory_id = rd.id
         WHERE {}
         LIMIT 500
-    ", PATH_CTE, where_clause);
+    ", where_clause);
 
     let mut stmt = conn.prepare(&sql)?;
     let mut rows = stmt.query(rusqlite::params_from_iter(tokens.iter()))?;
@@ -185,7 +183,6 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
 pub fn get_files_in_modules_async<F>(conn: &Connection, modules: Vec<String>, extensions: Option<Vec<String>>, filter: Option<String>, mut on_items: F) -> anyhow::Result<Value>
 where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
     let sql = format!("
-        {}
         SELECT dp.full_path || '/' || sn.text as path, sm.text as module_name, rd.full_path as module_root, f.extension
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
@@ -194,7 +191,7 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
         JOIN strings sm ON m.name_id = sm.id
         JOIN dir_paths rd ON m.root_directory_id = rd.id
         WHERE sm.text IN ({})
-    ", PATH_CTE, modules.iter().map(|_| "?").collect::<Vec<_>>().join(","));
+    ", modules.iter().map(|_| "?").collect::<Vec<_>>().join(","));
 
     let mut stmt = conn.prepare(&sql)?;
     let mut rows = stmt.query(rusqlite::params_from_iter(modules))?;
@@ -240,16 +237,15 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
 
 /// *.Target.cs ファイルの一覧を取得する
 pub fn get_target_files(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sn.text as filename, dp.full_path || '/' || sn.text as path
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         WHERE sn.text LIKE '%.Target.cs'
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([])?;
     
     let mut results = Vec::new();
@@ -264,15 +260,14 @@ pub fn get_target_files(conn: &Connection) -> anyhow::Result<Value> {
 
 /// 全てのファイルパスをリストで取得する
 pub fn get_all_file_paths(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT dp.full_path || '/' || sn.text as path
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([])?;
     
     let mut results = Vec::new();
@@ -302,23 +297,23 @@ pub fn get_files_in_favorite_paths(
 
     for dir in dirs {
         let db_dir = to_db_path_format(dir);
-        // 末尾スラッシュを保証した上でプレフィックスマッチ
-        let prefix = if db_dir.ends_with('/') {
-            format!("{}%", db_dir)
-        } else {
-            format!("{}/%", db_dir)
-        };
-        conditions.push("(dp.full_path || '/' || sn.text) LIKE ?".to_string());
-        params.push(prefix);
+        let db_dir = db_dir.trim_end_matches('/');
+        // ディレクトリ自身とその配下
+        conditions.push("(dp.full_path = ? OR dp.full_path LIKE ?)".to_string());
+        params.push(db_dir.to_string());
+        params.push(format!("{}/%", db_dir));
     }
     for file in exact_files {
-        conditions.push("(dp.full_path || '/' || sn.text) = ?".to_string());
-        params.push(to_db_path_format(file));
+        let db_file = to_db_path_format(file);
+        let (dir, filename) = split_db_path(&db_file);
+        conditions.push("(dp.full_path = ? AND sn.text = ?)".to_string());
+        params.push(dir.to_string());
+        params.push(filename.to_string());
     }
 
     let where_clause = conditions.join(" OR ");
     let sql = format!(
-        "{}
+        "
         SELECT
             dp.full_path || '/' || sn.text as path,
             sn.text as filename,
@@ -332,8 +327,7 @@ pub fn get_files_in_favorite_paths(
         LEFT JOIN strings sm ON m.name_id = sm.id
         LEFT JOIN dir_paths rd ON m.root_directory_id = rd.id
         WHERE {}
-        ORDER BY sn.text",
-        PATH_CTE, where_clause
+        ORDER BY sn.text", where_clause
     );
 
     let mut stmt = conn.prepare(&sql)?;
@@ -354,17 +348,16 @@ pub fn get_files_in_favorite_paths(
 
 /// 全てのファイルのメタデータ (filename, path, module_name) を取得する
 pub fn get_all_files_metadata(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sn.text as filename, dp.full_path || '/' || sn.text as path, sm.text as module_name
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         LEFT JOIN modules m ON f.module_id = m.id
         LEFT JOIN strings sm ON m.name_id = sm.id
-    ", PATH_CTE);
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([])?;
     
     let mut results = Vec::new();
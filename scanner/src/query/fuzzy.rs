@@ -0,0 +1,119 @@
+//! Unreal-aware fuzzy matching for symbol search.
+//!
+//! A query is scored against the symbol name both as written and without its
+//! UE type prefix (`AMyActor` ↔ `MyActor`).  Exact, prefix and substring
+//! matches are tiered above camel-case / subsequence matches (`PlyrCtrl` →
+//! `APlayerController`), which are scored by how many matched characters land
+//! on hump boundaries and how many are consecutive.
+
+pub const EXACT: i32 = 1000;
+const PREFIX: i32 = 900;
+const SUBSTRING: i32 = 700;
+/// サブシーケンス一致の上限 (部分文字列一致より常に下)
+const SUBSEQUENCE_MAX: i32 = 600;
+/// UE プレフィックスを外して一致した場合の減点
+const STRIPPED_PENALTY: i32 = 5;
+
+/// `AActor` / `UObject` / `FVector` / `EMode` / `IInterface` / `TArray` / `SWidget` のプレフィックスを外す
+pub fn strip_ue_prefix(name: &str) -> &str {
+    let b = name.as_bytes();
+    if b.len() >= 3 && b"AUFEITS".contains(&b[0]) && b[1].is_ascii_uppercase() {
+        &name[1..]
+    } else {
+        name
+    }
+}
+
+/// `name[i]` が単語の先頭か (先頭 / 小文字→大文字 / `_` の直後 / 英字→数字)
+fn is_boundary(name: &[u8], i: usize) -> bool {
+    if i == 0 { return true; }
+    let (p, c) = (name[i - 1], name[i]);
+    p == b'_' || (p.is_ascii_lowercase() && c.is_ascii_uppercase()) || (p.is_ascii_alphabetic() && c.is_ascii_digit())
+}
+
+/// 大文字小文字を区別しないサブシーケンス一致のスコア (DP)。一致しなければ None
+fn subsequence_score(query: &[u8], name: &[u8]) -> Option<i32> {
+    let (n, m) = (query.len(), name.len());
+    if n == 0 || n > m { return None; }
+    const NONE: i32 = i32::MIN / 2;
+    // prev[j]: query[..i] を name[j] で終えた場合の最大スコア
+    let mut prev = vec![NONE; m];
+    for (i, &qc) in query.iter().enumerate() {
+        let mut cur = vec![NONE; m];
+        // max(prev[k] + k) for k <= j - 2 (飛ばした文字 1 つにつき -1)
+        let mut gap_best = NONE;
+        for j in 0..m {
+            if j >= 2 && prev[j - 2] > NONE { gap_best = gap_best.max(prev[j - 2] + (j - 2) as i32); }
+            if !name[j].eq_ignore_ascii_case(&qc) { continue; }
+            let s = 10 + if is_boundary(name, j) { 20 } else { 0 };
+            if i == 0 {
+                cur[j] = if j == 0 { s } else { s - 5 - j.min(10) as i32 };
+                continue;
+            }
+            let consecutive = if j >= 1 && prev[j - 1] > NONE { prev[j - 1] + 15 } else { NONE };
+            let gapped = if gap_best > NONE { gap_best - (j as i32 - 1) } else { NONE };
+            let base = consecutive.max(gapped);
+            if base > NONE { cur[j] = base + s; }
+        }
+        prev = cur;
+    }
+    prev.into_iter().max().filter(|&s| s > NONE)
+}
+
+fn score_one(query: &str, name: &str) -> Option<i32> {
+    let (q, nm) = (query.to_ascii_lowercase(), name.to_ascii_lowercase());
+    if q.is_empty() { return None; }
+    let extra = (nm.len() - q.len().min(nm.len())) as i32;
+    if nm == q { return Some(EXACT); }
+    if nm.starts_with(&q) { return Some(PREFIX - extra.min(99)); }
+    if let Some(pos) = nm.find(&q) {
+        let boundary = if is_boundary(name.as_bytes(), pos) { 50 } else { 0 };
+        return Some(SUBSTRING - 50 + boundary - (pos as i32).min(49) - extra.min(49) / 5);
+    }
+    subsequence_score(query.as_bytes(), name.as_bytes())
+        .map(|s| (s + 200 - extra.min(99)).clamp(1, SUBSEQUENCE_MAX))
+}
+
+/// クエリとシンボル名の一致度。大きいほど良い。一致しなければ None
+pub fn score(query: &str, name: &str) -> Option<i32> {
+    let stripped_name = strip_ue_prefix(name);
+    let stripped_query = strip_ue_prefix(query);
+    let mut best = score_one(query, name);
+    if stripped_name.len() != name.len() || stripped_query.len() != query.len() {
+        let alt = score_one(stripped_query, stripped_name).map(|s| s - STRIPPED_PENALTY);
+        best = best.max(alt);
+        if stripped_name.len() != name.len() {
+            best = best.max(score_one(query, stripped_name).map(|s| s - STRIPPED_PENALTY));
+        }
+    }
+    best
+}
+
+/// SQL の `LIKE` 用の絞り込みパターン (`%p%l%y%...%`)。UE プレフィックスは外しておく
+pub fn like_pattern(query: &str) -> String {
+    let mut out = String::from("%");
+    for c in strip_ue_prefix(query).chars() {
+        if c == '%' || c == '_' || c == '\\' { out.push('\\'); }
+        out.push(c);
+        out.push('%');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_tiers() {
+        assert_eq!(score("AMyActor", "AMyActor"), Some(EXACT));
+        assert_eq!(score("MyActor", "AMyActor"), Some(EXACT - STRIPPED_PENALTY));
+        assert_eq!(score("amyactor", "AMyActor"), Some(EXACT));
+        assert!(score("playercont", "APlayerController").unwrap() > score("PlyrCtrl", "APlayerController").unwrap());
+        assert!(score("PlyrCtrl", "APlayerController").is_some());
+        assert!(score("PC", "APlayerController").unwrap() > score("PC", "APawnSpec").unwrap());
+        assert!(score("Controller", "APlayerController").unwrap() > score("PlyrCtrl", "APlayerController").unwrap());
+        assert!(score("xyz", "APlayerController").is_none());
+        assert_eq!(like_pattern("AMy_b"), "%M%y%\\_%b%");
+    }
+}
@@ -2,7 +2,7 @@ use rusqlite::{Connection, OptionalExtension, params};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use tree_sitter::{Parser, Point};
-use crate::db::path::PATH_CTE;
+use crate::parser::template;
 
 // ---------------------------------------------------------------------------
 // Internal helpers
@@ -77,6 +77,8 @@ pub struct CursorCtx {
     /// "::", ".", or "->"
     pub qualifier_op: Option<String>,
     pub enclosing_class: Option<String>,
+    /// `TFoo<int32>` の `TFoo` 上にカーソルがある場合の `<int32>`
+    pub template_args: Option<String>,
 }
 
 pub fn extract_cursor_context(content: &str, line: u32, character: u32) -> Option<CursorCtx> {
@@ -100,6 +102,10 @@ pub fn extract_cursor_context(content: &str, line: u32, character: u32) -> Optio
     }
 
     let enclosing_class = get_enclosing_class(node, src);
+    let template_args = node.parent()
+        .filter(|p| p.kind() == "template_type")
+        .and_then(|p| p.child_by_field_name("arguments"))
+        .map(|a| node_text(&a, src).to_string());
 
     let mut qualifier: Option<String> = None;
     let mut qualifier_op: Option<String> = None;
@@ -139,7 +145,7 @@ pub fn extract_cursor_context(content: &str, line: u32, character: u32) -> Optio
         cur = n.parent();
     }
 
-    Some(CursorCtx { symbol, qualifier, qualifier_op, enclosing_class })
+    Some(CursorCtx { symbol, qualifier, qualifier_op, enclosing_class, template_args })
 }
 
 // ---------------------------------------------------------------------------
@@ -263,10 +269,39 @@ impl<'a> GotoCtx<'a> {
                ELSE 2
              END",
         )?;
-        let ids: Vec<i64> = stmt
+        let mut ids: Vec<i64> = stmt
             .query_map([name], |r| r.get(0))?
             .filter_map(|r| r.ok())
             .collect();
+        // FMyOuter::FInner のようなネスト型は完全修飾名で引く
+        if ids.is_empty() && name.contains("::") {
+            let mut stmt = self.conn.prepare(
+                "SELECT c.id FROM classes c
+                 JOIN strings s ON c.qualified_name_id = s.id
+                 WHERE s.text = ?",
+            )?;
+            ids = stmt
+                .query_map([name], |r| r.get(0))?
+                .filter_map(|r| r.ok())
+                .collect();
+        }
+        // TFoo<int32> → 一致する特殊化、なければプライマリテンプレート
+        if ids.is_empty() {
+            if let (base, Some(args)) = template::split_type_args(name) {
+                let mut stmt = self.conn.prepare(
+                    "SELECT c.id FROM classes c
+                     JOIN strings s ON c.name_id = s.id
+                     WHERE s.text = ? AND c.specialization_args = ?",
+                )?;
+                ids = stmt
+                    .query_map(params![base, template::normalize_args(args)], |r| r.get(0))?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                if ids.is_empty() && base != name {
+                    ids = self.get_class_ids(base)?;
+                }
+            }
+        }
         self.class_id_cache.insert(name.to_string(), ids.clone());
         Ok(ids)
     }
@@ -288,8 +323,7 @@ pub fn find_symbol_in_inheritance_chain(
         return Ok(None);
     }
 
-    let member_sql = format!(
-        "{} SELECT sm.text, m.line_number, dp.full_path || '/' || sf.text, sc.text
+    let member_sql = "SELECT sm.text, m.line_number, dp.full_path || '/' || sf.text, sc.text
          FROM members m
          JOIN strings sm ON m.name_id = sm.id
          JOIN classes c ON m.class_id = c.id
@@ -305,9 +339,7 @@ pub fn find_symbol_in_inheritance_chain(
              WHEN sf.text LIKE '%.hpp' THEN 1
              ELSE 2
            END
-         LIMIT 1",
-        PATH_CTE
-    );
+         LIMIT 1";
 
     let mut queue: std::collections::VecDeque<i64> = start_ids.into_iter().collect();
     let mut visited: HashMap<i64, bool> = HashMap::new();
@@ -319,7 +351,7 @@ pub fn find_symbol_in_inheritance_chain(
         visited.insert(cls_id, true);
 
         let res = conn
-            .query_row(&member_sql, params![cls_id, symbol_name], |row| {
+            .query_row(member_sql, params![cls_id, symbol_name], |row| {
                 Ok(json!({
                     "symbol_name": row.get::<_, String>(0)?,
                     "line_number": row.get::<_, i64>(1)?,
@@ -366,8 +398,7 @@ pub fn find_symbol_in_module(
     module: &str,
     symbol: &str,
 ) -> anyhow::Result<Option<Value>> {
-    let class_sql = format!(
-        "{} SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
+    let class_sql = "SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
          FROM classes c
          JOIN strings sc ON c.name_id = sc.id
          JOIN files f ON c.file_id = f.id
@@ -376,11 +407,9 @@ pub fn find_symbol_in_module(
          JOIN modules m ON f.module_id = m.id
          JOIN strings sm ON m.name_id = sm.id
          WHERE sm.text = ? AND sc.text = ?
-         LIMIT 1",
-        PATH_CTE
-    );
+         LIMIT 1";
     let class_result = conn
-        .query_row(&class_sql, params![module, symbol], |row| {
+        .query_row(class_sql, params![module, symbol], |row| {
             Ok(json!({
                 "symbol_name": row.get::<_, String>(0)?,
                 "line_number": row.get::<_, i64>(1)?,
@@ -392,8 +421,7 @@ pub fn find_symbol_in_module(
         return Ok(class_result);
     }
 
-    let member_sql = format!(
-        "{} SELECT sm.text, mem.line_number, dp.full_path || '/' || sf.text
+    let member_sql = "SELECT sm.text, mem.line_number, dp.full_path || '/' || sf.text
          FROM members mem
          JOIN strings sm ON mem.name_id = sm.id
          JOIN classes c ON mem.class_id = c.id
@@ -408,11 +436,9 @@ pub fn find_symbol_in_module(
            WHEN sf.text LIKE '%.hpp' THEN 1
            ELSE 2
          END
-         LIMIT 1",
-        PATH_CTE
-    );
+         LIMIT 1";
     let member_result = conn
-        .query_row(&member_sql, params![module, symbol], |row| {
+        .query_row(member_sql, params![module, symbol], |row| {
             Ok(json!({
                 "symbol_name": row.get::<_, String>(0)?,
                 "line_number": row.get::<_, i64>(1)?,
@@ -425,8 +451,7 @@ pub fn find_symbol_in_module(
 
 /// クラス / 構造体 / Enum の定義場所を返す
 fn find_type_definition(conn: &Connection, name: &str) -> anyhow::Result<Option<Value>> {
-    let sql = format!(
-        "{} SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
+    let sql = "SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
          FROM classes c
          JOIN strings sc ON c.name_id = sc.id
          JOIN files f ON c.file_id = f.id
@@ -438,11 +463,9 @@ fn find_type_definition(conn: &Connection, name: &str) -> anyhow::Result<Option<
            WHEN sf.text LIKE '%.hpp' THEN 1
            ELSE 2
          END
-         LIMIT 1",
-        PATH_CTE
-    );
+         LIMIT 1";
     let result = conn
-        .query_row(&sql, [name], |row| {
+        .query_row(sql, [name], |row| {
             Ok(json!({
                 "symbol_name": row.get::<_, String>(0)?,
                 "line_number": row.get::<_, i64>(1)?,
@@ -454,10 +477,61 @@ fn find_type_definition(conn: &Connection, name: &str) -> anyhow::Result<Option<
     Ok(result)
 }
 
+/// `template<> struct TFoo<int32>` のような特殊化の定義場所を返す
+fn find_specialization_definition(conn: &Connection, name: &str, args: &str) -> anyhow::Result<Option<Value>> {
+    let sql = "SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
+         FROM classes c
+         JOIN strings sc ON c.name_id = sc.id
+         JOIN files f ON c.file_id = f.id
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sf ON f.filename_id = sf.id
+         WHERE sc.text = ? AND c.specialization_args = ?
+         LIMIT 1";
+    let result = conn
+        .query_row(sql, params![name, template::normalize_args(args)], |row| {
+            Ok(json!({
+                "symbol_name": row.get::<_, String>(0)?,
+                "line_number": row.get::<_, i64>(1)?,
+                "file_path":   row.get::<_, String>(2)?,
+                "class_name":  row.get::<_, String>(0)?,
+            }))
+        })
+        .optional()?;
+    Ok(result)
+}
+
+/// 完全修飾名 (`FMyOuter::FInner`) で型の定義場所を返す
+fn find_qualified_type_definition(conn: &Connection, qualified_name: &str) -> anyhow::Result<Option<Value>> {
+    let sql = "SELECT sc.text, c.line_number, dp.full_path || '/' || sf.text
+         FROM classes c
+         JOIN strings sq ON c.qualified_name_id = sq.id
+         JOIN strings sc ON c.name_id = sc.id
+         JOIN files f ON c.file_id = f.id
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sf ON f.filename_id = sf.id
+         WHERE sq.text = ?
+         ORDER BY CASE
+           WHEN sf.text LIKE '%.h'   THEN 0
+           WHEN sf.text LIKE '%.hpp' THEN 1
+           ELSE 2
+         END
+         LIMIT 1";
+    let result = conn
+        .query_row(sql, [qualified_name], |row| {
+            Ok(json!({
+                "symbol_name": row.get::<_, String>(0)?,
+                "line_number": row.get::<_, i64>(1)?,
+                "file_path":   row.get::<_, String>(2)?,
+                "class_name":  qualified_name,
+            }))
+        })
+        .optional()?;
+    Ok(result)
+}
+
 /// 全クラスからメンバー名で検索（最終フォールバック）
 fn find_member_anywhere(conn: &Connection, symbol_name: &str) -> anyhow::Result<Option<Value>> {
-    let sql = format!(
-        "{} SELECT sm.text, m.line_number, dp.full_path || '/' || sf.text, sc.text
+    let sql = "SELECT sm.text, m.line_number, dp.full_path || '/' || sf.text, sc.text
          FROM members m
          JOIN strings sm ON m.name_id = sm.id
          JOIN classes c ON m.class_id = c.id
@@ -473,11 +547,9 @@ fn find_member_anywhere(conn: &Connection, symbol_name: &str) -> anyhow::Result<
              WHEN sf.text LIKE '%.hpp' THEN 1
              ELSE 2
            END
-         LIMIT 1",
-        PATH_CTE
-    );
+         LIMIT 1";
     let result = conn
-        .query_row(&sql, [symbol_name], |row| {
+        .query_row(sql, [symbol_name], |row| {
             Ok(json!({
                 "symbol_name": row.get::<_, String>(0)?,
                 "line_number": row.get::<_, i64>(1)?,
@@ -489,6 +561,51 @@ fn find_member_anywhere(conn: &Connection, symbol_name: &str) -> anyhow::Result<
     Ok(result)
 }
 
+/// シェーダーバインディング経由のジャンプ先を返す。
+///
+/// - C++: `IMPLEMENT_GLOBAL_SHADER(...)` 行上 → .usf のエントリポイント
+/// - .usf/.ush: バインド済みエントリポイント上 → C++ の宣言行
+fn find_shader_binding_target(
+    conn: &Connection,
+    content: &str,
+    line: u32,
+    symbol: &str,
+    file_path: Option<&str>,
+) -> anyhow::Result<Option<Value>> {
+    let Some(file_path) = file_path else { return Ok(None) };
+    let is_shader_file = file_path.ends_with(".usf") || file_path.ends_with(".ush");
+    let current_line = content.lines().nth(line as usize).unwrap_or("");
+    let on_implement_line = current_line.contains("IMPLEMENT_") && current_line.contains("SHADER");
+    if !is_shader_file && !on_implement_line {
+        return Ok(None);
+    }
+
+    let bindings = super::shader::get_shader_bindings(conn, None, Some(file_path))?;
+    let Some(bindings) = bindings.as_array() else { return Ok(None) };
+
+    for b in bindings {
+        if is_shader_file {
+            if b["entry_point"].as_str() == Some(symbol) {
+                return Ok(Some(json!({
+                    "symbol_name": b["class_name"],
+                    "line_number": b["line_number"],
+                    "file_path":   b["file_path"],
+                    "class_name":  b["class_name"],
+                })));
+            }
+        } else if b["line_number"].as_i64() == Some(line as i64 + 1) {
+            let Some(shader_path) = b["shader_file_path"].as_str() else { continue };
+            return Ok(Some(json!({
+                "symbol_name": b["entry_point"],
+                "line_number": b["entry_line_number"].as_i64().unwrap_or(1),
+                "file_path":   shader_path,
+                "class_name":  b["class_name"],
+            })));
+        }
+    }
+    Ok(None)
+}
+
 // ---------------------------------------------------------------------------
 // Local definition search
 // ---------------------------------------------------------------------------
@@ -633,6 +750,11 @@ pub fn goto_definition(
         ctx.enclosing_class,
     );
 
+    // シェーダー: IMPLEMENT_GLOBAL_SHADER 行 → .usf エントリポイント / .usf エントリポイント → C++ クラス
+    if let Some(result) = find_shader_binding_target(conn, &content, line, &ctx.symbol, file_path.as_deref())? {
+        return Ok(result);
+    }
+
     // 0. ローカル（カレントバッファ内）の定義を優先的に検索
     let cursor_point = Point::new(line as usize, character as usize);
     if let Some(pos) = find_local_definition(root, src, &ctx.symbol, cursor_point) {
@@ -658,6 +780,14 @@ pub fn goto_definition(
 
         tracing::debug!("Qualifier resolved to class: '{}'", class_name);
 
+        // Outer::Inner → ネスト型 / ネストした名前空間内の型
+        if ctx.qualifier_op.as_deref() == Some("::") {
+            let qualified = format!("{}::{}", class_name, ctx.symbol);
+            if let Some(result) = find_qualified_type_definition(conn, &qualified)? {
+                return Ok(result);
+            }
+        }
+
         if let Some(result) = find_symbol_in_inheritance_chain(conn, &class_name, &ctx.symbol)? {
             return Ok(result);
         }
@@ -675,7 +805,12 @@ pub fn goto_definition(
         }
     }
 
-    // 3. クラス / 構造体 / enum の定義として検索
+    // 3. クラス / 構造体 / enum の定義として検索 (TFoo<int32> なら該当する特殊化を優先)
+    if let Some(ref args) = ctx.template_args {
+        if let Some(result) = find_specialization_definition(conn, &ctx.symbol, args)? {
+            return Ok(result);
+        }
+    }
     if let Some(result) = find_type_definition(conn, &ctx.symbol)? {
         return Ok(result);
     }
@@ -0,0 +1,180 @@
+//! Regex search over source files (`GrepCode`).
+//!
+//! Candidate files come from `files`, filtered by extension, module, component
+//! and enabled state.  When the trigram index in `db::code_index` is enabled,
+//! the candidates are narrowed further to files containing every trigram the
+//! pattern requires.  Matches are streamed back in batches of `FILE_BATCH_SIZE` files.
+
+use rusqlite::{Connection, ToSql};
+use serde_json::{json, Value};
+use rayon::prelude::*;
+use regex::RegexBuilder;
+use crate::db::code_index::{self, CODE_EXTENSIONS};
+
+const DEFAULT_LIMIT: usize = 1000;
+/// 並列に読むファイル数 (この単位で結果を通知する)
+const FILE_BATCH_SIZE: usize = 64;
+
+/// ソースコードを正規表現で検索する (GrepCode)。
+/// トライグラム索引が有効なら必須リテラルから候補ファイルを絞り込み、無効なら条件に合う全ファイルを走査する。
+#[allow(clippy::too_many_arguments)]
+pub fn grep_code<F>(
+    conn: &Connection,
+    pattern: &str,
+    modules: Option<Vec<String>>,
+    components: Option<Vec<String>>,
+    extensions: Option<Vec<String>>,
+    ignore_case: bool,
+    limit: Option<usize>,
+    enabled_only: bool,
+    mut on_items: F,
+) -> anyhow::Result<Value>
+where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
+    let re = RegexBuilder::new(pattern).case_insensitive(ignore_case).build()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+    let mut where_clauses: Vec<String> = Vec::new();
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    let in_list = |n: usize| vec!["?"; n].join(",");
+
+    let exts: Vec<String> = match extensions {
+        Some(e) if !e.is_empty() => e.iter().map(|x| x.trim_start_matches('.').to_lowercase()).collect(),
+        _ => CODE_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+    };
+    where_clauses.push(format!("LOWER(f.extension) IN ({})", in_list(exts.len())));
+    params.extend(exts.into_iter().map(|e| Box::new(e) as Box<dyn ToSql>));
+
+    if let Some(mods) = modules.filter(|m| !m.is_empty()) {
+        where_clauses.push(format!("sm.text IN ({})", in_list(mods.len())));
+        params.extend(mods.into_iter().map(|m| Box::new(m) as Box<dyn ToSql>));
+    }
+    if let Some(comps) = components.filter(|c| !c.is_empty()) {
+        // _Global (プロジェクト直下) は component_name を持たないので Game 扱い
+        where_clauses.push(format!("COALESCE(c.type, CASE WHEN m.scope = 'Game' THEN 'Game' END) IN ({})", in_list(comps.len())));
+        params.extend(comps.into_iter().map(|c| Box::new(c) as Box<dyn ToSql>));
+    }
+    if enabled_only {
+        where_clauses.push("COALESCE(c.enabled, 1) = 1".to_string());
+    }
+
+    let indexed = code_index::is_enabled(conn);
+    let trigrams = if indexed { code_index::required_trigrams(pattern) } else { None };
+    if let Some(ref t) = trigrams {
+        where_clauses.push(format!(
+            // インデックス未作成のファイル (共有エンジンインデックス側など) は従来どおり全走査
+            "(f.id IN (SELECT file_id FROM code_trigrams WHERE trigram IN ({}) GROUP BY file_id HAVING COUNT(*) = {})
+              OR f.id NOT IN (SELECT file_id FROM code_index_files))",
+            in_list(t.len()), t.len()
+        ));
+        params.extend(t.iter().map(|x| Box::new(*x) as Box<dyn ToSql>));
+    }
+
+    let sql = format!(
+        "SELECT dp.full_path || '/' || sn.text, sm.text
+         FROM files f
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sn ON f.filename_id = sn.id
+         LEFT JOIN modules m ON f.module_id = m.id
+         LEFT JOIN strings sm ON m.name_id = sm.id
+         LEFT JOIN components c ON c.name = m.component_name
+         WHERE {}
+         ORDER BY dp.full_path, sn.text", where_clauses.join(" AND ")
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let files: Vec<(String, Option<String>)> = stmt
+        .query_map(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())), |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    tracing::info!("GrepCode '{}': {} candidate file(s) (index: {})", pattern, files.len(), trigrams.is_some());
+
+    let mut total_results = 0usize;
+    'outer: for chunk in files.chunks(FILE_BATCH_SIZE) {
+        let matches: Vec<Vec<Value>> = chunk.par_iter().map(|(path, module_name)| {
+            let Ok(bytes) = std::fs::read(path) else { return vec![] };
+            if bytes.contains(&0) { return vec![]; }
+            let content = String::from_utf8_lossy(&bytes);
+            content.lines().enumerate().filter_map(|(i, line)| {
+                re.find(line).map(|m| json!({
+                    "path":        path,
+                    "module_name": module_name,
+                    "line":        i + 1,
+                    "col":         m.start(),
+                    "context":     line.trim(),
+                }))
+            }).take(limit).collect()
+        }).collect();
+
+        for items in matches.into_iter().filter(|v| !v.is_empty()) {
+            let remaining = limit - total_results;
+            let items: Vec<Value> = items.into_iter().take(remaining).collect();
+            total_results += items.len();
+            on_items(items)?;
+            if total_results >= limit { break 'outer; }
+        }
+    }
+
+    Ok(json!({
+        "searched_files": files.len(),
+        "total_results":  total_results,
+        "indexed":        trigrams.is_some(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use rusqlite::params;
+    use crate::db::get_or_create_string;
+    use crate::db::path::get_or_create_directory;
+    use crate::types::{ProgressReporter, StdoutReporter};
+
+    fn grep(conn: &Connection, pattern: &str, modules: Option<Vec<String>>, extensions: Option<Vec<String>>) -> (Vec<String>, Value) {
+        let mut paths = Vec::new();
+        let summary = grep_code(conn, pattern, modules, None, extensions, false, None, false, |items| {
+            paths.extend(items.iter().map(|v| v["path"].as_str().unwrap().rsplit('/').next().unwrap().to_string()));
+            Ok(())
+        }).unwrap();
+        (paths, summary)
+    }
+
+    #[test]
+    fn test_grep_code_indexed_with_filters() {
+        let dir = std::env::temp_dir().join(format!("unl_grep_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("MyActor.cpp"), "void AMyActor::Tick()\n{\n    GetWorld()->SpawnActor();\n}\n").unwrap();
+        std::fs::write(dir.join("MyPawn.h"), "class AMyPawn\n{\n    AActor* GetOwnerActor();\n};\n").unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let tx = conn.transaction().unwrap();
+        let (mut sc, mut dc) = (HashMap::new(), HashMap::new());
+        let d = get_or_create_directory(&tx, &mut sc, &mut dc, &dir).unwrap();
+        for (module, file, ext) in [("Game", "MyActor.cpp", "cpp"), ("Pawns", "MyPawn.h", "h")] {
+            let m = get_or_create_string(&tx, &mut sc, module).unwrap();
+            tx.execute("INSERT INTO modules (name_id, scope, root_directory_id) VALUES (?, 'Game', ?)", params![m, d]).unwrap();
+            let module_id = tx.last_insert_rowid();
+            let f = get_or_create_string(&tx, &mut sc, file).unwrap();
+            tx.execute("INSERT INTO files (directory_id, filename_id, extension, mtime, module_id) VALUES (?, ?, ?, 1, ?)", params![d, f, ext, module_id]).unwrap();
+        }
+        tx.commit().unwrap();
+        let reporter: Arc<dyn ProgressReporter> = Arc::new(StdoutReporter);
+        code_index::sync(&mut conn, true, &reporter).unwrap();
+
+        // トライグラムで MyActor.cpp だけが候補になる
+        let (paths, summary) = grep(&conn, r"GetWorld\(\)", None, None);
+        assert_eq!(paths, vec!["MyActor.cpp"]);
+        assert_eq!(summary["indexed"], true);
+        assert_eq!(summary["searched_files"], 1);
+
+        let (paths, _) = grep(&conn, r"Get\w+", None, None);
+        assert_eq!(paths, vec!["MyActor.cpp", "MyPawn.h"]);
+        let (paths, _) = grep(&conn, r"Get\w+", Some(vec!["Pawns".to_string()]), None);
+        assert_eq!(paths, vec!["MyPawn.h"]);
+        let (paths, _) = grep(&conn, r"Get\w+", None, Some(vec![".cpp".to_string()]));
+        assert_eq!(paths, vec!["MyActor.cpp"]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
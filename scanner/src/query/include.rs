@@ -1,6 +1,6 @@
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
 use serde_json::{json, Value};
-use crate::db::path::{PATH_CTE, to_db_path_format};
+use crate::db::path::{split_db_path, to_db_path_format};
 
 /// #include パス補完: 現在ファイルのモジュール + その依存モジュールの
 /// ヘッダーファイルを検索し、インクルードパス形式で返す
@@ -10,20 +10,18 @@ pub fn get_include_completions(
     prefix: &str,
 ) -> anyhow::Result<Value> {
     let db_path = to_db_path_format(file_path);
+    let (dir, filename) = split_db_path(&db_path);
 
     // 1. 現在ファイルの module_id を取得
     let module_id: Option<i64> = {
-        let sql = format!(
-            "{}
+        let sql = "
             SELECT f.module_id
             FROM files f
             JOIN dir_paths dp ON f.directory_id = dp.id
             JOIN strings sn ON f.filename_id = sn.id
-            WHERE dp.full_path || '/' || sn.text = ?
-            LIMIT 1",
-            PATH_CTE
-        );
-        conn.query_row(&sql, [&db_path], |row| row.get(0)).ok()
+            WHERE dp.full_path = ? AND sn.text = ?
+            LIMIT 1";
+        conn.query_row(sql, params![dir, filename], |row| row.get(0)).ok()
     };
 
     let Some(module_id) = module_id else {
@@ -32,16 +30,13 @@ pub fn get_include_completions(
 
     // 2. 現在モジュールの deep_dependencies (JSON) + モジュール名 を取得
     let (module_root, deep_deps_json): (String, Option<String>) = {
-        let sql = format!(
-            "{}
+        let sql = "
             SELECT dp.full_path, m.deep_dependencies
             FROM modules m
             JOIN dir_paths dp ON m.root_directory_id = dp.id
             WHERE m.id = ?
-            LIMIT 1",
-            PATH_CTE
-        );
-        conn.query_row(&sql, [module_id], |row| {
+            LIMIT 1";
+        conn.query_row(sql, [module_id], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
         })?
     };
@@ -65,13 +60,12 @@ pub fn get_include_completions(
     if !dep_names.is_empty() {
         let placeholders = dep_names.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
         let sql = format!(
-            "{}
+            "
             SELECT m.id, dp.full_path
             FROM modules m
             JOIN strings sm ON m.name_id = sm.id
             JOIN dir_paths dp ON m.root_directory_id = dp.id
-            WHERE sm.text IN ({})",
-            PATH_CTE, placeholders
+            WHERE sm.text IN ({})", placeholders
         );
         let mut stmt = conn.prepare(&sql)?;
         let params: Vec<&dyn rusqlite::ToSql> = dep_names.iter()
@@ -96,14 +90,13 @@ pub fn get_include_completions(
         .join(", ");
 
     let sql = format!(
-        "{}
+        "
         SELECT dp.full_path || '/' || sn.text, f.module_id
         FROM files f
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
         WHERE f.is_header = 1
-          AND f.module_id IN ({})",
-        PATH_CTE, module_ids_str
+          AND f.module_id IN ({})", module_ids_str
     );
 
     let mut stmt = conn.prepare(&sql)?;
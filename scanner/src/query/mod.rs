@@ -7,13 +7,18 @@ pub mod check_includes;
 pub mod class;
 pub mod include;
 pub mod module;
+pub mod project;
 pub mod buffer;
 pub mod config;
 pub mod file;
+pub mod fuzzy;
 pub mod search;
 pub mod util;
 pub mod goto;
+pub mod grep;
 pub mod usage;
+pub mod shader;
+pub mod replication;
 
 pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result<Value> {
     match request {
@@ -22,8 +27,8 @@ pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result
         QueryRequest::GetDependFiles { file_path, recursive, game_only } => 
             Ok(json!(file::get_depend_files(conn, &file_path, recursive, game_only)?)),
         
-        QueryRequest::SearchSymbols { pattern, limit } => 
-            search::search_symbols(conn, &pattern, limit),
+        QueryRequest::SearchSymbols { pattern, limit, modules, kinds, reflected, enabled_only } =>
+            search::search_symbols(conn, &pattern, limit, &search::SymbolFilter { modules, kinds, reflected, enabled_only }),
         QueryRequest::GetStructsOnly => 
             search::get_structs(conn),
         
@@ -31,14 +36,14 @@ pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result
             class::get_file_symbols(conn, &file_path),
         QueryRequest::GetClassMembers { class_name } => 
             class::get_class_members(conn, &class_name),
-        QueryRequest::FindSymbolUsages { symbol_name, file_path, method_name } =>
-            usage::find_symbol_usages(conn, &symbol_name, file_path.as_deref(), method_name.as_deref()),
+        QueryRequest::FindSymbolUsages { symbol_name, file_path, method_name, buffers } =>
+            usage::find_symbol_usages(conn, &symbol_name, file_path.as_deref(), method_name.as_deref(), &buffers),
         
         QueryRequest::FindIncluders { file_path } =>
             usage::find_includers(conn, &file_path),
         
-        QueryRequest::GetModules => 
-            module::get_modules(conn),
+        QueryRequest::GetModules { enabled_only } =>
+            module::get_modules(conn, enabled_only),
         QueryRequest::GetModuleByName { name } => 
             module::get_module_by_name(conn, &name),
         
@@ -82,8 +87,10 @@ pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result
 
         // Assets / Components
         QueryRequest::GetAssets => asset::get_assets(conn),
-        QueryRequest::GetComponents => crate::db::get_components(conn),
+        QueryRequest::GetComponents { enabled_only } => crate::db::get_components(conn, enabled_only),
         QueryRequest::GrepAssets { pattern } => asset::grep_assets(conn, pattern, |_| Ok(())),
+        QueryRequest::GrepCode { pattern, modules, components, extensions, ignore_case, limit, enabled_only } =>
+            grep::grep_code(conn, &pattern, modules, components, extensions, ignore_case, limit, enabled_only, |_| Ok(())),
 
         QueryRequest::GetConfigData { .. } => Err(anyhow::anyhow!("GetConfigData must be handled by server state")),
 
@@ -91,6 +98,7 @@ pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result
             class::get_enum_values(conn, &enum_name),
 
         QueryRequest::GetTargetFiles => file::get_target_files(conn),
+        QueryRequest::GetProjectInfo => project::get_project_info(conn),
         QueryRequest::GetAllFilePaths => file::get_all_file_paths(conn),
         QueryRequest::GetAllFilesMetadata => file::get_all_files_metadata(conn),
 
@@ -103,6 +111,15 @@ pub fn process_query(conn: &Connection, request: QueryRequest) -> anyhow::Result
         QueryRequest::CheckIncludes { file_path, content } =>
             check_includes::check_includes(conn, &file_path, &content),
 
+        QueryRequest::GetShaderBindings { class_name, file_path } =>
+            shader::get_shader_bindings(conn, class_name.as_deref(), file_path.as_deref()),
+
+        QueryRequest::GetReplicationDiagnostics { class_name, file_path } =>
+            replication::get_replication_diagnostics(conn, class_name.as_deref(), file_path.as_deref()),
+
+        QueryRequest::GetTemplateSpecializations { class_name } =>
+            class::get_template_specializations(conn, &class_name),
+
         _ => Err(anyhow::anyhow!("Query type not yet implemented in new structure: {:?}", request)),
     }
 }
@@ -111,6 +128,8 @@ pub fn process_query_streaming<F>(conn: &Connection, request: QueryRequest, on_i
 where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
     match request {
         QueryRequest::GrepAssets { pattern } => asset::grep_assets(conn, pattern, on_items),
+        QueryRequest::GrepCode { pattern, modules, components, extensions, ignore_case, limit, enabled_only } =>
+            grep::grep_code(conn, &pattern, modules, components, extensions, ignore_case, limit, enabled_only, on_items),
         
         QueryRequest::GetFilesInModulesAsync { modules, extensions, filter } => 
             file::get_files_in_modules_async(conn, modules, extensions, filter, on_items),
@@ -125,8 +144,8 @@ where F: FnMut(Vec<Value>) -> anyhow::Result<()> {
 
             class::get_classes_in_modules_async(conn, modules, symbol_type, on_items),
 
-        QueryRequest::FindSymbolUsagesAsync { symbol_name, file_path, method_name } =>
-            usage::find_symbol_usages_async(conn, &symbol_name, file_path.as_deref(), method_name.as_deref(), on_items),
+        QueryRequest::FindSymbolUsagesAsync { symbol_name, file_path, method_name, buffers } =>
+            usage::find_symbol_usages_async(conn, &symbol_name, file_path.as_deref(), method_name.as_deref(), &buffers, on_items),
 
         QueryRequest::FindIncludersAsync { file_path } =>
             usage::find_includers_async(conn, &file_path, on_items),
@@ -1,18 +1,19 @@
 use rusqlite::{Connection};
 use serde_json::{json, Value};
-use crate::db::path::{PATH_CTE};
 
-pub fn get_modules(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+/// `enabled_only` → 無効なプラグインに属するモジュールを除く
+pub fn get_modules(conn: &Connection, enabled_only: bool) -> anyhow::Result<Value> {
+    let sql = "
         SELECT sm.text as name, m.type, m.scope, dp.full_path as root_path, m.build_cs_path, m.owner_name, m.component_name, m.deep_dependencies
         FROM modules m
         JOIN strings sm ON m.name_id = sm.id
         JOIN dir_paths dp ON m.root_directory_id = dp.id
-    ", PATH_CTE);
+        LEFT JOIN components c ON c.name = m.component_name
+        WHERE ?1 = 0 OR COALESCE(c.enabled, 1) = 1
+    ";
     
-    let mut stmt = conn.prepare(&sql)?;
-    let mut rows = stmt.query([])?;
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([enabled_only])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
         results.push(json!({
@@ -30,16 +31,15 @@ pub fn get_modules(conn: &Connection) -> anyhow::Result<Value> {
 }
 
 pub fn get_module_by_name(conn: &Connection, name: &str) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sm.text as name, m.type, m.scope, dp.full_path as root_path, m.id
         FROM modules m
         JOIN strings sm ON m.name_id = sm.id
         JOIN dir_paths dp ON m.root_directory_id = dp.id
         WHERE sm.text = ? LIMIT 1
-    ", PATH_CTE);
+    ";
     
-    let res = conn.query_row(&sql, [name], |row| {
+    let res = conn.query_row(sql, [name], |row| {
         let mid: i64 = row.get(4)?;
         Ok(json!({
             "name": row.get::<_, String>(0)?,
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::{json, Value};
+use crate::db::path::to_db_path_format;
+
+/// プロジェクトとエンジンの情報 (バージョン依存の補完や非推奨 API チェック用)。
+/// エンジンのバージョンは最後のリフレッシュで Build.version から保存したもの。
+pub fn get_project_info(conn: &Connection) -> anyhow::Result<Value> {
+    let game: Option<(String, String, Option<String>, Option<String>)> = conn.query_row(
+        "SELECT name, root_path, uproject_path, engine_association FROM components WHERE type = 'Game' LIMIT 1",
+        [], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    ).optional()?;
+
+    let mut version: HashMap<String, String> = HashMap::new();
+    {
+        let mut stmt = conn.prepare("SELECT key, value FROM project_meta WHERE key LIKE 'ue_version_%'")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+        for (key, value) in rows.flatten() {
+            version.insert(key.trim_start_matches("ue_version_").to_string(), value);
+        }
+    }
+    let num = |key: &str| version.get(key).and_then(|v| v.parse::<i64>().ok());
+    let engine = match (num("major"), num("minor"), num("patch")) {
+        (Some(major), Some(minor), Some(patch)) => json!({
+            "version": format!("{}.{}.{}", major, minor, patch),
+            "major": major,
+            "minor": minor,
+            "patch": patch,
+            "branch": version.get("branch"),
+            "changelist": num("changelist").filter(|&cl| cl != 0),
+            "compatible_changelist": num("compatible_changelist").filter(|&cl| cl != 0),
+        }),
+        _ => Value::Null,
+    };
+
+    let targets = match &game {
+        Some((_, root, _, _)) => get_project_targets(conn, root)?,
+        None => Vec::new(),
+    };
+
+    Ok(json!({
+        "name": game.as_ref().map(|g| &g.0),
+        "root_path": game.as_ref().map(|g| &g.1),
+        "uproject_path": game.as_ref().and_then(|g| g.2.as_ref()),
+        "engine_association": game.as_ref().and_then(|g| g.3.as_ref()),
+        "engine": engine,
+        "targets": targets,
+    }))
+}
+
+/// プロジェクト配下の *.Target.cs。`type` はファイル中の `Type = TargetType.Xxx` から読む。
+fn get_project_targets(conn: &Connection, project_root: &str) -> anyhow::Result<Vec<Value>> {
+    static TARGET_TYPE: OnceLock<Regex> = OnceLock::new();
+    let target_type = TARGET_TYPE.get_or_init(|| Regex::new(r"\bType\s*=\s*TargetType\.(\w+)").unwrap());
+
+    let db_root = to_db_path_format(project_root);
+    let mut stmt = conn.prepare("
+        SELECT sn.text, dp.full_path || '/' || sn.text
+        FROM files f
+        JOIN dir_paths dp ON f.directory_id = dp.id
+        JOIN strings sn ON f.filename_id = sn.id
+        WHERE sn.text LIKE '%.Target.cs' AND (dp.full_path = ?1 OR dp.full_path LIKE ?1 || '/%')
+        ORDER BY sn.text
+    ")?;
+    let rows = stmt.query_map([&db_root], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?;
+    let mut targets = Vec::new();
+    for (filename, path) in rows.flatten() {
+        let kind = std::fs::read_to_string(&path).ok()
+            .and_then(|src| target_type.captures(&src).map(|c| c[1].to_string()));
+        targets.push(json!({
+            "name": filename.trim_end_matches(".Target.cs"),
+            "type": kind,
+            "path": path,
+        }));
+    }
+    Ok(targets)
+}
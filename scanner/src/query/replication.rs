@@ -0,0 +1,351 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use crate::db::path::find_file_id;
+
+/// レプリケーション関連の指定子を持つメンバー
+struct NetMember {
+    name: String,
+    mem_type: String,
+    flags: String,
+    access: String,
+    specifiers: Vec<(String, Option<String>)>,
+    detail: Option<String>,
+    return_type: Option<String>,
+    line: i64,
+    file_path: String,
+}
+
+impl NetMember {
+    fn spec(&self, key: &str) -> Option<&(String, Option<String>)> {
+        self.specifiers.iter().find(|(k, _)| k.eq_ignore_ascii_case(key))
+    }
+    fn has_spec(&self, key: &str) -> bool { self.spec(key).is_some() }
+}
+
+fn dorep_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\bDOREPLIFETIME\w*\s*\(\s*(\w+)\s*,\s*(\w+)").unwrap())
+}
+
+/// UPROPERTY / UFUNCTION のレプリケーション整合性を検査する。
+///
+/// - `Replicated` / `ReplicatedUsing` プロパティが `GetLifetimeReplicatedProps` の `DOREPLIFETIME*` に登録されているか
+/// - `ReplicatedUsing=OnRep_X` の `OnRep_X` が UFUNCTION として存在し、シグネチャが互換か
+/// - `Server` / `Client` / `NetMulticast` RPC に `_Implementation`（`WithValidation` なら `_Validate`）の定義があるか
+///
+/// `class_name` / `file_path` のどちらも未指定の場合はインデックス全体を対象とする。
+pub fn get_replication_diagnostics(conn: &Connection, class_name: Option<&str>, file_path: Option<&str>) -> anyhow::Result<Value> {
+    let class_names: Vec<String> = if let Some(cn) = class_name {
+        vec![cn.to_string()]
+    } else if let Some(fp) = file_path {
+        let Some(file_id) = find_file_id(conn, Path::new(fp))? else { return Ok(json!([])) };
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT sc.text FROM classes c JOIN strings sc ON c.name_id = sc.id WHERE c.file_id = ?"
+        )?;
+        let rows = stmt.query_map([file_id], |row| row.get(0))?;
+        rows.filter_map(|r| r.ok()).collect()
+    } else {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT sc.text FROM members m
+             JOIN classes c ON m.class_id = c.id
+             JOIN strings sc ON c.name_id = sc.id
+             WHERE m.specifiers LIKE '%Replicated%' OR m.specifiers LIKE '%Server%'
+                OR m.specifiers LIKE '%Client%' OR m.specifiers LIKE '%NetMulticast%'"
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    let member_sql = "SELECT sn.text, st.text, m.access, m.specifiers, m.detail, srt.text, m.line_number,
+                COALESCE(m.flags, ''),
+                dp.full_path || '/' || sf.text
+         FROM members m
+         JOIN classes c ON m.class_id = c.id
+         JOIN strings sc ON c.name_id = sc.id
+         JOIN strings sn ON m.name_id = sn.id
+         JOIN strings st ON m.type_id = st.id
+         LEFT JOIN strings srt ON m.return_type_id = srt.id
+         JOIN files f ON COALESCE(m.file_id, c.file_id) = f.id
+         JOIN dir_paths dp ON f.directory_id = dp.id
+         JOIN strings sf ON f.filename_id = sf.id
+         WHERE sc.text = ?";
+    let mut stmt = conn.prepare(member_sql)?;
+
+    let mut diagnostics = Vec::new();
+    for cls in &class_names {
+        let members: Vec<NetMember> = stmt.query_map(params![cls], |row| {
+            let spec: Option<String> = row.get(3)?;
+            Ok(NetMember {
+                name: row.get(0)?,
+                mem_type: row.get(1)?,
+                access: row.get(2)?,
+                specifiers: spec.as_deref().map(parse_specifiers).unwrap_or_default(),
+                detail: row.get(4)?,
+                return_type: row.get(5)?,
+                line: row.get(6)?,
+                flags: row.get(7)?,
+                file_path: row.get(8)?,
+            })
+        })?.filter_map(|r| r.ok()).collect();
+
+        check_class(cls, &members, &mut diagnostics);
+    }
+    Ok(json!(diagnostics))
+}
+
+fn check_class(class_name: &str, members: &[NetMember], out: &mut Vec<Value>) {
+    let decls: Vec<&NetMember> = members.iter().filter(|m| m.access != "impl").collect();
+    let impls: HashSet<&str> = members.iter().filter(|m| m.access == "impl").map(|m| m.name.as_str()).collect();
+    let functions: HashMap<&str, &NetMember> = decls.iter()
+        .filter(|m| m.mem_type == "function")
+        .map(|m| (m.name.as_str(), *m))
+        .collect();
+
+    let mut push = |severity: &str, kind: &str, m: &NetMember, message: String| {
+        out.push(json!({
+            "severity":   severity,
+            "kind":       kind,
+            "class_name": class_name,
+            "member":     m.name,
+            "message":    message,
+            "file_path":  m.file_path,
+            "line":       m.line,
+        }));
+    };
+
+    // 1. Replicated / ReplicatedUsing
+    let replicated: Vec<&NetMember> = decls.iter().copied()
+        .filter(|m| m.mem_type == "property" && (m.has_spec("Replicated") || m.has_spec("ReplicatedUsing")))
+        .collect();
+    if !replicated.is_empty() {
+        let registered = collect_registered_props(class_name, members);
+        for prop in &replicated {
+            match &registered {
+                None => push("error", "missing_lifetime_props", prop,
+                    format!("{}::{} is replicated but {}::GetLifetimeReplicatedProps is not implemented", class_name, prop.name, class_name)),
+                Some(set) if !set.contains(&prop.name) => push("error", "missing_doreplifetime", prop,
+                    format!("{} is not registered with DOREPLIFETIME in {}::GetLifetimeReplicatedProps", prop.name, class_name)),
+                _ => {}
+            }
+
+            let Some(rep_fn) = prop.spec("ReplicatedUsing").and_then(|(_, v)| v.as_deref()) else { continue };
+            match functions.get(rep_fn) {
+                None => push("error", "missing_onrep", prop,
+                    format!("RepNotify function {} for {} is not declared in {}", rep_fn, prop.name, class_name)),
+                Some(f) => {
+                    if !f.flags.split_whitespace().any(|fl| fl == "UFUNCTION") {
+                        push("error", "onrep_not_ufunction", f,
+                            format!("{} must be a UFUNCTION to be used as RepNotify for {}", rep_fn, prop.name));
+                    }
+                    if let Some(msg) = check_onrep_signature(f, prop) {
+                        push("error", "onrep_signature", f, msg);
+                    }
+                }
+            }
+        }
+    }
+
+    // 2. RPC
+    for f in decls.iter().filter(|m| m.mem_type == "function") {
+        let Some(rpc) = ["Server", "Client", "NetMulticast"].into_iter().find(|k| f.has_spec(k)) else { continue };
+        let impl_name = format!("{}_Implementation", f.name);
+        if !impls.contains(impl_name.as_str()) {
+            push("error", "missing_rpc_implementation", f,
+                format!("{} RPC {} has no {}::{} definition", rpc, f.name, class_name, impl_name));
+        }
+        if f.has_spec("WithValidation") {
+            let validate_name = format!("{}_Validate", f.name);
+            if !impls.contains(validate_name.as_str()) {
+                push("error", "missing_rpc_validate", f,
+                    format!("{} RPC {} is WithValidation but has no {}::{} definition", rpc, f.name, class_name, validate_name));
+            }
+        }
+    }
+}
+
+/// `GetLifetimeReplicatedProps` を実装しているファイルから `DOREPLIFETIME*(Class, Prop)` を収集する。
+/// 実装が見つからなければ None。
+fn collect_registered_props(class_name: &str, members: &[NetMember]) -> Option<BTreeSet<String>> {
+    let files: BTreeSet<&str> = members.iter()
+        .filter(|m| m.access == "impl" && m.name == "GetLifetimeReplicatedProps")
+        .map(|m| m.file_path.as_str())
+        .collect();
+    if files.is_empty() { return None; }
+
+    let mut registered = BTreeSet::new();
+    for path in files {
+        let Ok(content) = std::fs::read_to_string(path) else { continue };
+        registered.extend(registered_in(&content, class_name));
+    }
+    Some(registered)
+}
+
+/// `Class::GetLifetimeReplicatedProps` の本体にある `DOREPLIFETIME*(Class | ThisClass, Prop)` のプロパティ名。
+/// 本体が見つからない場合はファイル全体を見る
+fn registered_in(content: &str, class_name: &str) -> BTreeSet<String> {
+    let body = lifetime_props_body(content, class_name).unwrap_or(content);
+    dorep_regex().captures_iter(body)
+        .filter(|cap| &cap[1] == class_name || &cap[1] == "ThisClass")
+        .map(|cap| cap[2].to_string())
+        .collect()
+}
+
+/// `Class::GetLifetimeReplicatedProps(...) const { ... }` の `{ ... }` 部分
+fn lifetime_props_body<'a>(content: &'a str, class_name: &str) -> Option<&'a str> {
+    let re = Regex::new(&format!(r"\b{}\s*::\s*GetLifetimeReplicatedProps\s*\(", regex::escape(class_name))).ok()?;
+    let start = re.find(content)?.end();
+    let open = start + content[start..].find('{')?;
+    let mut depth = 0i32;
+    for (i, ch) in content[open..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 { return Some(&content[open..=open + i]); }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 括弧 (`<>` / `()` / `[]` / `{}`) の外にある `,` で分割する
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut last) = (0i32, 0);
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[last..]);
+    parts
+}
+
+/// OnRep 関数は引数なし、またはプロパティと同じ型 (値 / const 参照) の引数 1 つのみ許される。
+fn check_onrep_signature(f: &NetMember, prop: &NetMember) -> Option<String> {
+    let params = f.detail.as_deref().unwrap_or("()").trim();
+    let inner = params.trim_start_matches('(').trim_end_matches(')').trim();
+    if inner.is_empty() || inner == "void" { return None; }
+
+    let args = split_top_level(inner);
+    if args.len() > 1 {
+        return Some(format!("{} takes {} parameters; a RepNotify may take at most one (the previous value of {})", f.name, args.len(), prop.name));
+    }
+    let param_type = strip_param_name(args[0]);
+    let prop_type = prop.return_type.as_deref().map(normalize_type).unwrap_or_default();
+    if !prop_type.is_empty() && normalize_type(&param_type) != prop_type {
+        return Some(format!("{} parameter type '{}' does not match {} type '{}'", f.name, param_type.trim(), prop.name, prop_type));
+    }
+    None
+}
+
+/// `const FVector& OldValue` → `const FVector&`
+fn strip_param_name(param: &str) -> String {
+    let p = param.split('=').next().unwrap_or(param).trim();
+    match p.rfind(|c: char| c.is_whitespace() || c == '&' || c == '*') {
+        Some(idx) if p[idx + 1..].chars().all(|c| c.is_alphanumeric() || c == '_') && idx + 1 < p.len() => p[..idx + 1].to_string(),
+        _ => p.to_string(),
+    }
+}
+
+/// `const FVector&` / `FVector` を比較用に `FVector` に揃える
+fn normalize_type(t: &str) -> String {
+    t.replace("const", " ").replace('&', " ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `ReplicatedUsing=OnRep_Health, meta=(ClampMin=0)` → [("ReplicatedUsing", Some("OnRep_Health")), ("meta", Some("(ClampMin=0)"))]
+pub fn parse_specifiers(spec: &str) -> Vec<(String, Option<String>)> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    for ch in spec.chars() {
+        match ch {
+            '(' => { depth += 1; current.push(ch); }
+            ')' => { depth -= 1; current.push(ch); }
+            ',' if depth == 0 => parts.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+
+    parts.iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.split_once('=') {
+            Some((k, v)) => (k.trim().to_string(), Some(v.trim().trim_matches('"').to_string())),
+            None => (p.to_string(), None),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, mem_type: &str, access: &str, spec: Option<&str>, detail: Option<&str>, rt: Option<&str>) -> NetMember {
+        NetMember {
+            name: name.to_string(),
+            mem_type: mem_type.to_string(),
+            flags: if spec.is_some() { "UFUNCTION".to_string() } else { String::new() },
+            access: access.to_string(),
+            specifiers: spec.map(parse_specifiers).unwrap_or_default(),
+            detail: detail.map(|s| s.to_string()),
+            return_type: rt.map(|s| s.to_string()),
+            line: 1,
+            file_path: "/nonexistent/MyActor.h".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_specifiers_nested_meta() {
+        let spec = parse_specifiers("ReplicatedUsing=OnRep_Health, EditAnywhere, meta=(ClampMin=0, UIMin=0)");
+        assert_eq!(spec.len(), 3);
+        assert_eq!(spec[0], ("ReplicatedUsing".to_string(), Some("OnRep_Health".to_string())));
+        assert_eq!(spec[2].0, "meta");
+    }
+
+    #[test]
+    fn test_rpc_and_onrep_checks() {
+        let members = vec![
+            member("ServerFire", "function", "public", Some("Server, Reliable, WithValidation"), Some("()"), Some("void")),
+            member("ServerFire_Implementation", "function", "impl", None, Some("()"), Some("void")),
+            member("OnRep_Health", "function", "protected", Some(""), Some("(int32 OldHealth)"), Some("void")),
+        ];
+        let mut out = Vec::new();
+        check_class("AMyActor", &members, &mut out);
+        let kinds: Vec<&str> = out.iter().map(|d| d["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds, vec!["missing_rpc_validate"]);
+
+        let prop = member("Health", "property", "public", Some("ReplicatedUsing=OnRep_Health"), None, Some("float"));
+        assert!(check_onrep_signature(&members[2], &prop).is_some());
+        let prop = member("Health", "property", "public", Some("ReplicatedUsing=OnRep_Health"), None, Some("int32"));
+        assert!(check_onrep_signature(&members[2], &prop).is_none());
+
+        let on_rep_map = member("OnRep_Map", "function", "protected", Some(""), Some("(const TMap<int32, FFoo>& OldMap)"), Some("void"));
+        let prop = member("Map", "property", "public", Some("ReplicatedUsing=OnRep_Map"), None, Some("TMap<int32, FFoo>"));
+        assert!(check_onrep_signature(&on_rep_map, &prop).is_none());
+    }
+
+    #[test]
+    fn test_registered_in_lifetime_props_body() {
+        let src = "void AOther::GetLifetimeReplicatedProps(TArray<FLifetimeProperty>& Out) const { DOREPLIFETIME(ThisClass, Ammo); }\n\
+                   void AMyActor::GetLifetimeReplicatedProps(TArray<FLifetimeProperty>& OutLifetimeProps) const\n{\n\
+                   \tSuper::GetLifetimeReplicatedProps(OutLifetimeProps);\n\
+                   \tif (true) { DOREPLIFETIME(ThisClass, Health); }\n\
+                   \tDOREPLIFETIME_WITH_PARAMS_FAST(ThisClass, Armor, Params);\n\
+                   \tDOREPLIFETIME(AMyActor, Score);\n}\n";
+        let set: Vec<String> = registered_in(src, "AMyActor").into_iter().collect();
+        assert_eq!(set, vec!["Armor", "Health", "Score"]);
+    }
+}
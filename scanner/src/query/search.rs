@@ -1,40 +1,256 @@
-use rusqlite::{Connection, params};
+use std::collections::HashSet;
+use rusqlite::{Connection, ToSql};
 use serde_json::{json, Value};
-use crate::db::path::{PATH_CTE};
+use super::fuzzy;
+use crate::db::not_operator_sql;
+
+/// FTS / 名前インデックスで引いた候補 (完全一致・前方一致) の最大件数。上限を超えたら `candidate_order` の順に残す
+const MAX_INDEXED_CANDIDATES: usize = 20_000;
+/// 候補が足りないときのサブシーケンス LIKE (全件走査) で読む最大件数。同上
+const MAX_FALLBACK_CANDIDATES: usize = 5_000;
+
+/// 検索条件 (SearchSymbols の任意フィールド)
+#[derive(Default)]
+pub struct SymbolFilter {
+    pub modules: Option<Vec<String>>,
+    /// 正規化した種別: class / struct / enum / function / property / ...
+    pub kinds: Option<Vec<String>>,
+    /// Some(true) → UCLASS / USTRUCT / UENUM / UFUNCTION / UPROPERTY のみ、Some(false) → それ以外のみ
+    pub reflected: Option<bool>,
+    /// true → 無効なプラグインのシンボルを除く
+    pub enabled_only: bool,
+}
+
+/// symbol_type / メンバー種別を検索用の種別と「リフレクション対象か」に正規化する
+fn normalize_kind(symbol_type: &str, flags: Option<&str>) -> (&'static str, bool) {
+    let flags = flags.unwrap_or("");
+    match symbol_type {
+        "UCLASS" => ("class", true),
+        "USTRUCT" => ("struct", true),
+        "UENUM" => ("enum", true),
+        "class" => ("class", false),
+        "struct" => ("struct", false),
+        "enum" => ("enum", false),
+        "function" => ("function", flags.contains("UFUNCTION")),
+        "property" => ("property", flags.contains("UPROPERTY")),
+        "global_function" => ("function", false),
+        "global_var" => ("variable", false),
+        "type_alias" => ("type_alias", false),
+        "define" => ("define", false),
+        "namespace" => ("namespace", false),
+        _ if symbol_type.contains("DELEGATE") || symbol_type == "delegate" => ("delegate", false),
+        _ => ("other", false),
+    }
+}
+
+/// `normalize_kind` の種別を SQL で求める式 (フィルタを WHERE に入れるため。対応は両方同時に直すこと)
+fn kind_sql(t: &str) -> String {
+    format!("(CASE WHEN {t} IN ('UCLASS', 'class') THEN 'class' WHEN {t} IN ('USTRUCT', 'struct') THEN 'struct' \
+             WHEN {t} IN ('UENUM', 'enum') THEN 'enum' WHEN {t} IN ('function', 'global_function') THEN 'function' \
+             WHEN {t} = 'property' THEN 'property' WHEN {t} = 'global_var' THEN 'variable' \
+             WHEN {t} IN ('type_alias', 'define', 'namespace') THEN {t} \
+             WHEN {t} GLOB '*DELEGATE*' OR {t} = 'delegate' THEN 'delegate' ELSE 'other' END)")
+}
+
+/// `normalize_kind` の「リフレクション対象か」を SQL で求める式
+fn reflected_sql(t: &str, flags: &str) -> String {
+    format!("({t} IN ('UCLASS', 'USTRUCT', 'UENUM') OR ({t} = 'function' AND COALESCE({flags}, '') GLOB '*UFUNCTION*') \
+             OR ({t} = 'property' AND COALESCE({flags}, '') GLOB '*UPROPERTY*'))")
+}
+
+/// 同じ一致度なら型 > 関数 > プロパティ > その他 の順に並べる
+fn kind_rank(kind: &str) -> i32 {
+    match kind {
+        "class" => 6,
+        "struct" => 5,
+        "enum" => 4,
+        "delegate" | "type_alias" => 3,
+        "function" => 2,
+        "property" | "variable" => 1,
+        _ => 0,
+    }
+}
+
+/// Game > Plugin > Engine
+fn origin_rank(component_type: Option<&str>) -> i32 {
+    match component_type {
+        Some("Game") => 2,
+        Some("Plugin") => 1,
+        _ => 0,
+    }
+}
+
+/// 完全一致・前方一致の候補を引く FTS5 クエリ。`MyActor` は `AMyActor` / `UMyActor` ... も前方一致で引く。
+/// 英数字を含まないパターンは FTS のトークンにならないので None
+fn fts_prefix_query(pattern: &str) -> Option<String> {
+    if !pattern.chars().any(|c| c.is_alphanumeric()) { return None; }
+    let stripped = fuzzy::strip_ue_prefix(pattern);
+    let mut terms = vec![pattern.to_string(), stripped.to_string()];
+    terms.extend("AUFEITS".chars().map(|p| format!("{}{}", p, stripped)));
+    terms.dedup();
+    let quoted: Vec<String> = terms.iter().map(|t| format!("\"{}\"*", t.replace('"', "\"\""))).collect();
+    Some(format!("name : ({})", quoted.join(" OR ")))
+}
+
+/// 名前条件 + モジュール・種別・リフレクション・有効プラグインの条件。`t` / `flags` は種別・フラグの列
+fn where_clause(name_pred: &str, name_arg: &str, t: &str, flags: &str, filter: &SymbolFilter, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    let mut parts = vec![name_pred.to_string()];
+    params.push(Box::new(name_arg.to_string()));
+    if filter.enabled_only { parts.push("COALESCE(comp.enabled, 1) = 1".to_string()); }
+    if let Some(mods) = &filter.modules {
+        parts.push(format!("sm.text IN ({})", vec!["?"; mods.len()].join(", ")));
+        params.extend(mods.iter().map(|m| Box::new(m.clone()) as Box<dyn ToSql>));
+    }
+    if let Some(kinds) = &filter.kinds {
+        parts.push(format!("{} IN ({})", kind_sql(t), vec!["?"; kinds.len()].join(", ")));
+        params.extend(kinds.iter().map(|k| Box::new(k.to_ascii_lowercase()) as Box<dyn ToSql>));
+    }
+    match filter.reflected {
+        Some(true) => parts.push(reflected_sql(t, flags)),
+        Some(false) => parts.push(format!("NOT {}", reflected_sql(t, flags))),
+        None => {}
+    }
+    parts.join(" AND ")
+}
+
+/// `LIKE ? ESCAPE '\\'` 用の前方一致パターン
+fn like_prefix(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c == '%' || c == '_' || c == '\\' { out.push('\\'); }
+        out.push(c);
+    }
+    out.push('%');
+    out
+}
+
+/// 件数上限で切る前の並び順: 完全一致 (UE プレフィックスの有無は問わない) > 前方一致 > その他、同じなら短い名前から。
+/// 上限を超える候補があっても、スコアの高い名前が切り捨てられないようにする
+fn candidate_order(pattern: &str, params: &mut Vec<Box<dyn ToSql>>) -> &'static str {
+    let stripped = fuzzy::strip_ue_prefix(pattern);
+    for arg in [pattern.to_string(), stripped.to_string(), stripped.to_string(), like_prefix(pattern), like_prefix(stripped)] {
+        params.push(Box::new(arg));
+    }
+    "CASE WHEN name COLLATE NOCASE IN (?, ?) OR substr(name, 2) = ? COLLATE NOCASE THEN 0
+          WHEN name LIKE ? ESCAPE '\\' OR substr(name, 2) LIKE ? ESCAPE '\\' THEN 1 ELSE 2 END, length(name)"
+}
+
+/// classes / members の候補を 1 本の SQL で引く。`class_pred` / `member_pred` はそれぞれ `?` を 1 つ持つ名前条件。
+/// `limit` を超える分は `candidate_order` の順に後ろから捨てる
+fn candidate_sql(class_pred: &str, member_pred: &str, name_arg: &str, pattern: &str, filter: &SymbolFilter, limit: usize) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+    let class_where = where_clause(class_pred, name_arg, "c.symbol_type", "NULL", filter, &mut params);
+    let member_where = where_clause(member_pred, name_arg, "st.text", "mem.flags", filter, &mut params);
+    let order = candidate_order(pattern, &mut params);
 
-/// シンボル名で FTS 検索を行い、パス付きで結果を返す
-pub fn search_symbols(conn: &Connection, pattern: &str, limit: usize) -> anyhow::Result<Value> {
     let sql = format!("
-        {}
-        SELECT sfts.name, sfts.type, sfts.class_name, dp.full_path || '/' || sn.text as path
-        FROM symbols_fts sfts
-        JOIN classes c ON sfts.rowid_ref = c.id
+        SELECT * FROM (
+        SELECT sc.text AS name, c.symbol_type, sc.text, NULL, dp.full_path || '/' || sn.text, c.language, c.line_number, sm.text,
+               COALESCE(comp.type, CASE WHEN m.scope = 'Game' THEN 'Game' END)
+        FROM classes c
+        JOIN strings sc ON c.name_id = sc.id
         JOIN files f ON c.file_id = f.id
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.id
-        WHERE symbols_fts MATCH ?
-        LIMIT ?
-    ", PATH_CTE);
+        LEFT JOIN modules m ON f.module_id = m.id
+        LEFT JOIN strings sm ON m.name_id = sm.id
+        LEFT JOIN components comp ON comp.name = m.component_name
+        WHERE {}
+        UNION ALL
+        SELECT smn.text, st.text, sc.text, mem.flags, dp.full_path || '/' || sn.text, c.language, mem.line_number, sm.text,
+               COALESCE(comp.type, CASE WHEN m.scope = 'Game' THEN 'Game' END)
+        FROM members mem
+        JOIN strings smn ON mem.name_id = smn.id
+        JOIN strings st ON mem.type_id = st.id
+        JOIN classes c ON mem.class_id = c.id
+        JOIN strings sc ON c.name_id = sc.id
+        JOIN files f ON mem.file_id = f.id
+        JOIN dir_paths dp ON f.directory_id = dp.id
+        JOIN strings sn ON f.filename_id = sn.id
+        LEFT JOIN modules m ON f.module_id = m.id
+        LEFT JOIN strings sm ON m.name_id = sm.id
+        LEFT JOIN components comp ON comp.name = m.component_name
+        WHERE {} AND {}
+        )
+        ORDER BY {}
+        LIMIT {}
+    ", class_where, member_where, not_operator_sql("smn.text"), order, limit);
+    (sql, params)
+}
 
-    let mut stmt = conn.prepare(&sql)?;
-    let mut rows = stmt.query(params![pattern, limit])?;
-    
-    let mut results = Vec::new();
+/// 候補 SQL を実行してスコアを付ける。`seen` で FTS 候補と LIKE 候補の重複を除く
+fn collect_scored(conn: &Connection, pattern: &str, sql: &str, params: &[Box<dyn ToSql>],
+                  scored: &mut Vec<((i32, i32, i32), Value)>, seen: &mut HashSet<(String, String, String, Option<i64>)>) -> anyhow::Result<()> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())))?;
     while let Some(row) = rows.next()? {
-        results.push(json!({
-            "name": row.get::<_, String>(0)?,
-            "type": row.get::<_, String>(1)?,
-            "class_name": row.get::<_, String>(2)?,
-            "path": row.get::<_, String>(3)?,
-        }));
+        let name: String = row.get(0)?;
+        let symbol_type: String = row.get(1)?;
+        let class_name: String = row.get(2)?;
+        let flags: Option<String> = row.get(3)?;
+        let path: String = row.get(4)?;
+        let line: Option<i64> = row.get(6)?;
+        let module_name: Option<String> = row.get(7)?;
+        let component_type: Option<String> = row.get(8)?;
+
+        let Some(score) = fuzzy::score(pattern, &name) else { continue };
+        if !seen.insert((name.clone(), class_name.clone(), path.clone(), line)) { continue; }
+        let (kind, reflected) = normalize_kind(&symbol_type, flags.as_deref());
+
+        let rank = (score, kind_rank(kind), origin_rank(component_type.as_deref()));
+        scored.push((rank, json!({
+            "name": name,
+            "type": symbol_type,
+            "kind": kind,
+            "reflected": reflected,
+            "class_name": class_name,
+            "path": path,
+            "language": row.get::<_, Option<String>>(5)?,
+            "line": line,
+            "module_name": module_name,
+            "component_type": component_type,
+            "score": score,
+        })));
     }
-    Ok(json!(results))
+    Ok(())
+}
+
+/// シンボル名であいまい検索を行い、一致度・種別・Game/Engine の順に並べて返す。
+/// UE プレフィックス (`AMyActor` ↔ `MyActor`)、キャメルケースの略記 (`PlyrCtrl`)、部分一致に対応する。
+///
+/// 候補はまず FTS (`symbols_fts`) の前方一致から名前インデックス経由で引き、
+/// それで `limit` に満たないときだけ部分一致・サブシーケンスの LIKE を件数上限付きで追加する。
+/// どちらも上限を超えたら完全一致・前方一致の名前を優先して残す。
+pub fn search_symbols(conn: &Connection, pattern: &str, limit: usize, filter: &SymbolFilter) -> anyhow::Result<Value> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() { return Ok(json!([])); }
+
+    // ((score, kind_rank, origin_rank), item)
+    let mut scored: Vec<((i32, i32, i32), Value)> = Vec::new();
+    let mut seen: HashSet<(String, String, String, Option<i64>)> = HashSet::new();
+    if let Some(fts) = fts_prefix_query(pattern) {
+        let names = "(SELECT s.id FROM strings s WHERE s.text IN (SELECT name FROM symbols_fts WHERE symbols_fts MATCH ?))";
+        let (sql, params) = candidate_sql(&format!("c.name_id IN {}", names), &format!("mem.name_id IN {}", names), &fts, pattern, filter, MAX_INDEXED_CANDIDATES);
+        collect_scored(conn, pattern, &sql, &params, &mut scored, &mut seen)?;
+    }
+    if scored.len() < limit {
+        let like = fuzzy::like_pattern(pattern);
+        let (sql, params) = candidate_sql("sc.text LIKE ? ESCAPE '\\'", "smn.text LIKE ? ESCAPE '\\'", &like, pattern, filter, MAX_FALLBACK_CANDIDATES);
+        collect_scored(conn, pattern, &sql, &params, &mut scored, &mut seen)?;
+    }
+
+    scored.sort_by(|(a, av), (b, bv)| {
+        b.cmp(a).then_with(|| {
+            let len = |v: &Value| v["name"].as_str().map(str::len).unwrap_or(0);
+            len(av).cmp(&len(bv))
+        })
+    });
+    Ok(json!(scored.into_iter().take(limit).map(|(_, v)| v).collect::<Vec<_>>()))
 }
 
 /// 全ての構造体を取得する (USX 用など)
 pub fn get_structs(conn: &Connection) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT sc.text as name, sb.text as base_class, c.symbol_type, dp.full_path || '/' || sn.text as path, sm.text as module_name
         FROM classes c
         JOIN strings sc ON c.name_id = sc.id
@@ -46,9 +262,9 @@ pub fn get_structs(conn: &Connection) -> anyhow::Result<Value> {
         JOIN strings sm ON m.name_id = sm.id
         WHERE c.symbol_type = 'struct' AND sc.text NOT LIKE '(%'
         ORDER BY sc.text ASC
-    ", PATH_CTE);
+    ";
 
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query([])?;
     let mut results = Vec::new();
     while let Some(row) = rows.next()? {
@@ -62,3 +278,71 @@ pub fn get_structs(conn: &Connection) -> anyhow::Result<Value> {
     }
     Ok(json!(results))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_sql_matches_normalize_kind() {
+        let conn = Connection::open_in_memory().unwrap();
+        let sql = format!("SELECT {}, {}", kind_sql("?1"), reflected_sql("?1", "?2"));
+        for (t, flags) in [("UCLASS", None), ("class", None), ("USTRUCT", None), ("UENUM", None), ("enum", None),
+                           ("function", Some("UFUNCTION BlueprintCallable")), ("function", Some("")), ("property", Some("UPROPERTY")),
+                           ("property", None), ("global_function", None), ("global_var", None), ("type_alias", None),
+                           ("define", None), ("namespace", None), ("DECLARE_DYNAMIC_DELEGATE", None), ("delegate", None), ("enum_item", None)] {
+            let (kind, reflected): (String, bool) = conn.query_row(&sql, rusqlite::params![t, flags], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+            assert_eq!((kind.as_str(), reflected), normalize_kind(t, flags), "{}", t);
+        }
+    }
+
+    #[test]
+    fn test_search_uses_prefix_then_fallback() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO strings (id, text) VALUES (1, '/'), (2, 'proj'), (3, 'A.h'), (4, 'AMyActor'), (5, 'APlayerController'),
+                                                  (6, 'MyTick'), (7, 'function'), (8, 'Game');
+             INSERT INTO directories (id, parent_id, name_id) VALUES (1, NULL, 1), (2, 1, 2);
+             INSERT INTO modules (id, name_id, root_directory_id) VALUES (1, 8, 2);
+             INSERT INTO files (id, directory_id, filename_id, module_id) VALUES (1, 2, 3, 1);
+             INSERT INTO classes (id, name_id, qualified_name_id, file_id, line_number, symbol_type) VALUES
+                 (1, 4, 4, 1, 3, 'UCLASS'), (2, 5, 5, 1, 9, 'class');
+             INSERT INTO members (id, class_id, name_id, type_id, file_id, line_number, flags) VALUES (1, 1, 6, 7, 1, 5, 'UFUNCTION');
+             INSERT INTO symbols_fts (name, type, class_name, rowid_ref) VALUES
+                 ('AMyActor', 'UCLASS', 'AMyActor', 1), ('APlayerController', 'class', 'APlayerController', 2), ('MyTick', 'function', 'AMyActor', 1);",
+        ).unwrap();
+
+        let names = |v: Value| v.as_array().unwrap().iter().map(|r| r["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(search_symbols(&conn, "MyAct", 10, &SymbolFilter::default()).unwrap()), vec!["AMyActor"]);
+        assert_eq!(names(search_symbols(&conn, "PlyrCtrl", 10, &SymbolFilter::default()).unwrap()), vec!["APlayerController"]);
+
+        let functions = SymbolFilter { kinds: Some(vec!["Function".into()]), modules: Some(vec!["Game".into()]), ..Default::default() };
+        assert_eq!(names(search_symbols(&conn, "My", 10, &functions).unwrap()), vec!["MyTick"]);
+        let plain = SymbolFilter { reflected: Some(false), ..Default::default() };
+        assert!(names(search_symbols(&conn, "My", 10, &plain).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_candidate_cap_keeps_exact_match() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO strings (id, text) VALUES (1, '/'), (2, 'proj'), (3, 'A.h'), (4, 'AMyActorComponent'), (5, 'UMyActorSubsystem'), (6, 'AMyActor');
+             INSERT INTO directories (id, parent_id, name_id) VALUES (1, NULL, 1), (2, 1, 2);
+             INSERT INTO files (id, directory_id, filename_id) VALUES (1, 2, 3);
+             INSERT INTO classes (id, name_id, qualified_name_id, file_id, line_number, symbol_type) VALUES
+                 (1, 4, 4, 1, 1, 'UCLASS'), (2, 5, 5, 1, 2, 'UCLASS'), (3, 6, 6, 1, 3, 'UCLASS');",
+        ).unwrap();
+
+        // 上限 1 件でも、挿入順で先に来る前方一致ではなく完全一致が残る
+        for pattern in ["MyActor", "AMyActor", "myactor"] {
+            let (sql, params) = candidate_sql("sc.text LIKE ? ESCAPE '\\'", "smn.text LIKE ? ESCAPE '\\'", &fuzzy::like_pattern(pattern),
+                                              pattern, &SymbolFilter::default(), 1);
+            let (mut scored, mut seen) = (Vec::new(), HashSet::new());
+            collect_scored(&conn, pattern, &sql, &params, &mut scored, &mut seen).unwrap();
+            assert_eq!(scored.len(), 1);
+            assert_eq!(scored[0].1["name"], "AMyActor", "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::path::Path;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use serde_json::{json, Value};
+use crate::db::path::{find_file_id, find_file_id_by_db_path, to_db_path_format};
+use crate::parser::shader::resolve_virtual_path;
+
+struct BindingRow {
+    class_name: String,
+    virtual_path: String,
+    entry_point: String,
+    frequency: Option<String>,
+    line: i64,
+    cpp_path: String,
+}
+
+/// C++ シェーダークラスと .usf エントリポイントの対応を返す。
+///
+/// - `class_name` 指定時はそのクラスの宣言のみ
+/// - `file_path` が .cpp ならそのファイル内の宣言、.usf/.ush ならそのファイルを参照する宣言
+///
+/// 絞り込みは SQL 側 (class_name_id / file_id / 仮想パス) で行い、残った行だけ実ファイルに解決する。
+pub fn get_shader_bindings(conn: &Connection, class_name: Option<&str>, file_path: Option<&str>) -> anyhow::Result<Value> {
+    let mappings = load_mappings(conn)?;
+    let target = file_path.map(to_db_path_format);
+    let target_is_shader = target.as_deref().is_some_and(is_shader_path);
+
+    let mut where_clauses: Vec<String> = Vec::new();
+    let mut sql_params: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(name) = class_name {
+        where_clauses.push("sb.class_name_id = (SELECT id FROM strings WHERE text = ?)".to_string());
+        sql_params.push(Box::new(name.to_string()));
+    }
+    if let Some(t) = &target {
+        if target_is_shader {
+            let virtual_paths = virtual_paths_for(t, &mappings);
+            if virtual_paths.is_empty() { return Ok(json!([])); }
+            where_clauses.push(format!("sb.virtual_path IN ({})", vec!["?"; virtual_paths.len()].join(",")));
+            sql_params.extend(virtual_paths.into_iter().map(|v| Box::new(v) as Box<dyn ToSql>));
+        } else {
+            let Some(file_id) = find_file_id_by_db_path(conn, t)? else { return Ok(json!([])) };
+            where_clauses.push("sb.file_id = ?".to_string());
+            sql_params.push(Box::new(file_id));
+        }
+    }
+    let where_sql = if where_clauses.is_empty() { String::new() } else { format!("WHERE {}", where_clauses.join(" AND ")) };
+
+    let sql = format!("
+        SELECT sc.text, sb.virtual_path, sb.entry_point, sb.frequency, sb.line_number, dp.full_path || '/' || sn.text
+        FROM shader_bindings sb
+        JOIN strings sc ON sb.class_name_id = sc.id
+        JOIN files f ON sb.file_id = f.id
+        JOIN dir_paths dp ON f.directory_id = dp.id
+        JOIN strings sn ON f.filename_id = sn.id
+        {}
+        ORDER BY sc.text", where_sql);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows: Vec<BindingRow> = stmt.query_map(params_from_iter(sql_params.iter().map(|p| p.as_ref())), |row| {
+        Ok(BindingRow {
+            class_name: row.get(0)?,
+            virtual_path: row.get(1)?,
+            entry_point: row.get(2)?,
+            frequency: row.get(3)?,
+            line: row.get(4)?,
+            cpp_path: row.get(5)?,
+        })
+    })?.filter_map(|r| r.ok()).collect();
+
+    // 同じ .usf を指すバインディングが多いので、実パス → ファイルの解決はキャッシュする
+    let mut file_cache: HashMap<String, Option<(i64, String)>> = HashMap::new();
+    let mut results = Vec::new();
+    for b in rows {
+        let shader_file = match resolve_virtual_path(&b.virtual_path, &mappings) {
+            Some(real) => match file_cache.get(&real) {
+                Some(cached) => cached.clone(),
+                None => {
+                    let resolved = find_shader_file(conn, &real)?;
+                    file_cache.insert(real, resolved.clone());
+                    resolved
+                }
+            },
+            None => None,
+        };
+
+        // 逆引きした仮想パスがより長いマッピングで別のファイルに解決される場合を除く
+        if target_is_shader && shader_file.as_ref().map(|(_, p)| p) != target.as_ref() { continue; }
+
+        let (shader_path, entry_line) = match shader_file {
+            Some((file_id, p)) => (Some(p), find_entry_line(conn, file_id, &b.entry_point)?),
+            None => (None, None),
+        };
+        results.push(json!({
+            "class_name": b.class_name,
+            "virtual_path": b.virtual_path,
+            "entry_point": b.entry_point,
+            "frequency": b.frequency,
+            "file_path": b.cpp_path,
+            "line_number": b.line,
+            "shader_file_path": shader_path,
+            "entry_line_number": entry_line,
+        }));
+    }
+    Ok(json!(results))
+}
+
+fn is_shader_path(path: &str) -> bool {
+    path.ends_with(".usf") || path.ends_with(".ush")
+}
+
+/// DB 形式の実パスを、マッピングから考えられる仮想パスに逆変換する (`/Engine/Private/Foo.usf` など)
+fn virtual_paths_for(db_path: &str, mappings: &[(String, String)]) -> Vec<String> {
+    let mut out = Vec::new();
+    for (vdir, real) in mappings {
+        let real = to_db_path_format(real.trim_end_matches('/'));
+        if let Some(rest) = db_path.strip_prefix(real.as_str()).filter(|r| r.starts_with('/')) {
+            out.push(format!("{}{}", vdir.trim_end_matches('/'), rest));
+        }
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn load_mappings(conn: &Connection) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT virtual_path, real_path FROM shader_mappings")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// 実パスからインデックス済みのシェーダーファイルを探し、(file_id, DB 上のパス) を返す。
+fn find_shader_file(conn: &Connection, real_path: &str) -> anyhow::Result<Option<(i64, String)>> {
+    let Some(file_id) = find_file_id(conn, Path::new(real_path))? else { return Ok(None) };
+
+    let sql = "
+        SELECT dp.full_path || '/' || sn.text
+        FROM files f
+        JOIN dir_paths dp ON f.directory_id = dp.id
+        JOIN strings sn ON f.filename_id = sn.id
+        WHERE f.id = ?";
+    let path: String = conn.query_row(sql, [file_id], |row| row.get(0))?;
+    Ok(Some((file_id, path)))
+}
+
+fn find_entry_line(conn: &Connection, file_id: i64, entry_point: &str) -> anyhow::Result<Option<i64>> {
+    Ok(conn.query_row(
+        "SELECT c.line_number FROM classes c JOIN strings s ON c.name_id = s.id
+         WHERE c.file_id = ? AND s.text = ? AND c.symbol_type = 'shader_function' LIMIT 1",
+        params![file_id, entry_point],
+        |row| row.get(0),
+    ).optional()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_paths_for() {
+        let mappings = vec![
+            ("/Engine".to_string(), "C:/UE/Engine/Shaders".to_string()),
+            ("/Plugin/Foo".to_string(), "C:/Game/Plugins/Foo/Shaders/".to_string()),
+        ];
+        assert_eq!(virtual_paths_for("C:///UE/Engine/Shaders/Private/A.usf", &mappings), vec!["/Engine/Private/A.usf"]);
+        assert_eq!(virtual_paths_for("C:///Game/Plugins/Foo/Shaders/B.ush", &mappings), vec!["/Plugin/Foo/B.ush"]);
+        assert!(virtual_paths_for("C:///Game/Source/C.usf", &mappings).is_empty());
+    }
+}
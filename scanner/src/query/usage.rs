@@ -2,9 +2,9 @@ use rusqlite::Connection;
 use rusqlite::types::ToSql;
 use rusqlite::OptionalExtension;
 use serde_json::{json, Value};
-use std::collections::HashSet;
-use tree_sitter::Parser;
-use crate::db::path::{PATH_CTE, to_db_path_format};
+use std::collections::{HashMap, HashSet};
+use crate::db::path::{find_file_id_by_db_path, to_db_path_format};
+use crate::parser::refs;
 
 const MAX_RESULTS: usize = 300;
 const STREAM_BATCH_SIZE: usize = 15;
@@ -107,216 +107,16 @@ fn find_includer_file_ids(conn: &Connection, target_id: i64) -> anyhow::Result<H
     Ok(result)
 }
 
-/// tree-sitter でファイルをパースし、class_name が型として参照されている箇所を収集する。
-/// `is_extra` ノード（コメント等）は除外し、クラス/構造体の定義宣言名も除外する。
-/// 戻り値: Vec<(line_1based, col_0based, context_line_trimmed)>
-fn find_type_refs_in_file(path: &str, class_name: &str) -> Vec<(u32, u32, String)> {
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => return vec![],
-    };
-    let language: tree_sitter::Language = tree_sitter_unreal_cpp::LANGUAGE.into();
-    let mut parser = Parser::new();
-    if parser.set_language(&language).is_err() {
-        return vec![];
-    }
-    let tree = match parser.parse(&content, None) {
-        Some(t) => t,
-        None => return vec![],
-    };
-    let src = content.as_bytes();
-    let lines: Vec<&str> = content.lines().collect();
-    let mut results = Vec::new();
-    let mut seen: HashSet<(u32, u32)> = HashSet::new();
-    collect_type_refs(tree.root_node(), src, &lines, class_name, &mut results, &mut seen);
-    results
-}
-
-fn collect_type_refs(
-    node: tree_sitter::Node,
-    src: &[u8],
-    lines: &[&str],
-    class_name: &str,
-    results: &mut Vec<(u32, u32, String)>,
-    seen: &mut HashSet<(u32, u32)>,
-) {
-    // コメント・文字列リテラル等の extra ノードはスキップ
-    if node.is_extra() {
-        return;
-    }
-
-    match node.kind() {
-        "type_identifier" => {
-            let text = std::str::from_utf8(&src[node.byte_range()]).unwrap_or("");
-            if text == class_name {
-                // クラス/構造体の定義宣言名は除外（使用箇所ではないため）
-                let is_def = node.parent().map(|p| {
-                    matches!(
-                        p.kind(),
-                        "class_specifier" | "struct_specifier"
-                            | "unreal_class_declaration" | "unreal_struct_declaration"
-                    ) && p.child_by_field_name("name")
-                        .map(|n| n.id() == node.id())
-                        .unwrap_or(false)
-                }).unwrap_or(false);
-
-                if !is_def {
-                    let pos = node.start_position();
-                    if seen.insert((pos.row as u32, pos.column as u32)) {
-                        let ctx = lines.get(pos.row)
-                            .map(|l| l.trim().to_string())
-                            .unwrap_or_default();
-                        results.push((pos.row as u32 + 1, pos.column as u32, ctx));
-                    }
-                }
-            }
-        }
-        "namespace_identifier" => {
-            // qualified_identifier のスコープ部分: AMyActor::StaticClass() など
-            if let Some(parent) = node.parent() {
-                if parent.kind() == "qualified_identifier" {
-                    if let Some(scope) = parent.child_by_field_name("scope") {
-                        if scope.id() == node.id() {
-                            let text = std::str::from_utf8(&src[node.byte_range()]).unwrap_or("");
-                            if text == class_name {
-                                let pos = node.start_position();
-                                if seen.insert((pos.row as u32, pos.column as u32)) {
-                                    let ctx = lines.get(pos.row)
-                                        .map(|l| l.trim().to_string())
-                                        .unwrap_or_default();
-                                    results.push((pos.row as u32 + 1, pos.column as u32, ctx));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
-
-    let mut cursor = node.walk();
-    let children: Vec<_> = node.children(&mut cursor).collect();
-    for child in children {
-        collect_type_refs(child, src, lines, class_name, results, seen);
-    }
-}
-
-/// tree-sitter でファイルをパースし、class_name::method_name のメソッド呼び出し箇所を収集する。
-/// カバーするパターン:
-///   - `obj.method()` / `obj->method()`  → field_expression の field_identifier
-///   - `ClassName::method()`              → qualified_identifier (scope == class_name, name == method_name)
-fn find_method_refs_in_file(path: &str, class_name: &str, method_name: &str) -> Vec<(u32, u32, String)> {
-    let content = match std::fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => return vec![],
-    };
-    let language: tree_sitter::Language = tree_sitter_unreal_cpp::LANGUAGE.into();
-    let mut parser = Parser::new();
-    if parser.set_language(&language).is_err() {
-        return vec![];
-    }
-    let tree = match parser.parse(&content, None) {
-        Some(t) => t,
-        None => return vec![],
-    };
-    let src = content.as_bytes();
-    let lines: Vec<&str> = content.lines().collect();
-    let mut results = Vec::new();
-    let mut seen: HashSet<(u32, u32)> = HashSet::new();
-    collect_method_refs(tree.root_node(), src, &lines, class_name, method_name, &mut results, &mut seen);
-    results
-}
-
-fn collect_method_refs(
-    node: tree_sitter::Node,
-    src: &[u8],
-    lines: &[&str],
-    class_name: &str,
-    method_name: &str,
-    results: &mut Vec<(u32, u32, String)>,
-    seen: &mut HashSet<(u32, u32)>,
-) {
-    if node.is_extra() {
-        return;
-    }
-
-    match node.kind() {
-        // obj->method() / obj.method() → field_expression の field_identifier
-        "field_identifier" => {
-            let text = std::str::from_utf8(&src[node.byte_range()]).unwrap_or("");
-            if text == method_name {
-                if let Some(parent) = node.parent() {
-                    if parent.kind() == "field_expression" {
-                        let pos = node.start_position();
-                        if seen.insert((pos.row as u32, pos.column as u32)) {
-                            let ctx = lines.get(pos.row)
-                                .map(|l| l.trim().to_string())
-                                .unwrap_or_default();
-                            results.push((pos.row as u32 + 1, pos.column as u32, ctx));
-                        }
-                    }
-                }
-            }
-        }
-        // ClassName::method() → qualified_identifier の name (identifier)
-        "identifier" => {
-            let text = std::str::from_utf8(&src[node.byte_range()]).unwrap_or("");
-            if text == method_name {
-                if let Some(parent) = node.parent() {
-                    if parent.kind() == "qualified_identifier" {
-                        // name フィールドが自分自身であること
-                        if let Some(name_field) = parent.child_by_field_name("name") {
-                            if name_field.id() == node.id() {
-                                // scope が class_name と一致すること
-                                if let Some(scope_node) = parent.child_by_field_name("scope") {
-                                    let scope_text = std::str::from_utf8(&src[scope_node.byte_range()]).unwrap_or("");
-                                    if scope_text == class_name {
-                                        let pos = node.start_position();
-                                        if seen.insert((pos.row as u32, pos.column as u32)) {
-                                            let ctx = lines.get(pos.row)
-                                                .map(|l| l.trim().to_string())
-                                                .unwrap_or_default();
-                                            results.push((pos.row as u32 + 1, pos.column as u32, ctx));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        _ => {}
-    }
-
-    let mut cursor = node.walk();
-    let children: Vec<_> = node.children(&mut cursor).collect();
-    for child in children {
-        collect_method_refs(child, src, lines, class_name, method_name, results, seen);
-    }
-}
-
-
 /// - `header_path` が Some (DB形式パス) → find_includers ベースのスコープ
 /// - `header_path` が None → symbol_name でクラス定義を DB 検索してフォールバック
 fn resolve_search_scope(
     conn: &Connection,
     symbol_name: &str,
     header_path: Option<&str>,
-) -> anyhow::Result<(Vec<Value>, bool)> {
+) -> anyhow::Result<(Vec<(i64, Value)>, bool)> {
     if let Some(header) = header_path {
         let db_path = to_db_path_format(&header.replace('\\', "/"));
-        let path_sql = format!(
-            "{} SELECT f.id FROM files f
-             JOIN dir_paths dp ON f.directory_id = dp.id
-             JOIN strings sn ON f.filename_id = sn.id
-             WHERE dp.full_path || '/' || sn.text = ? LIMIT 1",
-            PATH_CTE
-        );
-        let target_id: Option<i64> = conn
-            .query_row(&path_sql, [&db_path], |r| r.get(0))
-            .optional()?;
+        let target_id = find_file_id_by_db_path(conn, &db_path)?;
 
         if let Some(tid) = target_id {
             let including_ids = find_includer_file_ids(conn, tid)?;
@@ -345,44 +145,118 @@ fn resolve_search_scope(
     Ok((files, found))
 }
 
-/// tree-sitter でシンボル使用箇所を検索する（同期版）。
+/// symbol_refs から候補ファイル内の参照位置を引く。戻り値: file_id → [(line_1based, col_0based)]
+fn load_indexed_refs(
+    conn: &Connection,
+    file_ids: &[i64],
+    symbol_name: &str,
+    method_name: Option<&str>,
+) -> anyhow::Result<HashMap<i64, Vec<(u32, u32)>>> {
+    let mut result: HashMap<i64, Vec<(u32, u32)>> = HashMap::new();
+    let string_id = |text: &str| -> anyhow::Result<Option<i64>> {
+        Ok(conn.query_row("SELECT id FROM strings WHERE text = ?", [text], |r| r.get(0)).optional()?)
+    };
+    let (name, kind) = match method_name {
+        Some(m) => (m, refs::KIND_MEMBER),
+        None => (symbol_name, refs::KIND_TYPE),
+    };
+    let Some(name_id) = string_id(name)? else { return Ok(result) };
+    // メソッド検索: `obj->Foo` (scope なし) と `AFoo::Foo` (scope 一致) のみ
+    let scope_id = if method_name.is_some() { string_id(symbol_name)?.unwrap_or(-1) } else { -1 };
+
+    for chunk in file_ids.chunks(500) {
+        let placeholders = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let sql = format!(
+            "SELECT file_id, line_number, col FROM symbol_refs
+             WHERE name_id = ? AND kind = ? AND (?3 = -1 OR scope_id IS NULL OR scope_id = ?3) AND file_id IN ({})
+             ORDER BY line_number, col",
+            placeholders
+        );
+        let mut params: Vec<&dyn ToSql> = vec![&name_id, &kind, &scope_id];
+        params.extend(chunk.iter().map(|id| id as &dyn ToSql));
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            result.entry(row.get(0)?).or_default().push((row.get::<_, i64>(1)? as u32, row.get::<_, i64>(2)? as u32));
+        }
+    }
+    Ok(result)
+}
+
+/// 候補ファイルを順に見て使用箇所を返す。未保存バッファはその場でパースし、それ以外は symbol_refs を引く。
+/// `on_file` は 1 ファイル分の結果ごとに呼ばれ、false を返すと打ち切る。
+fn collect_usages(
+    conn: &Connection,
+    symbol_name: &str,
+    file_path: Option<&str>,
+    method_name: Option<&str>,
+    buffers: &HashMap<String, String>,
+    mut on_file: impl FnMut(Vec<Value>) -> anyhow::Result<bool>,
+) -> anyhow::Result<(usize, bool)> {
+    let (files_meta, found_definition) = resolve_search_scope(conn, symbol_name, file_path)?;
+    let searched_files = files_meta.len();
+    let buffers: HashMap<String, &String> = buffers.iter()
+        .map(|(p, c)| (to_db_path_format(&p.replace('\\', "/")), c))
+        .collect();
+    let ids: Vec<i64> = files_meta.iter()
+        .filter(|(_, item)| !buffers.contains_key(item["path"].as_str().unwrap_or("")))
+        .map(|(id, _)| *id)
+        .collect();
+    let indexed = load_indexed_refs(conn, &ids, symbol_name, method_name)?;
+
+    for (id, item) in &files_meta {
+        let path = item["path"].as_str().unwrap_or("");
+        let (hits, content): (Vec<(u32, u32)>, Option<String>) = match buffers.get(path) {
+            Some(content) => {
+                let (name, kind, scope) = match method_name {
+                    Some(m) => (m, refs::KIND_MEMBER, Some(symbol_name)),
+                    None => (symbol_name, refs::KIND_TYPE, None),
+                };
+                let hits = refs::find_refs(content, name, kind, scope).into_iter()
+                    .map(|r| (r.line as u32, r.col as u32))
+                    .collect();
+                (hits, Some(content.to_string()))
+            }
+            None => match indexed.get(id) {
+                Some(h) => (h.clone(), std::fs::read_to_string(path).ok()),
+                None => continue,
+            },
+        };
+        if hits.is_empty() { continue; }
+        let lines: Vec<&str> = content.as_deref().map(|c| c.lines().collect()).unwrap_or_default();
+        let module_name = item["module_name"].as_str().unwrap_or("");
+        let module_root = item["module_root"].as_str().unwrap_or("");
+        let items: Vec<Value> = hits.into_iter().map(|(line, col)| json!({
+            "path":        path,
+            "module_name": module_name,
+            "module_root": module_root,
+            "line":        line,
+            "col":         col,
+            "context":     lines.get(line as usize - 1).map(|l| l.trim()).unwrap_or(""),
+        })).collect();
+        if !on_file(items)? { break; }
+    }
+    Ok((searched_files, found_definition))
+}
+
+/// シンボル使用箇所を検索する（同期版）。
 /// - `symbol_name`: 検索スコープのクラス名 (例: "AMyActor")
 /// - `file_path`: クラスのヘッダーファイルパス (DB形式、省略時は DB フォールバック)
 /// - `method_name`: Some → メソッド参照検索モード、None → 型参照検索モード
+/// - `buffers`: 未保存バッファ (path → content)。DB の代わりにその場でパースする
 pub fn find_symbol_usages(
     conn: &Connection,
     symbol_name: &str,
     file_path: Option<&str>,
     method_name: Option<&str>,
+    buffers: &HashMap<String, String>,
 ) -> anyhow::Result<Value> {
-    let (files_meta, found_definition) = resolve_search_scope(conn, symbol_name, file_path)?;
-    let searched_files = files_meta.len();
     let mut results: Vec<Value> = Vec::new();
-
-    'outer: for item in &files_meta {
-        let path = item["path"].as_str().unwrap_or("");
-        let module_name = item["module_name"].as_str().unwrap_or("").to_string();
-        let module_root = item["module_root"].as_str().unwrap_or("").to_string();
-
-        let refs = if let Some(method) = method_name {
-            find_method_refs_in_file(path, symbol_name, method)
-        } else {
-            find_type_refs_in_file(path, symbol_name)
-        };
-        for (line, col, context) in refs {
-            results.push(json!({
-                "path":        path,
-                "module_name": module_name,
-                "module_root": module_root,
-                "line":        line,
-                "col":         col,
-                "context":     context,
-            }));
-            if results.len() >= MAX_RESULTS {
-                break 'outer;
-            }
-        }
-    }
+    let (searched_files, found_definition) = collect_usages(conn, symbol_name, file_path, method_name, buffers, |items| {
+        results.extend(items);
+        Ok(results.len() < MAX_RESULTS)
+    })?;
+    results.truncate(MAX_RESULTS);
 
     Ok(json!({
         "results":          results,
@@ -399,12 +273,11 @@ fn find_cpp_peers_of_headers(conn: &Connection, ids: &HashSet<i64>) -> anyhow::R
     for chunk in ids_vec.chunks(50) {
         let placeholders = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let sql = format!(
-            "{} SELECT dp.full_path, sn.text
+            "SELECT dp.full_path, sn.text
              FROM files f
              JOIN dir_paths dp ON f.directory_id = dp.id
              JOIN strings sn ON f.filename_id = sn.id
-             WHERE f.id IN ({}) AND (f.extension = 'h' OR f.extension = 'hpp')",
-            PATH_CTE, placeholders
+             WHERE f.id IN ({}) AND (f.extension = 'h' OR f.extension = 'hpp')", placeholders
         );
         let params: Vec<&dyn ToSql> = chunk.iter().map(|id| id as &dyn ToSql).collect();
         let mut stmt = conn.prepare(&sql)?;
@@ -419,17 +292,7 @@ fn find_cpp_peers_of_headers(conn: &Connection, ids: &HashSet<i64>) -> anyhow::R
                 .unwrap_or("");
             let cpp_path = format!("{}/{}.cpp", dir, stem);
 
-            let lookup_sql = format!(
-                "{} SELECT f.id FROM files f
-                 JOIN dir_paths dp ON f.directory_id = dp.id
-                 JOIN strings sn ON f.filename_id = sn.id
-                 WHERE dp.full_path || '/' || sn.text = ? LIMIT 1",
-                PATH_CTE
-            );
-            if let Ok(Some(cpp_id)) = conn
-                .query_row(&lookup_sql, [&cpp_path], |r| r.get::<_, i64>(0))
-                .optional()
-            {
+            if let Ok(Some(cpp_id)) = find_file_id_by_db_path(conn, &cpp_path) {
                 result.push(cpp_id);
             }
         }
@@ -438,32 +301,31 @@ fn find_cpp_peers_of_headers(conn: &Connection, ids: &HashSet<i64>) -> anyhow::R
 }
 
 /// ファイル ID リストに対してパスとモジュール情報を取得する
-fn get_file_paths_with_metadata(conn: &Connection, ids: &[i64]) -> anyhow::Result<Vec<serde_json::Value>> {
+fn get_file_paths_with_metadata(conn: &Connection, ids: &[i64]) -> anyhow::Result<Vec<(i64, serde_json::Value)>> {
     let mut results = Vec::new();
 
     for chunk in ids.chunks(50) {
         let placeholders = chunk.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let sql = format!(
-            "{} SELECT dp.full_path || '/' || sn.text, sm.text, f.extension, rd.full_path
+            "SELECT dp.full_path || '/' || sn.text, sm.text, f.extension, rd.full_path, f.id
              FROM files f
              JOIN dir_paths dp ON f.directory_id = dp.id
              JOIN strings sn ON f.filename_id = sn.id
              LEFT JOIN modules m ON f.module_id = m.id
              LEFT JOIN strings sm ON m.name_id = sm.id
              LEFT JOIN dir_paths rd ON m.root_directory_id = rd.id
-             WHERE f.id IN ({})",
-            PATH_CTE, placeholders
+             WHERE f.id IN ({})", placeholders
         );
         let params: Vec<&dyn ToSql> = chunk.iter().map(|id| id as &dyn ToSql).collect();
         let mut stmt = conn.prepare(&sql)?;
         let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
         while let Some(row) = rows.next()? {
-            results.push(json!({
+            results.push((row.get::<_, i64>(4)?, json!({
                 "path":        row.get::<_, String>(0)?,
                 "module_name": row.get::<_, Option<String>>(1)?,
                 "extension":   row.get::<_, String>(2)?,
                 "module_root": row.get::<_, Option<String>>(3)?,
-            }));
+            })));
         }
     }
     Ok(results)
@@ -496,26 +358,15 @@ pub fn find_includers(conn: &Connection, file_path: &str) -> anyhow::Result<Valu
         to_db_path_format(&normalized)
     };
 
-    let path_sql = format!(
-        "{} SELECT f.id FROM files f
-         JOIN dir_paths dp ON f.directory_id = dp.id
-         JOIN strings sn ON f.filename_id = sn.id
-         WHERE dp.full_path || '/' || sn.text = ? LIMIT 1",
-        PATH_CTE
-    );
 
     // まず candidate_target で検索、見つからなければ元のパスで再試行
     let (target_id, resolved_target_path) = {
-        let id: Option<i64> = conn
-            .query_row(&path_sql, [&candidate_target], |r| r.get(0))
-            .optional()?;
+        let id = find_file_id_by_db_path(conn, &candidate_target)?;
         if let Some(i) = id {
             (i, candidate_target.clone())
         } else if ext == "cpp" && candidate_target != to_db_path_format(&normalized) {
             let orig_db = to_db_path_format(&normalized);
-            let orig_id: Option<i64> = conn
-                .query_row(&path_sql, [&orig_db], |r| r.get(0))
-                .optional()?;
+            let orig_id = find_file_id_by_db_path(conn, &orig_db)?;
             match orig_id {
                 Some(i) => (i, orig_db),
                 None => {
@@ -555,7 +406,7 @@ pub fn find_includers(conn: &Connection, file_path: &str) -> anyhow::Result<Valu
     }
 
     let ids_vec: Vec<i64> = all_ids.into_iter().collect();
-    let files = get_file_paths_with_metadata(conn, &ids_vec)?;
+    let files: Vec<Value> = get_file_paths_with_metadata(conn, &ids_vec)?.into_iter().map(|(_, v)| v).collect();
 
     Ok(json!({
         "files": files,
@@ -564,7 +415,7 @@ pub fn find_includers(conn: &Connection, file_path: &str) -> anyhow::Result<Valu
     }))
 }
 
-/// tree-sitter でシンボル使用箇所を検索するストリーミング版。
+/// シンボル使用箇所を検索するストリーミング版。
 /// 結果を STREAM_BATCH_SIZE ごとにバッチ通知する。
 /// - `method_name`: Some → メソッド参照検索モード、None → 型参照検索モード
 pub fn find_symbol_usages_async<F>(
@@ -572,45 +423,28 @@ pub fn find_symbol_usages_async<F>(
     symbol_name: &str,
     file_path: Option<&str>,
     method_name: Option<&str>,
+    buffers: &HashMap<String, String>,
     mut on_items: F,
 ) -> anyhow::Result<Value>
 where
     F: FnMut(Vec<Value>) -> anyhow::Result<()>,
 {
-    let (files_meta, found_definition) = resolve_search_scope(conn, symbol_name, file_path)?;
-    let searched_files = files_meta.len();
-
     let mut total_results = 0usize;
     let mut batch: Vec<Value> = Vec::new();
 
-    'outer: for item in &files_meta {
-        let path = item["path"].as_str().unwrap_or("");
-        let module_name = item["module_name"].as_str().unwrap_or("").to_string();
-        let module_root = item["module_root"].as_str().unwrap_or("").to_string();
-
-        let refs = if let Some(method) = method_name {
-            find_method_refs_in_file(path, symbol_name, method)
-        } else {
-            find_type_refs_in_file(path, symbol_name)
-        };
-        for (line, col, context) in refs {
-            batch.push(json!({
-                "path":        path,
-                "module_name": module_name,
-                "module_root": module_root,
-                "line":        line,
-                "col":         col,
-                "context":     context,
-            }));
+    let (searched_files, found_definition) = collect_usages(conn, symbol_name, file_path, method_name, buffers, |items| {
+        for item in items {
+            batch.push(item);
             total_results += 1;
             if batch.len() >= STREAM_BATCH_SIZE {
                 on_items(std::mem::take(&mut batch))?;
             }
             if total_results >= MAX_RESULTS {
-                break 'outer;
+                return Ok(false);
             }
         }
-    }
+        Ok(true)
+    })?;
 
     if !batch.is_empty() {
         on_items(batch)?;
@@ -652,25 +486,14 @@ where
         to_db_path_format(&normalized)
     };
 
-    let path_sql = format!(
-        "{} SELECT f.id FROM files f
-         JOIN dir_paths dp ON f.directory_id = dp.id
-         JOIN strings sn ON f.filename_id = sn.id
-         WHERE dp.full_path || '/' || sn.text = ? LIMIT 1",
-        PATH_CTE
-    );
 
     let (target_id, resolved_target_path) = {
-        let id: Option<i64> = conn
-            .query_row(&path_sql, [&candidate_target], |r| r.get(0))
-            .optional()?;
+        let id = find_file_id_by_db_path(conn, &candidate_target)?;
         if let Some(i) = id {
             (i, candidate_target.clone())
         } else if ext == "cpp" && candidate_target != to_db_path_format(&normalized) {
             let orig_db = to_db_path_format(&normalized);
-            let orig_id: Option<i64> = conn
-                .query_row(&path_sql, [&orig_db], |r| r.get(0))
-                .optional()?;
+            let orig_id = find_file_id_by_db_path(conn, &orig_db)?;
             match orig_id {
                 Some(i) => (i, orig_db),
                 None => {
@@ -708,7 +531,7 @@ where
 
     let ids_vec: Vec<i64> = all_ids.into_iter().collect();
     let total_files = ids_vec.len();
-    let all_files = get_file_paths_with_metadata(conn, &ids_vec)?;
+    let all_files: Vec<Value> = get_file_paths_with_metadata(conn, &ids_vec)?.into_iter().map(|(_, v)| v).collect();
 
     // STREAM_BATCH_SIZE ごとに通知
     for chunk in all_files.chunks(STREAM_BATCH_SIZE) {
@@ -1,11 +1,10 @@
+use std::collections::HashSet;
 use rusqlite::{Connection, OptionalExtension};
 use serde_json::{json, Value};
-use crate::db::path::{PATH_CTE};
 
 /// クラス名からその定義ファイルのフルパスを取得する
 pub fn get_class_file_path(conn: &Connection, class_name: &str) -> anyhow::Result<Value> {
-    let sql = format!("
-        {}
+    let sql = "
         SELECT dp.full_path || '/' || sn.text
         FROM classes c
         JOIN strings sc ON c.name_id = sc.id
@@ -13,9 +12,29 @@ pub fn get_class_file_path(conn: &Connection, class_name: &str) -> anyhow::Resul
         JOIN dir_paths dp ON f.directory_id = dp.id
         JOIN strings sn ON f.filename_id = sn.text
         WHERE sc.text = ? LIMIT 1
-    ", PATH_CTE);
+    ";
 
-    let mut stmt = conn.prepare(&sql)?;
+    let mut stmt = conn.prepare(sql)?;
     let res = stmt.query_row([class_name], |row| row.get::<_, String>(0)).optional()?;
     Ok(json!(res))
 }
+
+/// `seeds` から `step` (`{ids}` に前段で見つかった id の列が入る SQL) を繰り返し辿り、
+/// 見つかった順に (id, 深さ) を返す。`seeds` 自身は深さ 0。
+/// 共有エンジンインデックスのビューは WITH RECURSIVE の中ではインデックスが効かないので、再帰はここで回す
+pub(crate) fn walk_ids(conn: &Connection, seeds: &[i64], step: &str, max_depth: usize) -> rusqlite::Result<Vec<(i64, usize)>> {
+    let mut seen: HashSet<i64> = HashSet::new();
+    let mut frontier: Vec<i64> = seeds.iter().copied().filter(|id| seen.insert(*id)).collect();
+    let mut found: Vec<(i64, usize)> = frontier.iter().map(|&id| (id, 0)).collect();
+    let mut depth = 0;
+    while !frontier.is_empty() && depth < max_depth {
+        depth += 1;
+        let ids = frontier.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+        let next: Vec<i64> = conn.prepare(&step.replace("{ids}", &ids))?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<_, _>>()?;
+        frontier = next.into_iter().filter(|id| seen.insert(*id)).collect();
+        found.extend(frontier.iter().map(|&id| (id, depth)));
+    }
+    Ok(found)
+}
@@ -5,14 +5,16 @@ use std::sync::Arc;
 use parking_lot::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 use ignore::{WalkBuilder, WalkState};
 use regex::Regex;
 use tree_sitter::Query;
-use crate::types::{RefreshRequest, ModuleDef, ComponentDef, ProgressReporter, PhaseInfo, InputFile, ParseResult};
+use crate::types::{RefreshRequest, UEPConfig, ModuleDef, ComponentDef, ProgressReporter, PhaseInfo, InputFile, ParseResult};
 use crate::{scanner, db, vcs};
 use crate::db::path::get_or_create_directory;
 use crate::vcs::ChangedFiles;
+use crate::refresh_report::{FileChange, FileChanges, NameChanges, RefreshReport};
+use crate::refresh_checkpoint::{self, RefreshCheckpoint};
 
 #[derive(serde::Deserialize, Debug)]
 #[allow(non_snake_case)]
@@ -21,6 +23,16 @@ struct UeBuildVersion {
     MinorVersion: i32,
     PatchVersion: i32,
     BranchName: String,
+    #[serde(default)]
+    Changelist: i64,
+    #[serde(default)]
+    CompatibleChangelist: i64,
+}
+
+/// .uproject の `EngineAssociation` (空文字は未設定扱い)
+fn get_engine_association(uproject: &Path) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(uproject).ok()?).ok()?;
+    json["EngineAssociation"].as_str().filter(|s| !s.is_empty()).map(str::to_string)
 }
 
 fn get_ue_version(engine_root: &Path) -> Option<UeBuildVersion> {
@@ -31,7 +43,10 @@ fn get_ue_version(engine_root: &Path) -> Option<UeBuildVersion> {
     None
 }
 
-pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) -> anyhow::Result<()> {
+/// Tree-sitter / 専用スキャナで解析する拡張子
+const PARSEABLE_EXTS: &[&str] = &["h", "hpp", "cpp", "cc", "c", "inl", "usf", "ush", "verse"];
+
+pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) -> anyhow::Result<RefreshReport> {
     let db_path_str = req.db_path.as_ref().ok_or_else(|| anyhow::anyhow!("DB path required for refresh"))?;
     let normalize_to_native = |s: &str| { if cfg!(target_os = "windows") { s.replace('/', "\\") } else { s.replace('\\', "/") } };
     let project_root = PathBuf::from(normalize_to_native(&req.project_root));
@@ -41,54 +56,105 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
     let ue_version = engine_root.as_ref().and_then(|r| get_ue_version(r));
     if !project_root.exists() { return Err(anyhow::anyhow!("Project root does not exist: {:?}", project_root)); }
 
+    // The schema is migrated right below, so the version has to be compared before that.
+    let schema_change = Path::new(&db_path_native).exists() && !db_version_matches(&db_path_native);
+    // Migrate (or rebuild) the schema before anything reads project_meta. A migration
+    // may leave files marked for re-parse; those must go through the full walk below.
+    db::ensure_correct_version(&db_path_native)?;
+    let reindex_exts = Connection::open(Path::new(&db_path_native)).ok()
+        .map(|c| db::migrate::pending_extensions(&c))
+        .unwrap_or_default();
+    let reindex_pending = !reindex_exts.is_empty();
+
+    // Engine revision is needed up front: an empty project DB can be seeded from the
+    // shared per-revision engine index instead of walking the engine again.
+    let engine_vcs = engine_root.as_ref().map(|er| (vcs::detect(er), er));
+    let current_engine_rev = engine_vcs.as_ref().and_then(|(v, er)| v.current_revision(er));
+    // Local edits in the engine are not part of the revision; they are compared separately
+    // and keep the engine out of the shared cache, which is keyed by revision only.
+    let engine_worktree = engine_vcs.as_ref()
+        .and_then(|(v, er)| v.working_changes(er))
+        .map(|c| vcs::WorkingCopy::snapshot(&c));
+    let engine_dirty = dirty_fingerprint(engine_worktree.as_ref());
+    let engine_cache_path = match (&engine_root, &current_engine_rev, &engine_dirty) {
+        (Some(er), Some(rev), None) => Some(db::engine_cache::cache_path(&db_path_native, &normalize_path(er), rev)),
+        _ => None,
+    };
+    // A DB that reads another revision's shared index is emptied first.
+    let engine_seeded = Connection::open(Path::new(&db_path_native)).map_err(anyhow::Error::from)
+        .and_then(|c| db::engine_cache::prepare(&c, engine_cache_path.as_deref(), !reindex_pending))
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load the shared engine index: {}", e);
+            false
+        });
+
+    // Project-specific macros (.unl/macros.json). When the definition changes, every
+    // game file has to be re-parsed even though its mtime is unchanged.
+    let macros_hash = crate::parser::macros::register_project(&project_root).unwrap_or_else(|e| {
+        tracing::warn!("{}", e);
+        None
+    });
+    let stored_macros_hash: Option<String> = Connection::open(Path::new(&db_path_native)).ok()
+        .and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = 'custom_macros_hash'", [], |r| r.get::<_, String>(0)).ok());
+    let macros_changed = stored_macros_hash != macros_hash;
+
+    if req.scope.as_deref() == Some("Partial") {
+        let conn = Connection::open(Path::new(&db_path_native))?;
+        if let Some(reason) = partial_blocked_by(macros_changed, reindex_pending, refresh_checkpoint::exists(&conn)) {
+            anyhow::bail!("Partial refresh is not possible ({}); run a full refresh first", reason);
+        }
+        return run_partial_refresh(&req, reporter, &db_path_native);
+    }
+
     // === VCS Integration: determine whether engine scan can be skipped ===
     // Read stored revisions before walk (DB may not exist yet on first run).
     let stored_engine_rev: Option<String> = Connection::open(Path::new(&db_path_native)).ok()
         .and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = 'vcs_engine_revision'", [], |r| r.get::<_, String>(0)).ok());
-    let stored_game_rev: Option<String> = Connection::open(Path::new(&db_path_native)).ok()
-        .and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = 'vcs_game_revision'", [], |r| r.get::<_, String>(0)).ok());
+    let stored_engine_dirty: Option<String> = Connection::open(Path::new(&db_path_native)).ok()
+        .and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = 'vcs_engine_worktree'", [], |r| r.get::<_, String>(0)).ok());
+
+    // 中断したリフレッシュのチェックポイント。条件が同じなら走査をやり直さずに続きから解析する
+    let scope = req.scope.clone().unwrap_or_else(|| "Full".to_string());
+    let engine_root_str = engine_root.as_ref().map(|r| normalize_path(r));
+    let interrupted = Connection::open(Path::new(&db_path_native)).ok().and_then(|c| refresh_checkpoint::load(&c));
+    let interrupted_pending = interrupted.is_some();
+    let resume = interrupted.filter(|(cp, _)| cp.matches(
+        &normalize_path(&project_root), engine_root_str.as_deref(), &scope, current_engine_rev.as_deref(), macros_hash.as_deref(),
+    ));
 
     // Detect VCS providers for game and engine roots.
     let game_vcs = vcs::detect(&project_root);
-    let current_game_rev = game_vcs.current_revision(&project_root);
-    // Engine may be a git submodule with its own .git ref, so detect independently.
-    let current_engine_rev = engine_root.as_ref().and_then(|er| vcs::detect(er).current_revision(er));
+    let game_state = GameVcsState::detect(game_vcs.as_ref(), &project_root, |key| {
+        Connection::open(Path::new(&db_path_native)).ok()
+            .and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = ?", [key], |r| r.get::<_, String>(0)).ok())
+    });
 
     // === Incremental game refresh path ===
-    // When the game VCS revision changed but only non-structural files were modified,
-    // skip the full walk and only re-parse the files reported by `changed_since`.
-    // Structural changes (.build.cs / .uplugin / .uproject) still trigger a full scan
-    // because they may add/remove modules or plugins.
-    let game_rev_changed = match (&stored_game_rev, &current_game_rev) {
-        (Some(stored), Some(current)) => stored != current,
-        _ => false,
-    };
-    if game_rev_changed {
-        if let Some(stored_rev) = &stored_game_rev {
-            if let Some(changed) = game_vcs.changed_since(&project_root, stored_rev) {
-                let is_structural = changed.modified.iter().chain(changed.deleted.iter()).any(|p| {
-                    let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
-                    name.ends_with(".build.cs") || name.ends_with(".uplugin") || name.ends_with(".uproject")
-                });
-                // Also guard: if DB version doesn't match, a full rescan is required.
-                let db_version_ok = db_version_matches(&db_path_native);
-                if !is_structural && db_version_ok {
-                    tracing::info!(
-                        "Incremental game refresh: {} modified, {} deleted file(s). Skipping full walk.",
-                        changed.modified.len(), changed.deleted.len()
-                    );
-                    return run_incremental_game_refresh(
-                        &req, reporter, &project_root, changed, &db_path_native, current_game_rev
-                    );
-                }
-            }
+    // When the game VCS revision or the working copy changed but only non-structural files
+    // were modified, skip the full walk and only re-parse the files the VCS reports.
+    let incremental_blocked_by = match check_incremental(
+        &project_root, game_vcs.as_ref(), &game_state,
+        !schema_change, macros_changed, reindex_pending, interrupted_pending,
+    ) {
+        Ok(changed) => {
+            tracing::info!(
+                "Incremental game refresh: {} modified, {} deleted file(s). Skipping full walk.",
+                changed.modified.len(), changed.deleted.len()
+            );
+            return run_incremental_game_refresh(
+                &req, reporter, &project_root, changed, &db_path_native, &game_state
+            );
         }
-    }
+        Err(reason) => reason,
+    };
 
-    // Skip engine walk when its VCS revision is identical to the stored one.
-    let engine_rev_same = match (&stored_engine_rev, &current_engine_rev) {
-        (Some(stored), Some(current)) => stored == current,
-        _ => false,
+    // Skip engine walk when its VCS revision and its uncommitted changes are identical to the stored ones.
+    let engine_rev_same = match &resume {
+        Some((cp, _)) => cp.engine_walk_skipped,
+        None => !reindex_pending && stored_engine_dirty == engine_dirty && match (&stored_engine_rev, &current_engine_rev) {
+            (Some(stored), Some(current)) => stored == current,
+            _ => false,
+        },
     };
 
     // Send the phase plan first so the Lua client can build its progress UI
@@ -101,27 +167,391 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
     ]);
 
     reporter.report("discovery", 0, 100, &format!("Scanning: {:?}", project_root));
+    if engine_seeded {
+        reporter.report("discovery", 0, 100, "Loaded engine index from the shared engine cache.");
+    }
     if engine_rev_same {
         reporter.report("discovery", 0, 100, &format!("Engine revision unchanged ({}), skipping engine scan.", current_engine_rev.as_deref().unwrap_or("?")));
     }
 
-    let project_name = get_name_from_root(&project_root);
+    // 再開時は保存済みの走査結果を使う。コンポーネント / モジュールも登録済み
+    let (engine_name, walked_root_strs, all_discovered_files, discovered) = match &resume {
+        Some((cp, progress)) => {
+            reporter.report("discovery", 0, 100, &format!(
+                "Resuming interrupted refresh ({} file(s) in {} batch(es) already saved).", progress.files, progress.batches
+            ));
+            let files: Vec<(String, String)> = cp.files.iter().filter(|(p, _)| Path::new(p).exists()).cloned().collect();
+            (cp.engine_name.clone(), cp.search_roots.clone(), files, None)
+        }
+        None => {
+            let Discovery { engine_name, search_roots, files, component_defs, module_defs } =
+                discover(&req, &project_root, &engine_root, engine_rev_same, Arc::clone(&reporter))?;
+            // Normalised walked-root strings used later to scope the cleanup pass.
+            let walked_root_strs: Vec<String> = search_roots.iter().map(|r| normalize_path(r)).collect();
+            (engine_name, walked_root_strs, files, Some((component_defs, module_defs)))
+        }
+    };
+
+    let mut resolved_modules = Vec::new();
+    if let Some((_, module_defs)) = &discovered {
+        let name_to_def: HashMap<String, &ModuleDef> = module_defs.iter().map(|d| (d.name.clone(), d)).collect();
+        let mut memo: HashMap<String, HashSet<String>> = HashMap::new();
+        for (i, def) in module_defs.iter().enumerate() {
+            let mut stack = Vec::new();
+            let deep_deps = resolve_deep(&def.name, &name_to_def, &mut memo, &mut stack);
+            if (i + 1) % 50 == 0 || i + 1 == module_defs.len() { reporter.report("discovery", 60, 100, &format!("Resolving: {}/{} ({})", i + 1, module_defs.len(), def.name)); }
+            resolved_modules.push((def, deep_deps));
+        }
+    }
+
+    reporter.report("db_sync", 0, 100, "Updating database structure...");
+    let mut conn = Connection::open(Path::new(&db_path_native))?;
+    conn.busy_timeout(std::time::Duration::from_millis(10000))?;
+    db::init_db(&conn)?;
+    // FK ON にすることで孤立レコードが残らないようにする。
+    // modules 削除前に files.module_id を NULL 化しているので FK 違反は起きない。
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+
+    if let Some(v) = ue_version {
+        for (k, val) in [
+            ("major", v.MajorVersion.to_string()), ("minor", v.MinorVersion.to_string()), ("patch", v.PatchVersion.to_string()), ("branch", v.BranchName),
+            ("changelist", v.Changelist.to_string()), ("compatible_changelist", v.CompatibleChangelist.to_string()),
+        ] {
+            let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", [format!("ue_version_{}", k), val]);
+        }
+    }
+
+    let mut string_cache = HashMap::new();
+    let mut dir_cache = HashMap::new();
+
+    // ② Load all directory entries once, then reconstruct paths in Rust — avoids joining dir_paths per file.
+    // dir_map is kept alive so we can also reconstruct engine module root paths below.
+    let mut dir_map: HashMap<i64, (Option<i64>, String)> = HashMap::new();
+    let mut existing_mtimes = HashMap::new();
+    let mut existing_hashes: HashMap<String, String> = HashMap::new();
+    {
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0)).unwrap_or(0);
+        if count > 0 || resume.is_some() {
+            {
+                let mut stmt = conn.prepare(
+                    "SELECT d.id, d.parent_id, s.text FROM directories d JOIN strings s ON d.name_id = s.id"
+                )?;
+                let rows = stmt.query_map([], |row| Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                )))?;
+                for r in rows.flatten() { dir_map.insert(r.0, (r.1, r.2)); }
+            }
+            let mut stmt = conn.prepare(
+                "SELECT f.directory_id, s.text, f.mtime, f.file_hash FROM files f JOIN strings s ON f.filename_id = s.id"
+            )?;
+            let rows = stmt.query_map([], |row| Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            )))?;
+            for r in rows.flatten() {
+                let (dir_id, filename, mtime, hash) = r;
+                let full = reconstruct_path(&dir_map, dir_id, &filename);
+                if let Some(h) = hash { existing_hashes.insert(full.clone(), h); }
+                existing_mtimes.insert(full, mtime);
+            }
+        }
+    }
+
+    // When engine scan is skipped, load the existing engine module IDs from the
+    // DB so that game files can still be assigned to the correct module.
+    // A resumed refresh registered every module before it was interrupted.
+    let engine_mod_ids: HashMap<String, i64> = if resume.is_some() || (engine_rev_same && engine_name.is_some()) {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.root_directory_id FROM modules m JOIN strings s ON m.name_id = s.id
+             WHERE (?1 IS NULL OR m.owner_name = ?1) AND s.text != '_Global'"
+        )?;
+        let owner = if resume.is_some() { None } else { engine_name.as_deref() };
+        let rows = stmt.query_map(params![owner], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        let mut map = HashMap::new();
+        for r in rows.flatten() {
+            let (mod_id, root_dir_id) = r;
+            let path = reconstruct_dir_path(&dir_map, root_dir_id);
+            map.insert(path, mod_id);
+        }
+        map
+    } else {
+        HashMap::new()
+    };
+
+    let (old_modules, old_plugins) = indexed_names(&conn, if engine_rev_same { engine_name.as_deref() } else { None })?;
+    let (new_modules, new_plugins) = match &discovered {
+        Some((component_defs, module_defs)) => discovered_names(module_defs, component_defs),
+        None => (old_modules.clone(), old_plugins.clone()),
+    };
+    let mut report = RefreshReport {
+        mode: "full".into(),
+        incremental_blocked_by: incremental_blocked_by.map(str::to_string),
+        engine_walk_skipped: engine_rev_same,
+        resumed: resume.is_some(),
+        engine_seeded,
+        schema_change,
+        macros_changed,
+        modules: match &resume {
+            Some((cp, _)) => cp.modules.clone(),
+            None => NameChanges::diff(&old_modules, &new_modules),
+        },
+        plugins: match &resume {
+            Some((cp, _)) => cp.plugins.clone(),
+            None => NameChanges::diff(&old_plugins, &new_plugins),
+        },
+        ..Default::default()
+    };
+
+    let mut mod_id_map = HashMap::new();
+    let global_mod_id = if let Some((component_defs, _)) = &discovered {
+        let tx = conn.transaction()?;
+        // When the engine revision is unchanged, preserve engine components/modules
+        // in the DB — they are expensive to rebuild and nothing has changed.
+        // FK ON 状態での module_id チェックを回避するため、削除前に files.module_id を NULL にしておく。
+        match (engine_rev_same, engine_name.as_ref()) {
+            (true, Some(en)) => {
+                tx.execute("UPDATE files SET module_id = NULL WHERE module_id IN (SELECT id FROM modules WHERE owner_name != ? OR owner_name IS NULL)", params![en])?;
+                tx.execute("DELETE FROM components WHERE owner_name != ? OR owner_name IS NULL", params![en])?;
+                tx.execute("DELETE FROM modules WHERE owner_name != ? OR owner_name IS NULL", params![en])?;
+            }
+            _ => {
+                tx.execute("UPDATE files SET module_id = NULL", [])?;
+                tx.execute("DELETE FROM components", [])?;
+                tx.execute("DELETE FROM modules", [])?;
+            }
+        }
+
+        for comp in component_defs {
+            tx.execute("INSERT OR REPLACE INTO components (name, display_name, type, owner_name, root_path, uplugin_path, uproject_path, engine_association) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![comp.name, comp.display_name, comp.comp_type, comp.owner_name, normalize_path(&comp.root_path), comp.uplugin_path.as_ref().map(|p| normalize_path(p)), comp.uproject_path.as_ref().map(|p| normalize_path(p)), comp.engine_association],
+            )?;
+        }
+        // 保存済みのエンジン側プラグインも含めて、.uproject に合わせて有効/無効を付け直す
+        let disabled_plugins = crate::plugin::update_enabled(&tx)?;
+        if disabled_plugins > 0 {
+            reporter.report("db_sync", 5, 100, &format!("{} plugin(s) are disabled for this project.", disabled_plugins));
+        }
+        for (def, deep_deps) in &resolved_modules {
+            let name_id = db::get_or_create_string(&tx, &mut string_cache, &def.name)?;
+            let root_dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, &def.root)?;
+            tx.execute("INSERT INTO modules (name_id, type, scope, root_directory_id, build_cs_path, owner_name, component_name, deep_dependencies) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                params![name_id, def.mod_type, "Individual", root_dir_id, normalize_path(&def.path), def.owner_name, def.component_name, serde_json::to_string(&deep_deps.iter().collect::<Vec<_>>()).unwrap()],
+            )?;
+            mod_id_map.insert(normalize_path(&def.root), tx.last_insert_rowid());
+        }
+        let global_mod_id = {
+            let name_id = db::get_or_create_string(&tx, &mut string_cache, "_Global")?;
+            let root_dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, &project_root)?;
+            tx.execute("INSERT INTO modules (name_id, type, scope, root_directory_id) VALUES (?, ?, ?, ?)", params![name_id, "Global", "Game", root_dir_id])?;
+            tx.last_insert_rowid()
+        };
+        tx.commit()?;
+        global_mod_id
+    } else {
+        conn.query_row(
+            "SELECT m.id FROM modules m JOIN strings s ON m.name_id = s.id WHERE s.text = '_Global'",
+            [], |r| r.get(0),
+        ).unwrap_or(0)
+    };
+
+    // `#include "/Engine/..."` を解決するための組み込みシェーダーマッピング
+    if let Some(ref eroot) = engine_root {
+        let _ = db::set_engine_shader_mapping(&conn, &normalize_path(&eroot.join("Engine/Shaders")));
+    }
+
+    // Merge preserved engine module IDs so that file→module assignment is correct.
+    mod_id_map.extend(engine_mod_ids);
+
+    let mut sorted_roots: Vec<_> = mod_id_map.into_iter().collect();
+    sorted_roots.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+
+    // 中断に備えて、走査結果をモジュール構造と一緒に残しておく
+    let checkpoint = resume.is_none().then(|| RefreshCheckpoint {
+        project_root: normalize_path(&project_root),
+        engine_root: engine_root_str.clone(),
+        scope: scope.clone(),
+        engine_revision: current_engine_rev.clone(),
+        macros_hash: macros_hash.clone(),
+        engine_name: engine_name.clone(),
+        engine_walk_skipped: engine_rev_same,
+        search_roots: walked_root_strs.clone(),
+        files: all_discovered_files.clone(),
+        modules: report.modules.clone(),
+        plugins: report.plugins.clone(),
+    });
+
+    let project_root_str = normalize_path(&project_root);
+    let mut files_to_parse = Vec::new();
+    let mut other_files = Vec::new();
+    let mut current_on_disk = HashSet::new();
+    // 既存ファイルのうち再パースに回したもの: パス → モジュール名 (cache_hit かどうかで modified / touched を決める)
+    let mut reparsed: HashMap<String, String> = HashMap::new();
+    // mtime は同じでも再パースが必要なもの (マクロ変更)。中断しても分かるように mtime を 0 にしておく
+    let mut stale = Vec::new();
+
+    for (path_str, ext) in all_discovered_files {
+        current_on_disk.insert(path_str.clone());
+        let mod_id = sorted_roots.iter().find(|(r, _)| path_str.starts_with(r)).map(|(_, id)| *id).unwrap_or(global_mod_id);
+        let mtime = file_mtime(&path_str);
+        let module = module_of(&new_modules, &db::path::to_db_path_format(&path_str));
+        let parseable = PARSEABLE_EXTS.contains(&ext.as_str());
+        match existing_mtimes.get(&path_str) {
+            None => report.files.record(module, FileChange::Added),
+            Some(&old_mtime) if old_mtime != mtime && !parseable => report.files.record(module, FileChange::Modified),
+            _ => {}
+        }
+
+        let mut needs_parse = true;
+        // 再開時は中断前に mtime 0 にした分だけが対象
+        let macros_stale = macros_changed && resume.is_none() && path_str.starts_with(&project_root_str);
+        // Files zeroed by a schema migration (or before an interrupted refresh) must be
+        // re-parsed even if their content is unchanged.
+        let mut force_parse = macros_stale;
+        if let Some(&old_mtime) = existing_mtimes.get(&path_str) {
+            if old_mtime == mtime && !macros_stale {
+                needs_parse = false;
+            }
+            // mtime 0 は移行 / 中断だけでなくスナップショットの取り込みでも付くので、移行が挙げた拡張子に限る
+            force_parse |= (resume.is_some() || reindex_exts.contains(&ext.to_lowercase())) && old_mtime == 0;
+            if macros_stale && old_mtime != 0 { stale.push(path_str.clone()); }
+        }
+
+        if needs_parse && parseable {
+            if existing_mtimes.contains_key(&path_str) { reparsed.insert(path_str.clone(), module.to_string()); }
+            // Only the mtime differs (e.g. an imported snapshot): skip parsing if the content hash matches.
+            let old_hash = if force_parse { None } else { existing_hashes.get(&path_str).cloned() };
+            files_to_parse.push(InputFile { path: path_str, mtime: mtime as u64, old_hash, module_id: Some(mod_id), db_path: None });
+        } else {
+            other_files.push((path_str, mtime, mod_id, ext));
+        }
+    }
+
+    let tx = conn.transaction()?;
+    for path in existing_mtimes.keys() {
+        // Only clean up files that belong to a root we actually walked.
+        // Engine files are intentionally preserved when engine scan is skipped.
+        let in_walked_root = walked_root_strs.iter().any(|r| path.starts_with(r.as_str()));
+        if in_walked_root && !current_on_disk.contains(path) {
+            report.files.record(module_of(&old_modules, &db::path::to_db_path_format(path)), FileChange::Deleted);
+            let p = Path::new(path);
+            let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
+            let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
+            tx.execute("DELETE FROM files WHERE directory_id = ? AND filename_id = ?", params![dir_id, fn_id])?;
+        }
+    }
+    for path in &stale {
+        let p = Path::new(path);
+        let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
+        let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
+        tx.execute("UPDATE files SET mtime = 0 WHERE directory_id = ? AND filename_id = ?", params![dir_id, fn_id])?;
+    }
+    if let Some(ref cp) = checkpoint {
+        refresh_checkpoint::save(&tx, cp)?;
+    }
+    tx.commit()?;
+
+    // 書き込んだバッチ数をバッチと同じトランザクションで記録する
+    let resumed_from = resume.as_ref().map(|(_, p)| *p).unwrap_or_default();
+    let mut batches = resumed_from.batches;
+    let nothing_left = resume.is_some() && files_to_parse.is_empty();
+    parse_and_save(&mut conn, files_to_parse, "Analyzing", &reporter, |r| record_reparsed(&mut report.files, &reparsed, r), |tx, saved| {
+        batches += 1;
+        refresh_checkpoint::record_progress(tx, refresh_checkpoint::Progress { batches, files: resumed_from.files + saved })
+    })?;
+    if resume.is_some() {
+        if nothing_left {
+            // 中断したのは書き込みの後: 落とした索引の作り直しなどの後処理だけ行う
+            db::save_results(&mut conn, std::iter::empty::<ParseResult>(), 0, Arc::clone(&reporter), |_, _| Ok(()))?;
+        }
+        // 中断前に置き換えたファイルのシンボルは FK OFF のため残っている
+        db::purge_orphaned_rows(&conn)?;
+    }
+
+    if !other_files.is_empty() {
+        let tx = conn.transaction()?;
+        for (path, mtime, mod_id, ext) in other_files {
+            let p = Path::new(&path);
+            let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
+            let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
+            // INSERT OR REPLACE で module_id だけ更新されるようにする
+            tx.execute("INSERT OR REPLACE INTO files (directory_id, filename_id, extension, mtime, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?)", 
+                params![dir_id, fn_id, ext, { mtime }, mod_id, if ext == "h" || ext == "hpp" { 1 } else { 0 }])?;
+        }
+        tx.commit()?;
+    }
+
+    if let Err(e) = db::code_index::sync(&mut conn, req.config.code_search, &reporter) {
+        tracing::warn!("Failed to update code search index: {}", e);
+    }
+
+    reporter.report("complete", 100, 100, &format!("Refresh complete ({}).", summarize(&report.files)));
+
+    // Persist VCS revisions so the next refresh can detect unchanged roots.
+    let _ = game_state.store(&conn);
+    if let Some(ref rev) = current_engine_rev {
+        let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_engine_revision", rev]);
+    }
+    match engine_dirty {
+        Some(ref fp) => { let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_engine_worktree", fp]); }
+        None => { let _ = conn.execute("DELETE FROM project_meta WHERE key = 'vcs_engine_worktree'", []); }
+    }
+    match macros_hash {
+        Some(ref hash) => { let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["custom_macros_hash", hash]); }
+        None => { let _ = conn.execute("DELETE FROM project_meta WHERE key = 'custom_macros_hash'", []); }
+    }
+    if reindex_pending {
+        let _ = db::migrate::clear_reindex_pending(&conn);
+    }
+    let _ = refresh_checkpoint::clear(&conn);
+
+    // Share the freshly parsed engine with other projects on the same engine revision.
+    if let (Some(cp), Some(er), Some(en), Some(rev)) = (&engine_cache_path, &engine_root, &engine_name, &current_engine_rev) {
+        if walked_root_strs.contains(&normalize_path(er)) && !db::engine_cache::is_valid(cp) {
+            if let Err(e) = db::engine_cache::publish(&conn, cp, er, en, rev) {
+                tracing::warn!("Failed to publish shared engine index: {}", e);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// ディスク走査の結果 (コンポーネント / モジュール定義と対象ファイル)
+struct Discovery {
+    engine_name: Option<String>,
+    search_roots: Vec<PathBuf>,
+    /// (正規化パス, 拡張子)
+    files: Vec<(String, String)>,
+    component_defs: Vec<ComponentDef>,
+    module_defs: Vec<ModuleDef>,
+}
+
+/// Walk the project (and the engine unless `engine_rev_same`) and collect components,
+/// modules and indexable files.  Reads the file system only.
+fn discover(req: &RefreshRequest, project_root: &Path, engine_root: &Option<PathBuf>, engine_rev_same: bool, reporter: Arc<dyn ProgressReporter>) -> anyhow::Result<Discovery> {
+    let project_name = get_name_from_root(project_root);
     let engine_name = engine_root.as_ref().map(|r| get_name_from_root(r));
     let mut component_defs = Vec::new();
 
-    let uproject_path = fs::read_dir(&project_root)?.filter_map(|e| e.ok()).find(|e| e.path().extension().is_some_and(|ext| ext == "uproject")).map(|e| e.path());
-    component_defs.push(ComponentDef { name: project_name.clone(), display_name: project_root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| project_name.clone()), comp_type: "Game".to_string(), root_path: project_root.clone(), uproject_path: uproject_path.clone(), uplugin_path: None, owner_name: project_name.clone() });
+    let uproject_path = fs::read_dir(project_root)?.filter_map(|e| e.ok()).find(|e| e.path().extension().is_some_and(|ext| ext == "uproject")).map(|e| e.path());
+    component_defs.push(ComponentDef { name: project_name.clone(), display_name: project_root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| project_name.clone()), comp_type: "Game".to_string(), root_path: project_root.to_path_buf(), uproject_path: uproject_path.clone(), uplugin_path: None, owner_name: project_name.clone(), engine_association: uproject_path.as_deref().and_then(get_engine_association) });
 
     if let Some(ref eroot) = engine_root {
         // Only register engine component when we are actually scanning it.
         // When engine_rev_same, the component entry from the previous scan is
         // preserved in the DB (we skip the unconditional DELETE below).
         if !engine_rev_same {
-            component_defs.push(ComponentDef { name: engine_name.as_ref().unwrap().clone(), display_name: "Engine".to_string(), comp_type: "Engine".to_string(), root_path: eroot.clone(), uproject_path: None, uplugin_path: None, owner_name: engine_name.as_ref().unwrap().clone() });
+            component_defs.push(ComponentDef { name: engine_name.as_ref().unwrap().clone(), display_name: "Engine".to_string(), comp_type: "Engine".to_string(), root_path: eroot.clone(), uproject_path: None, uplugin_path: None, owner_name: engine_name.as_ref().unwrap().clone(), engine_association: None });
         }
     }
 
-    let mut search_roots = vec![project_root.clone()];
+    let mut search_roots = vec![project_root.to_path_buf()];
     // Only walk the engine when its revision changed (or VCS is unavailable).
     // When skipped, engine files and modules are preserved from the previous DB state.
     if !engine_rev_same && (req.scope.as_deref().unwrap_or("Full") == "Full" || req.scope.as_deref().unwrap_or("Full") == "Engine") {
@@ -129,8 +559,6 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
             search_roots.push(root.clone());
         }
     }
-    // Normalised walked-root strings used later to scope the cleanup pass.
-    let walked_root_strs: Vec<String> = search_roots.iter().map(|r| normalize_path(r)).collect();
 
     let excludes: HashSet<String> = req.config.excludes_directory.iter().map(|s| s.to_lowercase()).collect();
     let include_exts: HashSet<String> = req.config.include_extensions.iter().map(|e| e.to_lowercase()).collect();
@@ -145,7 +573,7 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
     let include_exts_a = Arc::new(include_exts);
     let engine_root_a: Arc<Option<PathBuf>> = Arc::new(engine_root.clone());
     let engine_name_a: Arc<Option<String>> = Arc::new(engine_name.clone());
-    let project_root_a = Arc::new(project_root.clone());
+    let project_root_a = Arc::new(project_root.to_path_buf());
     let project_name_a = Arc::new(project_name.clone());
 
     let mut builder = WalkBuilder::new(&search_roots[0]);
@@ -200,6 +628,7 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
                         uproject_path: None,
                         uplugin_path: Some(path.to_path_buf()),
                         owner_name: owner,
+                        engine_association: None,
                     };
                     pc.lock().push(comp);
                 }
@@ -249,210 +678,7 @@ pub fn run_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) ->
         module_defs.push(ModuleDef { name: path.file_name().unwrap().to_string_lossy().split('.').next().unwrap().to_string(), path, root, public_deps, private_deps, mod_type: "Runtime".to_string(), owner_name: owner, component_name });
     }
 
-    let name_to_def: HashMap<String, &ModuleDef> = module_defs.iter().map(|d| (d.name.clone(), d)).collect();
-    let mut memo: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut resolved_modules = Vec::new();
-    for (i, def) in module_defs.iter().enumerate() {
-        let mut stack = Vec::new();
-        let deep_deps = resolve_deep(&def.name, &name_to_def, &mut memo, &mut stack);
-        if (i + 1) % 50 == 0 || i + 1 == module_defs.len() { reporter.report("discovery", 60, 100, &format!("Resolving: {}/{} ({})", i + 1, module_defs.len(), def.name)); }
-        resolved_modules.push((def, deep_deps));
-    }
-
-    reporter.report("db_sync", 0, 100, "Updating database structure...");
-    db::ensure_correct_version(&db_path_native)?; 
-    let mut conn = Connection::open(Path::new(&db_path_native))?;
-    conn.busy_timeout(std::time::Duration::from_millis(10000))?;
-    db::init_db(&conn)?;
-    // FK ON にすることで孤立レコードが残らないようにする。
-    // modules 削除前に files.module_id を NULL 化しているので FK 違反は起きない。
-    conn.execute("PRAGMA foreign_keys = ON", [])?;
-
-    if let Some(v) = ue_version {
-        for (k, val) in [("major", v.MajorVersion.to_string()), ("minor", v.MinorVersion.to_string()), ("patch", v.PatchVersion.to_string()), ("branch", v.BranchName)] {
-            let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", [format!("ue_version_{}", k), val]);
-        }
-    }
-
-    let mut string_cache = HashMap::new();
-    let mut dir_cache = HashMap::new();
-
-    // ② Load all directory entries once, then reconstruct paths in Rust — avoids recursive PATH_CTE.
-    // dir_map is kept alive so we can also reconstruct engine module root paths below.
-    let mut dir_map: HashMap<i64, (Option<i64>, String)> = HashMap::new();
-    let mut existing_mtimes = HashMap::new();
-    {
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0)).unwrap_or(0);
-        if count > 0 {
-            {
-                let mut stmt = conn.prepare(
-                    "SELECT d.id, d.parent_id, s.text FROM directories d JOIN strings s ON d.name_id = s.id"
-                )?;
-                let rows = stmt.query_map([], |row| Ok((
-                    row.get::<_, i64>(0)?,
-                    row.get::<_, Option<i64>>(1)?,
-                    row.get::<_, String>(2)?,
-                )))?;
-                for r in rows.flatten() { dir_map.insert(r.0, (r.1, r.2)); }
-            }
-            let mut stmt = conn.prepare(
-                "SELECT f.directory_id, s.text, f.mtime FROM files f JOIN strings s ON f.filename_id = s.id"
-            )?;
-            let rows = stmt.query_map([], |row| Ok((
-                row.get::<_, i64>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, i64>(2)?,
-            )))?;
-            for r in rows.flatten() {
-                let (dir_id, filename, mtime) = r;
-                let full = reconstruct_path(&dir_map, dir_id, &filename);
-                existing_mtimes.insert(full, mtime);
-            }
-        }
-    }
-
-    // When engine scan is skipped, load the existing engine module IDs from the
-    // DB so that game files can still be assigned to the correct module.
-    let engine_mod_ids: HashMap<String, i64> = if engine_rev_same && engine_name.is_some() {
-        let mut stmt = conn.prepare(
-            "SELECT m.id, m.root_directory_id FROM modules m WHERE m.owner_name = ?"
-        )?;
-        let rows = stmt.query_map(params![engine_name.as_ref().unwrap()], |row| {
-            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
-        })?;
-        let mut map = HashMap::new();
-        for r in rows.flatten() {
-            let (mod_id, root_dir_id) = r;
-            let path = reconstruct_dir_path(&dir_map, root_dir_id);
-            map.insert(path, mod_id);
-        }
-        map
-    } else {
-        HashMap::new()
-    };
-
-    let tx = conn.transaction()?;
-    // When the engine revision is unchanged, preserve engine components/modules
-    // in the DB — they are expensive to rebuild and nothing has changed.
-    // FK ON 状態での module_id チェックを回避するため、削除前に files.module_id を NULL にしておく。
-    match (engine_rev_same, engine_name.as_ref()) {
-        (true, Some(en)) => {
-            tx.execute("UPDATE files SET module_id = NULL WHERE module_id IN (SELECT id FROM modules WHERE owner_name != ? OR owner_name IS NULL)", params![en])?;
-            tx.execute("DELETE FROM components WHERE owner_name != ? OR owner_name IS NULL", params![en])?;
-            tx.execute("DELETE FROM modules WHERE owner_name != ? OR owner_name IS NULL", params![en])?;
-        }
-        _ => {
-            tx.execute("UPDATE files SET module_id = NULL", [])?;
-            tx.execute("DELETE FROM components", [])?;
-            tx.execute("DELETE FROM modules", [])?;
-        }
-    }
-    
-    let mut mod_id_map = HashMap::new();
-    for comp in &component_defs {
-        tx.execute("INSERT OR REPLACE INTO components (name, display_name, type, owner_name, root_path, uplugin_path, uproject_path) VALUES (?, ?, ?, ?, ?, ?, ?)",
-            params![comp.name, comp.display_name, comp.comp_type, comp.owner_name, normalize_path(&comp.root_path), comp.uplugin_path.as_ref().map(|p| normalize_path(p)), comp.uproject_path.as_ref().map(|p| normalize_path(p))],
-        )?;
-    }
-    for (def, deep_deps) in &resolved_modules {
-        let name_id = db::get_or_create_string(&tx, &mut string_cache, &def.name)?;
-        let root_dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, &def.root)?;
-        tx.execute("INSERT INTO modules (name_id, type, scope, root_directory_id, build_cs_path, owner_name, component_name, deep_dependencies) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-            params![name_id, def.mod_type, "Individual", root_dir_id, normalize_path(&def.path), def.owner_name, def.component_name, serde_json::to_string(&deep_deps.iter().collect::<Vec<_>>()).unwrap()],
-        )?;
-        mod_id_map.insert(normalize_path(&def.root), tx.last_insert_rowid());
-    }
-    let global_mod_id = {
-        let name_id = db::get_or_create_string(&tx, &mut string_cache, "_Global")?;
-        let root_dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, &project_root)?;
-        tx.execute("INSERT INTO modules (name_id, type, scope, root_directory_id) VALUES (?, ?, ?, ?)", params![name_id, "Global", "Game", root_dir_id])?;
-        tx.last_insert_rowid()
-    };
-    tx.commit()?;
-
-    // Merge preserved engine module IDs so that file→module assignment is correct.
-    mod_id_map.extend(engine_mod_ids);
-
-    let mut sorted_roots: Vec<_> = mod_id_map.into_iter().collect();
-    sorted_roots.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
-
-    let mut files_to_parse = Vec::new();
-    let mut other_files = Vec::new();
-    let mut current_on_disk = HashSet::new();
-
-    for (path_str, ext) in all_discovered_files {
-        current_on_disk.insert(path_str.clone());
-        let mod_id = sorted_roots.iter().find(|(r, _)| path_str.starts_with(r)).map(|(_, id)| *id).unwrap_or(global_mod_id);
-        let mtime = fs::metadata(&path_str).and_then(|m| m.modified()).ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0) as i64;
-        
-        let mut needs_parse = true;
-        if let Some(&old_mtime) = existing_mtimes.get(&path_str) {
-            if old_mtime == mtime {
-                needs_parse = false;
-            }
-        }
-
-        if needs_parse && ["h", "hpp", "cpp", "cc", "c", "inl"].contains(&ext.as_str()) {
-            files_to_parse.push(InputFile { path: path_str, mtime: mtime as u64, old_hash: None, module_id: Some(mod_id), db_path: None });
-        } else {
-            other_files.push((path_str, mtime, mod_id, ext));
-        }
-    }
-
-    let tx = conn.transaction()?;
-    for path in existing_mtimes.keys() {
-        // Only clean up files that belong to a root we actually walked.
-        // Engine files are intentionally preserved when engine scan is skipped.
-        let in_walked_root = walked_root_strs.iter().any(|r| path.starts_with(r.as_str()));
-        if in_walked_root && !current_on_disk.contains(path) {
-            let p = Path::new(path);
-            let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
-            let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
-            tx.execute("DELETE FROM files WHERE directory_id = ? AND filename_id = ?", params![dir_id, fn_id])?;
-        }
-    }
-    tx.commit()?;
-
-    if !files_to_parse.is_empty() {
-        reporter.report("analysis", 0, files_to_parse.len(), &format!("Analyzing {} files...", files_to_parse.len()));
-        let language = tree_sitter_unreal_cpp::LANGUAGE.into();
-        let query = Arc::new(Query::new(&language, scanner::QUERY_STR).expect("Failed to parse query"));
-        let include_query = Arc::new(Query::new(&language, scanner::INCLUDE_QUERY_STR).expect("Failed to parse include query"));
-        let processed_count = Arc::new(AtomicUsize::new(0));
-        let total = files_to_parse.len();
-        let results: Vec<ParseResult> = files_to_parse.into_par_iter().map(|input| {
-            let res = scanner::process_file(&input, &language, &query, &include_query).unwrap_or_else(|_| ParseResult { path: input.path, status: "error".to_string(), mtime: input.mtime, data: None, module_id: input.module_id });
-            let current = processed_count.fetch_add(1, Ordering::Relaxed) + 1;
-            if current % 50 == 0 || current == total { reporter.report("analysis", current, total, &format!("Analyzing: {}/{}", current, total)); }
-            res
-        }).collect();
-        db::save_to_db(&mut conn, &results, Arc::clone(&reporter))?;
-    }
-
-    if !other_files.is_empty() {
-        let tx = conn.transaction()?;
-        for (path, mtime, mod_id, ext) in other_files {
-            let p = Path::new(&path);
-            let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
-            let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
-            // INSERT OR REPLACE で module_id だけ更新されるようにする
-            tx.execute("INSERT OR REPLACE INTO files (directory_id, filename_id, extension, mtime, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?)", 
-                params![dir_id, fn_id, ext, { mtime }, mod_id, if ext == "h" || ext == "hpp" { 1 } else { 0 }])?;
-        }
-        tx.commit()?;
-    }
-
-    reporter.report("complete", 100, 100, "Refresh complete.");
-
-    // Persist VCS revisions so the next refresh can detect unchanged roots.
-    if let Some(ref rev) = current_game_rev {
-        let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_game_revision", rev]);
-    }
-    if let Some(ref rev) = current_engine_rev {
-        let _ = conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_engine_revision", rev]);
-    }
-
-    Ok(())
+    Ok(Discovery { engine_name, search_roots, files: all_discovered_files, component_defs, module_defs })
 }
 
 fn normalize_path(path: &Path) -> String { path.to_string_lossy().replace(char::from(92), "/") }
@@ -524,8 +750,94 @@ fn db_version_matches(db_path: &str) -> bool {
         .and_then(|v| v.parse::<i32>().ok()) == Some(db::DB_VERSION)
 }
 
-/// Incremental game refresh: re-parse only files reported by VCS `changed_since`.
-/// Called when the game revision changed but no structural files (.build.cs / .uplugin /
+/// game 側の VCS 状態 (前回のリフレッシュで保存したものと現在のもの)
+struct GameVcsState {
+    stored_rev: Option<String>,
+    current_rev: Option<String>,
+    stored_worktree: Option<vcs::WorkingCopy>,
+    /// 未コミットの変更。取得できない VCS では None
+    working: Option<ChangedFiles>,
+    worktree: Option<vcs::WorkingCopy>,
+}
+
+impl GameVcsState {
+    fn detect(game_vcs: &dyn vcs::VcsProvider, project_root: &Path, meta: impl Fn(&str) -> Option<String>) -> Self {
+        let working = game_vcs.working_changes(project_root);
+        GameVcsState {
+            stored_rev: meta("vcs_game_revision"),
+            current_rev: game_vcs.current_revision(project_root),
+            stored_worktree: meta("vcs_game_worktree").and_then(|s| serde_json::from_str(&s).ok()),
+            worktree: working.as_ref().map(vcs::WorkingCopy::snapshot),
+            working,
+        }
+    }
+
+    /// 次回のリフレッシュで比較できるように現在の状態を保存する
+    fn store(&self, conn: &Connection) -> anyhow::Result<()> {
+        if let Some(rev) = &self.current_rev {
+            conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_game_revision", rev])?;
+        }
+        match &self.worktree {
+            Some(w) => conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params!["vcs_game_worktree", serde_json::to_string(w)?])?,
+            None => conn.execute("DELETE FROM project_meta WHERE key = 'vcs_game_worktree'", [])?,
+        };
+        Ok(())
+    }
+}
+
+/// 未コミットの変更がある場合だけその指紋 (変更なし・取得できない VCS では None)
+fn dirty_fingerprint(w: Option<&vcs::WorkingCopy>) -> Option<String> {
+    w.filter(|w| !w.is_clean()).map(|w| w.fingerprint.clone())
+}
+
+/// Decide whether the game side can be refreshed from the VCS alone: the committed
+/// diff since the stored revision plus the uncommitted working-copy changes.
+/// `Err(None)`: neither the revision nor the working copy changed (or there is no VCS);
+/// `Err(Some(reason))`: something changed but a full walk is still required.
+/// Structural changes (.build.cs / .uplugin / .uproject) need the walk because they
+/// may add or remove modules and plugins.
+fn check_incremental(
+    project_root: &Path,
+    game_vcs: &dyn vcs::VcsProvider,
+    state: &GameVcsState,
+    db_version_ok: bool,
+    macros_changed: bool,
+    reindex_pending: bool,
+    interrupted: bool,
+) -> Result<ChangedFiles, Option<&'static str>> {
+    let (Some(stored), Some(current)) = (state.stored_rev.as_deref(), state.current_rev.as_deref()) else {
+        return Err(None);
+    };
+    let rev_changed = stored != current;
+    let worktree_changed = dirty_fingerprint(state.stored_worktree.as_ref()) != dirty_fingerprint(state.worktree.as_ref());
+    if !rev_changed && !worktree_changed { return Err(None); }
+    if !db_version_ok { return Err(Some("database schema changed")); }
+    if reindex_pending { return Err(Some("re-index pending after migration")); }
+    if interrupted { return Err(Some("an interrupted refresh has to be finished")); }
+    if macros_changed { return Err(Some("custom macros changed")); }
+    let mut changed = if rev_changed {
+        game_vcs.changed_since(project_root, stored).ok_or(Some("VCS could not list changed files"))?
+    } else {
+        ChangedFiles::default()
+    };
+    if let Some(working) = &state.working {
+        changed.merge(working.clone());
+    }
+    // 前回は未コミットの変更があって今はクリーンなファイルは、コミット済みの内容に戻っている
+    for path in state.stored_worktree.iter().flat_map(|w| w.paths.iter()).map(PathBuf::from) {
+        if changed.modified.contains(&path) || changed.deleted.contains(&path) { continue; }
+        if path.exists() { changed.modified.insert(path); } else { changed.deleted.insert(path); }
+    }
+    let is_structural = changed.modified.iter().chain(changed.deleted.iter()).any(|p| {
+        let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+        name.ends_with(".build.cs") || name.ends_with(".uplugin") || name.ends_with(".uproject")
+    });
+    if is_structural { return Err(Some("module or plugin descriptors changed")); }
+    Ok(changed)
+}
+
+/// Incremental game refresh: re-parse only the files the VCS reports as changed.
+/// Called when the game revision or working copy changed but no structural files (.build.cs / .uplugin /
 /// .uproject) were affected, so modules and components are unchanged.
 fn run_incremental_game_refresh(
     req: &RefreshRequest,
@@ -533,8 +845,8 @@ fn run_incremental_game_refresh(
     _project_root: &Path,
     changed: ChangedFiles,
     db_path_native: &str,
-    current_game_rev: Option<String>,
-) -> anyhow::Result<()> {
+    game_state: &GameVcsState,
+) -> anyhow::Result<RefreshReport> {
     reporter.report_plan(&[
         PhaseInfo { name: "analysis".into(), label: "Analysis".into(), weight: 1.0 },
     ]);
@@ -549,6 +861,11 @@ fn run_incremental_game_refresh(
     // save_to_db が内部で FK OFF にして一括 INSERT し、終了後に再び FK ON に戻す。
     conn.execute("PRAGMA foreign_keys = ON", [])?;
 
+    let include_exts: HashSet<String> = req.config.include_extensions.iter()
+        .map(|e| e.to_lowercase()).collect();
+    let mut report = RefreshReport { mode: "incremental".into(), engine_walk_skipped: true, ..Default::default() };
+    count_vcs_changes(&conn, &changed, &include_exts, &mut report.files)?;
+
     // Rebuild the in-memory directory map so we can look up or create path IDs.
     let mut dir_map: HashMap<i64, (Option<i64>, String)> = HashMap::new();
     {
@@ -608,16 +925,12 @@ fn run_incremental_game_refresh(
         tx.commit()?;
     }
 
-    let include_exts: HashSet<String> = req.config.include_extensions.iter()
-        .map(|e| e.to_lowercase()).collect();
-    let parseable = ["h", "hpp", "cpp", "cc", "c", "inl"];
-
     let mut files_to_parse: Vec<InputFile> = Vec::new();
     for path in &changed.modified {
         if !path.exists() { continue; }
         let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
         if !include_exts.contains(&ext) { continue; }
-        if !parseable.contains(&ext.as_str()) { continue; }
+        if !PARSEABLE_EXTS.contains(&ext.as_str()) { continue; }
         let path_unix = normalize_path(path);
         let mtime = std::fs::metadata(path)
             .and_then(|m| m.modified()).ok()
@@ -636,36 +949,495 @@ fn run_incremental_game_refresh(
         });
     }
 
-    let total = files_to_parse.len();
-    if total > 0 {
-        reporter.report("analysis", 0, total, &format!("Re-parsing {} changed file(s)...", total));
-        let language = tree_sitter_unreal_cpp::LANGUAGE.into();
-        let query = Arc::new(Query::new(&language, scanner::QUERY_STR).expect("query"));
-        let include_query = Arc::new(Query::new(&language, scanner::INCLUDE_QUERY_STR).expect("include_query"));
-        let processed_count = Arc::new(AtomicUsize::new(0));
-        let results: Vec<ParseResult> = files_to_parse.into_par_iter().map(|input| {
-            let res = scanner::process_file(&input, &language, &query, &include_query)
-                .unwrap_or_else(|_| ParseResult {
-                    path: input.path.clone(), status: "error".to_string(),
-                    mtime: input.mtime, data: None, module_id: input.module_id,
-                });
-            let current = processed_count.fetch_add(1, Ordering::Relaxed) + 1;
-            reporter.report("analysis", current, total, &format!("Re-parsing: {}/{}", current, total));
-            res
-        }).collect();
-        db::save_to_db(&mut conn, &results, Arc::clone(&reporter))?;
+    parse_and_save(&mut conn, files_to_parse, "Re-parsing", &reporter, |_| {}, |_, _| Ok(()))?;
+
+    if let Err(e) = db::code_index::sync(&mut conn, req.config.code_search, &reporter) {
+        tracing::warn!("Failed to update code search index: {}", e);
     }
 
     reporter.report("complete", 100, 100,
-        &format!("Incremental refresh complete ({} file(s) updated).", total));
+        &format!("Incremental refresh complete ({}).", summarize(&report.files)));
 
-    if let Some(ref rev) = current_game_rev {
-        conn.execute(
-            "INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)",
-            params!["vcs_game_revision", rev],
-        )?;
+    game_state.store(&conn)?;
+
+    Ok(report)
+}
+
+
+/// 対象名 (コンポーネント名 / 表示名 / モジュール名) をルートディレクトリに解決する。
+/// 他の対象の配下にあるルートは外す。
+fn resolve_partial_roots(conn: &Connection, targets: &[String]) -> anyhow::Result<Vec<String>> {
+    if targets.is_empty() {
+        anyhow::bail!("Partial refresh requires at least one target component or module");
+    }
+    let mut roots = Vec::new();
+    let mut unknown = Vec::new();
+    for target in targets {
+        let component_root: Option<String> = conn.query_row(
+            "SELECT root_path FROM components WHERE name = ?1 COLLATE NOCASE OR display_name = ?1 COLLATE NOCASE LIMIT 1",
+            [target], |r| r.get(0),
+        ).optional()?;
+        let root = match component_root {
+            Some(r) => Some(r),
+            None => conn.query_row(
+                "SELECT m.build_cs_path FROM modules m JOIN strings s ON m.name_id = s.id
+                 WHERE s.text = ? COLLATE NOCASE AND m.build_cs_path IS NOT NULL LIMIT 1",
+                [target], |r| r.get::<_, String>(0),
+            ).optional()?.and_then(|p| Path::new(&p).parent().map(normalize_path)),
+        };
+        match root {
+            Some(r) => roots.push(r),
+            None => unknown.push(target.as_str()),
+        }
     }
+    if !unknown.is_empty() {
+        anyhow::bail!("Unknown component or module: {} (run a full refresh first if it was added recently)", unknown.join(", "));
+    }
+    roots.sort();
+    roots.dedup();
+    let all = roots.clone();
+    roots.retain(|r| !all.iter().any(|o| o != r && r.starts_with(&format!("{}/", o))));
+    Ok(roots)
+}
+
+/// `roots` 配下の対象ファイル: (ファイルシステム上のパス, dir_paths 形式のパス, 拡張子)
+fn walk_files(roots: &[String], config: &UEPConfig) -> Vec<(String, String, String)> {
+    let excludes: HashSet<String> = config.excludes_directory.iter().map(|s| s.to_lowercase()).collect();
+    let include_exts: HashSet<String> = config.include_extensions.iter().map(|e| e.to_lowercase()).collect();
+    let mut builder = WalkBuilder::new(&roots[0]);
+    for root in roots.iter().skip(1) { builder.add(root); }
+    builder.hidden(false).git_ignore(false);
+    builder.filter_entry(move |e| {
+        e.file_name().to_str().is_none_or(|name| !excludes.contains(&name.to_lowercase()))
+    });
+
+    let mut on_disk = Vec::new();
+    for entry in builder.build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) { continue; }
+        let ext = entry.path().extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+        if !include_exts.contains(&ext) { continue; }
+        let path = normalize_path(entry.path());
+        let db_path = db::path::to_db_path_format(&path);
+        on_disk.push((path, db_path, ext));
+    }
+    on_disk
+}
+
+/// dir_paths 形式のパス → (file id, mtime, hash)
+type IndexedFiles = HashMap<String, (i64, i64, Option<String>)>;
+/// (名前, ルート) の一覧
+type NamedRoots = Vec<(String, String)>;
+
+/// `roots` 配下の既存行
+fn indexed_files_under(conn: &Connection, roots: &[String]) -> anyhow::Result<IndexedFiles> {
+    let mut existing = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT f.id, dp.full_path || '/' || sn.text, COALESCE(f.mtime, 0), f.file_hash
+         FROM dir_paths dp
+         JOIN files f ON f.directory_id = dp.id
+         JOIN strings sn ON f.filename_id = sn.id
+         WHERE dp.full_path = ?1 OR dp.full_path LIKE ?2"
+    )?;
+    for root in roots {
+        let db_root = db::path::to_db_path_format(root);
+        let prefix = format!("{}/", db_root);
+        let rows = stmt.query_map(params![db_root, format!("{}%", prefix)], |r| Ok((
+            r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, i64>(2)?, r.get::<_, Option<String>>(3)?,
+        )))?;
+        for (id, path, mtime, hash) in rows.flatten() {
+            // LIKE の `_` は任意の 1 文字に一致するので前方一致を確かめる
+            if path.starts_with(&prefix) { existing.insert(path, (id, mtime, hash)); }
+        }
+    }
+    Ok(existing)
+}
+
+/// 部分リフレッシュを断る理由。対象外のファイルも再パースが必要な状態では、
+/// 対象の配下だけを mtime で比べても索引が正しくならない
+fn partial_blocked_by(macros_changed: bool, reindex_pending: bool, interrupted: bool) -> Option<&'static str> {
+    if reindex_pending { return Some("re-index pending after migration"); }
+    if interrupted { return Some("an interrupted refresh has to be finished"); }
+    if macros_changed { return Some("custom macros changed"); }
+    None
+}
+
+/// Partial refresh (`scope = "Partial"`): re-index only the files under the named
+/// components / modules.  Components and modules themselves are kept as they are,
+/// so adding a plugin or a module still needs a full refresh.
+fn run_partial_refresh(req: &RefreshRequest, reporter: Arc<dyn ProgressReporter>, db_path_native: &str) -> anyhow::Result<RefreshReport> {
+    reporter.report_plan(&[
+        PhaseInfo { name: "discovery".into(),  label: "Discovery".into(),  weight: 0.1 },
+        PhaseInfo { name: "analysis".into(),   label: "Analysis".into(),   weight: 0.8 },
+        PhaseInfo { name: "finalizing".into(), label: "Finalizing".into(), weight: 0.1 },
+    ]);
+
+    let mut conn = Connection::open(Path::new(db_path_native))?;
+    conn.busy_timeout(std::time::Duration::from_millis(10000))?;
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+
+    let roots = resolve_partial_roots(&conn, &req.targets)?;
+    // 共有エンジンインデックスの行はプロジェクト DB にないので、ここでは更新できない
+    if db::engine_cache::attached_path(&conn).is_some() {
+        let engine_roots: Vec<String> = conn.prepare("SELECT root_path FROM components WHERE type = 'Engine'")?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<_, _>>()?;
+        if let Some(root) = roots.iter().find(|r| engine_roots.iter().any(|e| r.starts_with(e.as_str()))) {
+            anyhow::bail!("{} is part of the shared engine index; it is re-indexed by a full refresh when the engine changes", root);
+        }
+    }
+    reporter.report("discovery", 0, 100, &format!("Scanning {}: {}", req.targets.join(", "), roots.join(", ")));
+    let on_disk = walk_files(&roots, &req.config);
+    reporter.report("discovery", 100, 100, &format!("Found {} file(s).", on_disk.len()));
+
+    let existing = indexed_files_under(&conn, &roots)?;
+    let modules = indexed_names(&conn, None)?.0;
+    let mut report = RefreshReport { mode: "partial".into(), engine_walk_skipped: true, ..Default::default() };
+
+    let mut mod_roots: Vec<(String, i64)> = conn.prepare(
+        "SELECT dp.full_path, m.id FROM modules m JOIN dir_paths dp ON m.root_directory_id = dp.id"
+    )?.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<Result<_, _>>()?;
+    mod_roots.sort_by_key(|(r, _)| std::cmp::Reverse(r.len()));
+    let global_mod_id: i64 = conn.query_row(
+        "SELECT m.id FROM modules m JOIN strings s ON m.name_id = s.id WHERE s.text = '_Global'",
+        [], |r| r.get(0),
+    ).unwrap_or(0);
+
+    let on_disk_set: HashSet<&str> = on_disk.iter().map(|(_, d, _)| d.as_str()).collect();
+    let removed: Vec<i64> = existing.iter()
+        .filter(|(path, _)| !on_disk_set.contains(path.as_str()))
+        .map(|(path, (id, _, _))| {
+            report.files.record(module_of(&modules, path), FileChange::Deleted);
+            *id
+        })
+        .collect();
+    {
+        let tx = conn.transaction()?;
+        for id in &removed {
+            tx.execute("DELETE FROM files WHERE id = ?", [id])?;
+        }
+        tx.commit()?;
+    }
+
+    let mut files_to_parse = Vec::new();
+    let mut other_files = Vec::new();
+    let mut reparsed: HashMap<String, String> = HashMap::new();
+    for (path, db_path, ext) in on_disk {
+        let mod_id = mod_roots.iter()
+            .find(|(r, _)| db_path.starts_with(&format!("{}/", r)))
+            .map(|(_, id)| *id)
+            .unwrap_or(global_mod_id);
+        let mtime = file_mtime(&path);
+        let old = existing.get(&db_path);
+        if old.is_some_and(|(_, old_mtime, _)| *old_mtime == mtime) { continue; }
+        let module = module_of(&modules, &db_path);
+        let parseable = PARSEABLE_EXTS.contains(&ext.as_str());
+        match old {
+            None => report.files.record(module, FileChange::Added),
+            Some(_) if parseable => { reparsed.insert(path.clone(), module.to_string()); }
+            Some(_) => report.files.record(module, FileChange::Modified),
+        }
+        if parseable {
+            let old_hash = old.and_then(|(_, _, h)| h.clone());
+            files_to_parse.push(InputFile { path, mtime: mtime as u64, old_hash, module_id: Some(mod_id), db_path: None });
+        } else {
+            other_files.push((path, mtime, mod_id, ext));
+        }
+    }
+
+    parse_and_save(&mut conn, files_to_parse, "Analyzing", &reporter, |r| record_reparsed(&mut report.files, &reparsed, r), |_, _| Ok(()))?;
+
+    if !other_files.is_empty() {
+        let mut string_cache = HashMap::new();
+        let mut dir_cache = HashMap::new();
+        let tx = conn.transaction()?;
+        for (path, mtime, mod_id, ext) in &other_files {
+            let p = Path::new(path);
+            let dir_id = get_or_create_directory(&tx, &mut string_cache, &mut dir_cache, p.parent().unwrap_or(Path::new("")))?;
+            let fn_id = db::get_or_create_string(&tx, &mut string_cache, p.file_name().unwrap().to_str().unwrap())?;
+            tx.execute("INSERT OR REPLACE INTO files (directory_id, filename_id, extension, mtime, module_id, is_header) VALUES (?, ?, ?, ?, ?, ?)",
+                params![dir_id, fn_id, ext, mtime, mod_id, if ext == "h" || ext == "hpp" { 1 } else { 0 }])?;
+        }
+        tx.commit()?;
+    }
+
+    if let Err(e) = db::code_index::sync(&mut conn, req.config.code_search, &reporter) {
+        tracing::warn!("Failed to update code search index: {}", e);
+    }
+
+    reporter.report("complete", 100, 100, &format!("Partial refresh complete ({}).", summarize(&report.files)));
+    Ok(report)
+}
+
+/// パース済みで書き込み待ちの結果を保持する上限。これを超えるとワーカーは書き込み側を待つ。
+const PIPELINE_DEPTH: usize = 256;
+
+/// Parse `files` on the rayon pool and stream the results through a bounded channel to a
+/// single DB writer, so at most `PIPELINE_DEPTH` results are held in memory regardless of
+/// how many files there are.  `on_result` sees each result just before it is written;
+/// `on_batch` runs inside each batch transaction (see [`db::save_results`]).
+fn parse_and_save(
+    conn: &mut Connection,
+    files: Vec<InputFile>,
+    label: &str,
+    reporter: &Arc<dyn ProgressReporter>,
+    mut on_result: impl FnMut(&ParseResult),
+    on_batch: impl FnMut(&rusqlite::Transaction, usize) -> rusqlite::Result<()>,
+) -> anyhow::Result<()> {
+    let total = files.len();
+    if total == 0 { return Ok(()); }
+    reporter.report("analysis", 0, total, &format!("{} {} file(s)...", label, total));
+    let language: tree_sitter::Language = tree_sitter_unreal_cpp::LANGUAGE.into();
+    let query = Query::new(&language, scanner::QUERY_STR).expect("Failed to parse query");
+    let include_query = Query::new(&language, scanner::INCLUDE_QUERY_STR).expect("Failed to parse include query");
+    let processed_count = AtomicUsize::new(0);
+    let (sender, receiver) = std::sync::mpsc::sync_channel::<ParseResult>(PIPELINE_DEPTH);
+
+    std::thread::scope(|s| {
+        let (language, query, include_query, processed_count) = (&language, &query, &include_query, &processed_count);
+        let parser = s.spawn(move || {
+            // 書き込み側が失敗して receiver が破棄されると send が失敗し、残りのパースは打ち切られる
+            files.into_par_iter().try_for_each_with(sender, |sender, input| {
+                let res = scanner::process_file(&input, language, query, include_query).unwrap_or_else(|_| ParseResult { path: input.path, status: "error".to_string(), mtime: input.mtime, data: None, module_id: input.module_id });
+                let current = processed_count.fetch_add(1, Ordering::Relaxed) + 1;
+                if current.is_multiple_of(50) || current == total { reporter.report("analysis", current, total, &format!("{}: {}/{}", label, current, total)); }
+                sender.send(res).map_err(|_| ())
+            })
+        });
+        let saved = db::save_results(conn, receiver.iter().inspect(|r| on_result(r)), total, Arc::clone(reporter), on_batch);
+        drop(receiver);
+        let parsed = parser.join();
+        saved?;
+        // send が失敗するのは書き込み側が先に止まったときだけで、その場合は saved がエラーになっている
+        let _ = parsed.map_err(|_| anyhow::anyhow!("Parser thread panicked"))?;
+        Ok(())
+    })
+}
+
+fn file_mtime(path: &str) -> i64 {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs()).unwrap_or(0) as i64
+}
+
+/// 走査で見つかったモジュールとプラグイン: (名前, ルート)。
+/// モジュールのルートは dir_paths 形式、プラグインは components.root_path と同じ形式。
+fn discovered_names(module_defs: &[ModuleDef], component_defs: &[ComponentDef]) -> (NamedRoots, NamedRoots) {
+    let modules = module_defs.iter()
+        .map(|m| (m.name.clone(), db::path::to_db_path_format(&normalize_path(&m.root))))
+        .collect();
+    let plugins = component_defs.iter()
+        .filter(|c| c.comp_type == "Plugin")
+        .map(|c| (c.display_name.clone(), normalize_path(&c.root_path)))
+        .collect();
+    (modules, plugins)
+}
+
+/// DB にあるモジュールとプラグイン (形式は `discovered_names` と同じ)。
+/// `skip_owner` が所有するもの (エンジン走査を省くときのエンジン側) は除く。
+fn indexed_names(conn: &Connection, skip_owner: Option<&str>) -> anyhow::Result<(NamedRoots, NamedRoots)> {
+    let modules = conn.prepare(
+        "SELECT s.text, dp.full_path FROM modules m
+         JOIN strings s ON m.name_id = s.id
+         JOIN dir_paths dp ON m.root_directory_id = dp.id
+         WHERE m.type != 'Global' AND (?1 IS NULL OR m.owner_name IS NOT ?1)"
+    )?.query_map([skip_owner], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<Result<_, _>>()?;
+    let plugins = conn.prepare(
+        "SELECT display_name, root_path FROM components WHERE type = 'Plugin' AND (?1 IS NULL OR owner_name IS NOT ?1)"
+    )?.query_map([skip_owner], |r| Ok((r.get(0)?, r.get(1)?)))?.collect::<Result<_, _>>()?;
+    Ok((modules, plugins))
+}
+
+/// ファイル (dir_paths 形式) が属するモジュール名。ルートの最長一致で、無ければ `_Global`。
+fn module_of<'a>(modules: &'a [(String, String)], db_path: &str) -> &'a str {
+    modules.iter()
+        .filter(|(_, root)| db_path.strip_prefix(root.as_str()).is_some_and(|rest| rest.starts_with('/')))
+        .max_by_key(|(_, root)| root.len())
+        .map(|(name, _)| name.as_str())
+        .unwrap_or("_Global")
+}
+
+/// 再パースに回した既存ファイルを、ハッシュが一致した (cache_hit) かどうかで振り分ける
+fn record_reparsed(files: &mut FileChanges, reparsed: &HashMap<String, String>, result: &ParseResult) {
+    if let Some(module) = reparsed.get(&result.path) {
+        files.record(module, if result.status == "cache_hit" { FileChange::Touched } else { FileChange::Modified });
+    }
+}
+
+fn summarize(files: &FileChanges) -> String {
+    format!("{} added, {} modified, {} deleted file(s)", files.total.added, files.total.modified, files.total.deleted)
+}
 
+/// VCS の差分一覧を件数にする。差分のうち実際に再パースされるファイルだけを数える。
+fn count_vcs_changes(conn: &Connection, changed: &ChangedFiles, include_exts: &HashSet<String>, files: &mut FileChanges) -> anyhow::Result<()> {
+    let modules = indexed_names(conn, None)?.0;
+    for path in &changed.modified {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if !path.exists() || !include_exts.contains(&ext) || !PARSEABLE_EXTS.contains(&ext.as_str()) { continue; }
+        let db_path = db::path::to_db_path_format(&normalize_path(path));
+        let change = if db::path::find_file_id_by_db_path(conn, &db_path)?.is_some() { FileChange::Modified } else { FileChange::Added };
+        files.record(module_of(&modules, &db_path), change);
+    }
+    for path in &changed.deleted {
+        let db_path = db::path::to_db_path_format(&normalize_path(path));
+        if db::path::find_file_id_by_db_path(conn, &db_path)?.is_some() {
+            files.record(module_of(&modules, &db_path), FileChange::Deleted);
+        }
+    }
+    Ok(())
+}
+
+/// 走査結果を DB と比べる (mtime、変わっていれば内容ハッシュ)。DB には書き込まない。
+/// `force(path, old_mtime)`: mtime に関係なく再パースされるファイル。
+fn compare_files(
+    conn: Option<&Connection>,
+    roots: &[String],
+    on_disk: Vec<(String, String, String)>,
+    modules: &[(String, String)],
+    old_modules: &[(String, String)],
+    force: impl Fn(&str, i64) -> bool,
+    files: &mut FileChanges,
+) -> anyhow::Result<()> {
+    let mut existing = match conn {
+        Some(c) => indexed_files_under(c, roots)?,
+        None => HashMap::new(),
+    };
+    let mut to_hash = Vec::new();
+    for (path, db_path, ext) in on_disk {
+        let module = module_of(modules, &db_path);
+        let Some((_, old_mtime, old_hash)) = existing.remove(&db_path) else {
+            files.record(module, FileChange::Added);
+            continue;
+        };
+        let parseable = PARSEABLE_EXTS.contains(&ext.as_str());
+        if parseable && force(&path, old_mtime) {
+            files.record(module, FileChange::Modified);
+        } else if file_mtime(&path) != old_mtime {
+            match old_hash {
+                Some(hash) if parseable => to_hash.push((path, hash, module.to_string())),
+                _ => files.record(module, FileChange::Modified),
+            }
+        }
+    }
+    let hashed: Vec<(String, bool)> = to_hash.into_par_iter()
+        .map(|(path, hash, module)| {
+            let same = fs::read(&path).is_ok_and(|bytes| scanner::content_hash(&bytes) == hash);
+            (module, same)
+        })
+        .collect();
+    for (module, same) in hashed {
+        files.record(&module, if same { FileChange::Touched } else { FileChange::Modified });
+    }
+    // 残りは走査で見つからなかった = 削除される
+    for db_path in existing.keys() {
+        files.record(module_of(old_modules, db_path), FileChange::Deleted);
+    }
     Ok(())
 }
 
+/// Dry run of [`run_refresh`]: discovery plus mtime / hash comparison against the
+/// index.  The DB is opened read-only and nothing is parsed or written.
+pub fn plan_refresh(req: RefreshRequest, reporter: Arc<dyn ProgressReporter>) -> anyhow::Result<RefreshReport> {
+    let db_path_str = req.db_path.as_ref().ok_or_else(|| anyhow::anyhow!("DB path required for refresh"))?;
+    let normalize_to_native = |s: &str| { if cfg!(target_os = "windows") { s.replace('/', "\\") } else { s.replace('\\', "/") } };
+    let project_root = PathBuf::from(normalize_to_native(&req.project_root));
+    let engine_root = req.engine_root.as_ref().map(|r| PathBuf::from(normalize_to_native(r)));
+    let db_path_native = normalize_to_native(db_path_str);
+    if !project_root.exists() { return Err(anyhow::anyhow!("Project root does not exist: {:?}", project_root)); }
+
+    reporter.report_plan(&[
+        PhaseInfo { name: "discovery".into(), label: "Discovery".into(), weight: 0.7 },
+        PhaseInfo { name: "analysis".into(),  label: "Comparing".into(), weight: 0.3 },
+    ]);
+
+    // 古いスキーマの DB は読まずに空の索引と比べる (実際のリフレッシュでは移行か作り直しになる)
+    let db_exists = Path::new(&db_path_native).exists();
+    let schema_change = db_exists && !db_version_matches(&db_path_native);
+    let conn = if db_exists && !schema_change {
+        Some(Connection::open_with_flags(Path::new(&db_path_native), OpenFlags::SQLITE_OPEN_READ_ONLY)?)
+    } else {
+        None
+    };
+    let meta = |key: &str| conn.as_ref().and_then(|c| c.query_row("SELECT value FROM project_meta WHERE key = ?", [key], |r| r.get::<_, String>(0)).ok());
+    let reindex_exts = conn.as_ref().map(db::migrate::pending_extensions).unwrap_or_default();
+    let reindex_pending = !reindex_exts.is_empty();
+    let macros_changed = meta("custom_macros_hash") != crate::parser::macros::file_hash(&project_root);
+    let mut report = RefreshReport { dry_run: true, schema_change, macros_changed, ..Default::default() };
+
+    if req.scope.as_deref() == Some("Partial") {
+        let conn = conn.as_ref().ok_or_else(|| anyhow::anyhow!("Partial refresh needs an up-to-date index; run a full refresh first"))?;
+        if let Some(reason) = partial_blocked_by(macros_changed, reindex_pending, refresh_checkpoint::exists(conn)) {
+            anyhow::bail!("Partial refresh is not possible ({}); run a full refresh first", reason);
+        }
+        report.mode = "partial".into();
+        report.engine_walk_skipped = true;
+        let roots = resolve_partial_roots(conn, &req.targets)?;
+        reporter.report("discovery", 0, 100, &format!("Scanning {}: {}", req.targets.join(", "), roots.join(", ")));
+        let on_disk = walk_files(&roots, &req.config);
+        reporter.report("analysis", 0, 100, &format!("Comparing {} file(s)...", on_disk.len()));
+        let modules = indexed_names(conn, None)?.0;
+        compare_files(Some(conn), &roots, on_disk, &modules, &modules, |_, _| false, &mut report.files)?;
+        reporter.report("complete", 100, 100, &format!("Plan: {}.", summarize(&report.files)));
+        return Ok(report);
+    }
+
+    let game_vcs = vcs::detect(&project_root);
+    let game_state = GameVcsState::detect(game_vcs.as_ref(), &project_root, meta);
+    match check_incremental(
+        &project_root, game_vcs.as_ref(), &game_state,
+        conn.is_some(), macros_changed, reindex_pending, conn.as_ref().is_some_and(refresh_checkpoint::exists),
+    ) {
+        Ok(changed) => {
+            report.mode = "incremental".into();
+            report.engine_walk_skipped = true;
+            if let Some(conn) = &conn {
+                let include_exts: HashSet<String> = req.config.include_extensions.iter().map(|e| e.to_lowercase()).collect();
+                count_vcs_changes(conn, &changed, &include_exts, &mut report.files)?;
+            }
+            reporter.report("complete", 100, 100, &format!("Plan: {}.", summarize(&report.files)));
+            return Ok(report);
+        }
+        Err(reason) => report.incremental_blocked_by = reason.map(str::to_string),
+    }
+
+    // run_refresh と同じ条件: 空の DB (別リビジョンの共有 DB を読んでいた DB を含む) は共有エンジンインデックスから作られ、
+    // その時点でエンジン側は最新になる
+    let engine_vcs = engine_root.as_ref().map(|er| (vcs::detect(er), er));
+    let current_engine_rev = engine_vcs.as_ref().and_then(|(v, er)| v.current_revision(er));
+    let engine_dirty = dirty_fingerprint(engine_vcs.as_ref()
+        .and_then(|(v, er)| v.working_changes(er))
+        .map(|c| vcs::WorkingCopy::snapshot(&c)).as_ref());
+    report.engine_seeded = !reindex_pending && engine_dirty.is_none() && match (&engine_root, &current_engine_rev) {
+        (Some(er), Some(rev)) => db::engine_cache::would_seed(conn.as_ref(), &db::engine_cache::cache_path(&db_path_native, &normalize_path(er), rev)),
+        _ => false,
+    };
+    let engine_rev_same = !reindex_pending && current_engine_rev.is_some()
+        && (report.engine_seeded || (meta("vcs_engine_revision") == current_engine_rev && meta("vcs_engine_worktree") == engine_dirty));
+    report.mode = "full".into();
+    report.engine_walk_skipped = engine_rev_same;
+
+    reporter.report("discovery", 0, 100, &format!("Scanning: {:?}", project_root));
+    let discovery = discover(&req, &project_root, &engine_root, engine_rev_same, Arc::clone(&reporter))?;
+    let skip_owner = if engine_rev_same { discovery.engine_name.as_deref() } else { None };
+    let (old_modules, old_plugins) = match &conn {
+        Some(c) => indexed_names(c, skip_owner)?,
+        None => Default::default(),
+    };
+    let (new_modules, new_plugins) = discovered_names(&discovery.module_defs, &discovery.component_defs);
+    report.modules = NameChanges::diff(&old_modules, &new_modules);
+    report.plugins = NameChanges::diff(&old_plugins, &new_plugins);
+
+    reporter.report("analysis", 0, 100, &format!("Comparing {} file(s)...", discovery.files.len()));
+    let roots: Vec<String> = discovery.search_roots.iter().map(|r| normalize_path(r)).collect();
+    let project_root_str = normalize_path(&project_root);
+    let on_disk = discovery.files.into_iter()
+        .map(|(path, ext)| { let db_path = db::path::to_db_path_format(&path); (path, db_path, ext) })
+        .collect();
+    compare_files(
+        conn.as_ref(), &roots, on_disk, &new_modules, &old_modules,
+        |path, old_mtime| (macros_changed && path.starts_with(&project_root_str))
+            || (old_mtime == 0 && Path::new(path).extension().is_some_and(|e| reindex_exts.contains(&e.to_string_lossy().to_lowercase()))),
+        &mut report.files,
+    )?;
+    reporter.report("complete", 100, 100, &format!("Plan: {}.", summarize(&report.files)));
+    Ok(report)
+}
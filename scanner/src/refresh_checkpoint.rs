@@ -0,0 +1,112 @@
+//! Refresh checkpoint.
+//!
+//! A full refresh stores its discovery result in `project_meta` as soon as the
+//! components / modules are registered, and bumps the committed batch count with
+//! every analysis batch.  When the server is killed part-way, the next refresh
+//! finds the checkpoint, skips discovery and module registration, and only
+//! analyses the files whose saved mtime does not match the disk yet.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use crate::refresh_report::NameChanges;
+
+const CHECKPOINT_KEY: &str = "refresh_checkpoint";
+const PROGRESS_KEY: &str = "refresh_checkpoint_progress";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RefreshCheckpoint {
+    /// この 4 つが今回のリフレッシュと一致するときだけ再開する
+    pub project_root: String,
+    pub engine_root: Option<String>,
+    pub scope: String,
+    pub engine_revision: Option<String>,
+    pub macros_hash: Option<String>,
+    pub engine_name: Option<String>,
+    pub engine_walk_skipped: bool,
+    /// 走査したルート (削除済みファイルの掃除範囲)
+    pub search_roots: Vec<String>,
+    /// 発見したファイル: (パス, 拡張子)
+    pub files: Vec<(String, String)>,
+    /// 中断したリフレッシュが登録したモジュール / プラグインの増減 (レポート用)
+    pub modules: NameChanges,
+    pub plugins: NameChanges,
+}
+
+/// 書き込み済みのバッチ数とファイル数
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub batches: usize,
+    pub files: usize,
+}
+
+impl RefreshCheckpoint {
+    pub fn matches(&self, project_root: &str, engine_root: Option<&str>, scope: &str, engine_revision: Option<&str>, macros_hash: Option<&str>) -> bool {
+        self.project_root == project_root
+            && self.engine_root.as_deref() == engine_root
+            && self.scope == scope
+            && self.engine_revision.as_deref() == engine_revision
+            && self.macros_hash.as_deref() == macros_hash
+    }
+}
+
+pub fn exists(conn: &Connection) -> bool {
+    conn.query_row("SELECT 1 FROM project_meta WHERE key = ?", [CHECKPOINT_KEY], |_| Ok(()))
+        .optional().ok().flatten().is_some()
+}
+
+pub fn load(conn: &Connection) -> Option<(RefreshCheckpoint, Progress)> {
+    let meta = |key: &str| conn.query_row("SELECT value FROM project_meta WHERE key = ?", [key], |r| r.get::<_, String>(0)).ok();
+    let checkpoint = serde_json::from_str(&meta(CHECKPOINT_KEY)?).ok()?;
+    let progress = meta(PROGRESS_KEY).and_then(|p| serde_json::from_str(&p).ok()).unwrap_or_default();
+    Some((checkpoint, progress))
+}
+
+/// Stores a new checkpoint and resets the progress.  Call it in the transaction
+/// that commits the module structure so both land together.
+pub fn save(conn: &Connection, checkpoint: &RefreshCheckpoint) -> anyhow::Result<()> {
+    conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params![CHECKPOINT_KEY, serde_json::to_string(checkpoint)?])?;
+    record_progress(conn, Progress::default())?;
+    Ok(())
+}
+
+/// Called inside each analysis batch transaction.
+pub fn record_progress(conn: &Connection, progress: Progress) -> rusqlite::Result<()> {
+    let value = serde_json::to_string(&progress).unwrap_or_default();
+    conn.execute("INSERT OR REPLACE INTO project_meta (key, value) VALUES (?, ?)", params![PROGRESS_KEY, value])?;
+    Ok(())
+}
+
+pub fn clear(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM project_meta WHERE key IN (?, ?)", params![CHECKPOINT_KEY, PROGRESS_KEY])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE project_meta (key TEXT PRIMARY KEY, value TEXT)", []).unwrap();
+        assert!(!exists(&conn));
+
+        let checkpoint = RefreshCheckpoint {
+            project_root: "/p".into(),
+            scope: "Full".into(),
+            files: vec![("/p/Source/A.h".into(), "h".into())],
+            ..Default::default()
+        };
+        save(&conn, &checkpoint).unwrap();
+        record_progress(&conn, Progress { batches: 2, files: 4000 }).unwrap();
+
+        let (loaded, progress) = load(&conn).unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(progress, Progress { batches: 2, files: 4000 });
+        assert!(loaded.matches("/p", None, "Full", None, None));
+        assert!(!loaded.matches("/p", None, "Game", None, None));
+
+        clear(&conn).unwrap();
+        assert!(load(&conn).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! Refresh change report.
+//!
+//! `plan_refresh` fills it without touching the DB (dry run), and every refresh
+//! returns one describing what it actually did.
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+/// モジュール / プラグインの増減
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct NameChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<Rename>,
+}
+
+impl NameChanges {
+    /// `(名前, ルートパス)` の新旧リストを比べる。ルートが同じで名前だけ違うものは rename 扱い。
+    pub fn diff(old: &[(String, String)], new: &[(String, String)]) -> Self {
+        let old_by_name: HashMap<&str, &str> = old.iter().map(|(n, r)| (n.as_str(), r.as_str())).collect();
+        let new_by_name: HashMap<&str, &str> = new.iter().map(|(n, r)| (n.as_str(), r.as_str())).collect();
+        let old_by_root: HashMap<&str, &str> = old.iter()
+            .filter(|(n, _)| !new_by_name.contains_key(n.as_str()))
+            .map(|(n, r)| (r.as_str(), n.as_str()))
+            .collect();
+
+        let mut changes = NameChanges::default();
+        let mut renamed_from = Vec::new();
+        for (name, root) in new {
+            if old_by_name.contains_key(name.as_str()) { continue; }
+            match old_by_root.get(root.as_str()) {
+                Some(from) => {
+                    renamed_from.push(*from);
+                    changes.renamed.push(Rename { from: from.to_string(), to: name.clone() });
+                }
+                None => changes.added.push(name.clone()),
+            }
+        }
+        changes.removed = old.iter()
+            .map(|(n, _)| n.as_str())
+            .filter(|n| !new_by_name.contains_key(n) && !renamed_from.contains(n))
+            .map(str::to_string)
+            .collect();
+        changes.added.sort();
+        changes.added.dedup();
+        changes.removed.sort();
+        changes.removed.dedup();
+        changes.renamed.sort_by(|a, b| a.to.cmp(&b.to));
+        changes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+    /// mtime だけ変わって内容は同じ (再パース不要)
+    Touched,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Copy)]
+pub struct FileCounts {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub touched: usize,
+}
+
+impl FileCounts {
+    fn add(&mut self, change: FileChange) {
+        match change {
+            FileChange::Added => self.added += 1,
+            FileChange::Modified => self.modified += 1,
+            FileChange::Deleted => self.deleted += 1,
+            FileChange::Touched => self.touched += 1,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct FileChanges {
+    #[serde(flatten)]
+    pub total: FileCounts,
+    /// モジュール名 → 件数。変更のないモジュールは含まない。
+    pub per_module: BTreeMap<String, FileCounts>,
+}
+
+impl FileChanges {
+    pub fn record(&mut self, module: &str, change: FileChange) {
+        self.total.add(change);
+        self.per_module.entry(module.to_string()).or_default().add(change);
+    }
+}
+
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct RefreshReport {
+    /// true: `plan_refresh` による見積もりで、DB は変更されていない
+    pub dry_run: bool,
+    /// "full" / "incremental" / "partial"
+    pub mode: String,
+    /// VCS 差分だけの更新にならない理由 (適用される場合や VCS 未使用時は None)
+    pub incremental_blocked_by: Option<String>,
+    pub engine_walk_skipped: bool,
+    /// 中断したリフレッシュのチェックポイントから再開した (走査とモジュール登録を省略)
+    pub resumed: bool,
+    /// 空の DB を共有エンジンインデックスから作る
+    pub engine_seeded: bool,
+    /// DB のスキーマが古い (移行または作り直しになる)
+    pub schema_change: bool,
+    pub macros_changed: bool,
+    pub modules: NameChanges,
+    pub plugins: NameChanges,
+    pub files: FileChanges,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(v: &[(&str, &str)]) -> Vec<(String, String)> {
+        v.iter().map(|(n, r)| (n.to_string(), r.to_string())).collect()
+    }
+
+    #[test]
+    fn test_name_changes_diff() {
+        let old = pairs(&[("Core", "/p/Source/Core"), ("OldName", "/p/Source/Foo"), ("Gone", "/p/Source/Gone")]);
+        let new = pairs(&[("Core", "/p/Source/Core"), ("NewName", "/p/Source/Foo"), ("Fresh", "/p/Source/Fresh")]);
+        let changes = NameChanges::diff(&old, &new);
+        assert_eq!(changes.added, vec!["Fresh"]);
+        assert_eq!(changes.removed, vec!["Gone"]);
+        assert_eq!(changes.renamed.len(), 1);
+        assert_eq!((changes.renamed[0].from.as_str(), changes.renamed[0].to.as_str()), ("OldName", "NewName"));
+
+        let mut files = FileChanges::default();
+        files.record("Core", FileChange::Added);
+        files.record("Core", FileChange::Deleted);
+        files.record("Fresh", FileChange::Touched);
+        assert_eq!(files.total.added + files.total.deleted + files.total.touched, 3);
+        assert_eq!(files.per_module["Core"].deleted, 1);
+    }
+}
@@ -12,7 +12,7 @@ use crate::server::state::{AppState, ProjectContext, RpcProgressReporter};
 use crate::server::utils::{convert_params, normalize_to_unix, normalize_to_native, normalize_path_key};
 use crate::server::asset::handle_asset_scan;
 use crate::types::{RefreshRequest, ScanRequest, QueryRequest, SetupRequest, ModifyUprojectAddModuleRequest, ModifyTargetAddModuleRequest, ModifyResult};
-use crate::{scanner, db, refresh};
+use crate::{scanner, db, refresh, refresh_checkpoint};
 
 /// RAII guard that decrements the appropriate in-flight query counter when dropped.
 /// Works correctly even if the spawn_blocking closure panics.
@@ -75,11 +75,13 @@ pub async fn handle_setup(state: Arc<AppState>, params: &Value) -> anyhow::Resul
             return Ok::<bool, anyhow::Error>(true);
         }
         
-        // バージョンが合っていても、中身が空ならリフレッシュが必要
+        // バージョンが合っていても、中身が空か移行後の再パース待ち、あるいは
+        // 中断したリフレッシュが残っているならリフレッシュが必要 (チェックポイントから再開する)
         if let Ok(conn) = rusqlite::Connection::open(&db_path_native_clone) {
+            let _ = db::engine_cache::attach_views(&conn);
             let file_count: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0)).unwrap_or(0);
             let class_count: i64 = conn.query_row("SELECT COUNT(*) FROM classes", [], |r| r.get(0)).unwrap_or(0);
-            if file_count == 0 || class_count == 0 {
+            if file_count == 0 || class_count == 0 || db::migrate::reindex_pending(&conn) || refresh_checkpoint::exists(&conn) {
                 return Ok(true);
             }
         }
@@ -160,7 +162,7 @@ pub async fn handle_refresh(state: &AppState, params: &Value, tx: mpsc::Sender<V
     let _ = state.save_registry();
     let reporter = Arc::new(RpcProgressReporter { tx });
     
-    tokio::task::spawn_blocking(move || { refresh::run_refresh(req, reporter) }).await??;
+    let report = tokio::task::spawn_blocking(move || { refresh::run_refresh(req, reporter) }).await??;
     
     {
         let cache_arc = state.get_completion_cache(&root_key);
@@ -172,7 +174,19 @@ pub async fn handle_refresh(state: &AppState, params: &Value, tx: mpsc::Sender<V
     let db_path_native = normalize_to_native(&db_path_unix);
     let _ = state.get_connection(&db_path_native);
     
-    Ok(Value::String("Refresh success".to_string()))
+    Ok(serde_json::to_value(report)?)
+}
+
+/// Same parameters as `refresh`, but only reports what would change (the DB is not written).
+pub async fn handle_plan_refresh(state: &AppState, params: &Value, tx: mpsc::Sender<Vec<u8>>) -> anyhow::Result<Value> {
+    let mut req: RefreshRequest = convert_params(params)?;
+    if req.db_path.is_none() {
+        let root_key = normalize_path_key(&req.project_root);
+        req.db_path = state.projects.lock().get(&root_key).map(|ctx| ctx.db_path.clone());
+    }
+    let reporter = Arc::new(RpcProgressReporter { tx });
+    let report = tokio::task::spawn_blocking(move || { refresh::plan_refresh(req, reporter) }).await??;
+    Ok(serde_json::to_value(report)?)
 }
 
 pub async fn handle_watch(state: &AppState, params: &Value) -> anyhow::Result<Value> {
@@ -188,6 +202,9 @@ pub async fn handle_watch(state: &AppState, params: &Value) -> anyhow::Result<Va
         let mut filters = state.watch_filters.lock();
         filters.insert(root_native.clone(), Arc::clone(&filter));
     }
+    if let Err(e) = crate::parser::macros::register_project(&root_path_native) {
+        tracing::warn!("{}", e);
+    }
 
     let mut watcher = state.watcher.lock();
     match watcher.watch(&root_path_native, notify::RecursiveMode::Recursive) {
@@ -227,7 +244,7 @@ pub async fn handle_query(state: Arc<AppState>, params: &Value, tx: mpsc::Sender
     };
     let db_path_native = normalize_to_native(&db_path_unix);
     let conn = state.get_read_only_connection(&db_path_native)?;
-    let is_async = matches!(req.query, QueryRequest::GetFilesInModulesAsync { .. } | QueryRequest::SearchFilesInModulesAsync { .. } | QueryRequest::GetClassesInModulesAsync { .. } | QueryRequest::FindSymbolUsagesAsync { .. } | QueryRequest::FindIncludersAsync { .. });
+    let is_async = matches!(req.query, QueryRequest::GetFilesInModulesAsync { .. } | QueryRequest::SearchFilesInModulesAsync { .. } | QueryRequest::GetClassesInModulesAsync { .. } | QueryRequest::FindSymbolUsagesAsync { .. } | QueryRequest::FindIncludersAsync { .. } | QueryRequest::GrepCode { .. });
     let is_completion = matches!(req.query, QueryRequest::GetCompletions { .. });
 
     // Track in-flight query counts so simple_status can show what's actually running.
@@ -463,12 +480,13 @@ pub async fn handle_modify_uproject_add_module(params: &Value) -> anyhow::Result
             &req.module_name,
             &req.module_type,
             &req.loading_phase,
+            req.changelist.as_deref(),
         )
     }).await?;
 
     match result {
-        Ok(()) => Ok(serde_json::to_value(ModifyResult { success: true, message: None })?),
-        Err(e) => Ok(serde_json::to_value(ModifyResult { success: false, message: Some(e.to_string()) })?),
+        Ok(vcs_action) => Ok(serde_json::to_value(ModifyResult { success: true, message: None, vcs_action })?),
+        Err(e) => Ok(serde_json::to_value(ModifyResult { success: false, message: Some(e.to_string()), vcs_action: None })?),
     }
 }
 
@@ -476,11 +494,11 @@ pub async fn handle_modify_uproject_add_module(params: &Value) -> anyhow::Result
 pub async fn handle_modify_target_add_module(params: &Value) -> anyhow::Result<Value> {
     let req: ModifyTargetAddModuleRequest = convert_params(params)?;
     let result = tokio::task::spawn_blocking(move || {
-        crate::modify::target::add_module(&req.file_path, &req.module_name)
+        crate::modify::target::add_module(&req.file_path, &req.module_name, req.changelist.as_deref())
     }).await?;
 
     match result {
-        Ok(()) => Ok(serde_json::to_value(ModifyResult { success: true, message: None })?),
-        Err(e) => Ok(serde_json::to_value(ModifyResult { success: false, message: Some(e.to_string()) })?),
+        Ok(vcs_action) => Ok(serde_json::to_value(ModifyResult { success: true, message: None, vcs_action })?),
+        Err(e) => Ok(serde_json::to_value(ModifyResult { success: false, message: Some(e.to_string()), vcs_action: None })?),
     }
 }
@@ -54,6 +54,7 @@ async fn process_msg(msgid: u64, method: String, params: Value, state: Arc<AppSt
         "ping" => handlers::handle_ping(&state, &params).await,
         "setup" => handlers::handle_setup(state.clone(), &params).await,
         "refresh" => handlers::handle_refresh(&state, &params, tx.clone()).await,
+        "plan_refresh" => handlers::handle_plan_refresh(&state, &params, tx.clone()).await,
         "watch" => handlers::handle_watch(&state, &params).await,
         "query" => handlers::handle_query(state.clone(), &params, tx.clone(), msgid).await,
         "scan" => handlers::handle_scan(&state, &params).await,
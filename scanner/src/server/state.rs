@@ -213,6 +213,8 @@ impl AppState {
         let _ = conn.pragma_update(None, "cache_size", "-4000"); // 約4MB (インデックス用)
         let _ = conn.pragma_update(None, "mmap_size", "0");      // メモリマップ無効 (20GBリークの主因)
         let _ = conn.pragma_update(None, "temp_store", "FILE");  // 一時データはディスクへ
+        // 共有エンジンインデックスを使うプロジェクトでは、エンジン側の行を一時ビューで合わせて見せる
+        db::engine_cache::attach_views(&conn)?;
         let _ = conn.pragma_update(None, "query_only", "ON");
 
         Ok(conn)
@@ -48,6 +48,14 @@ pub async fn handle_file_change(state: Arc<AppState>, path: PathBuf) {
             }
         }
 
+        if path_str_unix_lower.ends_with(crate::parser::macros::MACROS_FILE) {
+            // 反映済みファイルの再パースは次回の refresh で行われる
+            if let Err(e) = crate::parser::macros::register_project(&PathBuf::from(normalize_to_native(&root_clone))) {
+                tracing::warn!("{}", e);
+            }
+            return;
+        }
+
         let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
         if ext == "ini" {
             let mut caches = state.config_caches.lock();
@@ -61,7 +69,7 @@ pub async fn handle_file_change(state: Arc<AppState>, path: PathBuf) {
             }
             return;
         }
-        if !["h", "cpp", "hpp", "cs", "inl"].contains(&ext.as_str()) { return; }
+        if !["h", "cpp", "hpp", "cs", "inl", "usf", "ush", "verse"].contains(&ext.as_str()) { return; }
         
         let db_path_native = normalize_to_native(&db_path_unix);
         let conn_arc = match state.get_connection(&db_path_native) { Ok(c) => c, Err(_) => return };
@@ -80,6 +88,9 @@ pub async fn handle_file_change(state: Arc<AppState>, path: PathBuf) {
                 if let Ok(res) = scanner::process_file(&input, &language, &query, &include_query) {
                     match db::update_single_file(&mut conn, &res) {
                         Ok(class_names) => {
+                            if let Err(e) = db::code_index::index_file(&mut conn, &res.path) {
+                                tracing::warn!("Watcher: Failed to update code search index: {}", e);
+                            }
                             // Invalidate in-memory LRU completion cache
                             let cache_arc = state_for_update.get_completion_cache(&root_clone);
                             let mut cache = cache_arc.lock();
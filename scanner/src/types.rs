@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Deserialize, Debug)]
@@ -24,8 +25,12 @@ pub struct RefreshRequest {
     pub engine_root: Option<String>,
     pub db_path: Option<String>,
     pub config: UEPConfig,
+    /// "Full" / "Engine" / "Game" / "Partial"
     pub scope: Option<String>,
     pub vcs_hash: Option<String>,
+    /// scope が "Partial" のときに再インデックスするコンポーネント名 / モジュール名
+    #[serde(default)]
+    pub targets: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -47,6 +52,9 @@ pub struct SetupRequest {
 pub struct UEPConfig {
     pub excludes_directory: Vec<String>,
     pub include_extensions: Vec<String>,
+    /// GrepCode 用のトライグラム索引を作る
+    #[serde(default)]
+    pub code_search: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -76,6 +84,49 @@ pub struct ParseData {
     pub includes: Vec<String>,
     pub parser: String,
     pub new_hash: String,
+    /// `IMPLEMENT_GLOBAL_SHADER` 等で C++ シェーダークラスに紐づけられた .usf/.ush
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shader_bindings: Vec<ShaderBindingInfo>,
+    /// `AddShaderSourceDirectoryMapping` で登録された仮想パス → 実ディレクトリ
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shader_mappings: Vec<ShaderMappingInfo>,
+    /// 型 / メンバーの参照箇所 (`symbol_refs`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub refs: Vec<SymbolRef>,
+}
+
+/// C++ shader class → HLSL source file / entry point binding.
+#[derive(Serialize, Clone, Debug)]
+pub struct ShaderBindingInfo {
+    pub class_name: String,
+    /// Virtual shader path, e.g. `/Plugin/MyPlugin/Private/MyShader.usf`
+    pub virtual_path: String,
+    pub entry_point: String,
+    /// `SF_Compute`, `SF_Pixel`, ...
+    pub frequency: String,
+    pub line: usize,
+}
+
+/// Virtual shader directory (e.g. `/Plugin/MyPlugin`) → absolute directory on disk.
+#[derive(Serialize, Clone, Debug)]
+pub struct ShaderMappingInfo {
+    pub virtual_path: String,
+    pub real_path: String,
+    pub line: usize,
+}
+
+/// Reference to a type or member name found while indexing.
+#[derive(Serialize, Clone, Debug)]
+pub struct SymbolRef {
+    pub name: String,
+    /// `type` / `member`
+    pub kind: String,
+    /// `AFoo::Bar` の `AFoo` (メンバー参照のみ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub line: usize,
+    /// 0 始まりのバイト列
+    pub col: usize,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -99,6 +150,39 @@ pub struct ClassInfo {
     pub members: Vec<MemberInfo>,
     pub is_final: bool,
     pub is_interface: bool,
+    /// 囲んでいる型の完全修飾名 (`FMyOuter::FInner` なら `FMyOuter`)。名前空間のみの場合は None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_type: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub template_params: Vec<TemplateParam>,
+    /// 特殊化の引数リスト (`template<> struct TFoo<int32>` なら `<int32>`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specialization_args: Option<String>,
+}
+
+/// テンプレート仮引数 (`typename T = int32` / `int32 N` / `template<typename> class TT`)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TemplateParam {
+    pub name: String,
+    /// "type" | "value" | "template"
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub variadic: bool,
+}
+
+impl ClassInfo {
+    /// 名前空間・外側の型を含めた完全修飾名を返す。
+    pub fn qualified_name(&self, separator: &str) -> String {
+        match &self.namespace {
+            // `namespace EFoo { enum Type }` は class_name 側に既にスコープが入っている
+            Some(ns) if !ns.is_empty() && !self.class_name.starts_with(&format!("{}{}", ns, separator)) => {
+                format!("{}{}{}", ns, separator, self.class_name)
+            }
+            _ => self.class_name.clone(),
+        }
+    }
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -114,6 +198,11 @@ pub struct MemberInfo {
     pub detail: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub return_type: Option<String>,
+    /// `UPROPERTY(...)` / `UFUNCTION(...)` の括弧内 (例: "ReplicatedUsing=OnRep_Health, EditAnywhere")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specifiers: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub template_params: Vec<TemplateParam>,
 }
 
 #[derive(Serialize, Debug)]
@@ -164,6 +253,8 @@ pub struct ComponentDef {
     pub uproject_path: Option<PathBuf>,
     pub uplugin_path: Option<PathBuf>,
     pub owner_name: String,
+    /// Game のみ: .uproject の EngineAssociation ("5.4" やソースビルドの GUID)
+    pub engine_association: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -244,8 +335,8 @@ pub enum QueryRequest {
     SearchFilesByPathPart { part: String },
     SearchFilesByPathPartAsync { part: String },
     GetEnumValues { enum_name: String },
-    GetComponents,
-    GetModules,
+    GetComponents { #[serde(default)] enabled_only: bool },
+    GetModules { #[serde(default)] enabled_only: bool },
     GetModuleIdByName { name: String },
     GetModuleRootPath { name: String },
     GetFilesInModule { module_id: i64 },
@@ -272,7 +363,22 @@ pub enum QueryRequest {
     GetModuleDirsByNameAndRoot { name: String, root: String },
     GetClassFilePath { class_name: String },
     GetFileSymbols { file_path: String },
-    SearchSymbols { pattern: String, limit: usize },
+    /// あいまい検索 (UE プレフィックス / キャメルケース / 部分一致)
+    SearchSymbols {
+        pattern: String,
+        limit: usize,
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+        /// class / struct / enum / function / property / delegate / ...
+        #[serde(default)]
+        kinds: Option<Vec<String>>,
+        /// true → UCLASS / USTRUCT / UENUM / UFUNCTION / UPROPERTY のみ
+        #[serde(default)]
+        reflected: Option<bool>,
+        /// true → .uproject で無効なプラグインのシンボルを除く
+        #[serde(default)]
+        enabled_only: bool,
+    },
     GetDependFiles { 
         file_path: String, 
         #[serde(default)]
@@ -283,6 +389,25 @@ pub enum QueryRequest {
     GrepAssets {
         pattern: String,
     },
+    /// トライグラム索引を使ったソースコードの正規表現検索 (ストリーミング)
+    GrepCode {
+        pattern: String,
+        /// モジュール名で絞り込む
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+        /// コンポーネント種別 ("Game" / "Engine" / "Plugin") で絞り込む
+        #[serde(default)]
+        components: Option<Vec<String>>,
+        #[serde(default)]
+        extensions: Option<Vec<String>>,
+        #[serde(default)]
+        ignore_case: bool,
+        #[serde(default)]
+        limit: Option<usize>,
+        /// true → 無効なプラグインのファイルを除く
+        #[serde(default)]
+        enabled_only: bool,
+    },
     ParseBuffer { 
         content: String, 
         file_path: Option<String>,
@@ -293,6 +418,8 @@ pub enum QueryRequest {
     },
     UpdateMemberReturnType { class_name: String, member_name: String, return_type: String },
     GetTargetFiles,
+    /// エンジンのバージョン / EngineAssociation / ターゲット一覧
+    GetProjectInfo,
     GetAllFilePaths,
     GetAllFilesMetadata,
     GetCompletions {
@@ -325,6 +452,9 @@ pub enum QueryRequest {
         /// None             → 型（class）参照検索モード
         #[serde(default)]
         method_name: Option<String>,
+        /// 未保存バッファ (path → content)。これらは DB ではなくその場でパースする
+        #[serde(default)]
+        buffers: HashMap<String, String>,
     },
     FindSymbolUsagesAsync {
         symbol_name: String,
@@ -334,6 +464,9 @@ pub enum QueryRequest {
         /// None             → 型（class）参照検索モード
         #[serde(default)]
         method_name: Option<String>,
+        /// 未保存バッファ (path → content)。これらは DB ではなくその場でパースする
+        #[serde(default)]
+        buffers: HashMap<String, String>,
     },
     GetConfigData {
         engine_root: Option<String>,
@@ -364,6 +497,24 @@ pub enum QueryRequest {
     FindIncludersAsync {
         file_path: String,
     },
+    /// C++ シェーダークラスと .usf/.ush のエントリポイントの対応を取得する。
+    /// class_name / file_path (シェーダーファイル) のどちらかで絞り込む。
+    GetShaderBindings {
+        #[serde(default)]
+        class_name: Option<String>,
+        #[serde(default)]
+        file_path: Option<String>,
+    },
+    /// Replicated プロパティ / RPC の整合性チェック (DOREPLIFETIME 漏れ、OnRep、_Implementation 等)。
+    /// class_name / file_path のどちらも省略した場合はプロジェクト全体。
+    GetReplicationDiagnostics {
+        #[serde(default)]
+        class_name: Option<String>,
+        #[serde(default)]
+        file_path: Option<String>,
+    },
+    /// テンプレートのプライマリ定義と特殊化 (`template<> struct TFoo<int32>`) の一覧
+    GetTemplateSpecializations { class_name: String },
 }
 
 /// Request to add a module entry to a .uproject or .uplugin JSON file.
@@ -373,6 +524,9 @@ pub struct ModifyUprojectAddModuleRequest {
     pub module_name: String,
     pub module_type: String,
     pub loading_phase: String,
+    /// Perforce changelist to open the file in (default changelist when omitted).
+    #[serde(default)]
+    pub changelist: Option<String>,
 }
 
 /// Request to register a module in a .Target.cs C# file.
@@ -380,6 +534,9 @@ pub struct ModifyUprojectAddModuleRequest {
 pub struct ModifyTargetAddModuleRequest {
     pub file_path: String,
     pub module_name: String,
+    /// Perforce changelist to open the file in (default changelist when omitted).
+    #[serde(default)]
+    pub changelist: Option<String>,
 }
 
 /// Generic result for file-modification RPC calls.
@@ -387,6 +544,8 @@ pub struct ModifyTargetAddModuleRequest {
 pub struct ModifyResult {
     pub success: bool,
     pub message: Option<String>,
+    /// VCS operation run before writing (e.g. `p4 edit`), if any.
+    pub vcs_action: Option<crate::vcs::VcsAction>,
 }
 
 use std::io::{self, Write};
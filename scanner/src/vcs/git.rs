@@ -2,7 +2,13 @@
 //!
 //! Deliberately uses `std::process::Command` rather than libgit2/gitoxide to
 //! keep the binary lightweight and avoid C FFI link issues on Windows.
+//!
+//! The repository is resolved from the `.git` entry nearest to the root: a
+//! directory for a normal clone, or a `gitdir: <path>` file for worktrees and
+//! submodules.  When the root is a subdirectory of a larger repository (an
+//! engine or project inside a monorepo) the diff is limited to that subdirectory.
 
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::collections::HashSet;
 use std::process::Command;
@@ -10,13 +16,72 @@ use super::{VcsProvider, ChangedFiles};
 
 pub struct GitProvider;
 
+/// Work tree and git directory resolved for a root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitRepo {
+    /// Top level of the work tree (the directory holding `.git`).
+    pub work_tree: PathBuf,
+    /// Repository metadata: `.git` itself, or the `gitdir:` target for
+    /// worktrees (`<main>/.git/worktrees/<name>`) and submodules
+    /// (`<super>/.git/modules/<name>`).
+    pub git_dir: PathBuf,
+}
+
+/// Finds the repository for `root` by walking up to the nearest `.git` entry
+/// and following `gitdir:` pointers.  `None` if there is no `.git` or the
+/// pointer target does not exist.
+pub fn find_repo(root: &Path) -> Option<GitRepo> {
+    for dir in root.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(GitRepo { work_tree: dir.to_path_buf(), git_dir: dot_git });
+        }
+        if dot_git.is_file() {
+            let git_dir = read_gitdir_file(&dot_git)?;
+            return Some(GitRepo { work_tree: dir.to_path_buf(), git_dir });
+        }
+    }
+    None
+}
+
+/// `.git` file contents: `gitdir: ../.git/modules/Engine` (relative to the file's directory) or an absolute path.
+fn read_gitdir_file(dot_git: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(dot_git).ok()?;
+    let target = content.lines().find_map(|l| l.trim().strip_prefix("gitdir:"))?.trim();
+    if target.is_empty() { return None; }
+    let target = Path::new(target);
+    let resolved = if target.is_absolute() { target.to_path_buf() } else { dot_git.parent()?.join(target) };
+    resolved.is_dir().then_some(resolved)
+}
+
+/// `git` command running in `root`, pinned to the resolved repository so that
+/// worktrees and submodules use their own metadata.  Falls back to git's own
+/// discovery when no `.git` entry is found (e.g. `GIT_DIR` set by the user).
+fn git(root: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    if let Some(repo) = find_repo(root) {
+        cmd.arg("--git-dir").arg(&repo.git_dir).arg("--work-tree").arg(&repo.work_tree);
+    }
+    cmd.current_dir(root);
+    cmd
+}
+
 impl VcsProvider for GitProvider {
     fn name(&self) -> &'static str { "git" }
 
+    /// Returns the last commit that touched `root` (`git log -1 --format=%H -- .`),
+    /// so commits elsewhere in a larger repository leave the revision unchanged.
+    /// Falls back to `HEAD` when no commit touches `root` (nothing tracked yet).
     fn current_revision(&self, root: &Path) -> Option<String> {
-        let out = Command::new("git")
+        let out = git(root)
+            .args(["log", "-1", "--format=%H", "--", "."])
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        let rev = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if !rev.is_empty() { return Some(rev); }
+        let out = git(root)
             .args(["rev-parse", "HEAD"])
-            .current_dir(root)
             .output()
             .ok()?;
         if out.status.success() {
@@ -26,15 +91,16 @@ impl VcsProvider for GitProvider {
         }
     }
 
-    /// Returns files changed between `since_rev` and HEAD.
+    /// Returns files changed between `since_rev` and HEAD under `root`.
     ///
-    /// Uses `git diff --name-status` for committed changes plus
+    /// Uses `git diff --name-status --relative` for committed changes plus
     /// `git ls-files --others --exclude-standard` for untracked new files.
+    /// Both only look at `root` and print paths relative to it, so a root
+    /// inside a larger repository ignores changes elsewhere in the repository.
     fn changed_since(&self, root: &Path, since_rev: &str) -> Option<ChangedFiles> {
         // --- committed diff ---
-        let diff_out = Command::new("git")
-            .args(["diff", "--name-status", "--no-renames", since_rev, "HEAD"])
-            .current_dir(root)
+        let diff_out = git(root)
+            .args(["diff", "--name-status", "--no-renames", "--relative", since_rev, "HEAD"])
             .output()
             .ok()?;
         if !diff_out.status.success() { return None; }
@@ -57,9 +123,8 @@ impl VcsProvider for GitProvider {
         }
 
         // --- untracked files (new files not yet committed) ---
-        let untracked_out = Command::new("git")
+        let untracked_out = git(root)
             .args(["ls-files", "--others", "--exclude-standard"])
-            .current_dir(root)
             .output();
         if let Ok(out) = untracked_out {
             if out.status.success() {
@@ -74,4 +139,105 @@ impl VcsProvider for GitProvider {
 
         Some(ChangedFiles { modified, deleted })
     }
+
+    /// Returns uncommitted changes (staged, unstaged and untracked).
+    ///
+    /// Runs: `git status --porcelain=v1 -z --untracked-files=all -- ROOT`
+    ///
+    /// Entries are `XY path\0`; renames add the original path as an extra
+    /// `\0`-terminated field.  Paths are relative to the repository top level.
+    fn working_changes(&self, root: &Path) -> Option<ChangedFiles> {
+        let top = find_repo(root)?.work_tree;
+        let out = git(root)
+            .args(["status", "--porcelain=v1", "-z", "--untracked-files=all", "--"])
+            .arg(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        Some(parse_porcelain(&String::from_utf8_lossy(&out.stdout), &top))
+    }
+}
+
+fn parse_porcelain(output: &str, top: &Path) -> ChangedFiles {
+    let mut changes = ChangedFiles::default();
+    let mut fields = output.split('\0');
+    while let Some(entry) = fields.next() {
+        if entry.len() < 4 { continue; }
+        let (status, rel) = entry.split_at(3);
+        let status = status.trim();
+        if status.starts_with('R') || status.starts_with('C') {
+            // rename / copy: the next field is the original path
+            if let Some(orig) = fields.next() {
+                if status.starts_with('R') { changes.deleted.insert(top.join(orig)); }
+            }
+        }
+        if status.contains('D') {
+            changes.deleted.insert(top.join(rel));
+        } else {
+            changes.modified.insert(top.join(rel));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let top = Path::new("/repo");
+        let out = " M Source/Foo.cpp\0?? Source/New.h\0 D Source/Gone.cpp\0R  Source/To.h\0Source/From.h\0";
+        let changes = parse_porcelain(out, top);
+        assert!(changes.modified.contains(&top.join("Source/Foo.cpp")));
+        assert!(changes.modified.contains(&top.join("Source/New.h")));
+        assert!(changes.modified.contains(&top.join("Source/To.h")));
+        assert!(changes.deleted.contains(&top.join("Source/Gone.cpp")));
+        assert!(changes.deleted.contains(&top.join("Source/From.h")));
+    }
+
+    #[test]
+    fn test_worktree_subdirectory() {
+        let dir = std::env::temp_dir().join(format!("unl_git_test_{}", std::process::id()));
+        let main = dir.join("main");
+        let wt = dir.join("wt");
+        std::fs::create_dir_all(main.join("Engine/Source")).unwrap();
+        std::fs::create_dir_all(main.join("Game")).unwrap();
+        let run = |cwd: &Path, args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=unl", "-c", "user.email=unl@example.com", "-c", "commit.gpgsign=false"])
+                .args(args).current_dir(cwd).output().is_ok_and(|o| o.status.success())
+        };
+        // git が無い環境ではスキップ
+        if !run(&main, &["init", "-q"]) { return; }
+        std::fs::write(main.join("Engine/Source/A.h"), "a").unwrap();
+        std::fs::write(main.join("Game/G.h"), "g").unwrap();
+        assert!(run(&main, &["add", "-A"]));
+        assert!(run(&main, &["commit", "-q", "-m", "init"]));
+        assert!(run(&main, &["worktree", "add", "-q", wt.to_str().unwrap()]));
+
+        let repo = find_repo(&wt.join("Engine")).unwrap();
+        assert_eq!(repo.work_tree, wt);
+        assert!(repo.git_dir.ends_with("worktrees/wt"));
+        assert_eq!(super::super::detect(&wt.join("Engine")).name(), "git");
+
+        let engine = wt.join("Engine");
+        let base = GitProvider.current_revision(&engine).unwrap();
+        std::fs::write(wt.join("Engine/Source/A.h"), "a2").unwrap();
+        std::fs::write(wt.join("Game/G.h"), "g2").unwrap();
+        assert!(run(&wt, &["commit", "-q", "-am", "edit"]));
+        assert_ne!(GitProvider.current_revision(&engine).unwrap(), base);
+
+        let changed = GitProvider.changed_since(&engine, &base).unwrap();
+        assert_eq!(changed.modified, HashSet::from([engine.join("Source/A.h")]));
+
+        // game だけのコミットではエンジンのリビジョンは変わらない
+        let engine_rev = GitProvider.current_revision(&engine).unwrap();
+        std::fs::write(wt.join("Game/G.h"), "g3").unwrap();
+        assert!(run(&wt, &["commit", "-q", "-am", "game only"]));
+        assert_eq!(GitProvider.current_revision(&engine).unwrap(), engine_rev);
+        assert_ne!(GitProvider.current_revision(&wt.join("Game")).unwrap(), engine_rev);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -1,18 +1,22 @@
 //! VCS abstraction layer for incremental refresh optimization.
 //!
-//! Provides a unified interface for Git, Perforce, SVN, and other VCS tools.
+//! Provides a unified interface for Git, Perforce, SVN, Plastic SCM, and other VCS tools.
 //! Used by `refresh.rs` to skip rescanning unchanged files/roots.
 
 pub mod git;
 pub mod none;
 pub mod perforce;
+pub mod plastic;
 pub mod svn;
 
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Files changed between two VCS revisions.
+#[derive(Debug, Default, Clone)]
 pub struct ChangedFiles {
     /// Added or modified files (absolute paths).
     pub modified: HashSet<PathBuf>,
@@ -20,6 +24,65 @@ pub struct ChangedFiles {
     pub deleted: HashSet<PathBuf>,
 }
 
+impl ChangedFiles {
+    /// Merge `other` into `self`.  `other` wins when a path is modified on one
+    /// side and deleted on the other (working-copy changes are merged last).
+    pub fn merge(&mut self, other: ChangedFiles) {
+        for p in other.modified {
+            self.deleted.remove(&p);
+            self.modified.insert(p);
+        }
+        for p in other.deleted {
+            self.modified.remove(&p);
+            self.deleted.insert(p);
+        }
+    }
+}
+
+/// Snapshot of the uncommitted working-copy changes, stored in `project_meta`
+/// as JSON so the next refresh can tell whether they changed and which files
+/// went back to their committed content.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkingCopy {
+    /// SHA-256 over the changed paths with their mtime and size.
+    pub fingerprint: String,
+    pub paths: Vec<String>,
+}
+
+impl WorkingCopy {
+    pub fn snapshot(changes: &ChangedFiles) -> Self {
+        let mut entries: Vec<String> = changes.modified.iter()
+            .map(|p| {
+                let meta = fs::metadata(p).ok();
+                let mtime = meta.as_ref().and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos()).unwrap_or(0);
+                format!("M {} {} {}", p.display(), mtime, meta.map(|m| m.len()).unwrap_or(0))
+            })
+            .chain(changes.deleted.iter().map(|p| format!("D {}", p.display())))
+            .collect();
+        entries.sort();
+        let mut paths: Vec<String> = changes.modified.iter().chain(changes.deleted.iter())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        paths.sort();
+        WorkingCopy { fingerprint: format!("{:x}", Sha256::digest(entries.join("\n").as_bytes())), paths }
+    }
+
+    pub fn is_clean(&self) -> bool { self.paths.is_empty() }
+}
+
+/// VCS operation run to make a file writable before it is modified
+/// (reported back to the client in `ModifyResult`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VcsAction {
+    pub provider: String,
+    /// "edit" / "add"
+    pub action: String,
+    pub path: String,
+    pub changelist: Option<String>,
+}
+
 /// Abstract VCS provider. Implementations must be Send + Sync so they can be
 /// stored in Arc and used across Rayon threads.
 pub trait VcsProvider: Send + Sync {
@@ -27,9 +90,10 @@ pub trait VcsProvider: Send + Sync {
 
     /// Returns an opaque revision string for the repository root.
     ///
-    /// - Git         → 40-char SHA (`git rev-parse HEAD`)
+    /// - Git         → 40-char SHA of the last commit touching the root (`git log -1 -- .`)
     /// - P4          → changelist number string
     /// - SVN         → revision number string
+    /// - Plastic     → changeset number string
     /// - BuildVersion → `"ue:{Major}.{Minor}.{Patch}+{Branch}"` for binary engines
     /// - None        → always `None`
     fn current_revision(&self, root: &Path) -> Option<String>;
@@ -39,6 +103,23 @@ pub trait VcsProvider: Send + Sync {
     /// be computed (e.g. `since_rev` is no longer reachable, or provider
     /// does not support diff — e.g. BuildVersion).
     fn changed_since(&self, root: &Path, since_rev: &str) -> Option<ChangedFiles>;
+
+    /// Returns uncommitted changes in the working copy under `root` (local
+    /// edits, files opened for edit, untracked files).  `None` if the provider
+    /// cannot tell.
+    fn working_changes(&self, _root: &Path) -> Option<ChangedFiles> { None }
+
+    /// Prepares `path` for modification, e.g. `p4 edit` for a read-only file
+    /// under Perforce control.  `changelist` places the opened file in that
+    /// changelist (and lets files not yet under control be added to it).
+    /// `Ok(None)` when nothing had to be done.  Providers without changelists
+    /// reject a `changelist` rather than silently ignoring it.
+    fn checkout(&self, _path: &Path, changelist: Option<&str>) -> anyhow::Result<Option<VcsAction>> {
+        match changelist {
+            Some(cl) => anyhow::bail!("changelist {} was given but the file is not under Perforce control ({})", cl, self.name()),
+            None => Ok(None),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -92,34 +173,42 @@ impl VcsProvider for BuildVersionProvider {
 /// Detect the best available revision source for the given directory.
 ///
 /// Detection order:
-///   1. Git (`.git` directory / file, or `git rev-parse` succeeds)
-///   2. Perforce (`.p4config` present, or `p4 changes` succeeds in the dir)
-///   3. SVN (`.svn` directory present)
-///   4. `Engine/Build/Build.version` (binary UE from Epic Launcher)
-///   5. NoVcs (always returns `None` → mtime-based fallback)
+///   1. Git (`.git` directory / `gitdir:` file in the dir or an ancestor, or `git log` resolves a revision)
+///   2. Plastic SCM (`.plastic` directory in the dir or an ancestor)
+///   3. Perforce (`.p4config` present, or `p4 changes` succeeds in the dir)
+///   4. SVN (`.svn` directory present)
+///   5. `Engine/Build/Build.version` (binary UE from Epic Launcher)
+///   6. NoVcs (always returns `None` → mtime-based fallback)
 pub fn detect(root: &Path) -> Box<dyn VcsProvider> {
-    // 1. Git: .git can be a directory (normal clone) or a file (submodule/worktree)
-    if root.join(".git").exists() {
+    // 1. Git: .git can be a directory (normal clone) or a `gitdir:` file
+    //    (submodule/worktree), at the root or in an ancestor (monorepo).
+    if git::find_repo(root).is_some() {
         return Box::new(git::GitProvider);
     }
-    // Fallback: git rev-parse works even without a .git at root (sparse checkouts etc.)
+    // Fallback: git still resolves a revision even without a .git at root (sparse checkouts etc.)
     if git::GitProvider.current_revision(root).is_some() {
         return Box::new(git::GitProvider);
     }
 
-    // 2. Perforce: .p4config file (name may be overridden by P4CONFIG env var),
+    // 2. Plastic SCM: .plastic workspace metadata (checked before Perforce, whose
+    //    detection has to run `p4`).
+    if plastic::workspace_root(root).is_some() {
+        return Box::new(plastic::PlasticProvider::default());
+    }
+
+    // 3. Perforce: .p4config file (name may be overridden by P4CONFIG env var),
     //    or p4 changes succeeds meaning the directory is inside a mapped workspace.
     let p4config_name = std::env::var("P4CONFIG").unwrap_or_else(|_| ".p4config".to_string());
     if root.join(&p4config_name).exists() || perforce::detect_revision(root).is_some() {
         return Box::new(perforce::P4Provider);
     }
 
-    // 3. SVN: .svn working-copy metadata directory.
+    // 4. SVN: .svn working-copy metadata directory.
     if root.join(".svn").exists() {
         return Box::new(svn::SvnProvider);
     }
 
-    // 4. Binary UE engine (Epic Launcher): use Build.version as a stable fingerprint.
+    // 5. Binary UE engine (Epic Launcher): use Build.version as a stable fingerprint.
     if BuildVersionProvider.current_revision(root).is_some() {
         return Box::new(BuildVersionProvider);
     }
@@ -11,7 +11,8 @@
 use std::path::Path;
 use std::collections::HashSet;
 use std::process::Command;
-use super::{VcsProvider, ChangedFiles};
+use anyhow::{bail, Context};
+use super::{VcsProvider, ChangedFiles, VcsAction};
 
 pub struct P4Provider;
 
@@ -93,6 +94,110 @@ impl VcsProvider for P4Provider {
 
         Some(ChangedFiles { modified, deleted })
     }
+
+    /// Returns files opened in the workspace (the `p4 opened` set, with local paths).
+    ///
+    /// Runs: `p4 fstat -Ro -T clientFile,action ROOT/...`
+    ///
+    /// fstat output block per opened file:
+    /// ```
+    /// ... clientFile C:/work/proj/Source/Foo.cpp
+    /// ... action edit
+    /// ```
+    fn working_changes(&self, root: &Path) -> Option<ChangedFiles> {
+        let depot_spec = format!("{}/...", root.to_string_lossy().replace('\\', "/"));
+        let out = Command::new("p4")
+            .args(["fstat", "-Ro", "-T", "clientFile,action", &depot_spec])
+            .current_dir(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        Some(parse_opened(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Opens a read-only depot file for edit (`p4 edit [-c CL] PATH`), or adds a
+    /// file that is not in the depot yet when a changelist is given
+    /// (`p4 add -c CL PATH`).  Files already opened are left as they are.
+    fn checkout(&self, path: &Path, changelist: Option<&str>) -> anyhow::Result<Option<VcsAction>> {
+        let readonly = path.metadata().map(|m| m.permissions().readonly()).unwrap_or(false);
+        if !readonly && changelist.is_none() { return Ok(None); }
+
+        let file = path.to_string_lossy().replace('\\', "/");
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let out = Command::new("p4")
+            .args(["fstat", "-T", "headAction,action", &file])
+            .current_dir(dir)
+            .output()
+            .context("failed to run p4")?;
+        // ファイルが depot に無いときだけ "no such file(s)" が stderr に出る。
+        // それ以外 (認証切れ・接続失敗など) は not in depot と区別できないのでそのまま返す
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if !out.status.success() || (!stderr.trim().is_empty() && !stderr.contains("no such file(s)")) {
+            bail!("p4 fstat {} failed: {}", file, stderr.trim());
+        }
+        let action = match parse_file_state(&String::from_utf8_lossy(&out.stdout)) {
+            FileState::Opened => return Ok(None),
+            FileState::InDepot => "edit",
+            FileState::NotInDepot if changelist.is_some() => "add",
+            FileState::NotInDepot => return Ok(None),
+        };
+
+        let mut cmd = Command::new("p4");
+        cmd.arg(action);
+        if let Some(cl) = changelist { cmd.args(["-c", cl]); }
+        let out = cmd.arg(&file).current_dir(dir).output().context("failed to run p4")?;
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if !out.status.success() || !stderr.trim().is_empty() {
+            bail!("p4 {} {} failed: {}", action, file, stderr.trim());
+        }
+        Ok(Some(VcsAction {
+            provider: self.name().to_string(),
+            action: action.to_string(),
+            path: file,
+            changelist: changelist.map(str::to_string),
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum FileState { Opened, InDepot, NotInDepot }
+
+/// `p4 fstat -T headAction,action` output for a single file.  Files outside
+/// the depot print nothing on stdout ("no such file(s)" goes to stderr).
+/// A head revision that deletes the file (`delete` / `move/delete`) means the
+/// file has to be added again, not edited.
+fn parse_file_state(text: &str) -> FileState {
+    let field = |name: &str| text.lines().find_map(|l| l.trim().strip_prefix(name).map(str::trim));
+    if field("... action ").is_some() {
+        FileState::Opened
+    } else if field("... headAction ").is_some_and(|a| !a.ends_with("delete")) {
+        FileState::InDepot
+    } else {
+        FileState::NotInDepot
+    }
+}
+
+fn parse_opened(text: &str) -> ChangedFiles {
+    let mut changes = ChangedFiles::default();
+    let mut client_file: Option<std::path::PathBuf> = None;
+    let mut action: Option<String> = None;
+    let mut flush = |file: Option<std::path::PathBuf>, action: Option<String>| {
+        if let (Some(p), Some(a)) = (file, action) {
+            if a.contains("delete") { changes.deleted.insert(p); } else { changes.modified.insert(p); }
+        }
+    };
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            flush(client_file.take(), action.take());
+        } else if let Some(rest) = line.strip_prefix("... clientFile ") {
+            client_file = Some(std::path::PathBuf::from(rest.trim()));
+        } else if let Some(rest) = line.strip_prefix("... action ") {
+            action = Some(rest.trim().to_string());
+        }
+    }
+    flush(client_file, action);
+    changes
 }
 
 /// Attempt to detect an active P4 workspace at `root`.
@@ -116,3 +221,29 @@ fn parse_cl_from_changes(output: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_state() {
+        assert_eq!(parse_file_state("... headAction edit\n"), FileState::InDepot);
+        assert_eq!(parse_file_state("... headAction add\n... action edit\n"), FileState::Opened);
+        assert_eq!(parse_file_state(""), FileState::NotInDepot);
+        assert_eq!(parse_file_state("... headAction delete\n"), FileState::NotInDepot);
+        assert_eq!(parse_file_state("... headAction move/delete\n"), FileState::NotInDepot);
+        assert_eq!(parse_file_state("... headAction move/add\n"), FileState::InDepot);
+    }
+
+    #[test]
+    fn test_parse_opened() {
+        let out = "... clientFile /ws/Source/Foo.cpp\n... action edit\n\n\
+                   ... clientFile /ws/Source/Gone.h\n... action move/delete\n\n\
+                   ... clientFile /ws/Source/New.h\n... action add\n";
+        let changes = parse_opened(out);
+        let p = std::path::PathBuf::from;
+        assert_eq!(changes.modified, HashSet::from([p("/ws/Source/Foo.cpp"), p("/ws/Source/New.h")]));
+        assert_eq!(changes.deleted, HashSet::from([p("/ws/Source/Gone.h")]));
+    }
+}
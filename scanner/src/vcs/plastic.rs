@@ -0,0 +1,242 @@
+//! Plastic SCM (Unity Version Control) VCS provider.
+//!
+//! Uses the `cm` CLI.
+//!
+//! Revision token : changeset number loaded in the workspace, as returned by
+//!                  `cm status --header --machinereadable` (e.g. `"1234"`).
+//! changed_since  : `cm diff cs:PREV cs:CURRENT --format=...`
+//!                  returns the added/changed/moved/deleted items.
+//! working_changes: `cm status --all --machinereadable` (pending changes and
+//!                  private files in the workspace).
+
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::process::Command;
+use super::{VcsProvider, ChangedFiles};
+
+pub struct PlasticProvider {
+    /// `cm` executable (tests point this at a stub instead of touching PATH)
+    cm: PathBuf,
+}
+
+impl Default for PlasticProvider {
+    fn default() -> Self { PlasticProvider { cm: PathBuf::from("cm") } }
+}
+
+impl PlasticProvider {
+    pub fn with_command(cm: impl Into<PathBuf>) -> Self { PlasticProvider { cm: cm.into() } }
+}
+
+/// Returns the workspace root (the directory holding `.plastic`) for `root`
+/// or one of its ancestors.
+pub fn workspace_root(root: &Path) -> Option<PathBuf> {
+    root.ancestors().find(|p| p.join(".plastic").is_dir()).map(Path::to_path_buf)
+}
+
+impl VcsProvider for PlasticProvider {
+    fn name(&self) -> &'static str { "plastic" }
+
+    /// Returns the loaded changeset number as a string.
+    ///
+    /// Runs: `cm status --header --machinereadable`
+    /// Output: `STATUS 1234 MyRepo localhost:8087`
+    fn current_revision(&self, root: &Path) -> Option<String> {
+        let out = Command::new(&self.cm)
+            .args(["status", "--header", "--machinereadable"])
+            .current_dir(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        parse_changeset(&String::from_utf8_lossy(&out.stdout))
+    }
+
+    /// Returns items changed between changeset `since_rev` and the loaded one.
+    ///
+    /// Runs: `cm diff cs:PREV cs:CURRENT --format={status}|{path}|{srccmpath}`
+    ///
+    /// Output line format (paths are relative to the workspace root):
+    /// ```text
+    /// C|/Source/Game/Foo.cpp|
+    /// A|/Source/Game/New.cpp|
+    /// D|/Source/Game/Gone.cpp|
+    /// M|/Source/Game/Renamed.cpp|/Source/Game/Old.cpp
+    /// ```
+    /// Status chars: A=added, C=changed, D=deleted, M=moved
+    fn changed_since(&self, root: &Path, since_rev: &str) -> Option<ChangedFiles> {
+        let ws_root = workspace_root(root)?;
+        let current = self.current_revision(root)?;
+        let out = Command::new(&self.cm)
+            .args(["diff", &format!("cs:{}", since_rev), &format!("cs:{}", current), "--format={status}|{path}|{srccmpath}"])
+            .current_dir(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        // cm diff はワークスペース全体の変更を返すので root 配下だけに絞る
+        let mut changes = parse_diff(&String::from_utf8_lossy(&out.stdout), &ws_root);
+        changes.modified.retain(|p| p.starts_with(root));
+        changes.deleted.retain(|p| p.starts_with(root));
+        Some(changes)
+    }
+
+    /// Returns pending changes (changed / checked out / added / moved / deleted)
+    /// and private files under `root`.
+    ///
+    /// Runs: `cm status --all --noheader --machinereadable --fieldseparator=| ROOT`
+    ///
+    /// Output line format (absolute paths; moves list the source first):
+    /// ```text
+    /// CH|C:\ws\Source\Foo.cpp|False|NO_MERGES
+    /// PR|C:\ws\Source\New.cpp|False|NO_MERGES
+    /// MV|C:\ws\Source\Old.cpp|C:\ws\Source\Renamed.cpp|False|NO_MERGES
+    /// ```
+    fn working_changes(&self, root: &Path) -> Option<ChangedFiles> {
+        let ws_root = workspace_root(root)?;
+        let out = Command::new(&self.cm)
+            .args(["status", "--all", "--noheader", "--machinereadable", "--fieldseparator=|"])
+            .arg(root)
+            .current_dir(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+        let mut changes = parse_status(&String::from_utf8_lossy(&out.stdout), &ws_root);
+        changes.modified.retain(|p| p.starts_with(root));
+        changes.deleted.retain(|p| p.starts_with(root));
+        Some(changes)
+    }
+}
+
+/// `STATUS <cs> <repo> <server>` (machine readable) or `cs:<cs>@rep:...` (plain header).
+fn parse_changeset(output: &str) -> Option<String> {
+    let line = output.lines().find(|l| !l.trim().is_empty())?.trim();
+    let token = match line.strip_prefix("STATUS") {
+        Some(rest) => rest.split_whitespace().next()?,
+        None => line.strip_prefix("cs:")?.split('@').next()?,
+    };
+    token.parse::<u64>().ok().map(|cs| cs.to_string())
+}
+
+fn parse_diff(output: &str, ws_root: &Path) -> ChangedFiles {
+    let to_abs = |p: &str| ws_root.join(p.trim().trim_start_matches(['/', '\\']));
+    let mut modified = HashSet::new();
+    let mut deleted  = HashSet::new();
+    for line in output.lines() {
+        let mut fields = line.split('|');
+        let (Some(status), Some(path)) = (fields.next(), fields.next()) else { continue };
+        let path = path.trim();
+        if path.is_empty() { continue; }
+        match status.trim() {
+            "D" => { deleted.insert(to_abs(path)); }
+            "M" => {
+                if let Some(src) = fields.next().map(str::trim).filter(|s| !s.is_empty()) {
+                    deleted.insert(to_abs(src));
+                }
+                modified.insert(to_abs(path));
+            }
+            "" => {}
+            _ => { modified.insert(to_abs(path)); }
+        }
+    }
+    ChangedFiles { modified, deleted }
+}
+
+fn parse_status(output: &str, ws_root: &Path) -> ChangedFiles {
+    let to_abs = |p: &str| ws_root.join(p.trim());
+    let mut changes = ChangedFiles::default();
+    for line in output.lines() {
+        let mut fields = line.split('|');
+        let (Some(status), Some(path)) = (fields.next(), fields.next()) else { continue };
+        let path = path.trim();
+        if path.is_empty() { continue; }
+        match status.trim() {
+            "DE" | "LD" => { changes.deleted.insert(to_abs(path)); }
+            "MV" | "LM" => {
+                // 2 番目のパスが移動先 (無ければ移動先だけが出ている)
+                match fields.next().map(str::trim).filter(|d| !d.is_empty() && *d != "True" && *d != "False") {
+                    Some(dst) => {
+                        changes.deleted.insert(to_abs(path));
+                        changes.modified.insert(to_abs(dst));
+                    }
+                    None => { changes.modified.insert(to_abs(path)); }
+                }
+            }
+            "AD" | "CH" | "CO" | "CP" | "PR" | "RP" => { changes.modified.insert(to_abs(path)); }
+            _ => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plastic_provider_with_stub_cm() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("unl_plastic_test_{}", std::process::id()));
+        let bin = dir.join("bin");
+        let ws = dir.join("ws");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::create_dir_all(ws.join(".plastic")).unwrap();
+        std::fs::create_dir_all(ws.join("Source")).unwrap();
+
+        let script = r#"#!/bin/sh
+case "$1" in
+  status) echo "STATUS 42 MyGame localhost:8087" ;;
+  diff)
+    [ "$2" = "cs:40" ] && [ "$3" = "cs:42" ] || exit 1
+    echo "C|/Source/Foo.cpp|"
+    echo "D|/Source/Gone.h|"
+    echo "M|/Source/New.cpp|/Source/Old.cpp"
+    echo "C|/Engine/Source/Engine.cpp|"
+    ;;
+  *) exit 1 ;;
+esac
+"#;
+        let cm = bin.join("cm");
+        std::fs::write(&cm, script).unwrap();
+        std::fs::set_permissions(&cm, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(super::super::detect(&ws.join("Source")).name(), "plastic");
+        let provider = PlasticProvider::with_command(&cm);
+        assert_eq!(provider.current_revision(&ws).as_deref(), Some("42"));
+        let changed = provider.changed_since(&ws, "40").unwrap();
+        assert!(changed.modified.contains(&ws.join("Source/Foo.cpp")));
+        assert!(changed.modified.contains(&ws.join("Source/New.cpp")));
+        assert!(changed.deleted.contains(&ws.join("Source/Gone.h")));
+        assert!(changed.deleted.contains(&ws.join("Source/Old.cpp")));
+        assert!(provider.changed_since(&ws, "39").is_none());
+
+        // 同じワークスペースの root 外 (エンジン側) の変更は含めない
+        let source = ws.join("Source");
+        let changed = provider.changed_since(&source, "40").unwrap();
+        assert_eq!(changed.modified, HashSet::from([source.join("Foo.cpp"), source.join("New.cpp")]));
+        assert_eq!(changed.deleted, HashSet::from([source.join("Gone.h"), source.join("Old.cpp")]));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let ws = Path::new("/ws");
+        let out = "CH|/ws/Source/Foo.cpp|False|NO_MERGES\nPR|/ws/Source/New.h|False|NO_MERGES\n\
+                   LD|/ws/Source/Gone.h|False|NO_MERGES\nMV|/ws/Source/Old.cpp|/ws/Source/Renamed.cpp|False|NO_MERGES\n\
+                   IG|/ws/Binaries/x.dll|False|NO_MERGES\n";
+        let changes = parse_status(out, ws);
+        let mut modified: Vec<_> = changes.modified.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        modified.sort();
+        assert_eq!(modified, vec!["/ws/Source/Foo.cpp", "/ws/Source/New.h", "/ws/Source/Renamed.cpp"]);
+        assert!(changes.deleted.contains(Path::new("/ws/Source/Gone.h")));
+        assert!(changes.deleted.contains(Path::new("/ws/Source/Old.cpp")));
+        assert_eq!(changes.deleted.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_changeset() {
+        assert_eq!(parse_changeset("STATUS 1234 MyRepo localhost:8087\n").as_deref(), Some("1234"));
+        assert_eq!(parse_changeset("cs:77@rep:MyRepo@repserver:localhost:8087\n").as_deref(), Some("77"));
+        assert_eq!(parse_changeset("error: not a workspace").as_deref(), None);
+    }
+}
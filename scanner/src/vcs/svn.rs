@@ -7,7 +7,7 @@
 //! changed_since  : `svn diff --summarize -r PREV_REV:HEAD ROOT`
 //!                  returns the set of modified/added/deleted paths.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashSet;
 use std::process::Command;
 use super::{VcsProvider, ChangedFiles};
@@ -52,22 +52,87 @@ impl VcsProvider for SvnProvider {
             .ok()?;
         if !out.status.success() { return None; }
 
-        let mut modified = HashSet::new();
-        let mut deleted  = HashSet::new();
+        Some(parse_summarize(&String::from_utf8_lossy(&out.stdout)))
+    }
+
+    /// Returns local modifications in the working copy.
+    ///
+    /// Runs: `svn status ROOT`
+    ///
+    /// Same column layout as `svn diff --summarize`; `!` (missing) counts as deleted.
+    fn working_changes(&self, root: &Path) -> Option<ChangedFiles> {
+        let out = Command::new("svn")
+            .arg("status")
+            .arg(root)
+            .output()
+            .ok()?;
+        if !out.status.success() { return None; }
+
+        Some(parse_status(&String::from_utf8_lossy(&out.stdout)))
+    }
+}
 
-        for line in String::from_utf8_lossy(&out.stdout).lines() {
-            // Format: "<STATUS>       <path>" — status is 1–7 chars + spaces
-            // The status column is 8 chars wide (including trailing space).
-            if line.len() < 9 { continue; }
-            let status = line[..8].trim();
-            let path   = std::path::PathBuf::from(line[8..].trim());
-            if status.starts_with('D') {
-                deleted.insert(path);
-            } else if !status.is_empty() {
-                modified.insert(path);
-            }
+/// `"<STATUS>       <path>"` — the status column is 8 chars wide (including
+/// trailing space).  Lines that don't have that layout (e.g. localized
+/// "Performing status on external item" notes) yield `None`.
+fn split_status_line(line: &str) -> Option<(&str, PathBuf)> {
+    let status = line.get(..8)?;
+    let path = line.get(8..)?.trim();
+    if path.is_empty() { return None; }
+    Some((status, PathBuf::from(path)))
+}
+
+/// `svn diff --summarize` output.
+fn parse_summarize(output: &str) -> ChangedFiles {
+    let mut modified = HashSet::new();
+    let mut deleted  = HashSet::new();
+    for line in output.lines() {
+        let Some((status, path)) = split_status_line(line) else { continue };
+        let status = status.trim();
+        if status.starts_with('D') {
+            deleted.insert(path);
+        } else if !status.is_empty() {
+            modified.insert(path);
+        }
+    }
+    ChangedFiles { modified, deleted }
+}
+
+/// `svn status` output.  Only the first column (item state) is used.
+fn parse_status(output: &str) -> ChangedFiles {
+    let mut modified = HashSet::new();
+    let mut deleted  = HashSet::new();
+    for line in output.lines() {
+        let Some((status, path)) = split_status_line(line) else { continue };
+        match status.get(..1).unwrap_or("") {
+            "D" | "!" => { deleted.insert(path); }
+            "M" | "A" | "R" | "C" | "?" | "~" => { modified.insert(path); }
+            _ => {}
         }
+    }
+    ChangedFiles { modified, deleted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let out = "M       /wc/Source/Foo.cpp\n?       /wc/Source/New.h\n!       /wc/Source/Gone.h\n\
+                   X       /wc/External\n\n\
+                   外部項目のステータスを調べています: '/wc/External':\n\
+                   Performing status on external item at '/wc/External':\n M      /wc/Source/PropOnly.cpp\n";
+        let changes = parse_status(out);
+        assert_eq!(changes.modified, HashSet::from([PathBuf::from("/wc/Source/Foo.cpp"), PathBuf::from("/wc/Source/New.h")]));
+        assert_eq!(changes.deleted, HashSet::from([PathBuf::from("/wc/Source/Gone.h")]));
+    }
 
-        Some(ChangedFiles { modified, deleted })
+    #[test]
+    fn test_parse_summarize() {
+        let out = "M       /wc/Source/Foo.cpp\nD       /wc/Source/Gone.h\n M      /wc/Source/Prop.cpp\n変更された項目\n";
+        let changes = parse_summarize(out);
+        assert_eq!(changes.modified, HashSet::from([PathBuf::from("/wc/Source/Foo.cpp"), PathBuf::from("/wc/Source/Prop.cpp")]));
+        assert_eq!(changes.deleted, HashSet::from([PathBuf::from("/wc/Source/Gone.h")]));
     }
 }